@@ -0,0 +1,25 @@
+//! Runs a MQTT bridge from a YAML configuration file.
+//!
+//! Usage: `cargo run --example mqtt_bridge -- bridge.example.yaml`
+
+use anyhow::{Context, Result};
+use qi_bridges::{mqtt::Bridge, Config};
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let subscriber = tracing_subscriber::fmt()
+        .compact()
+        .with_max_level(tracing::Level::INFO)
+        .finish();
+    tracing::subscriber::set_global_default(subscriber)?;
+
+    let path = std::env::args()
+        .nth(1)
+        .context("missing the path to the configuration file")?;
+    let yaml = std::fs::read_to_string(&path)
+        .with_context(|| format!("failed to read the configuration file \"{path}\""))?;
+    let config = Config::from_yaml(&yaml)?;
+
+    Bridge::new(config).run().await;
+    Ok(())
+}
@@ -0,0 +1,199 @@
+use crate::types::object::{ActionId, ObjectId};
+use std::time::Duration;
+
+/// The configuration of a bridge, usually read from a YAML file.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub qi: QiConfig,
+    pub mqtt: MqttConfig,
+    #[serde(default)]
+    pub signals: Vec<SignalMapping>,
+    #[serde(default)]
+    pub commands: Vec<CommandMapping>,
+}
+
+impl Config {
+    pub fn from_yaml(yaml: &str) -> Result<Self, FromYamlError> {
+        let config: Self = serde_yaml::from_str(yaml)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), InvalidMappingError> {
+        let subjects = self
+            .signals
+            .iter()
+            .map(|signal| (&signal.service, signal.object))
+            .chain(
+                self.commands
+                    .iter()
+                    .map(|command| (&command.service, command.object)),
+            );
+        for (service, object) in subjects {
+            if object == ObjectId::new(0) {
+                return Err(InvalidMappingError {
+                    service: service.clone(),
+                    object,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum FromYamlError {
+    #[error("error parsing the YAML configuration")]
+    Parse(#[from] serde_yaml::Error),
+
+    #[error(transparent)]
+    InvalidMapping(#[from] InvalidMappingError),
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("the mapping to object \"{object}\" of service \"{service}\" is invalid, it is reserved to the control of sessions")]
+pub struct InvalidMappingError {
+    pub service: String,
+    pub object: ObjectId,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct QiConfig {
+    /// The address of the `qi` node to connect to, as "host:port".
+    pub address: String,
+    #[serde(default = "QiConfig::default_reconnect_delay_ms")]
+    pub reconnect_delay_ms: u64,
+}
+
+impl QiConfig {
+    const fn default_reconnect_delay_ms() -> u64 {
+        1000
+    }
+
+    pub fn reconnect_delay(&self) -> Duration {
+        Duration::from_millis(self.reconnect_delay_ms)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MqttConfig {
+    pub host: String,
+    #[serde(default = "MqttConfig::default_port")]
+    pub port: u16,
+    pub client_id: String,
+}
+
+impl MqttConfig {
+    const fn default_port() -> u16 {
+        1883
+    }
+}
+
+/// Republishes a signal of a `qi` object to a MQTT topic.
+///
+/// The service is resolved by its name in the service directory of the node, each time the
+/// bridge connects to it.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SignalMapping {
+    pub service: String,
+    pub object: ObjectId,
+    pub signal: ActionId,
+    pub topic: String,
+    #[serde(default)]
+    pub payload: PayloadFormat,
+}
+
+/// Forwards messages of a MQTT topic as calls to a method of a `qi` object.
+///
+/// The service is resolved by its name, as the one of a [`SignalMapping`].
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CommandMapping {
+    pub topic: String,
+    pub service: String,
+    pub object: ObjectId,
+    pub method: ActionId,
+    #[serde(default)]
+    pub payload: PayloadFormat,
+}
+
+/// How payloads are converted between the `qi` format and MQTT messages.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PayloadFormat {
+    /// The payload is forwarded as is, in the `qi` format.
+    #[default]
+    Raw,
+    /// The payload is a `qi` string, forwarded as UTF-8 text.
+    String,
+    /// The payload is a `qi` dynamic value, forwarded as its textual representation.
+    Dynamic,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::assert_matches;
+
+    #[test]
+    fn test_config_from_yaml() {
+        let config = Config::from_yaml(include_str!("../bridge.example.yaml")).unwrap();
+        assert_eq!(
+            config,
+            Config {
+                qi: QiConfig {
+                    address: "localhost:9559".to_owned(),
+                    reconnect_delay_ms: 1000,
+                },
+                mqtt: MqttConfig {
+                    host: "localhost".to_owned(),
+                    port: 1883,
+                    client_id: "qi-bridge".to_owned(),
+                },
+                signals: vec![SignalMapping {
+                    service: "ALBattery".to_owned(),
+                    object: ObjectId::new(1),
+                    signal: ActionId::new(104),
+                    topic: "robot/battery/charge".to_owned(),
+                    payload: PayloadFormat::Dynamic,
+                }],
+                commands: vec![CommandMapping {
+                    topic: "robot/tts/say".to_owned(),
+                    service: "ALTextToSpeech".to_owned(),
+                    object: ObjectId::new(1),
+                    method: ActionId::new(110),
+                    payload: PayloadFormat::String,
+                }],
+            }
+        );
+    }
+
+    #[test]
+    fn test_config_from_yaml_defaults() {
+        let config = Config::from_yaml(
+            "qi: { address: \"robot:9559\" }\nmqtt: { host: broker, client_id: id }",
+        )
+        .unwrap();
+        assert_eq!(config.qi.reconnect_delay(), Duration::from_secs(1));
+        assert_eq!(config.mqtt.port, 1883);
+        assert!(config.signals.is_empty());
+        assert!(config.commands.is_empty());
+    }
+
+    #[test]
+    fn test_config_from_yaml_control_subject_is_invalid() {
+        let config = Config::from_yaml(
+            "qi: { address: \"robot:9559\" }\n\
+             mqtt: { host: broker, client_id: id }\n\
+             signals: [{ service: ALBattery, object: 0, signal: 1, topic: t }]",
+        );
+        assert_matches!(
+            config,
+            Err(FromYamlError::InvalidMapping(InvalidMappingError { .. }))
+        );
+    }
+}
@@ -0,0 +1,98 @@
+use crate::{config::PayloadFormat, format, messaging::session, types::Dynamic};
+use bytes::Bytes;
+
+/// Converts the content of a `qi` event into the payload of a MQTT message.
+pub(crate) fn event_to_mqtt(format: PayloadFormat, content: Bytes) -> Result<Bytes, Error> {
+    let content = format::Value::from_bytes(content);
+    match format {
+        PayloadFormat::Raw => Ok(content.to_bytes()),
+        PayloadFormat::String => {
+            let string: String = content.to_deserializable()?;
+            Ok(string.into())
+        }
+        PayloadFormat::Dynamic => {
+            let dynamic: Dynamic = content.to_deserializable()?;
+            Ok(dynamic.to_string().into())
+        }
+    }
+}
+
+/// Converts the payload of a MQTT message into a call to a `qi` method with a single parameter.
+pub(crate) fn mqtt_to_call(
+    format: PayloadFormat,
+    subject: session::Subject,
+    payload: Bytes,
+) -> Result<session::Call, Error> {
+    let call = session::Call::new(subject);
+    let call = match format {
+        PayloadFormat::Raw => call.with_value(&(payload,))?,
+        PayloadFormat::String => call.with_value(&(utf8_payload(payload)?,))?,
+        PayloadFormat::Dynamic => call.with_value(&(Dynamic::from(utf8_payload(payload)?),))?,
+    };
+    Ok(call)
+}
+
+fn utf8_payload(payload: Bytes) -> Result<String, Error> {
+    String::from_utf8(payload.to_vec()).map_err(Error::PayloadUtf8)
+}
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum Error {
+    #[error("format error")]
+    Format(#[from] format::Error),
+
+    #[error("the payload is not valid UTF-8")]
+    PayloadUtf8(#[source] std::string::FromUtf8Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::object::{ActionId, ObjectId, ServiceId};
+    use assert_matches::assert_matches;
+
+    fn subject() -> session::Subject {
+        let service_object =
+            session::subject::ServiceObject::new(ServiceId::new(1), ObjectId::new(1)).unwrap();
+        session::Subject::new(service_object, ActionId::new(100))
+    }
+
+    #[test]
+    fn test_event_to_mqtt() {
+        let content = format::to_value(&"hello").unwrap().to_bytes();
+        assert_eq!(
+            event_to_mqtt(PayloadFormat::Raw, content.clone()).unwrap(),
+            content
+        );
+        assert_eq!(
+            event_to_mqtt(PayloadFormat::String, content).unwrap(),
+            Bytes::from_static(b"hello")
+        );
+
+        let content = format::to_value(&Dynamic::from(42i32)).unwrap().to_bytes();
+        assert_eq!(
+            event_to_mqtt(PayloadFormat::Dynamic, content).unwrap(),
+            Bytes::from_static(b"42")
+        );
+    }
+
+    #[test]
+    fn test_mqtt_to_call() {
+        let call = mqtt_to_call(
+            PayloadFormat::String,
+            subject(),
+            Bytes::from_static(b"hello"),
+        )
+        .unwrap();
+        assert_eq!(call.value::<(String,)>().unwrap(), ("hello".to_owned(),));
+
+        assert_matches!(
+            mqtt_to_call(
+                PayloadFormat::String,
+                subject(),
+                Bytes::from_static(&[0xff, 0xfe])
+            ),
+            Err(Error::PayloadUtf8(_))
+        );
+    }
+}
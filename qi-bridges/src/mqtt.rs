@@ -0,0 +1,622 @@
+use crate::{
+    config::{CommandMapping, Config, SignalMapping},
+    conversion,
+    messaging::{self, session, CallResult, CallTermination, SubjectPattern, SubjectRouter},
+    object::{object::client as object_client, service_directory, signal, ServiceDirectory},
+    types::object::ServiceId,
+};
+use futures::{future, StreamExt};
+use rumqttc::{AsyncClient, Event, EventLoop, MqttOptions, Packet, Publish, QoS};
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+use tokio::{net::TcpStream, pin, select, spawn, sync::mpsc, task::JoinHandle, time::sleep};
+use tracing::{debug, info, trace, warn};
+
+const MQTT_CHANNEL_CAPACITY: usize = 32;
+const MQTT_KEEP_ALIVE: Duration = Duration::from_secs(5);
+const MQTT_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// A bridge between the signals and methods of `qi` objects and MQTT topics.
+///
+/// The bridge keeps both connections alive: the MQTT client reconnects to the broker on its own,
+/// and the `qi` session is reopened after the configured delay when it is lost. Commands that are
+/// received while the `qi` session is down are dropped.
+///
+/// Each time the session is opened, the services of the mappings are resolved by their name in
+/// the service directory of the node, and the bridge registers a link to each mapped signal, for
+/// the objects to send it their events. The mappings of services that are not registered at that
+/// time are skipped until the session is reopened.
+#[derive(Debug)]
+pub struct Bridge {
+    config: Config,
+}
+
+impl Bridge {
+    pub fn new(config: Config) -> Self {
+        Self { config }
+    }
+
+    /// Runs the bridge.
+    ///
+    /// This function never returns, the future must be dropped to stop the bridge.
+    pub async fn run(self) {
+        let Config {
+            qi,
+            mqtt,
+            signals,
+            commands,
+        } = &self.config;
+
+        let mut options = MqttOptions::new(&mqtt.client_id, &mqtt.host, mqtt.port);
+        options.set_keep_alive(MQTT_KEEP_ALIVE);
+        let (mqtt_client, mqtt_event_loop) = AsyncClient::new(options, MQTT_CHANNEL_CAPACITY);
+        let (publishes_sender, mut publishes_receiver) = mpsc::channel(MQTT_CHANNEL_CAPACITY);
+        let command_topics = commands
+            .iter()
+            .map(|command| command.topic.clone())
+            .collect();
+        let _mqtt_task = spawn(poll_mqtt(
+            mqtt_event_loop,
+            mqtt_client.clone(),
+            command_topics,
+            publishes_sender,
+        ));
+
+        loop {
+            match connect(&qi.address).await {
+                Ok((client, dispatch)) => {
+                    info!(address = %qi.address, "connected to the qi node");
+                    match resolve_services(&client, signals, commands).await {
+                        Ok(services) => {
+                            let routes = Routes::new(signals, commands, &services);
+                            routes
+                                .bridge(&client, dispatch, &mqtt_client, &mut publishes_receiver)
+                                .await;
+                        }
+                        Err(err) => {
+                            warn!(
+                                error = &err as &dyn std::error::Error,
+                                "failed to connect to the service directory of the qi node"
+                            );
+                            dispatch.abort();
+                        }
+                    }
+                }
+                Err(err) => warn!(
+                    address = %qi.address,
+                    error = &err as &dyn std::error::Error,
+                    "failed to connect to the qi node"
+                ),
+            }
+            // Drop the commands received while the session is down.
+            while let Ok(publish) = publishes_receiver.try_recv() {
+                debug!(topic = %publish.topic, "no qi session, dropping the command");
+            }
+            sleep(qi.reconnect_delay()).await;
+        }
+    }
+}
+
+/// Resolves the services of the mappings by their name.
+///
+/// Services that cannot be resolved are left out, which skips their mappings.
+async fn resolve_services<'a>(
+    client: &session::Client,
+    signals: &'a [SignalMapping],
+    commands: &'a [CommandMapping],
+) -> CallResult<HashMap<&'a str, ServiceId>, object_client::ConnectError> {
+    let directory = service_directory::Client::connect(client.clone()).await?;
+    let names: BTreeSet<_> = signals
+        .iter()
+        .map(|signal| signal.service.as_str())
+        .chain(commands.iter().map(|command| command.service.as_str()))
+        .collect();
+    let mut services = HashMap::new();
+    for name in names {
+        match directory.resolve(name).await {
+            Ok(info) => {
+                services.insert(name, info.service_id);
+            }
+            Err(err) => warn!(
+                service = name,
+                error = &err as &dyn std::error::Error,
+                "failed to resolve a service, its mappings are skipped"
+            ),
+        }
+    }
+    Ok(services)
+}
+
+struct Routes<'a> {
+    signals: SubjectRouter<Vec<&'a SignalMapping>>,
+    signal_subjects: BTreeSet<session::Subject>,
+    commands: HashMap<&'a str, (session::Subject, &'a CommandMapping)>,
+}
+
+impl<'a> Routes<'a> {
+    fn new(
+        signals: &'a [SignalMapping],
+        commands: &'a [CommandMapping],
+        services: &HashMap<&str, ServiceId>,
+    ) -> Self {
+        let mut signal_routes = SubjectRouter::<Vec<_>>::new();
+        let mut signal_subjects = BTreeSet::new();
+        for signal in signals {
+            let subject = services
+                .get(signal.service.as_str())
+                .and_then(|&service| subject(service, signal.object, signal.signal));
+            if let Some(subject) = subject {
+                signal_routes
+                    .entry_or_default(SubjectPattern::from(subject))
+                    .push(signal);
+                signal_subjects.insert(subject);
+            }
+        }
+        let command_routes = commands
+            .iter()
+            .filter_map(|command| {
+                let service = *services.get(command.service.as_str())?;
+                let subject = subject(service, command.object, command.method)?;
+                Some((command.topic.as_str(), (subject, command)))
+            })
+            .collect();
+        Self {
+            signals: signal_routes,
+            signal_subjects,
+            commands: command_routes,
+        }
+    }
+
+    /// Bridges the events and commands until the session terminates.
+    async fn bridge(
+        &self,
+        client: &session::Client,
+        dispatch: JoinHandle<Result<(), session::Error>>,
+        mqtt_client: &AsyncClient,
+        publishes: &mut mpsc::Receiver<Publish>,
+    ) {
+        // Events are received from the start of the registrations, so that none is missed.
+        let events = client.routed_events(&self.signals);
+        pin!(events, dispatch);
+        let _registrations = Registrations::register(client, &self.signal_subjects).await;
+        loop {
+            select! {
+                Some((signals, _subject, content)) = events.next() => {
+                    for signal in signals {
+                        self.publish_signal(signal, content.clone(), mqtt_client).await;
+                    }
+                }
+                Some(publish) = publishes.recv() => self.call_command(client, publish),
+                res = &mut dispatch => {
+                    match res {
+                        Ok(Ok(())) => info!("the qi session was closed"),
+                        Ok(Err(err)) => warn!(
+                            error = &err as &dyn std::error::Error,
+                            "the qi session terminated with an error"
+                        ),
+                        Err(err) => warn!(
+                            error = &err as &dyn std::error::Error,
+                            "the qi session task failed"
+                        ),
+                    }
+                    break;
+                }
+            }
+        }
+    }
+
+    async fn publish_signal(
+        &self,
+        signal: &SignalMapping,
+        content: bytes::Bytes,
+        mqtt_client: &AsyncClient,
+    ) {
+        let payload = match conversion::event_to_mqtt(signal.payload, content) {
+            Ok(payload) => payload,
+            Err(err) => {
+                warn!(
+                    topic = %signal.topic,
+                    error = &err as &dyn std::error::Error,
+                    "failed to convert a signal payload"
+                );
+                return;
+            }
+        };
+        trace!(topic = %signal.topic, "publishing a signal");
+        if let Err(err) = mqtt_client
+            .publish(&signal.topic, QoS::AtLeastOnce, false, payload.to_vec())
+            .await
+        {
+            warn!(
+                topic = %signal.topic,
+                error = &err as &dyn std::error::Error,
+                "failed to publish a signal"
+            );
+        }
+    }
+
+    fn call_command(&self, client: &session::Client, publish: Publish) {
+        let (subject, command) = match self.commands.get(publish.topic.as_str()) {
+            Some(route) => *route,
+            None => return,
+        };
+        let call = match conversion::mqtt_to_call(command.payload, subject, publish.payload) {
+            Ok(call) => call,
+            Err(err) => {
+                warn!(
+                    topic = %publish.topic,
+                    error = &err as &dyn std::error::Error,
+                    "failed to convert a command payload"
+                );
+                return;
+            }
+        };
+        trace!(topic = %publish.topic, "calling a command");
+        let mut client = client.clone();
+        let topic = publish.topic;
+        spawn(async move {
+            use messaging::Service;
+            if let Err(err) = client.call(call).await {
+                warn!(
+                    %topic,
+                    error = &err as &dyn std::error::Error,
+                    "the command call failed"
+                );
+            }
+        });
+    }
+}
+
+fn subject(
+    service: crate::types::object::ServiceId,
+    object: crate::types::object::ObjectId,
+    action: crate::types::object::ActionId,
+) -> Option<session::Subject> {
+    let service_object = session::subject::ServiceObject::new(service, object)?;
+    Some(session::Subject::new(service_object, action))
+}
+
+/// The links registered to the signals of objects, for them to send their events to the bridge.
+///
+/// The links are unregistered when this is dropped. The objects also forget them when the session
+/// is closed.
+struct Registrations {
+    client: session::Client,
+    links: Vec<(session::Subject, signal::Link)>,
+}
+
+impl Registrations {
+    async fn register(client: &session::Client, signals: &BTreeSet<session::Subject>) -> Self {
+        static NEXT_LINK: AtomicU64 = AtomicU64::new(1);
+        let mut links = Vec::with_capacity(signals.len());
+        for &signal in signals {
+            let link = signal::Link::from(NEXT_LINK.fetch_add(1, Ordering::Relaxed));
+            let result = async {
+                let call =
+                    registration_call(signal, object_client::ACTION_ID_REGISTER_EVENT, link)?;
+                let mut client = client;
+                let reply = messaging::Service::call(&mut client, call).await?;
+                Ok::<_, RegistrationError>(reply.value::<signal::Link>()?)
+            }
+            .await;
+            match result {
+                Ok(link) => {
+                    trace!(subject = ?signal, ?link, "registered to a signal");
+                    links.push((signal, link));
+                }
+                Err(err) => warn!(
+                    subject = ?signal,
+                    error = &err as &dyn std::error::Error,
+                    "failed to register to a signal, it is not bridged"
+                ),
+            }
+        }
+        Self {
+            client: client.clone(),
+            links,
+        }
+    }
+}
+
+impl Drop for Registrations {
+    fn drop(&mut self) {
+        for (signal, link) in self.links.drain(..) {
+            let call =
+                match registration_call(signal, object_client::ACTION_ID_UNREGISTER_EVENT, link) {
+                    Ok(call) => call,
+                    Err(_err) => continue,
+                };
+            let mut client = self.client.clone();
+            // This is a best effort, the session may already be closed.
+            spawn(async move {
+                if let Err(err) = messaging::Service::call(&mut client, call).await {
+                    trace!(error = ?err, "failed to unregister from a signal");
+                }
+            });
+        }
+    }
+}
+
+/// A call of the registration, or unregistration, of a link to a signal of an object.
+fn registration_call(
+    signal: session::Subject,
+    action: crate::types::object::ActionId,
+    link: signal::Link,
+) -> Result<session::Call, crate::format::Error> {
+    let subject = session::Subject::new(signal.service_object(), action);
+    session::Call::new(subject).with_value(&(signal.service(), signal.action(), link))
+}
+
+#[derive(Debug, thiserror::Error)]
+enum RegistrationError {
+    #[error(transparent)]
+    Format(#[from] crate::format::Error),
+
+    #[error(transparent)]
+    Call(#[from] CallTermination<session::ClientError>),
+}
+
+async fn connect(
+    address: &str,
+) -> Result<(session::Client, JoinHandle<Result<(), session::Error>>), ConnectError> {
+    let stream = TcpStream::connect(address).await?;
+    let (client, dispatch) = session::connect(stream, NoService);
+    let dispatch = spawn(dispatch);
+    match client.await {
+        Ok(client) => Ok((client, dispatch)),
+        Err(err) => {
+            dispatch.abort();
+            Err(err.into())
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+enum ConnectError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Session(#[from] session::ConnectError),
+}
+
+async fn poll_mqtt(
+    mut event_loop: EventLoop,
+    client: AsyncClient,
+    command_topics: Vec<String>,
+    publishes: mpsc::Sender<Publish>,
+) {
+    loop {
+        match event_loop.poll().await {
+            Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                info!("connected to the MQTT broker");
+                // Subscriptions are not kept by the broker between connections.
+                for topic in &command_topics {
+                    if let Err(err) = client.try_subscribe(topic, QoS::AtLeastOnce) {
+                        warn!(
+                            %topic,
+                            error = &err as &dyn std::error::Error,
+                            "failed to subscribe to a command topic"
+                        );
+                    }
+                }
+            }
+            Ok(Event::Incoming(Packet::Publish(publish))) => {
+                if publishes.send(publish).await.is_err() {
+                    break;
+                }
+            }
+            Ok(_) => {}
+            Err(err) => {
+                warn!(
+                    error = &err as &dyn std::error::Error,
+                    "MQTT connection error"
+                );
+                sleep(MQTT_RECONNECT_DELAY).await;
+            }
+        }
+    }
+}
+
+/// The bridge does not offer any service to the `qi` node it is connected to.
+#[derive(Debug)]
+struct NoService;
+
+impl messaging::Service<session::CallWithId, session::NotificationWithId> for NoService {
+    type CallReply = ();
+    type Error = NoServiceError;
+    type CallFuture = future::Ready<CallResult<Self::CallReply, Self::Error>>;
+    type NotifyFuture = future::Ready<Result<(), Self::Error>>;
+
+    fn call(&mut self, _call: session::CallWithId) -> Self::CallFuture {
+        future::err(CallTermination::Error(NoServiceError))
+    }
+
+    fn notify(&mut self, _notif: session::NotificationWithId) -> Self::NotifyFuture {
+        future::ok(())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("the bridge does not provide any service")]
+struct NoServiceError;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::{MqttConfig, PayloadFormat, QiConfig},
+        object::ServiceInfo,
+        types::object::{ActionId, ObjectId},
+    };
+    use bytes::BytesMut;
+    use rumqttc::mqttbytes::{
+        self,
+        v4::{ConnAck, ConnectReturnCode, PingResp, PubAck, SubAck, SubscribeReasonCode},
+    };
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    const SERVICE_DIRECTORY: ServiceId = ServiceId::new(1);
+    const ACTION_SD_SERVICE: ActionId = ActionId::new(100);
+    const SERVICE: ServiceId = ServiceId::new(12);
+    const SIGNAL: ActionId = ActionId::new(104);
+
+    /// A `qi` node with a service directory and a service "Battery", which reports the
+    /// registrations to the signals of the service.
+    struct Robot {
+        registrations: mpsc::UnboundedSender<(ActionId, signal::Link)>,
+    }
+
+    #[derive(Debug, serde::Serialize)]
+    #[serde(untagged)]
+    enum RobotReply {
+        Service(ServiceInfo),
+        Link(signal::Link),
+        Unit,
+    }
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("the robot does not handle the call")]
+    struct RobotError;
+
+    impl messaging::Service<session::CallWithId, session::NotificationWithId> for Robot {
+        type CallReply = RobotReply;
+        type Error = RobotError;
+        type CallFuture = future::Ready<CallResult<Self::CallReply, Self::Error>>;
+        type NotifyFuture = future::Ready<Result<(), Self::Error>>;
+
+        fn call(&mut self, call: session::CallWithId) -> Self::CallFuture {
+            let call = call.into_inner();
+            let subject = *messaging::GetSubject::subject(&call);
+            let reply = match (subject.service(), subject.action()) {
+                (SERVICE_DIRECTORY, ACTION_SD_SERVICE)
+                    if call.value::<String>().unwrap() == "Battery" =>
+                {
+                    RobotReply::Service(ServiceInfo {
+                        name: "Battery".to_owned(),
+                        service_id: SERVICE,
+                        ..ServiceInfo::default()
+                    })
+                }
+                (SERVICE, object_client::ACTION_ID_REGISTER_EVENT) => {
+                    let (_service, signal, link) = call.value::<(ServiceId, _, _)>().unwrap();
+                    self.registrations.send((signal, link)).unwrap();
+                    RobotReply::Link(link)
+                }
+                (SERVICE, object_client::ACTION_ID_UNREGISTER_EVENT) => {
+                    let (_service, signal, link) = call.value::<(ServiceId, _, _)>().unwrap();
+                    self.registrations.send((signal, link)).unwrap();
+                    RobotReply::Unit
+                }
+                _ => return future::err(CallTermination::Error(RobotError)),
+            };
+            future::ok(reply)
+        }
+
+        fn notify(&mut self, _notif: session::NotificationWithId) -> Self::NotifyFuture {
+            future::ok(())
+        }
+    }
+
+    /// A MQTT broker that accepts a single client, and that forwards the messages that it
+    /// publishes.
+    async fn broker(listener: TcpListener, publishes: mpsc::UnboundedSender<Publish>) {
+        const MAX_PACKET_SIZE: usize = 1024;
+        let (mut stream, _address) = listener.accept().await.unwrap();
+        let mut input = BytesMut::new();
+        loop {
+            let packet = match mqttbytes::v4::read(&mut input, MAX_PACKET_SIZE) {
+                Err(mqttbytes::Error::InsufficientBytes(_)) => {
+                    if stream.read_buf(&mut input).await.unwrap() == 0 {
+                        return;
+                    }
+                    continue;
+                }
+                packet => packet.unwrap(),
+            };
+            let mut output = BytesMut::new();
+            match packet {
+                Packet::Connect(_) => {
+                    ConnAck::new(ConnectReturnCode::Success, false).write(&mut output)
+                }
+                Packet::Subscribe(subscribe) => {
+                    let codes = subscribe
+                        .filters
+                        .iter()
+                        .map(|filter| SubscribeReasonCode::Success(filter.qos))
+                        .collect();
+                    SubAck::new(subscribe.pkid, codes).write(&mut output)
+                }
+                Packet::Publish(publish) => {
+                    let written = PubAck::new(publish.pkid).write(&mut output);
+                    let _res = publishes.send(publish);
+                    written
+                }
+                Packet::PingReq => PingResp.write(&mut output),
+                _ => Ok(0),
+            }
+            .unwrap();
+            stream.write_all(&output).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_bridge_publishes_signals() {
+        let robot_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let broker_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let config = Config {
+            qi: QiConfig {
+                address: robot_listener.local_addr().unwrap().to_string(),
+                reconnect_delay_ms: 10,
+            },
+            mqtt: MqttConfig {
+                host: "127.0.0.1".to_owned(),
+                port: broker_listener.local_addr().unwrap().port(),
+                client_id: "qi-bridge".to_owned(),
+            },
+            signals: vec![SignalMapping {
+                service: "Battery".to_owned(),
+                object: ObjectId::new(1),
+                signal: SIGNAL,
+                topic: "robot/battery".to_owned(),
+                payload: PayloadFormat::String,
+            }],
+            commands: vec![],
+        };
+        let (publishes_sender, mut publishes) = mpsc::unbounded_channel();
+        spawn(broker(broker_listener, publishes_sender));
+        let bridge = spawn(Bridge::new(config).run());
+
+        // The bridge resolves the service and registers a link to its signal.
+        let (registrations_sender, mut registrations) = mpsc::unbounded_channel();
+        let (stream, _address) = robot_listener.accept().await.unwrap();
+        let (robot, dispatch) = session::listen(
+            stream,
+            Robot {
+                registrations: registrations_sender,
+            },
+        );
+        spawn(dispatch);
+        let robot = robot.await.unwrap();
+        let (signal, link) = registrations.recv().await.unwrap();
+        assert_eq!(signal, SIGNAL);
+
+        // The events of the signal are published to its topic.
+        let subject = subject(SERVICE, ObjectId::new(1), SIGNAL).unwrap();
+        let event = session::Event::new(subject).with_value(&"42%").unwrap();
+        robot.notify_events([event]).await.unwrap();
+        let publish = publishes.recv().await.unwrap();
+        assert_eq!(publish.topic, "robot/battery");
+        assert_eq!(publish.payload, "42%");
+
+        // The link is unregistered when the bridge is stopped.
+        bridge.abort();
+        assert_eq!(registrations.recv().await, Some((SIGNAL, link)));
+    }
+}
@@ -0,0 +1,303 @@
+#![deny(unreachable_pub, unsafe_code)]
+// TODO: #![deny(missing_docs)]
+#![warn(unused_crate_dependencies)]
+#![warn(
+    clippy::all,
+    clippy::clone_on_ref_ptr,
+    clippy::dbg_macro,
+    clippy::decimal_literal_representation,
+    clippy::empty_drop,
+    clippy::empty_structs_with_brackets,
+    clippy::exit,
+    clippy::float_cmp_const,
+    clippy::format_push_string,
+    clippy::get_unwrap,
+    clippy::if_then_some_else_none,
+    clippy::integer_division,
+    clippy::large_include_file,
+    clippy::let_underscore_must_use,
+    clippy::lossy_float_literal,
+    clippy::map_err_ignore,
+    clippy::mem_forget,
+    clippy::mixed_read_write_in_expression,
+    clippy::multiple_inherent_impl,
+    clippy::mutex_atomic,
+    clippy::panic,
+    clippy::print_stderr,
+    clippy::print_stdout,
+    clippy::rc_buffer,
+    clippy::rc_mutex,
+    clippy::rest_pat_in_fully_bound_structs,
+    clippy::same_name_method,
+    clippy::mod_module_files,
+    clippy::str_to_string,
+    clippy::string_slice,
+    clippy::string_to_string,
+    clippy::todo,
+    clippy::try_err,
+    clippy::unimplemented,
+    clippy::unnecessary_self_imports,
+    clippy::unneeded_field_pattern,
+    clippy::use_debug
+)]
+// Deny warnings in doc test.
+#![doc(test(attr(deny(warnings))))]
+#![doc = include_str!("../README.md")]
+
+use proc_macro2::{Ident, Span, TokenStream};
+use qi_types::{ty::TupleType, Type};
+use quote::quote;
+
+/// The Rust type that represents raw values.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RawType {
+    /// `qi_types::Raw`, which is `bytes::Bytes`.
+    #[default]
+    Bytes,
+    /// `Vec<u8>`.
+    Vec,
+}
+
+/// The Rust type that represents maps.
+///
+/// Maps with keys that cannot be hashed or ordered, such as floating point numbers, only have
+/// a valid representation with [`MapType::Qi`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MapType {
+    /// `std::collections::HashMap`.
+    #[default]
+    HashMap,
+    /// `std::collections::BTreeMap`.
+    BTreeMap,
+    /// `indexmap::IndexMap`, which keeps the order of the entries of the values.
+    IndexMap,
+    /// `qi_types::Map`, which keeps the order of the entries and accepts any key.
+    Qi,
+}
+
+/// The preferences of the generated code.
+#[derive(Debug, Clone)]
+pub struct Options {
+    raw: RawType,
+    map: MapType,
+    types_path: TokenStream,
+}
+
+impl Options {
+    pub fn new() -> Self {
+        Self {
+            raw: RawType::default(),
+            map: MapType::default(),
+            types_path: quote!(::qi::types),
+        }
+    }
+
+    pub fn with_raw_type(mut self, raw: RawType) -> Self {
+        self.raw = raw;
+        self
+    }
+
+    pub fn with_map_type(mut self, map: MapType) -> Self {
+        self.map = map;
+        self
+    }
+
+    /// Sets the path of the `qi_types` crate in the generated code, `::qi::types` by default.
+    pub fn with_types_path(mut self, path: TokenStream) -> Self {
+        self.types_path = path;
+        self
+    }
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns the tokens of the Rust type that represents the values of a type.
+///
+/// The dynamic type, which is the absence of type, is represented by `qi_types::Dynamic`.
+/// Structures are represented by a type of their name, that the generated code is expected to
+/// define.
+pub fn type_tokens(t: Option<&Type>, options: &Options) -> Result<TokenStream, Error> {
+    let types = &options.types_path;
+    let t = match t {
+        Some(t) => t,
+        None => return Ok(quote!(#types::Dynamic)),
+    };
+    let tokens = match t {
+        Type::Unit => quote!(()),
+        Type::Bool => quote!(bool),
+        Type::Int8 => quote!(i8),
+        Type::UInt8 => quote!(u8),
+        Type::Int16 => quote!(i16),
+        Type::UInt16 => quote!(u16),
+        Type::Int32 => quote!(i32),
+        Type::UInt32 => quote!(u32),
+        Type::Int64 => quote!(i64),
+        Type::UInt64 => quote!(u64),
+        Type::Float32 => quote!(f32),
+        Type::Float64 => quote!(f64),
+        Type::String => quote!(String),
+        Type::Raw => match options.raw {
+            RawType::Bytes => quote!(#types::Raw),
+            RawType::Vec => quote!(Vec<u8>),
+        },
+        Type::Object => quote!(#types::Object),
+        Type::Option(value) => {
+            let value = type_tokens(value.as_deref(), options)?;
+            quote!(Option<#value>)
+        }
+        Type::List(value) | Type::VarArgs(value) => {
+            let value = type_tokens(value.as_deref(), options)?;
+            quote!(Vec<#value>)
+        }
+        Type::Map { key, value } => {
+            let key = type_tokens(key.as_deref(), options)?;
+            let value = type_tokens(value.as_deref(), options)?;
+            match options.map {
+                MapType::HashMap => quote!(::std::collections::HashMap<#key, #value>),
+                MapType::BTreeMap => quote!(::std::collections::BTreeMap<#key, #value>),
+                MapType::IndexMap => quote!(::indexmap::IndexMap<#key, #value>),
+                MapType::Qi => quote!(#types::Map<#key, #value>),
+            }
+        }
+        Type::Tuple(TupleType::Tuple(elements)) => {
+            let elements = elements
+                .iter()
+                .map(|element| type_tokens(element.as_ref(), options))
+                .collect::<Result<Vec<_>, _>>()?;
+            quote!((#(#elements,)*))
+        }
+        Type::Tuple(TupleType::TupleStruct(name, _) | TupleType::Struct(name, _)) => {
+            let name = type_ident(name)?;
+            quote!(#name)
+        }
+    };
+    Ok(tokens)
+}
+
+/// Returns the identifier of a type or a field from its name in the `qi` type system.
+///
+/// Names that are Rust keywords are returned as raw identifiers.
+pub fn type_ident(name: &str) -> Result<Ident, Error> {
+    let mut chars = name.chars();
+    let is_identifier = match chars.next() {
+        Some(first) => {
+            (first.is_ascii_alphabetic() || first == '_')
+                && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+                && name != "_"
+        }
+        None => false,
+    };
+    if !is_identifier || UNRAWABLE_KEYWORDS.contains(&name) {
+        return Err(Error::InvalidIdentifier(name.to_owned()));
+    }
+    let ident = if KEYWORDS.contains(&name) {
+        Ident::new_raw(name, Span::call_site())
+    } else {
+        Ident::new(name, Span::call_site())
+    };
+    Ok(ident)
+}
+
+const UNRAWABLE_KEYWORDS: &[&str] = &["crate", "self", "Self", "super"];
+
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn",
+    "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in", "let", "loop",
+    "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return", "static",
+    "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use", "virtual",
+    "where", "while", "yield",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum Error {
+    #[error("\"{0}\" cannot be used as a Rust identifier")]
+    InvalidIdentifier(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use qi_types::Signature;
+
+    fn tokens_of(signature: &str, options: &Options) -> Result<String, Error> {
+        let signature: Signature = signature.parse().unwrap();
+        type_tokens(signature.into_type().as_ref(), options).map(|tokens| normalize(&tokens))
+    }
+
+    // The spacing of punctuation in the string of tokens depends on how they were built.
+    fn normalize(tokens: &TokenStream) -> String {
+        tokens.to_string().replace(' ', "")
+    }
+
+    #[test]
+    fn test_type_tokens() {
+        let options = Options::default();
+        let expected = |tokens: TokenStream| Ok(normalize(&tokens));
+        assert_eq!(tokens_of("v", &options), expected(quote!(())));
+        assert_eq!(tokens_of("L", &options), expected(quote!(u64)));
+        assert_eq!(
+            tokens_of("m", &options),
+            expected(quote!(::qi::types::Dynamic))
+        );
+        assert_eq!(
+            tokens_of("[{sd}]", &options),
+            expected(quote!(Vec<::std::collections::HashMap<String, f64>>))
+        );
+        assert_eq!(
+            tokens_of("+#r", &options),
+            expected(quote!(Option<Vec<::qi::types::Raw>>))
+        );
+        assert_eq!(tokens_of("(i)", &options), expected(quote!((i32,))));
+        assert_eq!(
+            tokens_of("(so)", &options),
+            expected(quote!((String, ::qi::types::Object,)))
+        );
+        assert_eq!(
+            tokens_of("[(dd)<Point,x,y>]", &options),
+            expected(quote!(Vec<Point>))
+        );
+        assert_eq!(tokens_of("(i)<type>", &options), expected(quote!(r#type)));
+    }
+
+    #[test]
+    fn test_type_tokens_options() {
+        let options = Options::new()
+            .with_raw_type(RawType::Vec)
+            .with_map_type(MapType::IndexMap)
+            .with_types_path(quote!(qi_types));
+        assert_eq!(
+            tokens_of("{rm}", &options),
+            Ok(normalize(&quote!(
+                ::indexmap::IndexMap<Vec<u8>, qi_types::Dynamic>
+            )))
+        );
+        let options = options.with_map_type(MapType::Qi);
+        assert_eq!(
+            tokens_of("{fI}", &options),
+            Ok(normalize(&quote!(qi_types::Map<f32, u32>)))
+        );
+    }
+
+    #[test]
+    fn test_type_ident() {
+        assert_eq!(type_ident("Point").unwrap().to_string(), "Point");
+        assert_eq!(type_ident("match").unwrap().to_string(), "r#match");
+        assert_eq!(
+            type_ident("self"),
+            Err(Error::InvalidIdentifier("self".to_owned()))
+        );
+        assert_eq!(
+            type_ident("_"),
+            Err(Error::InvalidIdentifier("_".to_owned()))
+        );
+        assert_eq!(
+            type_ident("2D"),
+            Err(Error::InvalidIdentifier("2D".to_owned()))
+        );
+        assert_eq!(type_ident(""), Err(Error::InvalidIdentifier(String::new())));
+    }
+}
@@ -0,0 +1,66 @@
+//! Serialization and deserialization of representative payloads.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use qi_format::{from_value, to_value, Value};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// The state of a joint of a robot, as published many times per second.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct JointState {
+    name: String,
+    position: f32,
+    velocity: f32,
+    stiffness: f32,
+}
+
+fn joint_states() -> Vec<JointState> {
+    (0..26)
+        .map(|index| JointState {
+            name: format!("Joint{index}"),
+            position: 0.5,
+            velocity: 0.01,
+            stiffness: 1.,
+        })
+        .collect()
+}
+
+fn image() -> serde_bytes::ByteBuf {
+    serde_bytes::ByteBuf::from(vec![0x7f; 320 * 240 * 3])
+}
+
+fn memory_keys() -> HashMap<String, Vec<i32>> {
+    (0..100)
+        .map(|index| (format!("Device/SubDeviceList/Key{index}"), vec![index; 4]))
+        .collect()
+}
+
+fn bench_payload<T>(c: &mut Criterion, name: &str, payload: &T)
+where
+    T: Serialize + DeserializeOwned,
+{
+    let value = to_value(payload).unwrap();
+    let mut group = c.benchmark_group("serialization");
+    group.throughput(Throughput::Bytes(value.as_bytes().len() as u64));
+    group.bench_with_input(
+        BenchmarkId::new("serialize", name),
+        payload,
+        |b, payload| b.iter(|| to_value(payload).unwrap()),
+    );
+    group.bench_with_input(
+        BenchmarkId::new("deserialize", name),
+        &value,
+        |b, value: &Value| b.iter(|| from_value::<T>(value).unwrap()),
+    );
+    group.finish();
+}
+
+fn bench_serialization(c: &mut Criterion) {
+    bench_payload(c, "scalars", &(42i32, 2.5f64, true, u64::MAX));
+    bench_payload(c, "joint_states", &joint_states());
+    bench_payload(c, "image", &image());
+    bench_payload(c, "memory_keys", &memory_keys());
+}
+
+criterion_group!(benches, bench_serialization);
+criterion_main!(benches);
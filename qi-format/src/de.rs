@@ -1,18 +1,257 @@
-use crate::{read, Error, Result, Value};
-use qi_types::Raw;
+use crate::{read, Error, Result, Value, DEFAULT_MAX_DEPTH};
+#[cfg(feature = "arena")]
+use qi_types::{
+    arena::{Arena, ValueRef, ValueRefSeed},
+    Type,
+};
+use qi_types::{
+    ty::{StaticGetType, StructAnnotations, StructCheck},
+    DisplayBytes, Dynamic, Raw,
+};
 use serde::de::IntoDeserializer;
 
 pub fn from_value<'v, T>(value: &'v Value) -> Result<T>
 where
     T: serde::de::Deserialize<'v>,
+{
+    from_value_with_utf8_policy(value, Utf8Policy::default())
+}
+
+pub fn from_value_with_utf8_policy<'v, T>(value: &'v Value, utf8_policy: Utf8Policy) -> Result<T>
+where
+    T: serde::de::Deserialize<'v>,
+{
+    let mut de = Deserializer::from_slice(value.as_bytes()).with_utf8_policy(utf8_policy);
+    T::deserialize(&mut de).map_err(|err| empty_value_error(value, err))
+}
+
+/// Deserializes a value that must be re-encoded exactly as it was received.
+///
+/// The deserialized value is serialized back and compared to the original bytes, an
+/// [`Error::NotByteExact`] is returned if they differ. This is meant for gateways that decode the
+/// values that they relay, so that relaying a value never alters it: the original bytes are
+/// forwarded as is when the value cannot be reproduced.
+///
+/// Most values are reproduced exactly, since maps keep the order of their entries and the
+/// annotations of tuple types are kept with them. The ones that are not, and are always rejected
+/// in this mode, are values with signatures that are not in their canonical form, such as
+/// unknown types, maps with duplicate keys, and strings that are not valid UTF-8.
+pub fn from_value_exact<'v, T>(value: &'v Value) -> Result<T>
+where
+    T: serde::de::Deserialize<'v> + serde::Serialize,
+{
+    let deserialized = from_value_with_utf8_policy(value, Utf8Policy::Strict)?;
+    let reencoded = crate::to_value(&deserialized)?;
+    let (original, reencoded) = (value.as_bytes(), reencoded.as_bytes());
+    if original != reencoded {
+        let offset = original
+            .iter()
+            .zip(reencoded.iter())
+            .position(|(original, reencoded)| original != reencoded)
+            .unwrap_or_else(|| original.len().min(reencoded.len()));
+        return Err(Error::NotByteExact(offset));
+    }
+    Ok(deserialized)
+}
+
+pub fn from_value_seed<'v, S>(value: &'v Value, seed: S) -> Result<S::Value>
+where
+    S: serde::de::DeserializeSeed<'v>,
 {
     let mut de = Deserializer::from_slice(value.as_bytes());
-    T::deserialize(&mut de)
+    seed.deserialize(&mut de)
+        .map_err(|err| empty_value_error(value, err))
 }
 
+/// Conversion of `dynamic` values into a type.
+///
+/// The type of a tuple value may be annotated with the name of the structure it represents and the
+/// names of its fields. Those annotations are not part of the serialized value, a structure with
+/// the same elements is deserialized from it whatever its name. Structures may instead check the
+/// annotations, see [`StructCheck`](qi_types::ty::StructCheck), to catch values of another type
+/// that happen to have the same elements.
+///
+/// This trait is usually derived with `qi::FromValue`.
+pub trait FromValue: serde::de::DeserializeOwned {
+    /// How the annotations of tuple values are checked against those of the structure.
+    const STRUCT_CHECK: StructCheck = StructCheck::Lenient;
+
+    /// The annotations of the structure, or nothing if the type is not a structure.
+    fn struct_annotations() -> Option<StructAnnotations> {
+        None
+    }
+
+    /// Deserializes the type from a value.
+    ///
+    /// Structures with raw fields override it to share the data of the value with those fields
+    /// instead of copying it, see [`FieldsReader`].
+    fn from_value(value: &Value) -> Result<Self> {
+        from_value(value)
+    }
+
+    fn from_dynamic(dynamic: &Dynamic) -> Result<Self> {
+        if let Some(annotations) = Self::struct_annotations() {
+            let actual = match dynamic {
+                Dynamic::Tuple(tuple) => tuple.tuple_type().annotations(),
+                _ => None,
+            };
+            annotations.check(actual.as_ref(), Self::STRUCT_CHECK)?;
+        }
+        let value = crate::to_value(&dynamic.clone().into_value())?;
+        Self::from_value(&value)
+    }
+}
+
+/// Conversion of `dynamic` values into types whose signature is known statically.
+///
+/// Unlike [`FromValue::from_dynamic`], the signature of the value is checked against the type
+/// before the conversion, see [`Dynamic::decode_with_type`]. A value of another type is then
+/// never read as a value of this type, such as the data of an integer as a float.
+pub trait DecodeDynamic {
+    /// Converts the value into a type, using the signature embedded in the value if it is
+    /// complete, or the signature of the type otherwise.
+    ///
+    /// ```
+    /// use qi_format::DecodeDynamic;
+    /// use qi_types::Dynamic;
+    ///
+    /// assert_eq!(Dynamic::from(42i32).decode_as::<i32>().unwrap(), 42);
+    /// assert!(Dynamic::from(42i32).decode_as::<f32>().is_err());
+    /// ```
+    fn decode_as<T>(&self) -> Result<T>
+    where
+        T: serde::de::DeserializeOwned + StaticGetType;
+}
+
+impl DecodeDynamic for Dynamic {
+    fn decode_as<T>(&self) -> Result<T>
+    where
+        T: serde::de::DeserializeOwned + StaticGetType,
+    {
+        let value = self
+            .decode_with_type(&T::static_type())
+            .map_err(Box::new)?
+            .into_value();
+        from_value(&crate::to_value(&value)?)
+    }
+}
+
+/// Reads the fields of a structure one after the other from a value.
+///
+/// Raw fields are read as slices of the data of the value, which is reference counted, so that
+/// large buffers such as images are not copied out of the messages that carry them. This is how
+/// structures deriving `qi::FromValue` read their fields annotated with `#[qi(raw)]`.
+#[derive(Debug)]
+pub struct FieldsReader<'v> {
+    value: &'v Value,
+    de: Deserializer<read::SliceRead<'v>>,
+}
+
+impl<'v> FieldsReader<'v> {
+    pub fn new(value: &'v Value) -> Self {
+        Self {
+            value,
+            de: Deserializer::from_slice(value.as_bytes()),
+        }
+    }
+
+    /// Deserializes the next field.
+    pub fn field<T>(&mut self) -> Result<T>
+    where
+        T: serde::de::Deserialize<'v>,
+    {
+        T::deserialize(&mut self.de).map_err(|err| empty_value_error(self.value, err))
+    }
+
+    /// Reads the next field as raw data, sharing the data of the value.
+    pub fn raw_field(&mut self) -> Result<Raw> {
+        use read::Read;
+        let raw = self
+            .de
+            .reader
+            .read_raw()
+            .map_err(|err| empty_value_error(self.value, err))?;
+        Ok(self.value.as_bytes().slice_ref(raw))
+    }
+}
+
+/// Values of the unit type, such as the reply of a method that returns nothing, are represented
+/// by no data at all. Deserializing any other type from an empty value fails with a dedicated error
+/// instead of the error of the reader.
+fn empty_value_error(value: &Value, err: Error) -> Error {
+    match err {
+        Error::Io(err)
+            if value.as_bytes().is_empty() && err.kind() == std::io::ErrorKind::UnexpectedEof =>
+        {
+            Error::EmptyValue
+        }
+        err => err,
+    }
+}
+
+/// Deserializes a `dynamic` value from bytes, allocating it in an arena.
+///
+/// Strings and raw data of the value are borrowed from the bytes, the rest of the value is
+/// allocated in the arena. This avoids an allocation per element of the value, which makes it
+/// suitable for decoding large values.
+#[cfg(feature = "arena")]
+pub fn from_bytes_in<'a>(arena: &'a Arena, bytes: &'a [u8]) -> Result<ValueRef<'a>> {
+    let value = from_bytes_typed_in(arena, bytes, None)?;
+    // Values deserialized without a type are always dynamic values.
+    Ok(match value {
+        ValueRef::Dynamic(value) => *value,
+        value => value,
+    })
+}
+
+/// Deserializes a value of a type known beforehand from bytes, allocating it in an arena.
+///
+/// See [`from_bytes_in`].
+#[cfg(feature = "arena")]
+pub fn from_bytes_typed_in<'a>(
+    arena: &'a Arena,
+    bytes: &'a [u8],
+    t: Option<&Type>,
+) -> Result<ValueRef<'a>> {
+    use serde::de::DeserializeSeed;
+    let mut de = Deserializer::from_slice(bytes);
+    ValueRefSeed::new(arena, t).deserialize(&mut de)
+}
+
+/// The policy of the deserializer for string data that is not valid UTF-8.
+///
+/// It only applies to values deserialized as strings, raw values are never checked.
 #[derive(Default, Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum Utf8Policy {
+    /// Invalid UTF-8 data is an error.
+    #[default]
+    Strict,
+    /// Invalid UTF-8 sequences are replaced by the replacement character `U+FFFD`.
+    Lossy,
+    /// Invalid UTF-8 data is yielded as bytes, for the visitor to handle as a raw value.
+    Bytes,
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
 pub struct Deserializer<R> {
     reader: R,
+    utf8_policy: Utf8Policy,
+    max_depth: usize,
+    depth: usize,
+}
+
+impl<R> Default for Deserializer<R>
+where
+    R: Default,
+{
+    fn default() -> Self {
+        Self {
+            reader: R::default(),
+            utf8_policy: Utf8Policy::default(),
+            max_depth: DEFAULT_MAX_DEPTH,
+            depth: 0,
+        }
+    }
 }
 
 impl<R> Deserializer<R>
@@ -20,7 +259,26 @@ where
     R: read::Read,
 {
     fn from_reader(reader: R) -> Self {
-        Self { reader }
+        Self {
+            reader,
+            utf8_policy: Utf8Policy::default(),
+            max_depth: DEFAULT_MAX_DEPTH,
+            depth: 0,
+        }
+    }
+
+    pub fn with_utf8_policy(mut self, utf8_policy: Utf8Policy) -> Self {
+        self.utf8_policy = utf8_policy;
+        self
+    }
+
+    /// Sets the maximum number of nested values, such as options, lists, maps or tuples, of the
+    /// deserialized values. Deeper values fail with [`Error::DepthLimitExceeded`].
+    ///
+    /// The default is [`DEFAULT_MAX_DEPTH`].
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
     }
 
     fn as_ref(&mut self) -> &mut Self {
@@ -28,6 +286,32 @@ where
     }
 }
 
+impl<R> Deserializer<R> {
+    /// Enters a value that contains other values, one level deeper.
+    fn enter(&mut self) -> Result<()> {
+        if self.depth >= self.max_depth {
+            return Err(Error::DepthLimitExceeded(self.max_depth));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn leave(&mut self) {
+        self.depth -= 1;
+    }
+
+    /// Deserializes the elements of a value one level deeper.
+    fn nested<T, F>(&mut self, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut Self) -> Result<T>,
+    {
+        self.enter()?;
+        let result = f(self);
+        self.leave();
+        result
+    }
+}
+
 impl<R> Deserializer<read::IoRead<R>>
 where
     R: std::io::Read,
@@ -93,6 +377,33 @@ trait BytesDeserializer<'de> {
     fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
     where
         V: serde::de::Visitor<'de>;
+
+    fn deserialize_str_with_policy<V>(self, policy: Utf8Policy, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>;
+
+    fn deserialize_string_with_policy<V>(self, policy: Utf8Policy, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>;
+}
+
+fn deserialize_invalid_utf8<'de, V>(
+    bytes: &[u8],
+    error: std::str::Utf8Error,
+    policy: Utf8Policy,
+    visitor: V,
+) -> Result<V::Value>
+where
+    V: serde::de::Visitor<'de>,
+{
+    match policy {
+        Utf8Policy::Strict => Err(Error::InvalidStringUtf8(
+            DisplayBytes(bytes).to_string(),
+            error,
+        )),
+        Utf8Policy::Lossy => visitor.visit_string(String::from_utf8_lossy(bytes).into_owned()),
+        Utf8Policy::Bytes => visitor.visit_byte_buf(bytes.to_vec()),
+    }
 }
 
 impl<'de> BytesDeserializer<'de> for &'de [u8] {
@@ -109,6 +420,26 @@ impl<'de> BytesDeserializer<'de> for &'de [u8] {
     {
         visitor.visit_byte_buf(self.to_vec())
     }
+
+    fn deserialize_str_with_policy<V>(self, policy: Utf8Policy, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        match std::str::from_utf8(self) {
+            Ok(str) => str.deserialize_str(visitor),
+            Err(err) => deserialize_invalid_utf8(self, err, policy, visitor),
+        }
+    }
+
+    fn deserialize_string_with_policy<V>(self, policy: Utf8Policy, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        match std::str::from_utf8(self) {
+            Ok(str) => str.deserialize_string(visitor),
+            Err(err) => deserialize_invalid_utf8(self, err, policy, visitor),
+        }
+    }
 }
 
 impl<'de> BytesDeserializer<'de> for Raw {
@@ -125,6 +456,26 @@ impl<'de> BytesDeserializer<'de> for Raw {
     {
         visitor.visit_byte_buf(self.to_vec())
     }
+
+    fn deserialize_str_with_policy<V>(self, policy: Utf8Policy, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        match std::str::from_utf8(&self) {
+            Ok(str) => visitor.visit_str(str),
+            Err(err) => deserialize_invalid_utf8(&self, err, policy, visitor),
+        }
+    }
+
+    fn deserialize_string_with_policy<V>(self, policy: Utf8Policy, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        match std::str::from_utf8(&self) {
+            Ok(str) => visitor.visit_string(str.to_owned()),
+            Err(err) => deserialize_invalid_utf8(&self, err, policy, visitor),
+        }
+    }
 }
 
 impl<'de, R> serde::Deserializer<'de> for &mut Deserializer<R>
@@ -235,16 +586,32 @@ where
     where
         V: serde::de::Visitor<'de>,
     {
-        let str = self.reader.read_str()?;
-        str.deserialize_str(visitor)
+        match self.utf8_policy {
+            Utf8Policy::Strict => {
+                let str = self.reader.read_str()?;
+                str.deserialize_str(visitor)
+            }
+            policy => {
+                let raw = self.reader.read_raw()?;
+                raw.deserialize_str_with_policy(policy, visitor)
+            }
+        }
     }
 
     fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
     where
         V: serde::de::Visitor<'de>,
     {
-        let str = self.reader.read_str()?;
-        str.deserialize_string(visitor)
+        match self.utf8_policy {
+            Utf8Policy::Strict => {
+                let str = self.reader.read_str()?;
+                str.deserialize_string(visitor)
+            }
+            policy => {
+                let raw = self.reader.read_raw()?;
+                raw.deserialize_string_with_policy(policy, visitor)
+            }
+        }
     }
 
     fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
@@ -268,7 +635,7 @@ where
         V: serde::de::Visitor<'de>,
     {
         match self.reader.read_bool()? {
-            true => visitor.visit_some(self),
+            true => self.nested(|de| visitor.visit_some(de)),
             false => visitor.visit_none(),
         }
     }
@@ -294,23 +661,27 @@ where
     where
         V: serde::de::Visitor<'de>,
     {
-        visitor.visit_newtype_struct(self)
+        self.nested(|de| visitor.visit_newtype_struct(de))
     }
 
     fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
     where
         V: serde::de::Visitor<'de>,
     {
-        let access = SequenceAccess::new_list_or_map(self)?;
-        visitor.visit_seq(access)
+        self.nested(|de| {
+            let access = SequenceAccess::new_list_or_map(de)?;
+            visitor.visit_seq(access)
+        })
     }
 
     fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value>
     where
         V: serde::de::Visitor<'de>,
     {
-        let access = SequenceAccess::new_sequence(len, self);
-        visitor.visit_seq(access)
+        self.nested(|de| {
+            let access = SequenceAccess::new_sequence(len, de);
+            visitor.visit_seq(access)
+        })
     }
 
     // equivalence: tuple_struct(T...) -> tuple(T...)
@@ -330,8 +701,10 @@ where
     where
         V: serde::de::Visitor<'de>,
     {
-        let access = SequenceAccess::new_list_or_map(self)?;
-        visitor.visit_map(access)
+        self.nested(|de| {
+            let access = SequenceAccess::new_list_or_map(de)?;
+            visitor.visit_map(access)
+        })
     }
 
     // equivalence: struct(T...) -> tuple(T...)
@@ -379,7 +752,7 @@ where
 impl<'de, R> serde::de::EnumAccess<'de> for &mut Deserializer<R>
 where
     R: read::Read,
-    Self: serde::Deserializer<'de, Error = Error>,
+    for<'d> &'d mut Deserializer<R>: serde::Deserializer<'de, Error = Error>,
 {
     type Error = Error;
     type Variant = Self;
@@ -397,19 +770,19 @@ where
 
 impl<'de, R> serde::de::VariantAccess<'de> for &mut Deserializer<R>
 where
-    Self: serde::Deserializer<'de, Error = Error>,
+    for<'d> &'d mut Deserializer<R>: serde::Deserializer<'de, Error = Error>,
 {
     type Error = Error;
 
     fn unit_variant(self) -> Result<()> {
-        Ok(())
+        self.nested(|_de| Ok(()))
     }
 
     fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
     where
         T: serde::de::DeserializeSeed<'de>,
     {
-        seed.deserialize(self)
+        self.nested(|de| seed.deserialize(de))
     }
 
     fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value>
@@ -541,6 +914,24 @@ mod tests {
     // uses `deserialize_struct/newtype_struct/unit_struct/tuple_struct`.
     // - enums use `deserializer_enum`.
 
+    #[test]
+    fn test_from_value_empty() {
+        let empty = crate::Value::new();
+        assert_matches!(from_value::<()>(&empty), Ok(()));
+        assert_matches!(from_value::<((), ())>(&empty), Ok(((), ())));
+        assert_matches!(from_value::<i32>(&empty), Err(Error::EmptyValue));
+        assert_matches!(from_value::<String>(&empty), Err(Error::EmptyValue));
+        assert_matches!(
+            from_value::<qi_types::Dynamic>(&empty),
+            Err(Error::EmptyValue)
+        );
+        // A value that is not empty but too short is truncated.
+        assert_matches!(
+            from_value::<i32>(&crate::Value::from([1, 2])),
+            Err(Error::Io(_))
+        );
+    }
+
     #[test]
     fn test_deserializer_deserialize_bool() {
         let data = [0, 1, 2];
@@ -780,6 +1171,38 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_deserializer_max_depth() {
+        // A list of options of tuples, with a single element each.
+        let data = [1, 0, 0, 0, 1, 42, 0];
+        let mut deserializer = super::Deserializer::from_slice(&data).with_max_depth(3);
+        assert_matches!(
+            Vec::<Option<(i16,)>>::deserialize(&mut deserializer),
+            Ok(v) => assert_eq!(v, [Some((42,))])
+        );
+        let mut deserializer = super::Deserializer::from_slice(&data).with_max_depth(2);
+        assert_matches!(
+            Vec::<Option<(i16,)>>::deserialize(&mut deserializer),
+            Err(Error::DepthLimitExceeded(2))
+        );
+    }
+
+    #[test]
+    fn test_from_value_max_depth_dynamic() {
+        // A dynamic value that contains a dynamic value, and so on, ending with a unit value. The
+        // depth of the value is only limited by the size of the data.
+        let mut data = Vec::new();
+        for _ in 0..10_000 {
+            data.extend_from_slice(&[1, 0, 0, 0, b'm']);
+        }
+        data.extend_from_slice(&[1, 0, 0, 0, b'v']);
+        let value = crate::Value::from_bytes(data.into());
+        assert_matches!(
+            from_value::<qi_types::Dynamic>(&value),
+            Err(Error::DepthLimitExceeded(crate::DEFAULT_MAX_DEPTH))
+        );
+    }
+
     #[test]
     fn test_deserializer_deserialize_unit() {
         let data = [];
@@ -920,6 +1343,71 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_deserializer_deserialize_str_utf8_policy() {
+        let data = [2, 0, 0, 0, 0xc3, 0x28, 2, 0, 0, 0, 98, 99];
+
+        let mut deserializer = super::Deserializer::from_slice(&data);
+        assert_matches!(
+            deserializer.deserialize_str(ValueVisitor),
+            Err(Error::InvalidStringUtf8(..))
+        );
+
+        let mut deserializer =
+            super::Deserializer::from_slice(&data).with_utf8_policy(Utf8Policy::Lossy);
+        assert_matches!(
+            deserializer.deserialize_str(ValueVisitor),
+            Ok(Value::String(s)) => assert_eq!(s, "\u{fffd}(")
+        );
+        assert_matches!(
+            deserializer.deserialize_str(ValueVisitor),
+            Ok(Value::String(s)) => assert_eq!(s, "bc")
+        );
+
+        let mut deserializer = super::Deserializer::from_io_reader(data.as_slice())
+            .with_utf8_policy(Utf8Policy::Bytes);
+        assert_matches!(
+            deserializer.deserialize_string(ValueVisitor),
+            Ok(Value::Bytes(b)) => assert_eq!(b, [0xc3, 0x28])
+        );
+        assert_matches!(
+            deserializer.deserialize_string(ValueVisitor),
+            Ok(Value::String(s)) => assert_eq!(s, "bc")
+        );
+    }
+
+    #[test]
+    fn test_deserializer_deserialize_str_control_characters() {
+        // A string with embedded NUL bytes, an escape sequence and an invalid byte, as sent by
+        // some services, followed by a dynamic string with a trailing NUL byte.
+        let data = [
+            12, 0, 0, 0, b'n', b'a', b'm', b'e', 0, 0x1b, b'[', b'm', 0, b'a', b'b', 0xff, //
+            1, 0, 0, 0, b's', 2, 0, 0, 0, b'x', 0,
+        ];
+        let value = crate::Value::from(data);
+
+        let mut deserializer =
+            super::Deserializer::from_slice(&data).with_utf8_policy(Utf8Policy::Lossy);
+        assert_matches!(
+            deserializer.deserialize_str(ValueVisitor),
+            Ok(Value::String(s)) => assert_eq!(s, "name\0\u{1b}[m\0ab\u{fffd}")
+        );
+        let dynamic = <qi_types::Dynamic as serde::Deserialize>::deserialize(&mut deserializer);
+        assert_matches!(dynamic, Ok(qi_types::Dynamic::String(s)) => assert_eq!(s, "x\0"));
+
+        // Valid strings are preserved by both kinds of readers, and once serialized again.
+        let valid = &data[16..];
+        let mut deserializer = super::Deserializer::from_io_reader(valid);
+        let dynamic = <qi_types::Dynamic as serde::Deserialize>::deserialize(&mut deserializer);
+        let dynamic = assert_matches!(dynamic, Ok(dynamic) => dynamic);
+        assert_eq!(crate::to_value(&dynamic).unwrap().as_bytes(), valid);
+        assert_eq!(dynamic.to_string(), r"x\u{0}");
+        assert_matches!(
+            from_value_with_utf8_policy::<(String, qi_types::Dynamic)>(&value, Utf8Policy::Lossy),
+            Ok((s, _)) => assert!(s.starts_with("name\0"))
+        );
+    }
+
     #[test]
     fn test_deserializer_deserialize_byte_buf() {
         let data = [1, 0, 0, 0, 97, 2, 0, 0, 0, 98, 99, 0, 0, 0, 0, 3, 0, 0, 0];
@@ -1072,4 +1560,218 @@ mod tests {
             Err(Error::CannotDeserializeAny)
         );
     }
+
+    #[test]
+    fn test_from_value_seed() {
+        use qi_types::{dynamic::DynamicSeed, Dynamic, Number, Type};
+        let value = crate::Value::from([42, 0, 0, 0]);
+        assert_matches!(
+            from_value_seed(&value, DynamicSeed::new(Some(Type::Int32))),
+            Ok(Dynamic::Number(Number::Int32(42)))
+        );
+        assert_matches!(
+            from_value_seed(&value, DynamicSeed::new(Some(Type::UInt8))),
+            Ok(Dynamic::Number(Number::UInt8(42)))
+        );
+    }
+
+    #[cfg(feature = "arena")]
+    #[test]
+    fn test_from_bytes_in() {
+        use qi_types::{arena::ValueRef, list_ty, map_ty, tuple_ty, Number};
+        let t = tuple_ty!(
+            Type::String,
+            list_ty!(Type::Int32),
+            map_ty!(Type::String, Type::Bool),
+            Type::Option(None)
+        );
+        let value = (
+            "hello",
+            vec![1, 2, 3],
+            [("a", true)].into_iter().collect::<qi_types::Map<_, _>>(),
+            Option::<qi_types::Dynamic>::None,
+        );
+        let bytes = crate::to_value(&value).unwrap();
+        let arena = Arena::new();
+
+        let value = from_bytes_typed_in(&arena, bytes.as_bytes(), Some(&t)).unwrap();
+        let elements = value.as_tuple().unwrap();
+        assert_eq!(elements[0], ValueRef::String("hello"));
+        // Strings are borrowed from the input data.
+        assert!(bytes
+            .as_bytes()
+            .as_ptr_range()
+            .contains(&elements[0].as_str().unwrap().as_ptr()));
+        assert_eq!(
+            elements[1],
+            ValueRef::List(&[
+                ValueRef::Number(Number::Int32(1)),
+                ValueRef::Number(Number::Int32(2)),
+                ValueRef::Number(Number::Int32(3)),
+            ])
+        );
+        assert_eq!(
+            elements[2],
+            ValueRef::Map(&[(ValueRef::String("a"), ValueRef::Bool(true))])
+        );
+        assert_eq!(elements[3], ValueRef::Option(None));
+
+        // The owned value is the same as the one deserialized without an arena.
+        let dynamic: qi_types::Dynamic =
+            from_value_seed(&bytes, qi_types::dynamic::DynamicSeed::new(Some(t))).unwrap();
+        assert_eq!(value.to_value(), dynamic.into_value());
+        assert_eq!(value.to_string(), value.to_value().to_string());
+        assert_eq!(qi_types::Value::from(elements[0]), qi_types::Value::from("hello"));
+    }
+
+    #[cfg(feature = "arena")]
+    #[test]
+    fn test_from_bytes_in_dynamic() {
+        use qi_types::{arena::ValueRef, Dynamic, Number};
+        let list = qi_types::Value::List(vec![1u8.into(), 2u8.into()]);
+        let bytes = crate::to_value(&Dynamic::from_value(list)).unwrap();
+        let arena = Arena::new();
+        let value = from_bytes_in(&arena, bytes.as_bytes()).unwrap();
+        assert_eq!(
+            value,
+            ValueRef::List(&[
+                ValueRef::Number(Number::UInt8(1)),
+                ValueRef::Number(Number::UInt8(2)),
+            ])
+        );
+        assert_matches!(
+            from_bytes_typed_in(&arena, bytes.as_bytes(), Some(&Type::Object)),
+            Err(Error::Custom(_))
+        );
+    }
+
+    #[test]
+    fn test_fields_reader_shares_raw_data() {
+        let value = crate::Value::from([
+            1, 0, 0, 0, // x
+            3, 0, 0, 0, 1, 2, 3, // data
+        ]);
+        let mut fields = FieldsReader::new(&value);
+        assert_eq!(fields.field::<i32>().unwrap(), 1);
+        let data = fields.raw_field().unwrap();
+        assert_eq!(data, [1, 2, 3].as_slice());
+        // The raw data points into the data of the value, it is not a copy.
+        assert_eq!(data.as_ptr(), value.as_bytes()[8..].as_ptr());
+        assert_matches!(fields.raw_field(), Err(Error::Io(_)));
+
+        let empty = crate::Value::new();
+        assert_matches!(
+            FieldsReader::new(&empty).raw_field(),
+            Err(Error::EmptyValue)
+        );
+    }
+
+    #[test]
+    fn test_unit_and_empty_tuple_interop() {
+        use qi_types::{tuple_ty, ty::DynamicGetType, Type};
+
+        // The unit value and the empty tuple are both represented by no data at all.
+        assert!(crate::to_value(&()).unwrap().as_bytes().is_empty());
+        assert!(crate::to_value(&qi_types::Tuple::new())
+            .unwrap()
+            .as_bytes()
+            .is_empty());
+        assert_matches!(from_value::<()>(&crate::Value::new()), Ok(()));
+
+        // Dynamic values of either type are accepted as the other.
+        let unit = crate::Value::from(*b"\x01\x00\x00\x00v");
+        let empty_tuple = crate::Value::from(*b"\x02\x00\x00\x00()");
+        for value in [&unit, &empty_tuple] {
+            let value = from_value::<Dynamic>(value).unwrap().into_value();
+            assert!(value.has_type(Some(&Type::Unit)));
+            assert!(value.has_type(Some(&tuple_ty!())));
+        }
+    }
+
+    #[test]
+    fn test_from_dynamic_struct_check() {
+        use qi_types::{
+            struct_ty,
+            ty::{StructAnnotationsError, Type},
+            Tuple,
+        };
+
+        #[derive(serde::Deserialize, PartialEq, Eq, Debug)]
+        struct Point {
+            x: i32,
+            y: i32,
+        }
+
+        impl FromValue for Point {
+            fn struct_annotations() -> Option<StructAnnotations> {
+                Some(StructAnnotations {
+                    name: "Point".to_owned(),
+                    field_names: Some(vec!["x".to_owned(), "y".to_owned()]),
+                })
+            }
+        }
+
+        #[derive(serde::Deserialize, PartialEq, Eq, Debug)]
+        struct StrictPoint {
+            x: i32,
+            y: i32,
+        }
+
+        impl FromValue for StrictPoint {
+            const STRUCT_CHECK: StructCheck = StructCheck::Strict;
+
+            fn struct_annotations() -> Option<StructAnnotations> {
+                Some(StructAnnotations {
+                    name: "Point".to_owned(),
+                    field_names: Some(vec!["x".to_owned(), "y".to_owned()]),
+                })
+            }
+        }
+
+        let dynamic = |t: Type| {
+            Dynamic::new(
+                qi_types::Value::Tuple(Tuple::from_vec(vec![1i32.into(), 2i32.into()])),
+                Some(t),
+            )
+            .unwrap()
+        };
+        let point = dynamic(struct_ty!(Point {
+            x: Type::Int32,
+            y: Type::Int32
+        }));
+        let size = dynamic(struct_ty!(Size {
+            width: Type::Int32,
+            height: Type::Int32
+        }));
+        let renamed = dynamic(struct_ty!(Point {
+            y: Type::Int32,
+            x: Type::Int32
+        }));
+        let tuple = dynamic(qi_types::tuple_ty!(Type::Int32, Type::Int32));
+
+        // Lenient structures ignore the annotations.
+        for value in [&point, &size, &renamed, &tuple] {
+            assert_eq!(Point::from_dynamic(value).unwrap(), Point { x: 1, y: 2 });
+        }
+
+        assert_eq!(
+            StrictPoint::from_dynamic(&point).unwrap(),
+            StrictPoint { x: 1, y: 2 }
+        );
+        assert_matches!(
+            StrictPoint::from_dynamic(&size),
+            Err(Error::StructAnnotations(StructAnnotationsError::NameMismatch { expected, actual }))
+                if expected == "Point" && actual == "Size"
+        );
+        assert_matches!(
+            StrictPoint::from_dynamic(&renamed),
+            Err(Error::StructAnnotations(
+                StructAnnotationsError::FieldNamesMismatch { .. }
+            ))
+        );
+        assert_matches!(
+            StrictPoint::from_dynamic(&tuple),
+            Err(Error::StructAnnotations(StructAnnotationsError::Missing(name))) if name == "Point"
+        );
+    }
 }
@@ -3,6 +3,13 @@
 #![doc(test(attr(deny(warnings))))]
 #![doc = include_str!("../README.md")]
 
+/// The maximum number of nested values, such as options, lists, maps or tuples, of the values
+/// that are serialized or deserialized, unless configured otherwise.
+///
+/// Values are received from remote peers, their depth is limited so that their deserialization
+/// does not exhaust the stack.
+pub const DEFAULT_MAX_DEPTH: usize = 128;
+
 const FALSE_BOOL: u8 = 0;
 const TRUE_BOOL: u8 = 1;
 
@@ -14,19 +21,27 @@ mod read;
 
 mod write;
 
+pub mod wire;
+
 pub mod ser;
 #[doc(inline)]
 pub use ser::{to_value, Serializer};
 
 pub mod de;
+#[cfg(feature = "arena")]
 #[doc(inline)]
-pub use de::{from_value, Deserializer};
+pub use de::{from_bytes_in, from_bytes_typed_in};
+#[doc(inline)]
+pub use de::{from_value, DecodeDynamic, Deserializer, FieldsReader, FromValue, Utf8Policy};
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
     #[error("input/output error")]
     Io(#[from] std::io::Error),
 
+    #[error("the value is empty, which only represents a value of the unit type")]
+    EmptyValue,
+
     #[error("the value '{0}' is not a `bool` value")]
     NotABoolValue(u8),
 
@@ -42,9 +57,22 @@ pub enum Error {
     #[error("expected {0} elements, got one more")]
     UnexpectedElement(usize),
 
+    #[error("the value exceeds the maximum depth of {0} nested values")]
+    DepthLimitExceeded(usize),
+
+    // Boxed, as the signatures of the error would otherwise grow all results of deserialization.
+    #[error(transparent)]
+    DynamicDecode(#[from] Box<qi_types::dynamic::DecodeError>),
+
+    #[error(transparent)]
+    StructAnnotations(#[from] qi_types::ty::StructAnnotationsError),
+
     #[error("string data \"{0}\" is not valid UTF-8")]
     InvalidStringUtf8(String, #[source] std::str::Utf8Error),
 
+    #[error("the value is not re-encoded identically, it differs from byte {0}")]
+    NotByteExact(usize),
+
     #[error("{0}")]
     Custom(std::string::String),
 }
@@ -159,9 +159,21 @@ where
     }
 
     fn read_raw(&mut self) -> Result<Self::Raw> {
+        use std::io::Read;
         let size = self.read_size()?;
-        let mut buf = vec![0; size];
-        self.reader.read_exact(&mut buf)?;
+        // The size comes from the input, it is not trusted to allocate the buffer upfront.
+        let mut buf = Vec::new();
+        let read_size = self
+            .reader
+            .by_ref()
+            .take(size as u64)
+            .read_to_end(&mut buf)?;
+        if read_size < size {
+            return Err(Error::Io(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                format!("data length inconsistent with raw/string size (expected {size}, found only {read_size})"),
+            )));
+        }
         Ok(Raw::from(buf))
     }
 
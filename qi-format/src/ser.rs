@@ -1,4 +1,4 @@
-use crate::{write::*, Error, Result, Value};
+use crate::{write::*, Error, Result, Value, DEFAULT_MAX_DEPTH};
 use bytes::{BufMut, BytesMut};
 
 fn to_writer<W, T>(writer: W, value: &T) -> Result<()>
@@ -20,9 +20,24 @@ where
     Ok(Value::from_bytes(writer.into_inner().freeze()))
 }
 
-#[derive(Default, Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+#[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
 pub struct Serializer<W> {
     writer: W,
+    max_depth: usize,
+    depth: usize,
+}
+
+impl<W> Default for Serializer<W>
+where
+    W: Default,
+{
+    fn default() -> Self {
+        Self {
+            writer: W::default(),
+            max_depth: DEFAULT_MAX_DEPTH,
+            depth: 0,
+        }
+    }
 }
 
 impl<W> Serializer<W>
@@ -30,7 +45,33 @@ where
     W: std::io::Write,
 {
     pub fn from_writer(writer: W) -> Self {
-        Self { writer }
+        Self {
+            writer,
+            max_depth: DEFAULT_MAX_DEPTH,
+            depth: 0,
+        }
+    }
+
+    /// Sets the maximum number of nested values, such as options, lists, maps or tuples, of the
+    /// serialized values. Deeper values fail with [`Error::DepthLimitExceeded`].
+    ///
+    /// The default is [`DEFAULT_MAX_DEPTH`].
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Enters a value that contains other values, one level deeper.
+    fn enter(&mut self) -> Result<()> {
+        if self.depth >= self.max_depth {
+            return Err(Error::DepthLimitExceeded(self.max_depth));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn leave(&mut self) {
+        self.depth -= 1;
     }
 }
 
@@ -120,7 +161,10 @@ where
         T: serde::Serialize,
     {
         write_bool(self.writer.by_ref(), true)?;
-        value.serialize(self)
+        self.enter()?;
+        value.serialize(&mut *self)?;
+        self.leave();
+        Ok(())
     }
 
     // sequence -> list
@@ -143,7 +187,7 @@ where
 
     // tuple -> tuple
     fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
-        Ok(SeqSerializer::new_tuple(self, len))
+        SeqSerializer::new_tuple(self, len)
     }
 
     // equivalence: tuple_struct(T...) -> tuple(T...)
@@ -245,6 +289,11 @@ where
 {
     fn new_list_or_map(serializer: &'s mut Serializer<W>, size: usize) -> Result<Self> {
         write_size(serializer.writer.by_ref(), size)?;
+        Self::new_tuple(serializer, size)
+    }
+
+    fn new_tuple(serializer: &'s mut Serializer<W>, size: usize) -> Result<Self> {
+        serializer.enter()?;
         Ok(Self {
             serializer,
             size,
@@ -252,12 +301,9 @@ where
         })
     }
 
-    fn new_tuple(serializer: &'s mut Serializer<W>, size: usize) -> Self {
-        Self {
-            serializer,
-            size,
-            elements_left: size,
-        }
+    fn end(self) -> Result<()> {
+        self.serializer.leave();
+        Ok(())
     }
 
     fn try_decr_elements_left(&mut self) -> Result<()> {
@@ -292,7 +338,7 @@ where
     }
 
     fn end(self) -> Result<Self::Ok> {
-        Ok(())
+        SeqSerializer::end(self)
     }
 }
 
@@ -319,7 +365,7 @@ where
     }
 
     fn end(self) -> Result<Self::Ok> {
-        Ok(())
+        SeqSerializer::end(self)
     }
 }
 
@@ -339,7 +385,7 @@ where
     }
 
     fn end(self) -> Result<Self::Ok> {
-        Ok(())
+        SeqSerializer::end(self)
     }
 }
 
@@ -359,7 +405,7 @@ where
     }
 
     fn end(self) -> Result<Self::Ok> {
-        Ok(())
+        SeqSerializer::end(self)
     }
 }
 
@@ -379,7 +425,7 @@ where
     }
 
     fn end(self) -> Result<Self::Ok> {
-        Ok(())
+        SeqSerializer::end(self)
     }
 }
 
@@ -399,7 +445,7 @@ where
     }
 
     fn end(self) -> Result<Self::Ok> {
-        Ok(())
+        SeqSerializer::end(self)
     }
 }
 
@@ -419,7 +465,7 @@ where
     }
 
     fn end(self) -> Result<Self::Ok> {
-        Ok(())
+        SeqSerializer::end(self)
     }
 }
 
@@ -556,12 +602,31 @@ mod tests {
         assert_eq!(buf, [0]);
     }
 
+    #[test]
+    fn test_serializer_max_depth() {
+        use serde::Serialize;
+        let value = vec![Some((42i16,))];
+        let mut buf = Vec::new();
+        let mut serializer = super::Serializer::from_writer(&mut buf).with_max_depth(3);
+        value.serialize(&mut serializer).unwrap();
+        assert_eq!(buf, [1, 0, 0, 0, 1, 42, 0]);
+
+        let mut serializer = super::Serializer::from_writer(Vec::new()).with_max_depth(2);
+        assert_matches!(
+            value.serialize(&mut serializer),
+            Err(Error::DepthLimitExceeded(2))
+        );
+        // Sibling values are at the same depth.
+        let mut serializer = super::Serializer::from_writer(Vec::new()).with_max_depth(2);
+        assert_matches!(((1, 2), (3, 4)).serialize(&mut serializer), Ok(()));
+    }
+
     #[test]
     fn test_serializer_serialize_unit() {
         let mut buf = Vec::new();
         let mut serializer = super::Serializer::from_writer(&mut buf);
         serializer.serialize_unit().unwrap();
-        assert_eq!(buf, []);
+        assert_eq!(buf, [] as [u8; 0]);
     }
 
     #[test]
@@ -690,7 +755,7 @@ mod tests {
         let mut buf = Vec::new();
         let mut serializer = super::Serializer::from_writer(&mut buf);
         serializer.serialize_unit_struct("MyStruct").unwrap();
-        assert_eq!(buf, []);
+        assert_eq!(buf, [] as [u8; 0]);
     }
 
     #[test]
@@ -1,4 +1,4 @@
-use crate::{from_value, to_value, Result};
+use crate::{de, from_value, to_value, Result, Utf8Policy};
 use bytes::Bytes;
 
 /// A formatted `qi` value.
@@ -40,6 +40,22 @@ impl Value {
     {
         from_value(self)
     }
+
+    /// Deserializes the value, checking that it is re-encoded identically, see
+    /// [`de::from_value_exact`].
+    pub fn to_deserializable_exact<'v, T>(&'v self) -> Result<T>
+    where
+        T: serde::Deserialize<'v> + serde::Serialize,
+    {
+        de::from_value_exact(self)
+    }
+
+    pub fn to_deserializable_with_utf8_policy<'v, T>(&'v self, utf8_policy: Utf8Policy) -> Result<T>
+    where
+        T: serde::Deserialize<'v>,
+    {
+        de::from_value_with_utf8_policy(self, utf8_policy)
+    }
 }
 
 #[doc(hidden)]
@@ -0,0 +1,41 @@
+//! The encoding of the primitives of the `qi` format: booleans, numbers, sizes, strings and raw
+//! buffers.
+//!
+//! The serializer and the deserializer of the crate are built upon these primitives. They are
+//! exposed for tools that handle the format at a lower level, such as fuzzers, analyzers of
+//! captured messages or bridges to other protocols, so that they reuse the exact same encoding
+//! instead of reimplementing it.
+//!
+//! Values are written with functions that take any [`std::io::Write`], and read with the methods
+//! of the [`Read`] trait, implemented by [`SliceRead`] for data in memory and by [`IoRead`] for
+//! any [`std::io::Read`].
+//!
+//! # Stability
+//!
+//! The encoding of the primitives is defined by the specification of the format, it does not
+//! change. The items of this module follow the semantic versioning of the crate. The [`Read`]
+//! trait is sealed, so that methods may be added to it in a minor version.
+//!
+//! # Example
+//!
+//! ```
+//! use qi_format::wire::{self, Read};
+//!
+//! let mut data = Vec::new();
+//! wire::write_u32(&mut data, 42).unwrap();
+//! wire::write_str(&mut data, "hello").unwrap();
+//! assert_eq!(data, [42, 0, 0, 0, 5, 0, 0, 0, b'h', b'e', b'l', b'l', b'o']);
+//!
+//! let mut reader = wire::SliceRead::new(&data);
+//! assert_eq!(reader.read_u32().unwrap(), 42);
+//! assert_eq!(reader.read_str().unwrap(), "hello");
+//! ```
+
+#[doc(inline)]
+pub use crate::read::{IoRead, Read, SliceRead};
+#[doc(inline)]
+pub use crate::write::{
+    write_bool, write_byte, write_dword, write_f32, write_f64, write_i16, write_i32, write_i64,
+    write_i8, write_qword, write_raw, write_size, write_str, write_u16, write_u32, write_u64,
+    write_u8, write_word,
+};
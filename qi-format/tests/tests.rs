@@ -502,3 +502,208 @@ fn test_object_to_from_value() {
     let value_out = to_value(&object).unwrap();
     assert_eq!(value_in, value_out);
 }
+
+/// A deterministic generator of pseudo-random numbers (xorshift), so that failures are
+/// reproducible.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+
+    fn name(&mut self) -> std::string::String {
+        let len = 1 + self.below(8);
+        (0..len)
+            .map(|_| char::from(b'a' + self.below(26) as u8))
+            .collect()
+    }
+
+    fn ty(&mut self, depth: usize) -> Option<Type> {
+        use qi_types::ty::{StructField, TupleType};
+        let composite = if depth == 0 { 0 } else { 7 };
+        let t = match self.below(15 + composite) {
+            0 => return None,
+            1 => Type::Unit,
+            2 => Type::Bool,
+            3 => Type::Int8,
+            4 => Type::UInt8,
+            5 => Type::Int16,
+            6 => Type::UInt16,
+            7 => Type::Int32,
+            8 => Type::UInt32,
+            9 => Type::Int64,
+            10 => Type::UInt64,
+            11 => Type::Float32,
+            12 => Type::Float64,
+            13 => Type::String,
+            14 => Type::Raw,
+            15 | 16 => Type::Option(self.ty(depth - 1).map(Box::new)),
+            17 | 18 => Type::List(self.ty(depth - 1).map(Box::new)),
+            19 => Type::Map {
+                key: self.ty(depth - 1).map(Box::new),
+                value: self.ty(depth - 1).map(Box::new),
+            },
+            _ => {
+                let elements: Vec<_> = (0..self.below(4)).map(|_| self.ty(depth - 1)).collect();
+                Type::Tuple(match self.below(3) {
+                    0 => TupleType::Tuple(elements),
+                    1 => TupleType::TupleStruct(self.name(), elements),
+                    _ => TupleType::Struct(
+                        self.name(),
+                        elements
+                            .into_iter()
+                            .map(|value_type| StructField {
+                                name: self.name(),
+                                value_type,
+                            })
+                            .collect(),
+                    ),
+                })
+            }
+        };
+        Some(t)
+    }
+
+    fn value(&mut self, t: Option<&Type>, depth: usize) -> Value {
+        use qi_types::{Map, Number, Tuple};
+        let t = match t {
+            Some(t) => t,
+            None => {
+                // The type of values that contain dynamic values may not be checked, other
+                // values are generated until one is.
+                loop {
+                    let t = self.ty(depth);
+                    let value = self.value(t.as_ref(), depth);
+                    if let Ok(dynamic) = Dynamic::new(value, t) {
+                        return Value::Dynamic(Box::new(dynamic));
+                    }
+                }
+            }
+        };
+        match t {
+            Type::Unit => Value::Unit,
+            Type::Bool => Value::Bool(self.below(2) == 1),
+            Type::Int8 => Value::Number(Number::Int8(self.next() as i8)),
+            Type::UInt8 => Value::Number(Number::UInt8(self.next() as u8)),
+            Type::Int16 => Value::Number(Number::Int16(self.next() as i16)),
+            Type::UInt16 => Value::Number(Number::UInt16(self.next() as u16)),
+            Type::Int32 => Value::Number(Number::Int32(self.next() as i32)),
+            Type::UInt32 => Value::Number(Number::UInt32(self.next() as u32)),
+            Type::Int64 => Value::Number(Number::Int64(self.next() as i64)),
+            Type::UInt64 => Value::Number(Number::UInt64(self.next())),
+            Type::Float32 => Value::from(Number::from(f32::from_bits(self.next() as u32))),
+            Type::Float64 => Value::from(Number::from(f64::from_bits(self.next()))),
+            Type::String => Value::String(self.name()),
+            Type::Raw => Value::Raw(self.name().into_bytes().into()),
+            Type::Object => unreachable!("objects are not generated"),
+            Type::Option(t) => Value::Option(Box::new(
+                (self.below(2) == 1).then(|| self.value(t.as_deref(), depth - 1)),
+            )),
+            Type::List(t) | Type::VarArgs(t) => (0..self.below(4))
+                .map(|_| self.value(t.as_deref(), depth - 1))
+                .collect::<Vec<_>>()
+                .into(),
+            Type::Map { key, value } => {
+                // Keys are not sorted, so that their order is checked. Duplicate keys are not
+                // representable by maps, they are skipped.
+                let mut map = Map::new();
+                for _ in 0..self.below(4) {
+                    let key = self.value(key.as_deref(), depth - 1);
+                    let value = self.value(value.as_deref(), depth - 1);
+                    if map.get(&key).is_none() {
+                        map.insert(key, value);
+                    }
+                }
+                Value::Map(map)
+            }
+            Type::Tuple(tuple) => Value::Tuple(Tuple::from_vec(
+                tuple
+                    .element_types()
+                    .iter()
+                    .map(|t| self.value(t.as_ref(), depth - 1))
+                    .collect(),
+            )),
+        }
+    }
+}
+
+#[test]
+fn test_from_value_exact_reencodes_identically() {
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+    for _ in 0..2000 {
+        let t = rng.ty(3);
+        let value = rng.value(t.as_ref(), 3);
+        // Serialized as a dynamic value, without checking that the value has the type.
+        let value_in = to_value(&(Signature::new(t), &value)).unwrap();
+        let dynamic: Dynamic = qi_format::de::from_value_exact(&value_in).unwrap();
+        let value_out = to_value(&dynamic).unwrap();
+        assert_eq!(
+            value_in, value_out,
+            "value {value:?} is not re-encoded identically"
+        );
+    }
+}
+
+#[test]
+fn test_from_value_exact_rejects_non_canonical_values() {
+    // A map of signature "{ii}" with the duplicate key 1.
+    let duplicate_keys = [
+        0x04, 0x00, 0x00, 0x00, b'{', b'i', b'i', b'}', 0x02, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00,
+        0x00, 0x02, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00,
+    ]
+    .into();
+    assert!(from_value::<Dynamic>(&duplicate_keys).is_ok());
+    assert_matches::assert_matches!(
+        qi_format::de::from_value_exact::<Dynamic>(&duplicate_keys),
+        Err(qi_format::Error::NotByteExact(8))
+    );
+
+    // A list of unknown type "[X]", whose type is re-encoded as dynamic.
+    let unknown_type = [
+        0x03, 0x00, 0x00, 0x00, b'[', b'X', b']', 0x00, 0x00, 0x00, 0x00,
+    ]
+    .into();
+    assert_matches::assert_matches!(
+        qi_format::de::from_value_exact::<Dynamic>(&unknown_type),
+        Err(qi_format::Error::NotByteExact(5))
+    );
+}
+
+#[test]
+fn test_dynamic_decode_as() {
+    use qi_format::{DecodeDynamic, Error};
+    use qi_types::dynamic::DecodeError;
+
+    let number = Dynamic::from_value(Value::from(qi_types::Number::Int32(42)));
+    assert_eq!(number.decode_as::<i32>().unwrap(), 42);
+    assert_eq!(
+        Dynamic::Dynamic(Box::new(number.clone()))
+            .decode_as::<i32>()
+            .unwrap(),
+        42
+    );
+    // The data of the integer would otherwise be read as a float.
+    assert_matches::assert_matches!(
+        number.decode_as::<f32>(),
+        Err(Error::DynamicDecode(err)) if matches!(*err, DecodeError::SignatureMismatch { .. })
+    );
+
+    // The signature of the type is used if the value has none for its elements.
+    let unsigned = Dynamic::from_value(Value::List(vec![Value::from("a"), Value::from("b")]));
+    assert_eq!(
+        unsigned.decode_as::<Vec<std::string::String>>().unwrap(),
+        ["a", "b"]
+    );
+    assert_matches::assert_matches!(
+        unsigned.decode_as::<Option<std::string::String>>(),
+        Err(Error::DynamicDecode(err)) if matches!(*err, DecodeError::SignatureMismatch { .. })
+    );
+}
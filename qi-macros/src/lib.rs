@@ -0,0 +1,504 @@
+#![deny(unreachable_pub, unsafe_code)]
+// TODO: #![deny(missing_docs)]
+#![warn(unused_crate_dependencies)]
+#![warn(
+    clippy::all,
+    clippy::clone_on_ref_ptr,
+    clippy::dbg_macro,
+    clippy::decimal_literal_representation,
+    clippy::empty_drop,
+    clippy::empty_structs_with_brackets,
+    clippy::exit,
+    clippy::float_cmp_const,
+    clippy::format_push_string,
+    clippy::get_unwrap,
+    clippy::if_then_some_else_none,
+    clippy::integer_division,
+    clippy::large_include_file,
+    clippy::let_underscore_must_use,
+    clippy::lossy_float_literal,
+    clippy::map_err_ignore,
+    clippy::mem_forget,
+    clippy::mixed_read_write_in_expression,
+    clippy::multiple_inherent_impl,
+    clippy::mutex_atomic,
+    clippy::panic,
+    clippy::print_stderr,
+    clippy::print_stdout,
+    clippy::rc_buffer,
+    clippy::rc_mutex,
+    clippy::rest_pat_in_fully_bound_structs,
+    clippy::same_name_method,
+    clippy::mod_module_files,
+    clippy::str_to_string,
+    clippy::string_slice,
+    clippy::string_to_string,
+    clippy::todo,
+    clippy::try_err,
+    clippy::unimplemented,
+    clippy::unnecessary_self_imports,
+    clippy::unneeded_field_pattern,
+    clippy::use_debug
+)]
+// Deny warnings in doc test.
+#![doc(test(attr(deny(warnings))))]
+#![doc = include_str!("../README.md")]
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{ext::IdentExt, parse_macro_input, Data, DeriveInput, Fields, ItemFn};
+
+/// Runs an asynchronous function as the entry point of an application connected to a namespace.
+///
+/// See the documentation of the re-export in `qi`.
+#[proc_macro_attribute]
+pub fn main(
+    args: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let function = parse_macro_input!(item as ItemFn);
+    expand_main(args.into(), function)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand_main(args: TokenStream, function: ItemFn) -> syn::Result<TokenStream> {
+    if !args.is_empty() {
+        return Err(syn::Error::new_spanned(
+            args,
+            "the `qi::main` attribute takes no argument",
+        ));
+    }
+    let signature = &function.sig;
+    if signature.asyncness.is_none() {
+        return Err(syn::Error::new_spanned(
+            signature.fn_token,
+            "the function annotated with `qi::main` must be async",
+        ));
+    }
+    if !signature.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &signature.generics,
+            "the function annotated with `qi::main` cannot be generic",
+        ));
+    }
+    if signature.inputs.len() != 1 {
+        return Err(syn::Error::new_spanned(
+            &signature.ident,
+            "the function annotated with `qi::main` must take the connected `qi::Node` as its \
+             only argument",
+        ));
+    }
+
+    // The function is kept as is, nested in a synchronous function of the same name.
+    let attrs = &function.attrs;
+    let visibility = &function.vis;
+    let name = &signature.ident;
+    let output = &signature.output;
+    let inner_name = format_ident!("__qi_main_{}", name);
+    let mut inner = function.clone();
+    inner.attrs.clear();
+    inner.vis = syn::Visibility::Inherited;
+    inner.sig.ident = inner_name.clone();
+    Ok(quote! {
+        #(#attrs)*
+        #visibility fn #name() #output {
+            #inner
+            ::qi::application::run(#inner_name)
+        }
+    })
+}
+
+/// Derives the conversion of `dynamic` values into a structure.
+///
+/// See the documentation of the re-export in `qi`.
+#[proc_macro_derive(FromValue, attributes(qi))]
+pub fn derive_from_value(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_from_value(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand_from_value(input: DeriveInput) -> syn::Result<TokenStream> {
+    let mut check = format_ident!("Lenient");
+    for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("qi")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("strict") {
+                check = format_ident!("Strict");
+                Ok(())
+            } else if meta.path.is_ident("lenient") {
+                check = format_ident!("Lenient");
+                Ok(())
+            } else {
+                Err(meta.error("expected `strict` or `lenient`"))
+            }
+        })?;
+    }
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        Data::Enum(_) | Data::Union(_) => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "`qi::FromValue` can only be derived for structures",
+            ))
+        }
+    };
+    let field_names = match fields {
+        Fields::Named(fields) => {
+            let names = fields
+                .named
+                .iter()
+                .filter_map(|field| field.ident.as_ref())
+                .map(|ident| ident.unraw().to_string());
+            quote! {
+                ::std::option::Option::Some(::std::vec![
+                    #(::std::string::ToString::to_string(#names)),*
+                ])
+            }
+        }
+        Fields::Unnamed(_) | Fields::Unit => quote!(::std::option::Option::None),
+    };
+    let from_value = expand_from_value_fields(fields)?;
+    let ident = &input.ident;
+    let name = ident.unraw().to_string();
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::qi::wire::FromValue for #ident #type_generics #where_clause {
+            const STRUCT_CHECK: ::qi::types::ty::StructCheck = ::qi::types::ty::StructCheck::#check;
+
+            fn struct_annotations() -> ::std::option::Option<::qi::types::ty::StructAnnotations> {
+                ::std::option::Option::Some(::qi::types::ty::StructAnnotations {
+                    name: ::std::string::ToString::to_string(#name),
+                    field_names: #field_names,
+                })
+            }
+
+            #from_value
+        }
+    })
+}
+
+/// Reads the fields one after the other from the value when some of them are raw, so that they
+/// share its data. Otherwise the structure is deserialized as usual.
+fn expand_from_value_fields(fields: &Fields) -> syn::Result<TokenStream> {
+    let mut has_raw_fields = false;
+    let mut reads = Vec::new();
+    for field in fields {
+        let mut is_raw = false;
+        for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("qi")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("raw") {
+                    is_raw = true;
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `raw`"))
+                }
+            })?;
+        }
+        has_raw_fields |= is_raw;
+        reads.push(if is_raw {
+            quote!(__fields.raw_field()?)
+        } else {
+            quote!(__fields.field()?)
+        });
+    }
+    if !has_raw_fields {
+        return Ok(TokenStream::new());
+    }
+    let construct = match fields {
+        Fields::Named(fields) => {
+            let names = fields.named.iter().filter_map(|field| field.ident.as_ref());
+            quote!(Self { #(#names: #reads),* })
+        }
+        Fields::Unnamed(_) => quote!(Self(#(#reads),*)),
+        Fields::Unit => quote!(Self),
+    };
+    Ok(quote! {
+        fn from_value(value: &::qi::wire::Value) -> ::qi::wire::Result<Self> {
+            let mut __fields = ::qi::wire::FieldsReader::new(value);
+            ::std::result::Result::Ok(#construct)
+        }
+    })
+}
+
+/// Derives the configuration of the signals of an object, for an enumeration of which each
+/// variant is a signal.
+///
+/// See the documentation of the re-export in `qi`.
+#[proc_macro_derive(Signals, attributes(qi))]
+pub fn derive_signals(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_signals(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand_signals(input: DeriveInput) -> syn::Result<TokenStream> {
+    let variants = match &input.data {
+        Data::Enum(data) => &data.variants,
+        Data::Struct(_) | Data::Union(_) => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "`qi::Signals` can only be derived for enumerations",
+            ))
+        }
+    };
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "`qi::Signals` cannot be derived for generic enumerations",
+        ));
+    }
+    let mut names = Vec::new();
+    let mut actions = Vec::new();
+    let mut replay_capacities = Vec::new();
+    for variant in variants {
+        if !matches!(variant.fields, Fields::Unit) {
+            return Err(syn::Error::new_spanned(
+                &variant.fields,
+                "the variants of `qi::Signals` cannot have fields",
+            ));
+        }
+        let mut action = None;
+        let mut replay_capacity = None;
+        for attr in variant
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("qi"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("action") {
+                    action = Some(
+                        meta.value()?
+                            .parse::<syn::LitInt>()?
+                            .base10_parse::<u32>()?,
+                    );
+                    Ok(())
+                } else if meta.path.is_ident("replay") {
+                    replay_capacity = Some(
+                        meta.value()?
+                            .parse::<syn::LitInt>()?
+                            .base10_parse::<usize>()?,
+                    );
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `action` or `replay`"))
+                }
+            })?;
+        }
+        let action = action.ok_or_else(|| {
+            syn::Error::new_spanned(
+                &variant.ident,
+                "the signal must have an action, for instance `#[qi(action = 100)]`",
+            )
+        })?;
+        names.push(&variant.ident);
+        actions.push(action);
+        replay_capacities.push(replay_capacity.unwrap_or(0));
+    }
+    let ident = &input.ident;
+    Ok(quote! {
+        impl ::qi::object::signal::Signals for #ident {
+            const ALL: &'static [Self] = &[#(Self::#names),*];
+
+            fn action(&self) -> ::qi::types::object::ActionId {
+                match self {
+                    #(Self::#names => ::qi::types::object::ActionId::new(#actions),)*
+                }
+            }
+
+            fn replay_capacity(&self) -> usize {
+                match self {
+                    #(Self::#names => #replay_capacities,)*
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    #[test]
+    fn test_expand_main() {
+        let function: ItemFn = parse_quote! {
+            /// The entry point.
+            async fn main(node: qi::Node) -> Result<(), Error> {
+                node.do_something().await
+            }
+        };
+        let expanded = expand_main(TokenStream::new(), function).unwrap();
+        let expected = quote! {
+            /// The entry point.
+            fn main() -> Result<(), Error> {
+                async fn __qi_main_main(node: qi::Node) -> Result<(), Error> {
+                    node.do_something().await
+                }
+                ::qi::application::run(__qi_main_main)
+            }
+        };
+        assert_eq!(expanded.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn test_expand_main_errors() {
+        let not_async: ItemFn = parse_quote! {
+            fn main(node: qi::Node) {}
+        };
+        assert!(expand_main(TokenStream::new(), not_async).is_err());
+
+        let no_node: ItemFn = parse_quote! {
+            async fn main() {}
+        };
+        assert!(expand_main(TokenStream::new(), no_node).is_err());
+
+        let valid: ItemFn = parse_quote! {
+            async fn main(node: qi::Node) {}
+        };
+        assert!(expand_main(quote!(flavor = "current_thread"), valid).is_err());
+    }
+
+    #[test]
+    fn test_expand_from_value() {
+        let input: DeriveInput = parse_quote! {
+            #[derive(Deserialize)]
+            #[qi(strict)]
+            struct Point {
+                x: i32,
+                r#y: i32,
+            }
+        };
+        let expanded = expand_from_value(input).unwrap();
+        let expected = quote! {
+            impl ::qi::wire::FromValue for Point {
+                const STRUCT_CHECK: ::qi::types::ty::StructCheck = ::qi::types::ty::StructCheck::Strict;
+
+                fn struct_annotations() -> ::std::option::Option<::qi::types::ty::StructAnnotations> {
+                    ::std::option::Option::Some(::qi::types::ty::StructAnnotations {
+                        name: ::std::string::ToString::to_string("Point"),
+                        field_names: ::std::option::Option::Some(::std::vec![
+                            ::std::string::ToString::to_string("x"),
+                            ::std::string::ToString::to_string("y")
+                        ]),
+                    })
+                }
+            }
+        };
+        assert_eq!(expanded.to_string(), expected.to_string());
+
+        let input: DeriveInput = parse_quote! {
+            struct Size<T>(T, T);
+        };
+        let expanded = expand_from_value(input).unwrap().to_string();
+        assert!(expanded.contains(&quote!(StructCheck::Lenient).to_string()));
+        assert!(expanded.contains(&quote!(field_names: ::std::option::Option::None).to_string()));
+    }
+
+    #[test]
+    fn test_expand_from_value_raw_fields() {
+        let input: DeriveInput = parse_quote! {
+            struct Image {
+                width: u32,
+                #[qi(raw)]
+                data: Bytes,
+            }
+        };
+        let expanded = expand_from_value(input).unwrap().to_string();
+        let expected = quote! {
+            fn from_value(value: &::qi::wire::Value) -> ::qi::wire::Result<Self> {
+                let mut __fields = ::qi::wire::FieldsReader::new(value);
+                ::std::result::Result::Ok(Self {
+                    width: __fields.field()?,
+                    data: __fields.raw_field()?
+                })
+            }
+        };
+        assert!(expanded.contains(&expected.to_string()));
+
+        let input: DeriveInput = parse_quote! {
+            struct Frame(u32, #[qi(raw)] Bytes);
+        };
+        let expanded = expand_from_value(input).unwrap().to_string();
+        assert!(
+            expanded.contains(&quote!(Self(__fields.field()?, __fields.raw_field()?)).to_string())
+        );
+    }
+
+    #[test]
+    fn test_expand_signals() {
+        let input: DeriveInput = parse_quote! {
+            enum MotionSignal {
+                #[qi(action = 100, replay = 1)]
+                Posture,
+                #[qi(action = 101)]
+                Moved,
+            }
+        };
+        let expanded = expand_signals(input).unwrap();
+        let expected = quote! {
+            impl ::qi::object::signal::Signals for MotionSignal {
+                const ALL: &'static [Self] = &[Self::Posture, Self::Moved];
+
+                fn action(&self) -> ::qi::types::object::ActionId {
+                    match self {
+                        Self::Posture => ::qi::types::object::ActionId::new(100u32),
+                        Self::Moved => ::qi::types::object::ActionId::new(101u32),
+                    }
+                }
+
+                fn replay_capacity(&self) -> usize {
+                    match self {
+                        Self::Posture => 1usize,
+                        Self::Moved => 0usize,
+                    }
+                }
+            }
+        };
+        assert_eq!(expanded.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn test_expand_signals_errors() {
+        let not_enum: DeriveInput = parse_quote! {
+            struct Signals { posture: u32 }
+        };
+        assert!(expand_signals(not_enum).is_err());
+
+        let no_action: DeriveInput = parse_quote! {
+            enum MotionSignal { #[qi(replay = 1)] Posture }
+        };
+        assert!(expand_signals(no_action).is_err());
+
+        let with_fields: DeriveInput = parse_quote! {
+            enum MotionSignal { #[qi(action = 100)] Posture(u32) }
+        };
+        assert!(expand_signals(with_fields).is_err());
+
+        let unknown_attr: DeriveInput = parse_quote! {
+            enum MotionSignal { #[qi(action = 100, buffered)] Posture }
+        };
+        assert!(expand_signals(unknown_attr).is_err());
+    }
+
+    #[test]
+    fn test_expand_from_value_errors() {
+        let not_struct: DeriveInput = parse_quote! {
+            enum Shape { Point, Size }
+        };
+        assert!(expand_from_value(not_struct).is_err());
+
+        let unknown_mode: DeriveInput = parse_quote! {
+            #[qi(pedantic)]
+            struct Point { x: i32, y: i32 }
+        };
+        assert!(expand_from_value(unknown_mode).is_err());
+
+        let unknown_field_attr: DeriveInput = parse_quote! {
+            struct Image { #[qi(zero_copy)] data: Bytes }
+        };
+        assert!(expand_from_value(unknown_field_attr).is_err());
+    }
+}
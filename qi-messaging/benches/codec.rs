@@ -0,0 +1,63 @@
+//! Encoding and decoding of framed messages.
+
+use bytes::{Buf, BytesMut};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use qi_format as format;
+use qi_messaging::bench;
+
+/// The number of messages encoded or decoded in each iteration.
+const MESSAGES: usize = 64;
+
+fn payloads(size: usize) -> impl Iterator<Item = format::Value> {
+    std::iter::repeat_n(format::Value::from_bytes(vec![0x2a; size].into()), MESSAGES)
+}
+
+fn bench_encode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("encode");
+    for size in [16, 1024, 64 * 1024] {
+        group.throughput(Throughput::Bytes((size * MESSAGES) as u64));
+        group.bench_with_input(BenchmarkId::new("frames", size), &size, |b, &size| {
+            b.iter_batched(
+                || payloads(size).collect::<Vec<_>>(),
+                |payloads| {
+                    let frames = bench::encode_event_frames(payloads);
+                    assert_eq!(frames.len(), MESSAGES);
+                    frames
+                },
+                criterion::BatchSize::SmallInput,
+            )
+        });
+        group.bench_with_input(BenchmarkId::new("buffer", size), &size, |b, &size| {
+            b.iter_batched(
+                || payloads(size).collect::<Vec<_>>(),
+                |payloads| {
+                    let mut buf = BytesMut::new();
+                    bench::write_events(payloads, &mut buf);
+                    buf
+                },
+                criterion::BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn bench_decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode");
+    for size in [16, 1024, 64 * 1024] {
+        let mut input = BytesMut::new();
+        bench::write_events(payloads(size), &mut input);
+        group.throughput(Throughput::Bytes(input.remaining() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &input, |b, input| {
+            b.iter_batched(
+                || input.clone(),
+                |mut input| assert_eq!(bench::decode_messages(&mut input), Some(MESSAGES)),
+                criterion::BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_encode, bench_decode);
+criterion_main!(benches);
@@ -0,0 +1,56 @@
+//! Compression of the content of large replies, such as meta objects.
+//!
+//! The meta object is the content of a reply to a `metaObject` call read from the file at the path
+//! of the `QI_BENCH_META_OBJECT` environment variable, for instance extracted from a capture of
+//! the traffic of a robot, or a reconstruction of the one of `ALMotion` by default.
+
+use bytes::Bytes;
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use qi_format as format;
+use qi_messaging::bench;
+use std::time::Duration;
+
+/// The throughput of a slow WiFi link, in bytes per second, to estimate the transfer time saved by
+/// compression.
+const SLOW_LINK_THROUGHPUT: f64 = 1_000_000.0;
+
+fn meta_object() -> Bytes {
+    match std::env::var_os("QI_BENCH_META_OBJECT") {
+        Some(path) => std::fs::read(path)
+            .expect("failed to read the meta object")
+            .into(),
+        None => format::to_value(&bench::motion_meta_object())
+            .expect("failed to serialize the meta object")
+            .as_bytes()
+            .clone(),
+    }
+}
+
+fn transfer_time(size: usize) -> Duration {
+    Duration::from_secs_f64(size as f64 / SLOW_LINK_THROUGHPUT)
+}
+
+fn bench_meta_object(c: &mut Criterion) {
+    let meta_object = meta_object();
+    let compressed = bench::compress(&meta_object).expect("the meta object is too large");
+    println!(
+        "meta object: {} bytes, compressed: {} bytes ({:.1}%), transfer time on a slow link: \
+         {:?}, compressed: {:?}",
+        meta_object.len(),
+        compressed.len(),
+        compressed.len() as f64 * 100.0 / meta_object.len() as f64,
+        transfer_time(meta_object.len()),
+        transfer_time(compressed.len()),
+    );
+
+    let mut group = c.benchmark_group("meta_object");
+    group.throughput(Throughput::Bytes(meta_object.len() as u64));
+    group.bench_function("compress", |b| b.iter(|| bench::compress(&meta_object)));
+    group.bench_function("decompress", |b| {
+        b.iter(|| assert!(bench::decompress(&compressed).is_some()))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_meta_object);
+criterion_main!(benches);
@@ -0,0 +1,140 @@
+//! Calls and events between sessions over in-process connections.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use futures::{future, stream::FuturesUnordered, StreamExt};
+use qi_messaging::{
+    session::{self, subject::ServiceObject, Call, CallWithId, NotificationWithId},
+    CallTermination, Service,
+};
+use qi_types::object::{ActionId, ObjectId, ServiceId};
+use tokio::{io, runtime::Runtime};
+
+/// A service that replies to calls with their value, a list of floats, and ignores notifications.
+struct Echo;
+
+impl Service<CallWithId, NotificationWithId> for Echo {
+    type CallReply = Vec<f32>;
+    type Error = String;
+    type CallFuture = future::Ready<qi_messaging::CallResult<Vec<f32>, Self::Error>>;
+    type NotifyFuture = future::Ready<Result<(), Self::Error>>;
+
+    fn call(&mut self, call: CallWithId) -> Self::CallFuture {
+        future::ready(
+            call.inner()
+                .value()
+                .map_err(|err| CallTermination::Error(err.to_string())),
+        )
+    }
+
+    fn notify(&mut self, _notif: NotificationWithId) -> Self::NotifyFuture {
+        future::ready(Ok(()))
+    }
+}
+
+async fn session_pair() -> (session::Client, session::Client) {
+    let (io_client, io_server) = io::duplex(64 * 1024);
+    let (client, client_dispatch) = session::connect(io_client, Echo);
+    let (server, server_dispatch) = session::listen(io_server, Echo);
+    tokio::spawn(async move {
+        tokio::select! {
+            _res = client_dispatch => {},
+            _res = server_dispatch => {},
+        }
+    });
+    let (client, server) = tokio::join!(client, server);
+    (client.unwrap(), server.unwrap())
+}
+
+fn subject() -> session::Subject {
+    let service_object = ServiceObject::new(ServiceId::new(1), ObjectId::new(1)).unwrap();
+    session::Subject::new(service_object, ActionId::new(1))
+}
+
+fn events(count: usize) -> impl Iterator<Item = session::Event> {
+    (0..count).map(|joint| {
+        session::Event::new(subject())
+            .with_value(&(joint, 0.5f32))
+            .unwrap()
+    })
+}
+
+fn bench_call(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let (client, _server) = runtime.block_on(session_pair());
+
+    let mut group = c.benchmark_group("call");
+    for len in [0, 26, 16 * 1024] {
+        let call = Call::new(subject()).with_value(&vec![0.5f32; len]).unwrap();
+        group.bench_with_input(BenchmarkId::new("round_trip", len), &call, |b, call| {
+            b.to_async(&runtime).iter(|| async {
+                let mut client = &client;
+                client.call(call.clone()).await.unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_fan_out(c: &mut Criterion) {
+    // A value is emitted once on a session, and delivered by its events tap to every subscriber.
+    const SUBSCRIBERS: usize = 100;
+    let runtime = Runtime::new().unwrap();
+    let (client, server) = runtime.block_on(session_pair());
+
+    let mut group = c.benchmark_group("fan_out");
+    group.throughput(Throughput::Elements(SUBSCRIBERS as u64));
+    group.bench_function(BenchmarkId::from_parameter(SUBSCRIBERS), |b| {
+        b.to_async(&runtime).iter(|| async {
+            let mut received: FuturesUnordered<_> = (0..SUBSCRIBERS)
+                .map(|_| Box::pin(client.events(|_| true)).into_future())
+                .collect();
+            let event = events(1).next().unwrap();
+            let mut server = &server;
+            server.notify(event.into()).await.unwrap();
+            while let Some((event, _events)) = received.next().await {
+                event.unwrap();
+            }
+        })
+    });
+    group.finish();
+}
+
+fn bench_event_batches(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let (client, server) = runtime.block_on(session_pair());
+
+    let mut group = c.benchmark_group("event_burst");
+    // Bursts stay below the capacity of the events tap of the session, so that none is skipped.
+    for count in [1, 8, 32] {
+        group.throughput(Throughput::Elements(count as u64));
+        group.bench_with_input(
+            BenchmarkId::new("individual", count),
+            &count,
+            |b, &count| {
+                b.to_async(&runtime).iter(|| async {
+                    let mut received = Box::pin(client.events(|_| true));
+                    for event in events(count) {
+                        let mut server = &server;
+                        server.notify(event.into()).await.unwrap();
+                    }
+                    for _ in 0..count {
+                        received.next().await.unwrap();
+                    }
+                })
+            },
+        );
+        group.bench_with_input(BenchmarkId::new("batch", count), &count, |b, &count| {
+            b.to_async(&runtime).iter(|| async {
+                let mut received = Box::pin(client.events(|_| true));
+                server.notify_events(events(count)).await.unwrap();
+                for _ in 0..count {
+                    received.next().await.unwrap();
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_call, bench_fan_out, bench_event_batches);
+criterion_main!(benches);
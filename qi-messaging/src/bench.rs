@@ -0,0 +1,163 @@
+//! Internals of the crate that its benchmarks measure. This is not part of the API of the crate,
+//! and may change at any time.
+
+use crate::{
+    format,
+    message::{
+        self,
+        codec::{Decoder, Encoder},
+        compression, Message,
+    },
+    types::{
+        object::{ActionId, MetaMethodParameter, MetaObject},
+        Signature,
+    },
+};
+use bytes::{buf::Chain, Bytes, BytesMut};
+
+fn events<I>(payloads: I) -> impl Iterator<Item = Message>
+where
+    I: IntoIterator<Item = format::Value>,
+{
+    payloads.into_iter().zip(1..).map(|(payload, id)| {
+        Message::event(message::Id(id), message::Subject::default())
+            .set_content(payload)
+            .build_unchecked()
+    })
+}
+
+/// Encodes events with these payloads into frames, as they are written by sessions.
+pub fn encode_event_frames<I>(payloads: I) -> Vec<Chain<Bytes, Bytes>>
+where
+    I: IntoIterator<Item = format::Value>,
+{
+    events(payloads)
+        .filter_map(|message| Encoder.encode_frame(message).ok())
+        .collect()
+}
+
+/// Writes events with these payloads into a buffer.
+pub fn write_events<I>(payloads: I, dst: &mut BytesMut)
+where
+    I: IntoIterator<Item = format::Value>,
+{
+    for message in events(payloads) {
+        // Encoding an event with a default subject cannot fail.
+        let _res = tokio_util::codec::Encoder::encode(&mut Encoder, message, dst);
+    }
+}
+
+/// Decodes the messages of a buffer, and returns their number, or `None` if the buffer does not
+/// contain valid messages.
+pub fn decode_messages(src: &mut BytesMut) -> Option<usize> {
+    let mut decoder = Decoder::new();
+    let mut count = 0;
+    while tokio_util::codec::Decoder::decode(&mut decoder, src)
+        .ok()?
+        .is_some()
+    {
+        count += 1;
+    }
+    Some(count)
+}
+
+/// Compresses the content of a message, as sessions do for large replies when their remote accepts
+/// compressed replies.
+pub fn compress(content: &[u8]) -> Option<Bytes> {
+    compression::compress(content)
+}
+
+/// Decompresses the content of a message compressed with [`compress`], or returns `None` if it is
+/// invalid.
+pub fn decompress(content: &[u8]) -> Option<Bytes> {
+    compression::decompress(content).ok()
+}
+
+/// A meta object with the methods of the `ALMotion` service, as listed by its documentation, with
+/// generated descriptions.
+///
+/// This is a reconstruction, not a capture of the meta object of the service.
+pub fn motion_meta_object() -> MetaObject {
+    const METHODS: [(&str, &str, &str); 60] = [
+        ("registerEvent", "(IIL)", "L"),
+        ("unregisterEvent", "(IIL)", "v"),
+        ("terminate", "(I)", "v"),
+        ("property", "(m)", "m"),
+        ("setProperty", "(mm)", "v"),
+        ("properties", "()", "[s]"),
+        ("registerEventWithSignature", "(IILs)", "L"),
+        ("isStatsEnabled", "()", "b"),
+        ("enableStats", "(b)", "v"),
+        ("clearStats", "()", "v"),
+        ("isTraceEnabled", "()", "b"),
+        ("enableTrace", "(b)", "v"),
+        ("exit", "()", "v"),
+        ("getBrokerName", "()", "s"),
+        ("getMethodList", "()", "[s]"),
+        ("getModuleHelp", "()", "m"),
+        ("ping", "()", "b"),
+        ("version", "()", "s"),
+        ("isRunning", "(i)", "b"),
+        ("wait", "(ii)", "b"),
+        ("stop", "(i)", "v"),
+        ("wakeUp", "()", "v"),
+        ("rest", "()", "v"),
+        ("robotIsWakeUp", "()", "b"),
+        ("setStiffnesses", "(mm)", "v"),
+        ("getStiffnesses", "(m)", "[f]"),
+        ("angleInterpolation", "(mmmb)", "v"),
+        ("angleInterpolationWithSpeed", "(mmf)", "v"),
+        ("angleInterpolationBezier", "([s][[f]][[m]])", "v"),
+        ("setAngles", "(mmf)", "v"),
+        ("changeAngles", "(mmf)", "v"),
+        ("getAngles", "(mb)", "[f]"),
+        ("openHand", "(s)", "v"),
+        ("closeHand", "(s)", "v"),
+        ("move", "(fff)", "v"),
+        ("moveToward", "(fff)", "v"),
+        ("moveTo", "(fff)", "b"),
+        ("moveInit", "()", "v"),
+        ("waitUntilMoveIsFinished", "()", "v"),
+        ("moveIsActive", "()", "b"),
+        ("stopMove", "()", "v"),
+        ("getRobotPosition", "(b)", "[f]"),
+        ("getNextRobotPosition", "()", "[f]"),
+        ("getRobotVelocity", "()", "[f]"),
+        ("getMoveConfig", "(s)", "[m]"),
+        ("positionInterpolations", "(mmmmm)", "v"),
+        ("setPositions", "(mmmfm)", "v"),
+        ("getPosition", "(sib)", "[f]"),
+        ("getTransform", "(sib)", "[f]"),
+        ("getBodyNames", "(s)", "[s]"),
+        ("getJointNames", "(s)", "[s]"),
+        ("getSensorNames", "()", "[s]"),
+        ("getLimits", "(s)", "[[f]]"),
+        ("getRobotConfig", "()", "[m]"),
+        ("getSummary", "()", "s"),
+        ("getMass", "(s)", "f"),
+        ("getCOM", "(sib)", "[f]"),
+        ("setBreathEnabled", "(sb)", "v"),
+        ("getBreathEnabled", "(s)", "b"),
+        ("setIdlePostureEnabled", "(sb)", "v"),
+    ];
+    let mut builder = MetaObject::builder();
+    for (uid, (name, parameters, ret)) in (0..).zip(METHODS) {
+        builder.add_method(
+            ActionId::new(uid),
+            name,
+            parameters.parse::<Signature>().unwrap(),
+            ret.parse::<Signature>().unwrap(),
+        );
+    }
+    let mut meta_object = builder.build();
+    for method in meta_object.methods.iter_mut().map(|(_uid, method)| method) {
+        method.description = format!("Calls the {} method of the motion service.", method.name);
+        method.parameters = (0..method.parameters_signature.to_string().len())
+            .map(|index| MetaMethodParameter {
+                name: format!("arg{index}"),
+                description: "An argument of the method.".to_owned(),
+            })
+            .collect();
+    }
+    meta_object
+}
@@ -1,8 +1,65 @@
 use crate::types::{Dynamic, Map};
-use std::cmp::Ordering;
+use std::{borrow::Borrow, cmp::Ordering, hash::Hash};
 
 type MapImpl = Map<String, Dynamic>;
 
+/// The well-known capabilities of the `qi` protocol.
+///
+/// Each of them is a flag: the end of a session that has it set to `true` in its capabilities
+/// supports the feature. A capability that is missing is not supported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub enum Capability {
+    /// Both ends of a session may serve calls of the other, on the same connection.
+    ClientServerSocket,
+    /// Messages may have flags, such as the request of the return type of a call.
+    MessageFlags,
+    /// The meta objects of the objects that are sent may be cached by the receiver, and are only
+    /// sent once per session.
+    MetaObjectCache,
+    /// Calls may be canceled by the caller.
+    RemoteCancelableCalls,
+    /// The objects that are sent are identified by a unique id.
+    ObjectPtrUid,
+    /// The endpoints of services may be relative to the address of the remote.
+    RelativeEndpointUri,
+}
+
+impl Capability {
+    pub const ALL: [Self; 6] = [
+        Self::ClientServerSocket,
+        Self::MessageFlags,
+        Self::MetaObjectCache,
+        Self::RemoteCancelableCalls,
+        Self::ObjectPtrUid,
+        Self::RelativeEndpointUri,
+    ];
+
+    /// The key of the capability in a capabilities map.
+    pub const fn key(self) -> &'static str {
+        match self {
+            Self::ClientServerSocket => "ClientServerSocket",
+            Self::MessageFlags => "MessageFlags",
+            Self::MetaObjectCache => "MetaObjectCache",
+            Self::RemoteCancelableCalls => "RemoteCancelableCalls",
+            Self::ObjectPtrUid => "ObjectPtrUID",
+            Self::RelativeEndpointUri => "RelativeEndpointURI",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|capability| capability.key() == key)
+    }
+}
+
+impl std::fmt::Display for Capability {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.key())
+    }
+}
+
 #[derive(
     Default, Clone, PartialEq, Eq, PartialOrd, Debug, serde::Serialize, serde::Deserialize,
 )]
@@ -27,18 +84,49 @@ impl CapabilitiesMap {
 
     pub fn get<K>(&self, key: &K) -> Option<&Dynamic>
     where
-        K: PartialEq<String> + ?Sized,
+        String: Borrow<K>,
+        K: Hash + Eq + ?Sized,
     {
         self.0.get(key)
     }
 
     pub fn has_flag_capability<K>(&self, key: &K) -> bool
     where
-        K: PartialEq<String> + ?Sized,
+        String: Borrow<K>,
+        K: Hash + Eq + ?Sized,
     {
         matches!(self.get(key), Some(Dynamic::Bool(true)))
     }
 
+    /// The value of a well-known capability, if it is set as a flag.
+    pub fn capability(&self, capability: Capability) -> Option<bool> {
+        match self.get(capability.key()) {
+            Some(Dynamic::Bool(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn set_flag_capability(&mut self, capability: Capability, value: bool) {
+        self.set_capability(capability.key(), value);
+    }
+
+    /// Negotiates the capabilities of both ends of a session.
+    ///
+    /// As `libqi` does, the negotiated value of each capability is the lowest of the values of both
+    /// ends, and a capability that one of the ends does not have is not negotiated. For flags, it
+    /// means that a feature is only used if both ends support it.
+    pub fn negotiate(local: &Self, remote: &Self) -> Self {
+        let mut capabilities = local.clone();
+        capabilities.intersect(remote);
+        capabilities
+    }
+
+    /// Returns true if both ends of a session support a well-known capability.
+    pub fn is_shared(local: &Self, remote: &Self, capability: Capability) -> bool {
+        local.capability(capability).unwrap_or_default()
+            && remote.capability(capability).unwrap_or_default()
+    }
+
     pub fn intersect(&mut self, other: &Self) -> &mut Self {
         for (key, other_value) in other.iter() {
             if let Some(value) = self.0.get_mut(key) {
@@ -123,4 +211,67 @@ mod tests {
         assert_matches!(m.get("H"), None);
         assert_matches!(m.get("I"), None);
     }
+
+    #[test]
+    fn test_capability_key() {
+        for capability in Capability::ALL {
+            assert_eq!(capability.key(), capability.to_string());
+            assert_eq!(Capability::from_key(capability.key()), Some(capability));
+        }
+        assert_eq!(Capability::from_key("ObjectPtrUid"), None);
+        assert_eq!(Capability::from_key("StreamingCallReplies"), None);
+    }
+
+    #[test]
+    fn test_capabilities_map_flag_capability() {
+        let mut map = CapabilitiesMap::from_iter([("MessageFlags", Dynamic::from("yes"))]);
+        assert_eq!(map.capability(Capability::MessageFlags), None);
+        assert_eq!(map.capability(Capability::MetaObjectCache), None);
+        map.set_flag_capability(Capability::MetaObjectCache, true);
+        assert_eq!(map.capability(Capability::MetaObjectCache), Some(true));
+        assert_matches!(map.get("MetaObjectCache"), Some(Dynamic::Bool(true)));
+    }
+
+    #[test]
+    fn test_capabilities_map_negotiate() {
+        // A peer that supports the well-known capabilities but the meta object cache, and an older
+        // client that does not know all of them.
+        let peer = CapabilitiesMap::from_iter([
+            ("ClientServerSocket", true),
+            ("MessageFlags", true),
+            ("MetaObjectCache", false),
+            ("ObjectPtrUID", true),
+            ("RelativeEndpointURI", true),
+            ("RemoteCancelableCalls", true),
+        ]);
+        let client = CapabilitiesMap::from_iter([
+            ("ClientServerSocket", true),
+            ("MetaObjectCache", true),
+            ("RemoteCancelableCalls", true),
+        ]);
+
+        let negotiated = CapabilitiesMap::negotiate(&peer, &client);
+        assert_eq!(negotiated, CapabilitiesMap::negotiate(&client, &peer));
+        assert_eq!(
+            negotiated,
+            CapabilitiesMap::from_iter([
+                ("ClientServerSocket", true),
+                ("MetaObjectCache", false),
+                ("RemoteCancelableCalls", true),
+            ])
+        );
+
+        let shared = |capability| CapabilitiesMap::is_shared(&peer, &client, capability);
+        assert!(shared(Capability::ClientServerSocket));
+        assert!(shared(Capability::RemoteCancelableCalls));
+        assert!(!shared(Capability::MetaObjectCache));
+        assert!(!shared(Capability::MessageFlags));
+        assert!(!shared(Capability::ObjectPtrUid));
+        for capability in Capability::ALL {
+            assert_eq!(
+                shared(capability),
+                negotiated.capability(capability).unwrap_or_default()
+            );
+        }
+    }
 }
@@ -2,118 +2,581 @@ use crate::{
     client, format,
     message::{
         self,
-        codec::{DecodeError, Decoder, EncodeError, Encoder},
+        codec::{DecodeError, Decoder, EncodeError, Writer},
     },
     messaging::{
         self, CallTermination, CallWithId, NotificationWithId, Reply, RequestWithId, Service,
     },
     server,
 };
-use futures::{SinkExt, StreamExt};
-use std::fmt::Debug;
+use bytes::Bytes;
+use futures::StreamExt;
+use std::{
+    fmt::Debug,
+    sync::{
+        atomic::{AtomicU32, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 use tokio::{
     io::{split, AsyncRead, AsyncWrite},
     pin, select,
-    sync::mpsc,
+    sync::{broadcast, mpsc, oneshot, watch},
+    time::Instant,
 };
-use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::wrappers::{ReceiverStream, UnboundedReceiverStream};
 use tokio_util::{
-    codec::{FramedRead, FramedWrite},
+    codec::FramedRead,
     sync::{PollSendError, PollSender},
 };
 use tracing::trace;
 
+mod stats;
+pub(crate) use stats::Stats;
+pub use stats::{ChannelStats, KindStats, TrafficStats};
+
 pub(crate) fn open<IO, Svc>(
     io: IO,
     service: Svc,
+    scheduling: server::Scheduling,
+    reply_compression: ReplyCompression,
 ) -> (
-    client::Client,
+    Handles,
     impl std::future::Future<Output = Result<(), Error<Svc::CallReply, Svc::Error>>>,
 )
 where
     IO: AsyncWrite + AsyncRead,
     Svc: Service<CallWithId, NotificationWithId>,
     Svc::Error: ToString + std::fmt::Debug + Send + 'static,
-    Svc::CallReply: Into<format::Value> + Send + 'static,
+    Svc::CallReply: Into<Reply> + Send + 'static,
 {
     let (input, output) = split(io);
     let mut stream = FramedRead::new(input, Decoder::new()).fuse();
-    let mut sink = FramedWrite::new(output, Encoder);
+    let mut writer = Writer::new(output);
 
     const DISPATCH_CHANNEL_SIZE: usize = 1;
-    let (client_responses_tx, client_responses_rx) = mpsc::channel(DISPATCH_CHANNEL_SIZE);
+    // Responses are bounded by the calls that are waiting for them, they are forwarded without
+    // waiting, see below.
+    let (client_responses_tx, client_responses_rx) = mpsc::unbounded_channel();
     let (client_requests_tx, mut client_requests_rx) = mpsc::channel(DISPATCH_CHANNEL_SIZE);
     let (server_requests_tx, server_requests_rx) = mpsc::channel(DISPATCH_CHANNEL_SIZE);
-    let (server_responses_tx, mut server_responses_rx) = mpsc::channel(DISPATCH_CHANNEL_SIZE);
+    let (server_responses_tx, mut server_responses_rx) = mpsc::unbounded_channel();
+    let (reply_chunks_tx, mut reply_chunks_rx) = mpsc::channel(DISPATCH_CHANNEL_SIZE);
+    let (event_batches_tx, mut event_batches_rx) = mpsc::channel(DISPATCH_CHANNEL_SIZE);
+    let (close_requests_tx, mut close_requests_rx) = mpsc::channel::<CloseRequest>(1);
 
     let (client, client_dispatch) = client::setup(
-        ReceiverStream::new(client_responses_rx),
+        UnboundedReceiverStream::new(client_responses_rx),
         PollSender::new(client_requests_tx),
     );
     let server = server::serve(
         ReceiverStream::new(server_requests_rx),
-        PollSender::new(server_responses_tx),
+        futures::sink::unfold(
+            server_responses_tx,
+            |server_responses_tx, response| async move {
+                server_responses_tx.send(response)?;
+                Ok(server_responses_tx)
+            },
+        ),
         service,
+        scheduling,
     );
+    let reply_chunks_senders = client.reply_chunks_senders();
+    let events = Events::new();
+    let events_tap = events.clone();
+    let stats = Stats::new();
+    let input_stats = stats.clone();
+    let remote_version = RemoteVersion::default();
+    let input_remote_version = remote_version.clone();
+    let output_stats = stats.clone();
 
-    let dispatch = async move {
-        pin!(client_dispatch, server);
-        loop {
-            select! {
-                Some(message) = stream.next() => {
+    // The input and the output of the channel are driven concurrently, and so are the client
+    // dispatch and the server, so that none of them waits for another that waits for it in turn.
+    //
+    // This is required for services that call the remote while they serve a call, which the
+    // remote may serve by calling back: the output keeps sending requests while the input waits
+    // for the server to take a request, and neither the client dispatch nor the server ever wait
+    // for the input or the output to send them a response.
+    let io = async move {
+        // The number of messages taken from the outputs that are being written.
+        let in_flight = AtomicUsize::new(0);
+        // The sender of the report of the closing of the channel, once it is requested.
+        let mut close_report = None;
+        {
+            // Tells the output that the channel is closing, see `Close`. It is dropped once the
+            // channel cannot be closed anymore, so that the output may terminate.
+            let (closing_tx, mut closing_rx) = watch::channel(false);
+            let mut closing_tx = Some(closing_tx);
+            let input = async {
+                while let Some(message) = stream.next().await {
                     let message = message?;
+                    input_stats.record_received(&message);
+                    input_remote_version.record(message.version());
+                    let message = message.decompress()?;
+                    if message.kind() == message::Kind::Event {
+                        // Events with the id and subject of an ongoing streaming call are chunks of
+                        // its reply, and not requests.
+                        if reply_chunks_senders.is_streaming(message.id(), message.subject()) {
+                            // The chunks are buffered, the input does not wait for the caller.
+                            let id = message.id();
+                            if reply_chunks_senders.send(id, Reply::new(message.into_content())) {
+                                // The buffer of the chunks is full, the call is terminated at
+                                // once. This placeholder response is replaced by the overflow
+                                // error by the client dispatch.
+                                let _res =
+                                    client_responses_tx.send((id, Err(CallTermination::Canceled)));
+                            }
+                            continue;
+                        }
+                        events_tap.publish(&message);
+                    }
                     // Ignore the results of send, it occurs when the client or server dropped the
                     // request or response stream, which means that their task have terminated.
-                    match RequestWithId::try_from_message(message).map_err(Error::MessageIntoRequest)? {
+                    match RequestWithId::try_from_message(message)
+                        .map_err(Error::MessageIntoRequest)?
+                    {
                         Ok(request) => {
                             let _res = server_requests_tx.send(request).await;
                         }
                         Err(message) => {
                             let id = message.id();
-                            let send_response = match message.kind() {
-                                message::Kind::Reply => {
-                                    let reply = Reply::new(message.into_content());
-                                    client_responses_tx.send((id, Ok(reply)))
-                                },
-                                message::Kind::Canceled => {
-                                    client_responses_tx.send((id, Err(CallTermination::Canceled)))
-                                },
+                            let response = match message.kind() {
+                                message::Kind::Reply => reply_from_message(message),
+                                message::Kind::Canceled => Err(CallTermination::Canceled),
                                 message::Kind::Error => {
-                                    let error_description = message.deserialize_error_description().map_err(Error::GetErrorDescription)?;
-                                    let error = messaging::Error(error_description);
-                                    client_responses_tx.send((id, Err(CallTermination::Error(error))))
-                                },
+                                    let error = match message.error_description() {
+                                        Some(description) => messaging::Error::from(description),
+                                        None => messaging::Error::with_undecodable_description(
+                                            Bytes::copy_from_slice(message.content().as_bytes()),
+                                        ),
+                                    };
+                                    Err(CallTermination::Error(error))
+                                }
                                 // Either a message is a request, or it is a call response.
                                 // There are no other cases.
                                 _ => unreachable!(),
                             };
-                            let _res = send_response.await;
-                        },
+                            let _res = client_responses_tx.send((id, response));
+                        }
                     }
                 }
-                Some(request) = client_requests_rx.recv() => {
-                    let message = request.try_into().map_err(Error::RequestIntoMessage)?;
-                    sink.send(message).await?;
-                }
-                Some(response) = server_responses_rx.recv() => {
-                    let message = response.try_into().map_err(Error::ResponseIntoMessage)?;
-                    sink.send(message).await?;
-                }
-                res = &mut client_dispatch => {
-                    res.map_err(Error::ClientDispatch)?;
-                    trace!("client dispatch has terminated with success");
-                    break Ok(());
+                trace!("channel input is closed");
+                Ok::<_, Error<Svc::CallReply, Svc::Error>>(())
+            };
+            let output = async {
+                let mut closing = false;
+                loop {
+                    let outgoing = if closing {
+                        // The messages that were queued are written, then the output terminates.
+                        if let Ok(request) = client_requests_rx.try_recv() {
+                            Outgoing::Request(request)
+                        } else if let Ok(chunk) = reply_chunks_rx.try_recv() {
+                            Outgoing::ReplyChunk(chunk)
+                        } else if let Ok(events) = event_batches_rx.try_recv() {
+                            Outgoing::Events(events)
+                        } else if let Ok(response) = server_responses_rx.try_recv() {
+                            Outgoing::Response(response)
+                        } else {
+                            break Ok::<_, Error<Svc::CallReply, Svc::Error>>(OutputEnd::Closed);
+                        }
+                    } else {
+                        select! {
+                            Some(request) = client_requests_rx.recv() => Outgoing::Request(request),
+                            Some(chunk) = reply_chunks_rx.recv() => Outgoing::ReplyChunk(chunk),
+                            Some(events) = event_batches_rx.recv() => Outgoing::Events(events),
+                            Some(response) = server_responses_rx.recv() => {
+                                Outgoing::Response(response)
+                            }
+                            Ok(()) = closing_rx.changed() => {
+                                closing = *closing_rx.borrow();
+                                continue;
+                            }
+                            else => {
+                                trace!("channel outputs are closed");
+                                break Ok(OutputEnd::Terminated);
+                            }
+                        }
+                    };
+                    in_flight.store(outgoing.messages(), Ordering::Relaxed);
+                    match outgoing {
+                        Outgoing::Request(request) => {
+                            let message = request.try_into().map_err(Error::RequestIntoMessage)?;
+                            output_stats.record_sent(&message);
+                            writer.send(message).await?;
+                        }
+                        Outgoing::ReplyChunk(chunk) => {
+                            output_stats.record_sent(&chunk);
+                            writer.send(chunk).await?;
+                        }
+                        Outgoing::Events(events) => {
+                            for event in &events {
+                                output_stats.record_sent(event);
+                            }
+                            writer.send_all(events).await?;
+                        }
+                        Outgoing::Response(response) => {
+                            // Chunks of a reply that were sent before the service returned must
+                            // precede it.
+                            while let Ok(chunk) = reply_chunks_rx.try_recv() {
+                                in_flight.fetch_add(1, Ordering::Relaxed);
+                                output_stats.record_sent(&chunk);
+                                writer.send(chunk).await?;
+                                in_flight.fetch_sub(1, Ordering::Relaxed);
+                            }
+                            let message =
+                                response.try_into().map_err(Error::ResponseIntoMessage)?;
+                            let message = reply_compression.apply(message);
+                            output_stats.record_sent(&message);
+                            writer.send(message).await?;
+                        }
+                    }
+                    in_flight.store(0, Ordering::Relaxed);
                 }
-                res = &mut server => {
-                    res.map_err(Error::Server)?;
-                    trace!("server has terminated with success");
-                    break Ok(());
+            };
+            // Writing the queued messages of a closing channel is abandoned at the end of the
+            // linger duration.
+            let lingered = tokio::time::sleep(Duration::ZERO);
+
+            pin!(input, output, lingered);
+            let mut input_terminated = false;
+            loop {
+                // Only the first close request is served, the others fail once the channel is
+                // closed.
+                let closable = closing_tx.is_some() && close_report.is_none();
+                select! {
+                    res = &mut input, if !input_terminated => {
+                        res?;
+                        input_terminated = true;
+                    }
+                    res = &mut output => match res? {
+                        OutputEnd::Closed => break,
+                        OutputEnd::Terminated if close_report.is_some() => break,
+                        OutputEnd::Terminated => {
+                            if !input_terminated {
+                                input.as_mut().await?;
+                            }
+                            return Ok(());
+                        }
+                    },
+                    request = close_requests_rx.recv(), if closable => {
+                        let request = match request {
+                            Some(request) => request,
+                            None => {
+                                closing_tx = None;
+                                continue;
+                            }
+                        };
+                        trace!(linger = ?request.linger, "closing the channel");
+                        close_report = Some(request.report);
+                        match request.linger {
+                            Some(linger) => {
+                                lingered.as_mut().reset(Instant::now() + linger);
+                                if let Some(closing_tx) = &closing_tx {
+                                    closing_tx.send_replace(true);
+                                }
+                            }
+                            None => break,
+                        }
+                    }
+                    () = &mut lingered, if close_report.is_some() => break,
                 }
             }
         }
+
+        // The channel is closing, the messages that are left are dropped.
+        let mut dropped = in_flight.into_inner();
+        while client_requests_rx.try_recv().is_ok() {
+            dropped += 1;
+        }
+        while reply_chunks_rx.try_recv().is_ok() {
+            dropped += 1;
+        }
+        while let Ok(events) = event_batches_rx.try_recv() {
+            dropped += events.len();
+        }
+        while server_responses_rx.try_recv().is_ok() {
+            dropped += 1;
+        }
+        if dropped == 0 {
+            // The remote knows that nothing more is coming. This is a best effort, the connection
+            // is closed anyway.
+            let _res = writer.shutdown().await;
+        }
+        trace!(dropped, "channel is closed");
+        if let Some(report) = close_report {
+            let _res = report.send(CloseReport { dropped });
+        }
+        Ok(())
+    };
+
+    let dispatch = async move {
+        pin!(io, client_dispatch, server);
+        select! {
+            res = &mut io => res,
+            res = &mut client_dispatch => {
+                res.map_err(Error::ClientDispatch)?;
+                trace!("client dispatch has terminated with success");
+                Ok(())
+            }
+            res = &mut server => {
+                res.map_err(Error::Server)?;
+                trace!("server has terminated with success");
+                Ok(())
+            }
+        }
+    };
+
+    let handles = Handles {
+        client,
+        events,
+        reply_chunks: ReplyChunks(reply_chunks_tx),
+        event_batches: EventBatches(event_batches_tx),
+        stats,
+        close: Close(close_requests_tx),
+        remote_version,
     };
+    (handles, dispatch)
+}
 
-    (client, dispatch)
+/// Reads the reply of a call from a message, with the signature of the returned value that precedes
+/// it if the message has the `RETURN_TYPE` flag.
+///
+/// A reply of which the signature cannot be read terminates the call with an error, the other calls
+/// of the channel are not affected.
+fn reply_from_message(
+    message: message::Message,
+) -> Result<Reply, CallTermination<messaging::Error>> {
+    if !message.flags().contains(message::Flags::RETURN_TYPE) {
+        return Ok(Reply::new(message.into_content()));
+    }
+    Reply::from_content_with_return_signature(message.into_content())
+        .map_err(|err| CallTermination::Error(messaging::Error::from(err.to_string())))
+}
+
+/// The handles to send and receive messages on an open channel.
+#[derive(Debug)]
+pub(crate) struct Handles {
+    pub(crate) client: client::Client,
+    pub(crate) events: Events,
+    pub(crate) reply_chunks: ReplyChunks,
+    pub(crate) event_batches: EventBatches,
+    pub(crate) stats: Stats,
+    pub(crate) close: Close,
+    pub(crate) remote_version: RemoteVersion,
+}
+
+/// The version of the protocol of the messages received from the remote end, once it sent one.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RemoteVersion(Arc<AtomicU32>);
+
+impl RemoteVersion {
+    fn record(&self, version: message::Version) {
+        // Zero means that no message was received yet.
+        self.0
+            .store(u32::from(u16::from(version)) + 1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn get(&self) -> Option<message::Version> {
+        match self.0.load(Ordering::Relaxed) {
+            0 => None,
+            version => Some(message::Version::from((version - 1) as u16)),
+        }
+    }
+}
+
+/// A message to write on the output of a channel.
+enum Outgoing<SvcRep, SvcErr> {
+    Request(RequestWithId),
+    ReplyChunk(message::Message),
+    Events(Vec<message::Message>),
+    Response(server::Response<SvcRep, SvcErr>),
+}
+
+impl<SvcRep, SvcErr> Outgoing<SvcRep, SvcErr> {
+    fn messages(&self) -> usize {
+        match self {
+            Self::Events(events) => events.len(),
+            _ => 1,
+        }
+    }
+}
+
+enum OutputEnd {
+    /// The channel was closed, see `Close`.
+    Closed,
+    /// All the senders of messages were dropped.
+    Terminated,
+}
+
+/// A closer of a channel.
+///
+/// Closing a channel stops taking the messages to send from the client, the server and the senders
+/// of events. The messages that were already queued are written, for up to a linger duration, then
+/// the output is shut down and the channel terminates. Messages that are not written in time are
+/// dropped and counted in the report of the closing. Without a linger duration, they are dropped
+/// right away.
+#[derive(Debug, Clone)]
+pub(crate) struct Close(mpsc::Sender<CloseRequest>);
+
+impl Close {
+    pub(crate) async fn close(
+        &self,
+        linger: Option<Duration>,
+    ) -> Result<CloseReport, ChannelClosedError> {
+        let (report, report_receiver) = oneshot::channel();
+        self.0
+            .send(CloseRequest { linger, report })
+            .await
+            .map_err(|_err| ChannelClosedError)?;
+        report_receiver.await.map_err(|_err| ChannelClosedError)
+    }
+}
+
+#[derive(Debug)]
+struct CloseRequest {
+    linger: Option<Duration>,
+    report: oneshot::Sender<CloseReport>,
+}
+
+/// The report of the closing of a session, see [`Client::close`](crate::session::Client::close).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[must_use]
+pub struct CloseReport {
+    dropped: usize,
+}
+
+impl CloseReport {
+    /// The number of queued messages that were not written before the session closed.
+    pub fn dropped_messages(&self) -> usize {
+        self.dropped
+    }
+
+    /// Returns true if all the queued messages were written before the session closed.
+    pub fn is_complete(&self) -> bool {
+        self.dropped == 0
+    }
+}
+
+/// The compression of the replies sent by a channel.
+///
+/// Replies with a content larger than a threshold are compressed, as long as the remote accepts
+/// compressed replies. Whether it does may change during the lifetime of the channel. Compressed
+/// messages that are received are always decompressed.
+#[derive(Debug, Clone)]
+pub(crate) struct ReplyCompression {
+    accepted: watch::Receiver<bool>,
+    threshold: usize,
+}
+
+impl ReplyCompression {
+    /// Compressing small replies is not worth the cost.
+    pub(crate) const DEFAULT_THRESHOLD: usize = 4 * 1024;
+
+    pub(crate) fn new(accepted: watch::Receiver<bool>, threshold: usize) -> Self {
+        Self {
+            accepted,
+            threshold,
+        }
+    }
+
+    fn apply(&self, message: message::Message) -> message::Message {
+        if message.kind() == message::Kind::Reply
+            && message.content().as_bytes().len() > self.threshold
+            && *self.accepted.borrow()
+        {
+            message.compress()
+        } else {
+            message
+        }
+    }
+}
+
+/// A sender of chunks of the replies to the calls received by a channel.
+///
+/// Chunks are sent as events with the id and subject of the call. They are written before the
+/// reply of the call if they are sent before the service returns it.
+#[derive(Debug, Clone)]
+pub(crate) struct ReplyChunks(mpsc::Sender<message::Message>);
+
+impl ReplyChunks {
+    pub(crate) async fn send(
+        &self,
+        call_id: message::Id,
+        subject: message::Subject,
+        chunk: Reply,
+    ) -> Result<(), ChannelClosedError> {
+        let message = message::Message::event(call_id, subject)
+            .set_content(chunk.into())
+            .build_unchecked();
+        self.0
+            .send(message)
+            .await
+            .map_err(|_err| ChannelClosedError)
+    }
+}
+
+/// A sender of batches of events.
+///
+/// The events of a batch are encoded together and written with a single flush of the output,
+/// which saves the cost of writing them one by one when they are emitted in bursts.
+#[derive(Debug, Clone)]
+pub(crate) struct EventBatches(mpsc::Sender<Vec<message::Message>>);
+
+impl EventBatches {
+    pub(crate) async fn send<I>(&self, events: I) -> Result<(), ChannelClosedError>
+    where
+        I: IntoIterator<Item = (message::Id, message::Subject, format::Value)>,
+    {
+        let messages = events
+            .into_iter()
+            .map(|(id, subject, value)| {
+                message::Message::event(id, subject)
+                    .set_content(value)
+                    .build_unchecked()
+            })
+            .collect();
+        self.0
+            .send(messages)
+            .await
+            .map_err(|_err| ChannelClosedError)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("the channel is closed")]
+pub(crate) struct ChannelClosedError;
+
+/// A tap on the event messages that are received by a channel.
+///
+/// Events are published to subscribers independently of the service of the channel, which still
+/// receives them as notifications. Subscribers that do not keep up with the traffic miss the
+/// oldest events.
+#[derive(Debug, Clone)]
+pub(crate) struct Events(broadcast::Sender<(messaging::Subject, Bytes)>);
+
+impl Events {
+    const CHANNEL_SIZE: usize = 64;
+
+    fn new() -> Self {
+        let (sender, _receiver) = broadcast::channel(Self::CHANNEL_SIZE);
+        Self(sender)
+    }
+
+    fn publish(&self, message: &message::Message) {
+        // Avoid copying the content of the message if nobody is listening.
+        if self.0.receiver_count() == 0 {
+            return;
+        }
+        // An error means there are no more subscribers, which is fine.
+        let _res = self
+            .0
+            .send((message.subject(), message.content().to_bytes()));
+    }
+
+    pub(crate) fn subscribe(&self) -> broadcast::Receiver<(messaging::Subject, Bytes)> {
+        self.0.subscribe()
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -121,6 +584,9 @@ pub(crate) enum Error<SvcRep, SvcErr> {
     #[error("messaging decoding error")]
     Decode(#[from] DecodeError),
 
+    #[error("message decompression error")]
+    Decompress(#[from] message::compression::DecompressError),
+
     #[error("message encoding error")]
     Encode(#[from] EncodeError),
 
@@ -128,13 +594,10 @@ pub(crate) enum Error<SvcRep, SvcErr> {
     ClientDispatch(#[source] PollSendError<RequestWithId>),
 
     #[error("server error")]
-    Server(#[source] PollSendError<server::Response<SvcRep, SvcErr>>),
+    Server(#[source] mpsc::error::SendError<server::Response<SvcRep, SvcErr>>),
 
     #[error("error converting a message into a request")]
-    MessageIntoRequest(#[source] format::Error),
-
-    #[error("error converting an error message content into an error description")]
-    GetErrorDescription(#[source] message::GetErrorDescriptionError),
+    MessageIntoRequest(#[source] messaging::FromMessageError),
 
     #[error("error converting a client request into a message")]
     RequestIntoMessage(#[source] format::Error),
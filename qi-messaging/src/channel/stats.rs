@@ -0,0 +1,189 @@
+//! The statistics of the messages that go through a channel.
+//!
+//! The channel records each message that it receives or sends in counters that are shared with the
+//! clients of its session. Reading them only loads a few atomics, so that applications may poll
+//! them as often as they report.
+
+use crate::message::{Kind, Message};
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+// The number of kinds of messages.
+const KIND_COUNT: usize = 8;
+
+fn kind_index(kind: Kind) -> usize {
+    match kind {
+        Kind::Call => 0,
+        Kind::Reply => 1,
+        Kind::Error => 2,
+        Kind::Post => 3,
+        Kind::Event => 4,
+        Kind::Capabilities => 5,
+        Kind::Cancel => 6,
+        Kind::Canceled => 7,
+    }
+}
+
+/// A snapshot of the statistics of the channel of a session, see
+/// [`Client::stats`](crate::session::Client::stats).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelStats {
+    received: TrafficStats,
+    sent: TrafficStats,
+}
+
+impl ChannelStats {
+    /// The statistics of the messages received from the remote end.
+    pub fn received(&self) -> &TrafficStats {
+        &self.received
+    }
+
+    /// The statistics of the messages sent to the remote end.
+    pub fn sent(&self) -> &TrafficStats {
+        &self.sent
+    }
+
+    /// The number of error messages received and sent, each of which terminated a call.
+    pub fn errors(&self) -> u64 {
+        self.received.kind(Kind::Error).messages() + self.sent.kind(Kind::Error).messages()
+    }
+
+    /// The number of cancellations of calls received and sent.
+    pub fn cancellations(&self) -> u64 {
+        self.received.kind(Kind::Cancel).messages() + self.sent.kind(Kind::Cancel).messages()
+    }
+
+    /// The last time a message was received or sent, if any.
+    pub fn last_activity(&self) -> Option<Instant> {
+        self.received.last_activity().max(self.sent.last_activity())
+    }
+}
+
+/// The statistics of the messages of a channel in one direction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrafficStats {
+    kinds: [KindStats; KIND_COUNT],
+    last_activity: Option<Instant>,
+}
+
+impl TrafficStats {
+    /// The total number of messages.
+    pub fn messages(&self) -> u64 {
+        self.kinds.iter().map(KindStats::messages).sum()
+    }
+
+    /// The total size of the messages, headers included, as they are on the wire.
+    pub fn bytes(&self) -> u64 {
+        self.kinds.iter().map(KindStats::bytes).sum()
+    }
+
+    /// The statistics of the messages of a kind.
+    pub fn kind(&self, kind: Kind) -> KindStats {
+        self.kinds[kind_index(kind)]
+    }
+
+    /// The last time a message was transferred, if any.
+    pub fn last_activity(&self) -> Option<Instant> {
+        self.last_activity
+    }
+}
+
+/// The statistics of the messages of a kind, in one direction.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct KindStats {
+    messages: u64,
+    bytes: u64,
+}
+
+impl KindStats {
+    pub fn messages(&self) -> u64 {
+        self.messages
+    }
+
+    /// The size of the messages, headers included, as they are on the wire.
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+}
+
+/// The counters of a channel, shared between the channel that records messages and the clients that
+/// read them.
+#[derive(Debug, Clone)]
+pub(crate) struct Stats(Arc<Counters>);
+
+#[derive(Debug)]
+struct Counters {
+    // The reference of the last activity timestamps.
+    opened: Instant,
+    received: TrafficCounters,
+    sent: TrafficCounters,
+}
+
+#[derive(Debug, Default)]
+struct TrafficCounters {
+    messages: [AtomicU64; KIND_COUNT],
+    bytes: [AtomicU64; KIND_COUNT],
+    // The duration since the opening of the channel in nanoseconds, plus one, or zero if no message
+    // was transferred yet.
+    last_activity: AtomicU64,
+}
+
+impl Stats {
+    pub(crate) fn new() -> Self {
+        Self(Arc::new(Counters {
+            opened: Instant::now(),
+            received: TrafficCounters::default(),
+            sent: TrafficCounters::default(),
+        }))
+    }
+
+    pub(crate) fn record_received(&self, message: &Message) {
+        self.0.received.record(self.0.opened, message);
+    }
+
+    pub(crate) fn record_sent(&self, message: &Message) {
+        self.0.sent.record(self.0.opened, message);
+    }
+
+    pub(crate) fn snapshot(&self) -> ChannelStats {
+        ChannelStats {
+            received: self.0.received.snapshot(self.0.opened),
+            sent: self.0.sent.snapshot(self.0.opened),
+        }
+    }
+}
+
+impl TrafficCounters {
+    fn record(&self, opened: Instant, message: &Message) {
+        // Counters are independent from each other, a snapshot may be taken in the middle of a
+        // record, which only delays the counting of the message.
+        let index = kind_index(message.kind());
+        self.messages[index].fetch_add(1, Ordering::Relaxed);
+        self.bytes[index].fetch_add(message.size() as u64, Ordering::Relaxed);
+        let elapsed = u64::try_from(opened.elapsed().as_nanos()).unwrap_or(u64::MAX - 1);
+        self.last_activity.fetch_max(elapsed + 1, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self, opened: Instant) -> TrafficStats {
+        let mut kinds = [KindStats::default(); KIND_COUNT];
+        for (index, stats) in kinds.iter_mut().enumerate() {
+            *stats = KindStats {
+                messages: self.messages[index].load(Ordering::Relaxed),
+                bytes: self.bytes[index].load(Ordering::Relaxed),
+            };
+        }
+        let last_activity = match self.last_activity.load(Ordering::Relaxed) {
+            0 => None,
+            nanos => opened.checked_add(Duration::from_nanos(nanos - 1)),
+        };
+        TrafficStats {
+            kinds,
+            last_activity,
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use crate::{
     messaging::{
-        self, Call, CallResult, Cancel, Notification, Reply, RequestId, RequestWithId, Service,
-        Subject, ToRequestId,
+        self, Call, CallResult, CallTermination, Cancel, Notification, Reply, Request, RequestId,
+        RequestWithId, Service, Subject, ToRequestId,
     },
     GetSubject,
 };
@@ -14,7 +14,10 @@ use std::{
     fmt::Debug,
     future::Future,
     pin::Pin,
-    sync::{atomic::AtomicU32, Arc},
+    sync::{
+        atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex, MutexGuard, PoisonError,
+    },
     task::{Context, Poll},
 };
 use tokio::{
@@ -23,7 +26,7 @@ use tokio::{
     task,
 };
 use tokio_util::sync::PollSender;
-use tracing::trace;
+use tracing::{debug, trace};
 
 pub(crate) fn setup<Si, St>(
     responses_stream: St,
@@ -37,11 +40,28 @@ where
     const DISPATCH_CHANNEL_SIZE: usize = 1;
     let (dispatch_sender, dispatch_receiver) = mpsc::channel(DISPATCH_CHANNEL_SIZE);
     let dispatch_sender = PollSender::new(dispatch_sender);
-    let dispatch = dispatch(dispatch_receiver, requests_sink, responses_stream);
+    let reply_chunks_senders = ReplyChunksSenders::default();
+    let pending_calls = PendingCalls::new();
+    let late_replies = LateReplies::default();
+    let ongoing_calls = OngoingCalls::default();
+    let dispatch = dispatch(
+        dispatch_receiver,
+        requests_sink,
+        responses_stream,
+        reply_chunks_senders.clone(),
+        pending_calls.clone(),
+        late_replies.clone(),
+        ongoing_calls.clone(),
+    );
     (
         Client {
             dispatch_request_sender: dispatch_sender,
             id_factory: IdFactory::new(),
+            reply_chunks_senders,
+            late_replies,
+            ongoing_calls,
+            #[cfg(feature = "debug")]
+            pending_calls,
         },
         dispatch,
     )
@@ -51,6 +71,274 @@ where
 pub(crate) struct Client {
     dispatch_request_sender: PollSender<DispatchRequest>,
     id_factory: IdFactory,
+    reply_chunks_senders: ReplyChunksSenders,
+    late_replies: LateReplies,
+    ongoing_calls: OngoingCalls,
+    #[cfg(feature = "debug")]
+    pending_calls: PendingCalls,
+}
+
+impl Client {
+    /// Sends a call for which the reply may be preceded by chunks.
+    ///
+    /// Chunks are sent by the remote as events with the same id and subject as the call. They are
+    /// yielded by the returned receiver until the call terminates.
+    ///
+    /// Up to [`REPLY_CHUNKS_CAPACITY`] chunks are buffered until they are received. The input of
+    /// the channel never waits for the caller, who may await the reply of the call before reading
+    /// its chunks, and whose reply would then never be read. Instead, if the buffer is full when a
+    /// chunk arrives, the call fails with [`Error::ReplyChunksOverflow`]: it is canceled on the
+    /// remote, the chunks that are already buffered are still yielded and the next ones are
+    /// discarded.
+    pub(crate) fn call_streaming(&self, call: Call) -> (mpsc::Receiver<Reply>, CallFuture) {
+        let (chunks_sender, chunks_receiver) = mpsc::channel(REPLY_CHUNKS_CAPACITY);
+        let call = CallFuture::new(
+            self.id_factory.create(),
+            call,
+            Some(chunks_sender),
+            self.id_factory.clone(),
+            self.dispatch_request_sender.clone(),
+        );
+        (chunks_receiver, call)
+    }
+
+    /// Returns the senders of the reply chunks of the ongoing streaming calls of this client.
+    pub(crate) fn reply_chunks_senders(&self) -> ReplyChunksSenders {
+        self.reply_chunks_senders.clone()
+    }
+
+    /// Creates an id for a request that is sent without the client, such as a batch of events.
+    pub(crate) fn create_request_id(&self) -> RequestId {
+        self.id_factory.create()
+    }
+
+    /// Returns the number of responses received for calls that were not waiting for them anymore.
+    pub(crate) fn late_replies(&self) -> u64 {
+        self.late_replies.get()
+    }
+
+    /// Returns the number of calls registered by the dispatch that are waiting for their response.
+    pub(crate) fn ongoing_calls(&self) -> usize {
+        self.ongoing_calls.get()
+    }
+
+    /// Returns the number of requests that are queued for the dispatch, and not yet handled by it.
+    pub(crate) fn queued_requests(&self) -> usize {
+        self.dispatch_request_sender
+            .get_ref()
+            .map_or(0, |sender| sender.max_capacity() - sender.capacity())
+    }
+
+    /// Returns true if the dispatch is terminated, in which case no request can be sent anymore.
+    pub(crate) fn is_closed(&self) -> bool {
+        self.dispatch_request_sender
+            .get_ref()
+            .is_none_or(mpsc::Sender::is_closed)
+    }
+
+    /// Returns a snapshot of the calls sent by this client that are waiting for their response.
+    #[cfg(feature = "debug")]
+    pub(crate) fn pending_calls(&self) -> Vec<PendingCall> {
+        self.pending_calls.snapshot()
+    }
+}
+
+/// A call sent by a client that is waiting for its response.
+#[cfg(feature = "debug")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PendingCall {
+    pub(crate) id: RequestId,
+    pub(crate) subject: Subject,
+    pub(crate) elapsed: std::time::Duration,
+    pub(crate) state: PendingCallState,
+}
+
+#[cfg(feature = "debug")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PendingCallState {
+    /// The call is being written to the channel.
+    Sending,
+    /// The call has been sent and is waiting for its response.
+    WaitingForResponse,
+}
+
+/// The registry of the calls of a client that are waiting for their response.
+///
+/// Calls are only registered if the `debug` feature is enabled, otherwise the registry does
+/// nothing.
+#[cfg(feature = "debug")]
+#[derive(Debug, Clone)]
+struct PendingCalls(Arc<Mutex<PendingCallsMap>>);
+
+#[cfg(feature = "debug")]
+type PendingCallsMap = HashMap<RequestId, (Subject, std::time::Instant, PendingCallState)>;
+
+#[cfg(feature = "debug")]
+impl PendingCalls {
+    fn new() -> Self {
+        Self(Arc::default())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, PendingCallsMap> {
+        // The map is always left in a consistent state, poisoning can be ignored.
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn insert(&self, id: RequestId, subject: Subject) {
+        self.lock().insert(
+            id,
+            (
+                subject,
+                std::time::Instant::now(),
+                PendingCallState::Sending,
+            ),
+        );
+    }
+
+    fn sent(&self, id: RequestId) {
+        if let Some((_, _, state)) = self.lock().get_mut(&id) {
+            *state = PendingCallState::WaitingForResponse;
+        }
+    }
+
+    fn remove(&self, id: RequestId) {
+        self.lock().remove(&id);
+    }
+
+    fn snapshot(&self) -> Vec<PendingCall> {
+        let mut calls: Vec<_> = self
+            .lock()
+            .iter()
+            .map(|(&id, &(subject, start, state))| PendingCall {
+                id,
+                subject,
+                elapsed: start.elapsed(),
+                state,
+            })
+            .collect();
+        calls.sort_by_key(|call| call.id);
+        calls
+    }
+}
+
+#[cfg(not(feature = "debug"))]
+#[derive(Debug, Clone)]
+struct PendingCalls;
+
+#[cfg(not(feature = "debug"))]
+impl PendingCalls {
+    fn new() -> Self {
+        Self
+    }
+
+    fn insert(&self, _id: RequestId, _subject: Subject) {}
+
+    fn sent(&self, _id: RequestId) {}
+
+    fn remove(&self, _id: RequestId) {}
+}
+
+/// The counter of the responses received for calls that were not waiting for them anymore.
+///
+/// A response may arrive after its call terminated on our side, for instance when it crosses the
+/// cancellation sent after a timeout. This is expected of a slow remote and is not an error.
+#[derive(Debug, Clone, Default)]
+struct LateReplies(Arc<AtomicU64>);
+
+impl LateReplies {
+    fn increment(&self) -> u64 {
+        self.0.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// The number of calls registered by the dispatch that are waiting for their response.
+#[derive(Debug, Clone, Default)]
+struct OngoingCalls(Arc<AtomicUsize>);
+
+impl OngoingCalls {
+    fn set(&self, count: usize) {
+        self.0.store(count, Ordering::Relaxed);
+    }
+
+    fn get(&self) -> usize {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// The number of reply chunks of a streaming call that are buffered until the caller receives
+/// them, see [`Client::call_streaming`].
+pub(crate) const REPLY_CHUNKS_CAPACITY: usize = 64;
+
+/// The senders of the reply chunks of ongoing streaming calls, indexed by the id of the call.
+///
+/// They are registered by the client dispatch before the call is sent, and removed when the call
+/// gets its response.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ReplyChunksSenders(Arc<Mutex<ReplyChunksSendersMap>>);
+
+type ReplyChunksSendersMap = HashMap<RequestId, ReplyChunksSender>;
+
+#[derive(Debug)]
+struct ReplyChunksSender {
+    subject: Subject,
+    sender: mpsc::Sender<Reply>,
+    // Once set, the next chunks are discarded and the call fails.
+    overflowed: bool,
+}
+
+impl ReplyChunksSenders {
+    fn lock(&self) -> MutexGuard<'_, ReplyChunksSendersMap> {
+        // The map is always left in a consistent state, poisoning can be ignored.
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn insert(&self, id: RequestId, subject: Subject, sender: mpsc::Sender<Reply>) {
+        let sender = ReplyChunksSender {
+            subject,
+            sender,
+            overflowed: false,
+        };
+        self.lock().insert(id, sender);
+    }
+
+    fn remove(&self, id: RequestId) -> Option<ReplyChunksSender> {
+        self.lock().remove(&id)
+    }
+
+    /// Returns true if a streaming call with this id and subject is ongoing, in which case the
+    /// events with the same id and subject are the chunks of its reply.
+    pub(crate) fn is_streaming(&self, id: RequestId, subject: Subject) -> bool {
+        self.lock()
+            .get(&id)
+            .is_some_and(|sender| sender.subject == subject)
+    }
+
+    /// Buffers a chunk of the reply of the call, without waiting.
+    ///
+    /// If the buffer is full, the chunk is discarded and the call is marked as overflowed: the
+    /// client dispatch fails it with [`Error::ReplyChunksOverflow`] once it gets its response,
+    /// whatever it is. Returns true if the call has just overflowed, in which case the caller
+    /// should terminate it without waiting for the remote.
+    #[must_use]
+    pub(crate) fn send(&self, id: RequestId, chunk: Reply) -> bool {
+        let mut senders = self.lock();
+        let sender = match senders.get_mut(&id) {
+            Some(sender) if !sender.overflowed => sender,
+            _ => return false,
+        };
+        match sender.sender.try_send(chunk) {
+            // The caller dropped the stream of chunks, they are not wanted anymore.
+            Ok(()) | Err(mpsc::error::TrySendError::Closed(_)) => false,
+            Err(mpsc::error::TrySendError::Full(_)) => {
+                sender.overflowed = true;
+                true
+            }
+        }
+    }
 }
 
 impl Service<Call, Notification> for Client {
@@ -80,6 +368,7 @@ impl Service<Call, Notification> for &Client {
         CallFuture::new(
             self.id_factory.create(),
             call,
+            None,
             self.id_factory.clone(),
             self.dispatch_request_sender.clone(),
         )
@@ -129,11 +418,12 @@ impl CallFuture {
     fn new(
         request_id: RequestId,
         call: Call,
+        reply_chunks_sender: Option<mpsc::Sender<Reply>>,
         id_factory: IdFactory,
         dispatch_request_sender: PollSender<DispatchRequest>,
     ) -> Self {
         let subject = *call.subject();
-        let running = CallFutureRunning::SendDispatchRequest(Some(call));
+        let running = CallFutureRunning::SendDispatchRequest(Some((call, reply_chunks_sender)));
         Self {
             request_id,
             subject,
@@ -174,6 +464,10 @@ impl Future for CallFuture {
                     &mut this.dispatch_request_sender,
                     cx
                 ));
+                if let Err(CallTermination::Error(Error::ReplyChunksOverflow)) = &result {
+                    // The remote is still sending the chunks of the reply, the call is canceled.
+                    task::spawn(this.cancel());
+                }
                 this.running = None;
                 Poll::Ready(result)
             }
@@ -200,8 +494,8 @@ impl futures::future::FusedFuture for CallFuture {
 
 #[derive(Debug)]
 enum CallFutureRunning {
-    SendDispatchRequest(Option<Call>),
-    WaitForResponse(oneshot::Receiver<CallResult<Reply, messaging::Error>>),
+    SendDispatchRequest(Option<(Call, Option<mpsc::Sender<Reply>>)>),
+    WaitForResponse(oneshot::Receiver<CallResult<Reply, Error>>),
 }
 
 impl CallFutureRunning {
@@ -217,7 +511,7 @@ impl CallFutureRunning {
                     ready!(dispatch_request_sender.poll_reserve(cx))
                         .map_err(|_err| Error::DispatchTerminated)?;
                     let (response_sender, response_receiver) = oneshot::channel();
-                    let call = match call.take() {
+                    let (call, reply_chunks_sender) = match call.take() {
                         Some(call) => call,
                         // Theoretically should not occur. The only possible case that
                         // it could happen is if `send_item` fails and user polls the
@@ -229,14 +523,14 @@ impl CallFutureRunning {
                             id,
                             call,
                             response_sender,
+                            reply_chunks_sender,
                         })
                         .map_err(|_err| Error::DispatchDroppedResponse)?;
                     *self = Self::WaitForResponse(response_receiver);
                 }
                 Self::WaitForResponse(response_receiver) => {
                     let reply = ready!(response_receiver.poll_unpin(cx))
-                        .map_err(|_err| Error::DispatchDroppedResponse)?
-                        .map_err(|err| err.map_err(Error::Messaging))?;
+                        .map_err(|_err| Error::DispatchDroppedResponse)??;
                     break Poll::Ready(Ok(reply));
                 }
             }
@@ -312,12 +606,17 @@ impl Future for NotifyFuture {
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let this = self.get_mut();
+        if this.notification.is_none() {
+            return Poll::Pending;
+        }
+        // Only take the notification once a slot is reserved, otherwise it would be lost if the
+        // dispatch channel is full.
+        ready!(this.dispatch_request_sender.poll_reserve(cx))
+            .map_err(|_err| Error::DispatchTerminated)?;
         let notif = match this.notification.take() {
             Some(notif) => notif,
             None => return Poll::Pending,
         };
-        ready!(this.dispatch_request_sender.poll_reserve(cx))
-            .map_err(|_err| Error::DispatchTerminated)?;
         this.dispatch_request_sender
             .send_item(DispatchRequest::Notification { id: this.id, notif })
             .map_err(|_err| Error::DispatchTerminated)?;
@@ -333,6 +632,11 @@ pub(crate) enum Error {
     #[error("the client dispatch task has dropped the request response")]
     DispatchDroppedResponse,
 
+    #[error(
+        "the reply chunks of the call overflowed their buffer of {REPLY_CHUNKS_CAPACITY} chunks"
+    )]
+    ReplyChunksOverflow,
+
     #[error(transparent)]
     Messaging(#[from] messaging::Error),
 }
@@ -341,6 +645,10 @@ async fn dispatch<St, Si>(
     mut request_receiver: mpsc::Receiver<DispatchRequest>,
     requests_sink: Si,
     responses_stream: St,
+    reply_chunks_senders: ReplyChunksSenders,
+    pending_calls: PendingCalls,
+    late_replies: LateReplies,
+    ongoing_calls: OngoingCalls,
 ) -> Result<(), Si::Error>
 where
     Si: Sink<RequestWithId>,
@@ -360,21 +668,44 @@ where
                         id,
                         call,
                         response_sender,
+                        reply_chunks_sender,
                     } => {
                         trace!(%id, "registering a call request waiting for a response from the server");
                         ongoing_call_requests.insert(id, response_sender);
+                        pending_calls.insert(id, *call.subject());
+                        if let Some(reply_chunks_sender) = reply_chunks_sender {
+                            reply_chunks_senders.insert(id, *call.subject(), reply_chunks_sender);
+                        }
                         (id, call.into())
                     }
                     DispatchRequest::Notification{ id, notif } => (id, notif.into()),
                 };
+                let is_call = matches!(request, Request::Call(_));
                 requests_sink.send(RequestWithId::new(id, request)).await?;
+                if is_call {
+                    pending_calls.sent(id);
+                }
             }
             Some((id, response)) = responses_stream.next() => {
                 trace!(response = ?response, "received a call response from the server");
-                if let Some(response_sender) = ongoing_call_requests.remove(&id) {
-                    if let Err(response) = response_sender.send(response) {
-                        trace!(response = ?response, "the client for a call request response has dropped, discarding response");
-                    }
+                // The response terminates the call, so does the stream of its reply chunks.
+                let overflowed = reply_chunks_senders
+                    .remove(id)
+                    .is_some_and(|sender| sender.overflowed);
+                pending_calls.remove(id);
+                let response = if overflowed {
+                    Err(CallTermination::Error(Error::ReplyChunksOverflow))
+                } else {
+                    response.map_err(|err| err.map_err(Error::Messaging))
+                };
+                let delivered = match ongoing_call_requests.remove(&id) {
+                    Some(response_sender) => response_sender.send(response).is_ok(),
+                    None => false,
+                };
+                if !delivered {
+                    // The call terminated before its response arrived, it is discarded.
+                    let count = late_replies.increment();
+                    debug!(%id, count, "received a late response to a call that is not waiting for it, discarding it");
                 }
             }
             else => {
@@ -384,7 +715,15 @@ where
         }
 
         // Cleanup ongoing call requests for which the client has dropped the channel.
-        ongoing_call_requests.retain(|_id, response_sender| !response_sender.is_closed())
+        ongoing_call_requests.retain(|id, response_sender| {
+            let closed = response_sender.is_closed();
+            if closed {
+                reply_chunks_senders.remove(*id);
+                pending_calls.remove(*id);
+            }
+            !closed
+        });
+        ongoing_calls.set(ongoing_call_requests.len());
     }
 }
 
@@ -393,7 +732,8 @@ pub(crate) enum DispatchRequest {
     Call {
         id: RequestId,
         call: Call,
-        response_sender: oneshot::Sender<CallResult<Reply, messaging::Error>>,
+        response_sender: oneshot::Sender<CallResult<Reply, Error>>,
+        reply_chunks_sender: Option<mpsc::Sender<Reply>>,
     },
     Notification {
         id: RequestId,
@@ -404,10 +744,7 @@ pub(crate) enum DispatchRequest {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{
-        messaging::{CallTermination, Post, Reply, Request, Subject},
-        service,
-    };
+    use crate::messaging::{CallTermination, Post, Reply, Request, Subject};
     use assert_matches::assert_matches;
     use futures::future::{poll_immediate, BoxFuture};
     use tokio_stream::wrappers::ReceiverStream;
@@ -528,6 +865,48 @@ mod tests {
         assert_matches!(poll_immediate(test.requests_rx.recv()).await, None);
     }
 
+    #[cfg(feature = "debug")]
+    #[tokio::test]
+    async fn test_client_pending_calls() {
+        use crate::types::object::{ActionId, ObjectId, ServiceId};
+
+        let mut test = TestClient::new();
+        assert_eq!(test.client.pending_calls(), []);
+
+        let subject = Subject::new(ServiceId::new(1), ObjectId::new(2), ActionId::new(3));
+        let mut call_future = test
+            .client
+            .call(Call::new(subject).with_formatted_value([1, 2, 3, 4].into()));
+        assert_matches!(poll_immediate(&mut call_future).await, None);
+        assert_matches!(poll_immediate(&mut test.dispatch).await, None);
+        assert_matches!(poll_immediate(test.requests_rx.recv()).await, Some(Some(_)));
+        assert_matches!(test.client.pending_calls().as_slice(), [call] => {
+            assert_eq!(call.id, RequestId(1));
+            assert_eq!(call.subject, subject);
+            assert_eq!(call.state, PendingCallState::WaitingForResponse);
+        });
+
+        // The response terminates the call.
+        test.responses_tx
+            .send((RequestId(1), Ok(Reply::new([5, 6, 7, 8].into()))))
+            .await
+            .unwrap();
+        assert_matches!(poll_immediate(&mut test.dispatch).await, None);
+        assert_eq!(test.client.pending_calls(), []);
+
+        // A call is not pending anymore once it is canceled.
+        let mut call_future = test
+            .client
+            .call(Call::new(subject).with_formatted_value([1, 2, 3, 4].into()));
+        assert_matches!(poll_immediate(&mut call_future).await, None);
+        assert_matches!(poll_immediate(&mut test.dispatch).await, None);
+        assert_matches!(poll_immediate(test.requests_rx.recv()).await, Some(Some(_)));
+        assert_eq!(test.client.pending_calls().len(), 1);
+        assert_matches!(poll_immediate(call_future.cancel()).await, Some(()));
+        assert_matches!(poll_immediate(&mut test.dispatch).await, None);
+        assert_eq!(test.client.pending_calls(), []);
+    }
+
     #[tokio::test]
     async fn test_client_call_cancel() {
         let mut test = TestClient::new();
@@ -623,7 +1002,7 @@ mod tests {
         test.responses_tx
             .send((
                 RequestId(1),
-                Err(CallTermination::Error(messaging::Error(
+                Err(CallTermination::Error(messaging::Error::from(
                     "some error".to_owned(),
                 ))),
             ))
@@ -634,8 +1013,8 @@ mod tests {
         // The call gets its response.
         assert_matches!(
             poll_immediate(&mut call_future).await,
-            Some(Err(CallTermination::Error(Error::Messaging(service::Error(err))))) => {
-                assert_eq!(err, "some error");
+            Some(Err(CallTermination::Error(Error::Messaging(err)))) => {
+                assert_eq!(err.reason(), "some error");
             }
         );
     }
@@ -719,6 +1098,71 @@ mod tests {
         });
     }
 
+    #[tokio::test]
+    async fn test_client_ongoing_calls() {
+        let mut test = TestClient::new();
+        assert_eq!(test.client.ongoing_calls(), 0);
+        assert!(!test.client.is_closed());
+
+        let mut call_future = test.client.call(Call::new(Subject::default()));
+        assert_matches!(poll_immediate(&mut call_future).await, None);
+        assert_eq!(test.client.queued_requests(), 1);
+        assert_matches!(poll_immediate(&mut test.dispatch).await, None);
+        assert_eq!(test.client.queued_requests(), 0);
+        assert_eq!(test.client.ongoing_calls(), 1);
+
+        test.responses_tx
+            .send((RequestId(1), Ok(Reply::new([1, 2].into()))))
+            .await
+            .unwrap();
+        assert_matches!(poll_immediate(&mut test.dispatch).await, None);
+        assert_eq!(test.client.ongoing_calls(), 0);
+
+        drop(test.dispatch);
+        assert!(test.client.is_closed());
+    }
+
+    #[tokio::test]
+    async fn test_client_late_response_after_cancel() {
+        let mut test = TestClient::new();
+
+        let mut call_future = test.client.call(Call::new(Subject::default()));
+        assert_matches!(poll_immediate(&mut call_future).await, None);
+        assert_matches!(poll_immediate(&mut test.dispatch).await, None);
+        assert_matches!(poll_immediate(test.requests_rx.recv()).await, Some(Some(_)));
+
+        // The call is canceled and dropped, then the response of the remote crosses the
+        // cancellation.
+        assert_matches!(poll_immediate(call_future.cancel()).await, Some(()));
+        drop(call_future);
+        assert_matches!(poll_immediate(&mut test.dispatch).await, None);
+        assert_matches!(poll_immediate(test.requests_rx.recv()).await, Some(Some(_)));
+        test.responses_tx
+            .send((RequestId(1), Ok(Reply::new([1, 2].into()))))
+            .await
+            .unwrap();
+        assert_matches!(poll_immediate(&mut test.dispatch).await, None);
+        assert_eq!(test.client.late_replies(), 1);
+
+        // The client is still operational.
+        let mut call_future = test.client.call(Call::new(Subject::default()));
+        assert_matches!(poll_immediate(&mut call_future).await, None);
+        assert_matches!(poll_immediate(&mut test.dispatch).await, None);
+        assert_matches!(
+            poll_immediate(test.requests_rx.recv()).await,
+            Some(Some(request)) => {
+                assert_eq!(request.id(), RequestId(3));
+            }
+        );
+        test.responses_tx
+            .send((RequestId(3), Ok(Reply::new([3, 4].into()))))
+            .await
+            .unwrap();
+        assert_matches!(poll_immediate(&mut test.dispatch).await, None);
+        assert_matches!(poll_immediate(&mut call_future).await, Some(Ok(_)));
+        assert_eq!(test.client.late_replies(), 1);
+    }
+
     #[tokio::test]
     async fn test_client_sink_error_stops_dispatch_task() {
         let mut test = TestClient::new();
@@ -0,0 +1,350 @@
+//! A checklist of the behaviors expected from a peer, that may be run against any of them.
+//!
+//! [`run`] establishes a session over a connection, and runs a scripted sequence of requests
+//! against the service directory of the peer. The outcome of each step is collected in a
+//! [`Report`], so that differences between versions of the protocol, such as those of robots that
+//! run older versions of `libqi`, can be diagnosed in one go.
+
+use crate::{
+    format,
+    session::{self, Call, CallWithId, ClientError, NotificationWithId},
+    types::object::{ActionId, MetaObject, ObjectId, ServiceId},
+    CallResult, CallTermination, CapabilitiesMap, Service,
+};
+use futures::future;
+use std::{fmt, time::Duration};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    pin, select,
+};
+
+/// The service directory is the service with id 1 of a namespace, and its main object has id 1.
+const SERVICE_DIRECTORY: ServiceId = ServiceId::new(1);
+const MAIN_OBJECT: ObjectId = ObjectId::new(1);
+/// The action of all objects that returns their meta object.
+const META_OBJECT_ACTION: ActionId = ActionId::new(2);
+/// An action that objects are not expected to have.
+const UNKNOWN_ACTION: ActionId = ActionId::new(0xdead);
+
+/// The duration after which a step of the checklist fails if the peer has not responded.
+pub const STEP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The names of the checks, in the order in which they are run.
+pub const CHECKS: [&str; 5] = [
+    "authentication",
+    "capabilities",
+    "directory_meta_object",
+    "unknown_action_error",
+    "cancel",
+];
+
+/// Runs the checklist against the peer at the other end of the connection.
+///
+/// The checks are run in order, a check that cannot run because a previous one failed is
+/// skipped. The connection is closed when the checklist is done.
+pub async fn run<IO>(io: IO) -> Report
+where
+    IO: AsyncRead + AsyncWrite,
+{
+    let (client, dispatch) = session::connect(io, NoService);
+    pin!(dispatch);
+    let mut report = Report::default();
+    let terminated = {
+        let checks = run_checks(client, &mut report);
+        pin!(checks);
+        select! {
+            () = &mut checks => None,
+            result = &mut dispatch => Some(match result {
+                Ok(()) => "the session was closed by the peer".to_owned(),
+                Err(err) => format!("the session terminated with an error: {err}"),
+            }),
+        }
+    };
+    let reason = terminated.unwrap_or_else(|| "the checklist was interrupted".to_owned());
+    for name in CHECKS.into_iter().skip(report.checks.len()) {
+        report.push(name, Outcome::Failed(reason.clone()));
+    }
+    report
+}
+
+async fn run_checks(client: impl future::Future<Output = ConnectResult>, report: &mut Report) {
+    let client = match client.await {
+        Ok(client) => {
+            report.push(CHECKS[0], Outcome::Passed(String::new()));
+            client
+        }
+        Err(err) => {
+            report.push(CHECKS[0], Outcome::Failed(err.to_string()));
+            for name in CHECKS.into_iter().skip(1) {
+                report.push(
+                    name,
+                    Outcome::Skipped("the session is not established".to_owned()),
+                );
+            }
+            return;
+        }
+    };
+    client
+        .config()
+        .update(|config| config.with_call_timeout(Some(STEP_TIMEOUT)));
+
+    let capabilities = client.capabilities();
+    report.push(
+        CHECKS[1],
+        Outcome::Passed(
+            capabilities
+                .iter()
+                .map(|(key, value)| format!("{key}={value}"))
+                .collect::<Vec<_>>()
+                .join(", "),
+        ),
+    );
+    report.capabilities = Some(capabilities);
+
+    let outcome = match call(&client, META_OBJECT_ACTION).await {
+        Ok(reply) => match reply.value_with_utf8_policy::<MetaObject>(format::Utf8Policy::Lossy) {
+            Ok(meta_object) => Outcome::Passed(format!(
+                "{} methods, {} signals, {} properties",
+                meta_object.methods.len(),
+                meta_object.signals.len(),
+                meta_object.properties.len()
+            )),
+            Err(err) => {
+                Outcome::Failed(format!("the meta object could not be deserialized: {err}"))
+            }
+        },
+        Err(termination) => Outcome::Failed(termination_reason(&termination)),
+    };
+    report.push(CHECKS[2], outcome);
+
+    let outcome = match call(&client, UNKNOWN_ACTION).await {
+        Err(CallTermination::Error(ClientError::Service(err))) => Outcome::Passed(err.to_string()),
+        Ok(_reply) => Outcome::Failed("the call of an unknown action was replied to".to_owned()),
+        Err(termination) => Outcome::Failed(termination_reason(&termination)),
+    };
+    report.push(CHECKS[3], outcome);
+
+    // Whether the peer acknowledges the cancellation or replies first, the session must go on.
+    let mut sender = &client;
+    let canceled = sender.call(meta_object_call());
+    canceled.cancel().await;
+    let outcome = match call(&client, META_OBJECT_ACTION).await {
+        Ok(_reply) => Outcome::Passed(String::new()),
+        Err(termination) => Outcome::Failed(format!(
+            "a call after a cancellation failed: {}",
+            termination_reason(&termination)
+        )),
+    };
+    report.push(CHECKS[4], outcome);
+}
+
+type ConnectResult = Result<session::Client, session::ConnectError>;
+
+fn meta_object_call() -> Call {
+    directory_call(META_OBJECT_ACTION)
+}
+
+fn directory_call(action: ActionId) -> Call {
+    let service_object = session::subject::ServiceObject::new(SERVICE_DIRECTORY, MAIN_OBJECT)
+        .unwrap_or_else(|| unreachable!("the service directory is not the control service"));
+    let subject = session::Subject::new(service_object, action);
+    Call::new(subject)
+        .with_value(&(MAIN_OBJECT,))
+        .unwrap_or_else(|_err| unreachable!("an object id is always serializable"))
+}
+
+async fn call(
+    client: &session::Client,
+    action: ActionId,
+) -> CallResult<session::Reply, ClientError> {
+    let mut client = client;
+    client.call(directory_call(action)).await
+}
+
+fn termination_reason(termination: &CallTermination<ClientError>) -> String {
+    match termination {
+        CallTermination::Canceled => "the call was canceled by the peer".to_owned(),
+        CallTermination::Error(err) => err.to_string(),
+    }
+}
+
+/// The outcomes of the checks run against a peer, see [`run`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Report {
+    checks: Vec<Check>,
+    capabilities: Option<CapabilitiesMap>,
+}
+
+impl Report {
+    /// The checks, in the order in which they were run.
+    pub fn checks(&self) -> &[Check] {
+        &self.checks
+    }
+
+    pub fn check(&self, name: &str) -> Option<&Check> {
+        self.checks.iter().find(|check| check.name == name)
+    }
+
+    /// The capabilities resolved with the peer, if the session was established.
+    pub fn capabilities(&self) -> Option<&CapabilitiesMap> {
+        self.capabilities.as_ref()
+    }
+
+    /// Returns true if no check failed nor was skipped.
+    pub fn is_conformant(&self) -> bool {
+        self.checks
+            .iter()
+            .all(|check| matches!(check.outcome, Outcome::Passed(_)))
+    }
+
+    fn push(&mut self, name: &'static str, outcome: Outcome) {
+        self.checks.push(Check { name, outcome });
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            writeln!(f, "{check}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Check {
+    name: &'static str,
+    outcome: Outcome,
+}
+
+impl Check {
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn outcome(&self) -> &Outcome {
+        &self.outcome
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (status, details) = match &self.outcome {
+            Outcome::Passed(details) => ("pass", details),
+            Outcome::Failed(reason) => ("FAIL", reason),
+            Outcome::Skipped(reason) => ("skip", reason),
+        };
+        write!(f, "[{status}] {}", self.name)?;
+        if !details.is_empty() {
+            write!(f, ": {details}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The outcome of a check, with details or the reason of a failure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Passed(String),
+    Failed(String),
+    Skipped(String),
+}
+
+/// The service of the session of the checklist, the peer is not expected to call it.
+#[derive(Debug)]
+struct NoService;
+
+impl Service<CallWithId, NotificationWithId> for NoService {
+    type CallReply = ();
+    type Error = String;
+    type CallFuture = future::Ready<CallResult<(), String>>;
+    type NotifyFuture = future::Ready<Result<(), String>>;
+
+    fn call(&mut self, _call: CallWithId) -> Self::CallFuture {
+        future::err(CallTermination::Error(
+            "the conformance checklist serves no object".to_owned(),
+        ))
+    }
+
+    fn notify(&mut self, _notif: NotificationWithId) -> Self::NotifyFuture {
+        future::ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GetSubject;
+    use futures::{future::BoxFuture, FutureExt};
+    use tokio::{io, spawn};
+
+    /// A peer with a service directory that has no method.
+    struct Directory {
+        reply_unknown_actions: bool,
+    }
+
+    impl Service<CallWithId, NotificationWithId> for Directory {
+        type CallReply = MetaObject;
+        type Error = String;
+        type CallFuture = BoxFuture<'static, CallResult<MetaObject, String>>;
+        type NotifyFuture = future::Ready<Result<(), String>>;
+
+        fn call(&mut self, call: CallWithId) -> Self::CallFuture {
+            let action = call.inner().subject().action();
+            let result = if action == META_OBJECT_ACTION || self.reply_unknown_actions {
+                Ok(MetaObject::default())
+            } else {
+                Err(CallTermination::Error(format!("no action {action}")))
+            };
+            future::ready(result).boxed()
+        }
+
+        fn notify(&mut self, _notif: NotificationWithId) -> Self::NotifyFuture {
+            future::ok(())
+        }
+    }
+
+    async fn run_against(directory: Directory) -> Report {
+        let (io_client, io_server) = io::duplex(4096);
+        let (server, server_dispatch) = session::listen(io_server, directory);
+        spawn(server_dispatch);
+        let (report, server) = tokio::join!(run(io_client), server);
+        drop(server);
+        report
+    }
+
+    #[tokio::test]
+    async fn test_run_conformant_peer() {
+        let report = run_against(Directory {
+            reply_unknown_actions: false,
+        })
+        .await;
+        let names: Vec<_> = report.checks().iter().map(Check::name).collect();
+        assert_eq!(names, CHECKS);
+        assert!(report.is_conformant(), "{report}");
+        assert!(report.capabilities().is_some());
+        assert_eq!(
+            report.check("directory_meta_object").map(Check::outcome),
+            Some(&Outcome::Passed(
+                "0 methods, 0 signals, 0 properties".to_owned()
+            ))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_peer_replying_to_unknown_actions() {
+        let report = run_against(Directory {
+            reply_unknown_actions: true,
+        })
+        .await;
+        assert!(!report.is_conformant());
+        assert_matches::assert_matches!(
+            report.check("unknown_action_error").map(Check::outcome),
+            Some(Outcome::Failed(_))
+        );
+        assert_matches::assert_matches!(
+            report.check("cancel").map(Check::outcome),
+            Some(Outcome::Passed(_))
+        );
+    }
+}
@@ -43,18 +43,31 @@
 #![doc(test(attr(deny(warnings))))]
 #![doc = include_str!("../README.md")]
 
+#[doc(hidden)]
+pub mod bench;
 mod capabilities;
 mod channel;
 mod client;
-mod message;
+pub mod conformance;
+pub mod message;
 mod messaging;
 mod server;
 mod service;
 pub mod session;
+mod subject_router;
+mod trace_context;
 
 use qi_format as format;
 use qi_types as types;
+// Only used by the benchmarks.
+#[cfg(test)]
+use criterion as _;
 
 pub use service::{CallResult, CallTermination, GetSubject, Service, ToRequestId};
+pub use subject_router::{SubjectPattern, SubjectRouter};
+pub use trace_context::{ParseTraceContextError, TraceContext};
 #[doc(inline)]
-pub use {capabilities::CapabilitiesMap, service::RequestId};
+pub use {
+    capabilities::{CapabilitiesMap, Capability},
+    service::RequestId,
+};
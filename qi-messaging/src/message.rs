@@ -41,11 +41,38 @@
 //!    - action
 //!
 //!  The total header size is therefore 28 bytes.
+//!
+//! ## Building messages
+//!
+//! Messages are built from the constructor of their kind, such as [`Message::call`], then
+//! completed with a [`Builder`]. Building checks that the content of the message is consistent with
+//! its kind:
+//!
+//! ```
+//! use qi_messaging::message::{Flags, Id, Message, Subject};
+//! # use qi_types::object::{ActionId, ObjectId, ServiceId};
+//!
+//! let subject = Subject::new(ServiceId::new(1), ObjectId::new(1), ActionId::new(100));
+//! let call = Message::call(Id::new(1), subject)
+//!     .payload(bytes::Bytes::from_static(&[1, 0, 0, 0]))
+//!     .flag(Flags::RETURN_TYPE)
+//!     .build()
+//!     .unwrap();
+//! assert_eq!(call.id(), Id::new(1));
+//!
+//! // A cancel message must hold the id of the canceled call.
+//! assert!(Message::cancel(Id::new(2), subject, Id::new(1)).build().is_ok());
+//! assert!(Message::cancel(Id::new(2), subject, Id::new(1))
+//!     .payload(bytes::Bytes::new())
+//!     .build()
+//!     .is_err());
+//! ```
 
 pub(crate) mod codec;
+pub(crate) mod compression;
 
 use crate::{capabilities, format, types};
-use bytes::{Buf, BufMut};
+use bytes::{buf::Chain, Buf, BufMut, Bytes, BytesMut};
 use types::{
     object::{ActionId, ObjectId, ServiceId},
     Dynamic,
@@ -78,7 +105,6 @@ impl Default for Id {
 impl Id {
     const SIZE: usize = std::mem::size_of::<u32>();
 
-    #[allow(unused)]
     pub const fn new(value: u32) -> Self {
         Self(value)
     }
@@ -99,13 +125,24 @@ impl Id {
 }
 
 #[derive(
-    Default, Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, derive_more::Display,
+    Default,
+    Debug,
+    Hash,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Clone,
+    Copy,
+    derive_more::Display,
+    derive_more::From,
+    derive_more::Into,
 )]
-struct Version(u16);
+pub(crate) struct Version(u16);
 
 impl Version {
     const SIZE: usize = std::mem::size_of::<u16>();
-    const CURRENT: Self = Self(0);
+    pub(crate) const CURRENT: Self = Self(0);
 
     fn read<B>(buf: &mut B) -> Self
     where
@@ -136,7 +173,7 @@ impl Version {
     derive_more::Display,
 )]
 #[display(fmt = "({service}, {object}, {action})")]
-pub(crate) struct Subject {
+pub struct Subject {
     service: ServiceId,
     object: ObjectId,
     action: ActionId,
@@ -145,15 +182,15 @@ pub(crate) struct Subject {
 impl Subject {
     const SIZE: usize = std::mem::size_of::<u32>() * 3;
 
-    pub(crate) const fn service(&self) -> ServiceId {
+    pub const fn service(&self) -> ServiceId {
         self.service
     }
 
-    pub(crate) const fn object(&self) -> ObjectId {
+    pub const fn object(&self) -> ObjectId {
         self.object
     }
 
-    pub(crate) const fn action(&self) -> ActionId {
+    pub const fn action(&self) -> ActionId {
         self.action
     }
 
@@ -275,7 +312,7 @@ pub(crate) struct BodyCannotBeRepresentedAsU32Error(usize);
     num_derive::ToPrimitive,
 )]
 #[repr(u8)]
-pub(crate) enum Kind {
+pub enum Kind {
     #[display(fmt = "call")]
     Call = 1,
     #[display(fmt = "reply")]
@@ -336,14 +373,19 @@ impl std::convert::TryFrom<u8> for Kind {
 
 #[derive(Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Hash, thiserror::Error)]
 #[error("invalid message kind value {0}")]
-pub(crate) struct InvalidKindValueError(u8);
+pub struct InvalidKindValueError(u8);
 
 bitflags::bitflags! {
     #[derive(Default, derive_more::Display)]
     #[display(fmt = "{:b}", "self.bits()")]
-    pub(crate) struct Flags: u8 {
+    pub struct Flags: u8 {
         const DYNAMIC_PAYLOAD = 0b00000001;
         const RETURN_TYPE = 0b00000010;
+        // Extension of this implementation, the body is compressed, see `compression`.
+        const COMPRESSED = 0b00000100;
+        // Extension of this implementation, the body starts with a trace context, see
+        // `TraceContext`.
+        const TRACE_CONTEXT = 0b00001000;
     }
 }
 
@@ -377,11 +419,12 @@ impl std::convert::TryFrom<u8> for Flags {
 
 #[derive(Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, thiserror::Error)]
 #[error("invalid message flags value {0}")]
-pub(crate) struct InvalidFlagsValueError(u8);
+pub struct InvalidFlagsValueError(u8);
 
 #[derive(Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
 struct Header {
     id: Id,
+    version: Version,
     kind: Kind,
     body_size: usize,
     flags: Flags,
@@ -414,6 +457,7 @@ impl Header {
         let subject = Subject::read(buf);
         Ok(Self {
             id,
+            version,
             kind: ty,
             body_size,
             flags,
@@ -465,8 +509,9 @@ pub(crate) enum WriteHeaderError {
 
 #[derive(Default, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash, derive_more::Display)]
 #[display(fmt = "message(id={id}, {kind}, subject={subject}, flags={flags})")]
-pub(crate) struct Message {
+pub struct Message {
     id: Id,
+    version: Version,
     kind: Kind,
     subject: Subject,
     flags: Flags,
@@ -477,6 +522,7 @@ impl Message {
     fn new(header: Header, body: format::Value) -> Self {
         Self {
             id: header.id,
+            version: header.version,
             kind: header.kind,
             subject: header.subject,
             flags: header.flags,
@@ -487,7 +533,7 @@ impl Message {
     /// Builds a "call" message.
     ///
     /// This sets the kind, the id and the subject of the message.
-    pub(crate) fn call(id: Id, subject: Subject) -> Builder {
+    pub fn call(id: Id, subject: Subject) -> Builder {
         Builder::new()
             .set_id(id)
             .set_kind(Kind::Call)
@@ -497,7 +543,7 @@ impl Message {
     /// Builds a "reply" message.
     ///
     /// This sets the kind, the id and the subject of the message.
-    pub(crate) fn reply(id: Id, subject: Subject) -> Builder {
+    pub fn reply(id: Id, subject: Subject) -> Builder {
         Builder::new()
             .set_id(id)
             .set_kind(Kind::Reply)
@@ -507,11 +553,7 @@ impl Message {
     /// Builds a "error" message.
     ///
     /// This sets the kind, the id, the subject and the content of the message.
-    pub(crate) fn error(
-        id: Id,
-        subject: Subject,
-        description: &str,
-    ) -> Result<Builder, format::Error> {
+    pub fn error(id: Id, subject: Subject, description: &str) -> Result<Builder, format::Error> {
         Builder::new()
             .set_id(id)
             .set_kind(Kind::Error)
@@ -522,7 +564,7 @@ impl Message {
     /// Builds a "post" message.
     ///
     /// This sets the kind, the id and the subject of the message.
-    pub(crate) fn post(id: Id, subject: Subject) -> Builder {
+    pub fn post(id: Id, subject: Subject) -> Builder {
         Builder::new()
             .set_id(id)
             .set_kind(Kind::Post)
@@ -532,7 +574,7 @@ impl Message {
     /// Builds a "event" message.
     ///
     /// This sets the kind, the id and the subject of the message.
-    pub(crate) fn event(id: Id, subject: Subject) -> Builder {
+    pub fn event(id: Id, subject: Subject) -> Builder {
         Builder::new()
             .set_id(id)
             .set_kind(Kind::Event)
@@ -542,7 +584,7 @@ impl Message {
     /// Builds a "capabilities" message.
     ///
     /// This sets the kind, the id, the subject and the content of the message.
-    pub(crate) fn capabilities(
+    pub fn capabilities(
         id: Id,
         subject: Subject,
         map: &capabilities::CapabilitiesMap,
@@ -557,7 +599,7 @@ impl Message {
     /// Builds a "cancel" message.
     ///
     /// This sets the kind, the id, the subject and the content of the message.
-    pub(crate) fn cancel(id: Id, subject: Subject, call_id: Id) -> Builder {
+    pub fn cancel(id: Id, subject: Subject, call_id: Id) -> Builder {
         Builder::new()
             .set_id(id)
             .set_kind(Kind::Cancel)
@@ -569,45 +611,113 @@ impl Message {
     /// Builds a "canceled" message.
     ///
     /// This sets the kind, the id and the subject of the message.
-    pub(crate) fn canceled(id: Id, subject: Subject) -> Builder {
+    pub fn canceled(id: Id, subject: Subject) -> Builder {
         Builder::new()
             .set_id(id)
             .set_subject(subject)
             .set_kind(Kind::Canceled)
     }
 
-    fn write<B>(self, buf: &mut B) -> Result<(), WriteHeaderError>
-    where
-        B: BufMut,
-    {
+    fn header(&self) -> Header {
         Header {
             id: self.id,
+            version: self.version,
             kind: self.kind,
-            body_size: self.content.to_bytes().len(),
+            body_size: self.content.as_bytes().len(),
             flags: self.flags,
             subject: self.subject,
         }
-        .write(buf)?;
+    }
+
+    fn write<B>(self, buf: &mut B) -> Result<(), WriteHeaderError>
+    where
+        B: BufMut,
+    {
+        self.header().write(buf)?;
         buf.put(self.content.to_bytes());
         Ok(())
     }
 
-    pub(crate) fn id(&self) -> Id {
+    /// Encodes the header of the message and chains it with the payload.
+    ///
+    /// The payload is the buffer of the content of the message, it is shared and not copied.
+    fn into_frame(self) -> Result<Chain<Bytes, Bytes>, WriteHeaderError> {
+        let mut headers = BytesMut::with_capacity(Header::SIZE);
+        self.into_frame_in(&mut headers)
+    }
+
+    /// Encodes the header of the message at the end of a buffer shared by the headers of several
+    /// frames, and chains it with the payload.
+    ///
+    /// The header is split from the buffer, so that all the headers of a batch share a single
+    /// allocation.
+    fn into_frame_in(
+        self,
+        headers: &mut BytesMut,
+    ) -> Result<Chain<Bytes, Bytes>, WriteHeaderError> {
+        self.header().write(headers)?;
+        Ok(headers.split().freeze().chain(self.content.to_bytes()))
+    }
+
+    pub fn id(&self) -> Id {
         self.id
     }
 
-    pub(crate) fn kind(&self) -> Kind {
+    /// The version of the protocol in the header of the message, as it was received.
+    pub(crate) fn version(&self) -> Version {
+        self.version
+    }
+
+    pub fn kind(&self) -> Kind {
         self.kind
     }
 
-    pub(crate) fn subject(&self) -> Subject {
+    pub fn flags(&self) -> Flags {
+        self.flags
+    }
+
+    pub fn subject(&self) -> Subject {
         self.subject
     }
 
-    pub(crate) fn into_content(self) -> format::Value {
+    pub fn content(&self) -> &format::Value {
+        &self.content
+    }
+
+    pub fn into_content(self) -> format::Value {
         self.content
     }
 
+    pub(crate) fn is_compressed(&self) -> bool {
+        self.flags.contains(Flags::COMPRESSED)
+    }
+
+    /// Compresses the content of the message.
+    ///
+    /// The message is left uncompressed if compressing its content does not make it smaller.
+    pub(crate) fn compress(mut self) -> Self {
+        if self.is_compressed() {
+            return self;
+        }
+        if let Some(compressed) = compression::compress(self.content.as_bytes()) {
+            if compressed.len() < self.content.as_bytes().len() {
+                self.content = format::Value::from_bytes(compressed);
+                self.flags.insert(Flags::COMPRESSED);
+            }
+        }
+        self
+    }
+
+    /// Decompresses the content of the message, if it is compressed.
+    pub(crate) fn decompress(mut self) -> Result<Self, compression::DecompressError> {
+        if self.is_compressed() {
+            let content = compression::decompress(self.content.as_bytes())?;
+            self.content = format::Value::from_bytes(content);
+            self.flags.remove(Flags::COMPRESSED);
+        }
+        Ok(self)
+    }
+
     pub(crate) fn size(&self) -> usize {
         Header::SIZE + self.content.as_bytes().len()
     }
@@ -620,26 +730,37 @@ impl Message {
         self.content.to_deserializable()
     }
 
-    pub(crate) fn deserialize_error_description(&self) -> Result<String, GetErrorDescriptionError> {
-        let dynamic: Dynamic = self.deserialize_content()?;
-        match dynamic {
-            Dynamic::String(s) => Ok(s),
-            d => Err(GetErrorDescriptionError::DynamicValueIsNotAString(d)),
+    /// Decodes the description of the error of a call from the content of the message.
+    ///
+    /// Implementations do not all encode it the same way. The description is expected to be a
+    /// dynamic string, but some send a plain string, and others a dynamic value of another type,
+    /// such as a structure, that is then described as it is. Strings that are not valid UTF-8 are
+    /// decoded lossily. Returns `None` if none of these encodings applies.
+    pub(crate) fn error_description(&self) -> Option<String> {
+        let policy = format::Utf8Policy::Lossy;
+        if let Ok(dynamic) = self
+            .content
+            .to_deserializable_with_utf8_policy::<Dynamic>(policy)
+        {
+            return Some(describe_error(dynamic));
         }
+        self.content
+            .to_deserializable_with_utf8_policy::<String>(policy)
+            .ok()
     }
 }
 
-#[derive(Debug, thiserror::Error)]
-pub(crate) enum GetErrorDescriptionError {
-    #[error("dynamic value {0} of error description is not a string")]
-    DynamicValueIsNotAString(Dynamic),
-
-    #[error(transparent)]
-    Format(#[from] format::Error),
+fn describe_error(dynamic: Dynamic) -> String {
+    match dynamic {
+        Dynamic::String(description) => description,
+        Dynamic::Dynamic(dynamic) => describe_error(*dynamic),
+        dynamic => dynamic.to_string(),
+    }
 }
 
+/// A builder of a [`Message`], returned by the constructors of each kind of message.
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
-pub(crate) struct Builder(Message);
+pub struct Builder(Message);
 
 impl Default for Builder {
     fn default() -> Self {
@@ -688,11 +809,64 @@ impl Builder {
         self.set_value(&Dynamic::from(description))
     }
 
-    pub(crate) fn build(self) -> Message {
+    /// Sets the content of the message, as it is represented in the format.
+    pub fn payload(self, payload: Bytes) -> Self {
+        self.set_content(format::Value::from_bytes(payload))
+    }
+
+    /// Sets the content of the message to the representation of a value in the format.
+    pub fn value<T>(self, value: &T) -> Result<Self, format::Error>
+    where
+        T: serde::Serialize,
+    {
+        self.set_value(value)
+    }
+
+    /// Adds a flag to the message.
+    pub fn flag(mut self, flag: Flags) -> Self {
+        self.0.flags.insert(flag);
+        self
+    }
+
+    /// Builds the message, after checking that its content is consistent with its kind.
+    pub fn build(self) -> Result<Message, BuildError> {
+        let message = self.0;
+        match message.kind {
+            Kind::Cancel if message.content.as_bytes().len() != Id::SIZE => {
+                Err(BuildError::CancelWithoutCallId)
+            }
+            Kind::Error if message.error_description().is_none() => {
+                Err(BuildError::ErrorWithoutDescription)
+            }
+            Kind::Capabilities
+                if message
+                    .deserialize_content::<capabilities::CapabilitiesMap>()
+                    .is_err() =>
+            {
+                Err(BuildError::CapabilitiesWithoutMap)
+            }
+            _ => Ok(message),
+        }
+    }
+
+    /// Builds the message without checking it, for messages built by this crate.
+    pub(crate) fn build_unchecked(self) -> Message {
         self.0
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, thiserror::Error)]
+pub enum BuildError {
+    #[error("the content of a cancel message must be the id of the canceled call")]
+    CancelWithoutCallId,
+
+    #[error("the content of an error message must be the description of the error")]
+    ErrorWithoutDescription,
+
+    #[error("the content of a capabilities message must be a map of capabilities")]
+    CapabilitiesWithoutMap,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -713,6 +887,7 @@ mod tests {
             Header::read(&mut input),
             Ok(Header {
                 id: Id(990340),
+                version: Version::CURRENT,
                 kind: Kind::Error,
                 body_size: 35,
                 subject: Subject {
@@ -729,6 +904,7 @@ mod tests {
     fn test_message_write() {
         let msg = Message {
             id: Id(329),
+            version: Version::CURRENT,
             kind: Kind::Capabilities,
             subject: Subject {
                 service: ServiceId::new(1),
@@ -755,6 +931,107 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_message_compress() {
+        let content = format::Value::from_bytes(Bytes::from(b"setAngles::(vmmf)".repeat(64)));
+        let msg = Message::reply(Id(1), Subject::default())
+            .set_content(content.clone())
+            .build_unchecked();
+        let compressed = msg.clone().compress();
+        assert!(compressed.is_compressed());
+        assert!(compressed.content().as_bytes().len() < content.as_bytes().len());
+        assert_eq!(compressed.decompress().unwrap(), msg);
+
+        let msg = Message::reply(Id(2), Subject::default())
+            .set_content([0x17, 0x2b, 0xe6, 0x01, 0x5f].into())
+            .build_unchecked();
+        assert_eq!(msg.clone().compress(), msg);
+    }
+
+    #[test]
+    fn test_message_error_description() {
+        fn error_with_content(content: Vec<u8>) -> Message {
+            Builder::new()
+                .set_kind(Kind::Error)
+                .set_content(format::Value::from_bytes(content.into()))
+                .build_unchecked()
+        }
+
+        let message = Builder::new()
+            .set_kind(Kind::Error)
+            .set_error_description("boom")
+            .unwrap()
+            .build_unchecked();
+        assert_eq!(message.error_description().as_deref(), Some("boom"));
+
+        // A plain string.
+        let content = format::Value::from_serializable(&"boom").unwrap();
+        let message = error_with_content(content.as_bytes().to_vec());
+        assert_eq!(message.error_description().as_deref(), Some("boom"));
+
+        // A dynamic of a dynamic string.
+        let dynamic = Dynamic::Dynamic(Box::new(Dynamic::from("boom")));
+        let content = format::Value::from_serializable(&dynamic).unwrap();
+        let message = error_with_content(content.as_bytes().to_vec());
+        assert_eq!(message.error_description().as_deref(), Some("boom"));
+
+        // A dynamic of another type.
+        let content = format::Value::from_serializable(&Dynamic::from(42i32)).unwrap();
+        let message = error_with_content(content.as_bytes().to_vec());
+        assert_eq!(message.error_description().as_deref(), Some("42"));
+
+        // A dynamic string that is not valid UTF-8.
+        let message = error_with_content(vec![1, 0, 0, 0, b's', 3, 0, 0, 0, b'b', 0xff, b'm']);
+        assert_eq!(message.error_description().as_deref(), Some("b\u{fffd}m"));
+
+        let message = error_with_content(vec![0xff, 0xff]);
+        assert_eq!(message.error_description(), None);
+    }
+
+    #[test]
+    fn test_message_builder_validation() {
+        let subject = Subject::new(ServiceId::new(1), ObjectId::new(2), ActionId::new(3));
+
+        let message = Message::call(Id::new(1), subject)
+            .payload(Bytes::from_static(&[1, 2, 3]))
+            .flag(Flags::RETURN_TYPE)
+            .build()
+            .unwrap();
+        assert_eq!(message.kind(), Kind::Call);
+        assert_eq!(message.flags(), Flags::RETURN_TYPE);
+        assert_eq!(message.content().as_bytes(), &[1, 2, 3][..]);
+
+        assert!(Message::cancel(Id::new(2), subject, Id::new(1))
+            .build()
+            .is_ok());
+        assert_eq!(
+            Message::cancel(Id::new(2), subject, Id::new(1))
+                .payload(Bytes::new())
+                .build(),
+            Err(BuildError::CancelWithoutCallId)
+        );
+
+        assert!(Message::error(Id::new(3), subject, "boom")
+            .unwrap()
+            .build()
+            .is_ok());
+        assert_eq!(
+            Message::error(Id::new(3), subject, "boom")
+                .unwrap()
+                .payload(Bytes::from_static(&[0xff]))
+                .build(),
+            Err(BuildError::ErrorWithoutDescription)
+        );
+
+        assert_eq!(
+            Message::capabilities(Id::new(4), subject, &capabilities::CapabilitiesMap::new())
+                .unwrap()
+                .payload(Bytes::from_static(&[1]))
+                .build(),
+            Err(BuildError::CapabilitiesWithoutMap)
+        );
+    }
+
     #[test]
     fn test_header_read_invalid_magic_cookie_value() {
         let mut input: &[u8] = &[
@@ -1,6 +1,8 @@
 use super::{Header, Message, ReadHeaderError, WriteHeaderError};
 use crate::format;
-use bytes::{Buf, BytesMut};
+use bytes::{buf::Chain, Buf, Bytes, BytesMut};
+use std::io::IoSlice;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
 use tracing::instrument;
 
 #[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Debug)]
@@ -17,6 +19,90 @@ impl tokio_util::codec::Encoder<Message> for Encoder {
     }
 }
 
+impl Encoder {
+    /// Encodes a message into a frame, made of its header followed by its payload.
+    ///
+    /// Only the header is encoded, the payload is the buffer of the message content and is not
+    /// copied. A received message may therefore be forwarded with another header at no cost.
+    pub(crate) fn encode_frame(&mut self, msg: Message) -> Result<Frame, EncodeError> {
+        Ok(msg.into_frame()?)
+    }
+
+    /// Encodes messages into frames, the headers of which share a single buffer.
+    pub(crate) fn encode_frames<I>(&mut self, msgs: I) -> Result<Vec<Frame>, EncodeError>
+    where
+        I: IntoIterator<Item = Message>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        let msgs = msgs.into_iter();
+        let mut headers = BytesMut::with_capacity(Header::SIZE * msgs.len());
+        msgs.map(|msg| Ok(msg.into_frame_in(&mut headers)?))
+            .collect()
+    }
+}
+
+pub(crate) type Frame = Chain<Bytes, Bytes>;
+
+/// Writes messages to an output.
+///
+/// Unlike a `FramedWrite` with an [`Encoder`], the payload of messages is never copied into an
+/// intermediate buffer, the header and the payload of each frame are written with vectored
+/// writes.
+#[derive(Debug)]
+pub(crate) struct Writer<W> {
+    output: W,
+    encoder: Encoder,
+}
+
+impl<W> Writer<W>
+where
+    W: AsyncWrite + Unpin,
+{
+    pub(crate) fn new(output: W) -> Self {
+        Self {
+            output,
+            encoder: Encoder,
+        }
+    }
+
+    #[instrument(level = "trace", name = "write", skip_all, err)]
+    pub(crate) async fn send(&mut self, msg: Message) -> Result<(), EncodeError> {
+        let frame = self.encoder.encode_frame(msg)?;
+        self.write_frame(frame).await?;
+        self.output.flush().await?;
+        Ok(())
+    }
+
+    /// Writes a batch of messages, and flushes the output once after the last one.
+    #[instrument(level = "trace", name = "write_all", skip_all, err)]
+    pub(crate) async fn send_all(&mut self, msgs: Vec<Message>) -> Result<(), EncodeError> {
+        let frames = self.encoder.encode_frames(msgs)?;
+        for frame in frames {
+            self.write_frame(frame).await?;
+        }
+        self.output.flush().await?;
+        Ok(())
+    }
+
+    /// Shuts the output down, once everything was written.
+    pub(crate) async fn shutdown(&mut self) -> std::io::Result<()> {
+        self.output.shutdown().await
+    }
+
+    async fn write_frame(&mut self, mut frame: Frame) -> std::io::Result<()> {
+        while frame.has_remaining() {
+            let mut slices = [IoSlice::new(&[]); 2];
+            let count = frame.chunks_vectored(&mut slices);
+            let written = self.output.write_vectored(&slices[..count]).await?;
+            if written == 0 {
+                return Err(std::io::Error::from(std::io::ErrorKind::WriteZero));
+            }
+            frame.advance(written);
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub(crate) enum EncodeError {
     #[error("write header error")]
@@ -51,23 +137,22 @@ impl tokio_util::codec::Decoder for Decoder {
 
     #[instrument(level = "trace", name = "decode", skip_all, err)]
     fn decode(&mut self, src: &mut bytes::BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        let msg = loop {
-            match self.state {
+        loop {
+            let (state, message) = match self.state {
                 DecoderState::Header => match decode_header(src)? {
-                    None => break None,
-                    Some(header) => self.state = DecoderState::Body(header),
+                    None => return Ok(None),
+                    Some(header) => (DecoderState::Body(header), None),
                 },
                 DecoderState::Body(header) => match decode_body(header.body_size, src) {
-                    None => break None,
-                    Some(body) => {
-                        self.state = DecoderState::Header;
-                        src.reserve(src.len());
-                        break Some(Message::new(header, body));
-                    }
+                    None => return Ok(None),
+                    Some(body) => (DecoderState::Header, Some(Message::new(header, body))),
                 },
+            };
+            self.state = state;
+            if let Some(message) = message {
+                return Ok(Some(message));
             }
-        };
-        Ok(msg)
+        }
     }
 }
 
@@ -80,9 +165,16 @@ pub(crate) enum DecodeError {
     IO(#[from] std::io::Error),
 }
 
+/// The state of the decoder between reads of the input.
+///
+/// The input may be split in any way across reads. The decoder stays in a state until the input
+/// holds enough data to leave it, and only consumes the data of the input when it does. The data of
+/// the next messages that follows in the same read is left in the input for the next calls.
 #[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Debug)]
 enum DecoderState {
+    /// Waiting for the header of the next message.
     Header,
+    /// The header of the message was consumed, waiting for its whole body.
     Body(Header),
 }
 
@@ -98,10 +190,13 @@ fn decode_header(src: &mut bytes::BytesMut) -> Result<Option<Header>, DecodeErro
     Ok(Some(header))
 }
 
+const MAX_BODY_RESERVE: usize = 64 * 1024;
+
 #[instrument(level = "trace", skip_all)]
 fn decode_body(size: usize, src: &mut BytesMut) -> Option<format::Value> {
     if src.len() < size {
-        src.reserve(size - src.len());
+        // The size comes from the input, it is not trusted to allocate the buffer upfront.
+        src.reserve((size - src.len()).min(MAX_BODY_RESERVE));
         return None;
     }
     let bytes = src.copy_to_bytes(size);
@@ -119,6 +214,7 @@ mod tests {
     fn test_encoder_success() {
         let message = Message {
             id: message::Id(1),
+            version: message::Version::CURRENT,
             kind: message::Kind::Call,
             subject: message::Subject::default(),
             flags: message::Flags::all(),
@@ -134,6 +230,83 @@ mod tests {
         assert_eq!(buf, buf2);
     }
 
+    #[test]
+    fn test_encoder_encode_frame_does_not_copy_payload() {
+        let data = [
+            0x42, 0xde, 0xad, 0x42, // cookie
+            1, 0, 0, 0, // id
+            4, 0, 0, 0, // size
+            0, 0, 1, 0, // version, type, flags
+            1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, // subject,
+            1, 2, 3, 4, // body
+        ];
+        let mut buf = BytesMut::from_iter(data);
+        let received = tokio_util::codec::Decoder::decode(&mut Decoder::new(), &mut buf)
+            .unwrap()
+            .unwrap();
+        let payload = received.content().to_bytes();
+
+        // Republish the payload with another header.
+        let message = Message::reply(message::Id(2), received.subject())
+            .set_content(received.into_content())
+            .build_unchecked();
+        let frame = Encoder.encode_frame(message.clone()).unwrap();
+        assert_eq!(frame.first_ref().len(), Header::SIZE);
+        assert_eq!(frame.last_ref().as_ptr(), payload.as_ptr());
+        assert_eq!(frame.last_ref().len(), payload.len());
+
+        let mut expected = vec![];
+        message.write(&mut expected).unwrap();
+        assert_eq!(frame.chunk(), &expected[..Header::SIZE]);
+        assert_eq!(frame.last_ref(), &expected[Header::SIZE..]);
+    }
+
+    #[tokio::test]
+    async fn test_writer_send() {
+        let message = Message {
+            id: message::Id(1),
+            version: message::Version::CURRENT,
+            kind: message::Kind::Call,
+            subject: message::Subject::default(),
+            flags: message::Flags::all(),
+            content: [1, 2, 3].into(),
+        };
+        let mut output = vec![];
+        let mut writer = Writer::new(&mut output);
+        writer.send(message.clone()).await.unwrap();
+
+        let mut expected = vec![];
+        message.write(&mut expected).unwrap();
+        assert_eq!(output, expected);
+    }
+
+    #[tokio::test]
+    async fn test_writer_send_all() {
+        let messages: Vec<_> = (1u8..=3)
+            .map(|id| {
+                Message::event(message::Id(id.into()), message::Subject::default())
+                    .set_content([id; 4].into())
+                    .build_unchecked()
+            })
+            .collect();
+        let frames = Encoder.encode_frames(messages.clone()).unwrap();
+        let headers = frames.iter().map(|frame| frame.first_ref().as_ptr());
+        assert!(headers
+            .clone()
+            .zip(headers.skip(1))
+            .all(|(first, second)| first.wrapping_add(Header::SIZE) == second));
+
+        let mut output = vec![];
+        let mut writer = Writer::new(&mut output);
+        writer.send_all(messages.clone()).await.unwrap();
+
+        let mut expected = vec![];
+        for message in messages {
+            message.write(&mut expected).unwrap();
+        }
+        assert_eq!(output, expected);
+    }
+
     #[test]
     fn test_decoder_not_enough_data_for_header() {
         let data = [0x42, 0xde, 0xad];
@@ -171,6 +344,175 @@ mod tests {
         );
     }
 
+    /// Feeds random mutations of valid messages to the decoder, and deserializes their content,
+    /// to check that hostile inputs are rejected with errors and never cause a panic.
+    #[test]
+    fn test_decoder_mutated_messages_do_not_panic() {
+        use crate::types::{dynamic::DynamicSeed, Dynamic};
+        use serde::de::DeserializeSeed;
+        use std::collections::HashMap;
+
+        type Content = (i32, String, Vec<u8>, Option<bool>, HashMap<String, f64>);
+        let content: Content = (
+            -42,
+            "hello".to_owned(),
+            vec![1, 2, 3],
+            Some(true),
+            [("ratio".to_owned(), 1.5)].into_iter().collect(),
+        );
+        let valid_messages = [
+            Message::call(message::Id(1), message::Subject::default())
+                .set_value(&content)
+                .unwrap()
+                .build_unchecked(),
+            // The representation of a dynamic value: its signature, then its value.
+            Message::reply(message::Id(2), message::Subject::default())
+                .set_value(&(
+                    "(is[i]{sb})",
+                    (1i32, "a", vec![1i32, 2], HashMap::from([("b", true)])),
+                ))
+                .unwrap()
+                .build_unchecked(),
+        ];
+        let valid_data: Vec<Vec<u8>> = valid_messages
+            .into_iter()
+            .map(|message| {
+                let mut data = vec![];
+                message.write(&mut data).unwrap();
+                data
+            })
+            .collect();
+
+        // A xorshift generator, seeded for reproducibility.
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut random = move |bound: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            usize::try_from(state % u64::try_from(bound.max(1)).unwrap()).unwrap()
+        };
+
+        for _ in 0..5000 {
+            let mut data = valid_data[random(valid_data.len())].clone();
+            for _ in 0..=random(4) {
+                let index = random(data.len());
+                match random(6) {
+                    0 => data[index] = u8::try_from(random(256)).unwrap(),
+                    1 => data[index] ^= 1 << random(8),
+                    2 => data.insert(index, u8::try_from(random(256)).unwrap()),
+                    3 => {
+                        data.remove(index);
+                    }
+                    4 => data.truncate(index),
+                    _ => {
+                        let end = (index + 4).min(data.len());
+                        data[index..end].fill(0xff);
+                    }
+                }
+                if data.is_empty() {
+                    break;
+                }
+            }
+
+            let mut buf = BytesMut::from(data.as_slice());
+            let mut decoder = Decoder::new();
+            while let Ok(Some(message)) = tokio_util::codec::Decoder::decode(&mut decoder, &mut buf)
+            {
+                let _res = message.deserialize_content::<Content>();
+                let _res = message.error_description();
+                let _res = DynamicSeed::new(None).deserialize(
+                    &mut format::de::Deserializer::from_slice(message.content().as_bytes()),
+                );
+                let _res: Result<Dynamic, _> = message.deserialize_content();
+            }
+        }
+    }
+
+    /// Returns the data of messages with bodies of various sizes, and the messages.
+    fn fragmentation_messages() -> (Vec<u8>, Vec<Message>) {
+        let messages: Vec<_> = [0, 1, 27, 300]
+            .into_iter()
+            .enumerate()
+            .map(|(index, size)| {
+                let id = message::Id(u32::try_from(index).unwrap());
+                Message::call(id, message::Subject::default())
+                    .set_content(format::Value::from_bytes(bytes::Bytes::from(vec![
+                        0xab;
+                        size
+                    ])))
+                    .build_unchecked()
+            })
+            .collect();
+        let mut data = vec![];
+        for message in &messages {
+            message.clone().write(&mut data).unwrap();
+        }
+        (data, messages)
+    }
+
+    /// Feeds the data to a decoder in successive reads of the given sizes, as a connection would.
+    fn decode_in_reads<I>(data: &[u8], read_sizes: I) -> Vec<Message>
+    where
+        I: IntoIterator<Item = usize>,
+    {
+        let mut decoder = Decoder::new();
+        let mut buf = BytesMut::new();
+        let mut messages = vec![];
+        let mut data = data;
+        let mut read_sizes = read_sizes.into_iter();
+        while !data.is_empty() {
+            let size = read_sizes.next().unwrap().clamp(1, data.len());
+            let (read, tail) = data.split_at(size);
+            buf.extend_from_slice(read);
+            data = tail;
+            while let Some(message) =
+                tokio_util::codec::Decoder::decode(&mut decoder, &mut buf).unwrap()
+            {
+                messages.push(message);
+            }
+        }
+        assert!(buf.is_empty());
+        assert_eq!(decoder.state, DecoderState::Header);
+        messages
+    }
+
+    #[test]
+    fn test_decoder_byte_at_a_time() {
+        let (data, expected) = fragmentation_messages();
+        assert_eq!(decode_in_reads(&data, std::iter::repeat(1)), expected);
+    }
+
+    #[test]
+    fn test_decoder_header_split_across_reads() {
+        let (data, expected) = fragmentation_messages();
+        for split in 1..Header::SIZE {
+            let messages = decode_in_reads(&data, [split, Header::SIZE - split, usize::MAX]);
+            assert_eq!(messages, expected);
+        }
+    }
+
+    #[test]
+    fn test_decoder_multiple_messages_per_read() {
+        let (data, expected) = fragmentation_messages();
+        assert_eq!(decode_in_reads(&data, [usize::MAX]), expected);
+    }
+
+    #[test]
+    fn test_decoder_random_reads() {
+        let (data, expected) = fragmentation_messages();
+        // A xorshift generator, seeded for reproducibility.
+        let mut state = 0x9e37_79b9_7f4a_7c15_u64;
+        for _ in 0..500 {
+            let read_sizes = std::iter::from_fn(|| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                Some(usize::try_from(state % 64).unwrap() + 1)
+            });
+            assert_eq!(decode_in_reads(&data, read_sizes), expected);
+        }
+    }
+
     #[test]
     fn test_decoder_success() {
         let data = [
@@ -0,0 +1,139 @@
+//! Compression of the body of messages.
+//!
+//! Bodies are compressed in the LZ4 block format, prefixed with the size of the uncompressed body
+//! as a 4 bytes unsigned integer, little endian. The format favors the speed of compression over
+//! its ratio, which suits the highly redundant payloads it is used for, such as meta objects.
+//!
+//! The codec is the one of `lz4_flex`, without its unsafe optimizations and with the checks of
+//! its decoder, which otherwise panics on some invalid data. Its versions from 0.11.5 on require a
+//! more recent compiler than the one supported by this crate, and the previous 0.11 versions are
+//! yanked.
+
+use bytes::Bytes;
+
+// The ratio of the format is bounded: each byte of a match length extends it by at most 255 bytes.
+const MAX_RATIO: usize = u8::MAX as usize;
+
+/// Compresses data.
+///
+/// Returns `None` if the data cannot be represented compressed, because it is too large.
+pub(crate) fn compress(data: &[u8]) -> Option<Bytes> {
+    u32::try_from(data.len()).ok()?;
+    Some(lz4_flex::block::compress_prepend_size(data).into())
+}
+
+/// Decompresses data that was compressed with [`compress`].
+pub(crate) fn decompress(data: &[u8]) -> Result<Bytes, DecompressError> {
+    let (size, block) =
+        lz4_flex::block::uncompressed_size(data).map_err(|_err| DecompressError::Truncated)?;
+    // The declared size is not trusted to allocate memory upfront.
+    if size > block.len().saturating_mul(MAX_RATIO) {
+        return Err(DecompressError::TooLarge);
+    }
+    let output = lz4_flex::block::decompress(block, size)?;
+    Ok(output.into())
+}
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum DecompressError {
+    #[error("the compressed data is truncated")]
+    Truncated,
+
+    #[error("the declared size of the decompressed data is too large for the compressed data")]
+    TooLarge,
+
+    #[error("the compressed data is invalid")]
+    Invalid(#[from] lz4_flex::block::DecompressError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::assert_matches;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_compress_decompress_round_trip() {
+        let inputs: [&[u8]; 6] = [
+            b"",
+            b"a",
+            b"abcdefghijkl",
+            b"abcdabcdabcdabcdabcdabcdabcdabcdabcdabcd",
+            &[0; 1000],
+            &[42; 70000],
+        ];
+        for input in inputs {
+            let compressed = compress(input).unwrap();
+            assert_eq!(decompress(&compressed).unwrap(), input);
+        }
+
+        let pseudo_random: Vec<u8> = (0u32..5000)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8)
+            .collect();
+        let compressed = compress(&pseudo_random).unwrap();
+        assert_eq!(decompress(&compressed).unwrap(), pseudo_random);
+    }
+
+    #[test]
+    fn test_compress_redundant_data() {
+        let input = b"methodName::(s)".repeat(100);
+        let compressed = compress(&input).unwrap();
+        assert!(compressed.len() * 10 < input.len());
+    }
+
+    #[test]
+    fn test_compress_meta_object() {
+        let meta_object = crate::format::to_value(&crate::bench::motion_meta_object()).unwrap();
+        let meta_object = meta_object.as_bytes();
+        let compressed = compress(meta_object).unwrap();
+        // Meta objects are highly redundant, compression at least halves their size.
+        assert!(compressed.len() * 2 < meta_object.len());
+        assert_eq!(decompress(&compressed).unwrap(), meta_object);
+    }
+
+    #[test]
+    fn test_decompress_reference_block() {
+        // 1 literal, a match of 20 bytes at offset 1, and 3 last literals.
+        let compressed = [
+            24, 0, 0, 0, // size
+            0x1f, b'a', 0x01, 0x00, 0x01, // literals and match
+            0x30, b'b', b'c', b'd', // last literals
+        ];
+        assert_eq!(
+            decompress(&compressed).unwrap(),
+            b"aaaaaaaaaaaaaaaaaaaaabcd"[..]
+        );
+    }
+
+    #[test]
+    fn test_decompress_invalid() {
+        assert_matches!(decompress(&[1, 0]), Err(DecompressError::Truncated));
+        assert_matches!(
+            decompress(&[3, 0, 0, 0, 0x30, b'a']),
+            Err(DecompressError::Invalid(_))
+        );
+        assert_matches!(
+            decompress(&[8, 0, 0, 0, 0x10, b'a', 0x02, 0x00, 0x00]),
+            Err(DecompressError::Invalid(_))
+        );
+        assert_matches!(
+            decompress(&[2, 0, 0, 0, 0x10, b'a', 0x01, 0x00, 0x00]),
+            Err(DecompressError::Invalid(_))
+        );
+        assert_matches!(
+            decompress(&[3, 0, 0, 0, 0x10, b'a']),
+            Err(DecompressError::Invalid(_))
+        );
+        // A declared size that the compressed data cannot reach is not allocated.
+        assert_matches!(
+            decompress(&[0xff, 0xff, 0xff, 0xff, 0x10, b'a']),
+            Err(DecompressError::TooLarge)
+        );
+
+        // Truncated data is rejected, whatever its length.
+        let compressed = compress(&b"methodName::(s)".repeat(20)).unwrap();
+        for length in 0..compressed.len() {
+            assert!(decompress(&compressed[..length]).is_err());
+        }
+    }
+}
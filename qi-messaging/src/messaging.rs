@@ -1,4 +1,4 @@
-use crate::{capabilities, format, message};
+use crate::{capabilities, format, message, ParseTraceContextError, TraceContext};
 pub(crate) use crate::{
     message::Message,
     service::{
@@ -13,13 +13,31 @@ pub(crate) use subject::Subject;
 pub(crate) type Request = service::Request<Call, Notification>;
 
 impl Request {
+    /// Converts a message into a request, or returns it if it is a response.
+    ///
+    /// Calls, posts and events carry their content as is, an empty content is only a value of the
+    /// unit type (see [`format::Error::EmptyValue`]). A cancel message without the id of the
+    /// canceled call is an error. A capabilities message without capabilities updates none.
     pub(crate) fn try_from_message(
         message: Message,
-    ) -> Result<Result<Self, Message>, format::Error> {
-        let request = match message.kind() {
-            message::Kind::Call => Ok(Self::Call(
-                Call::new(message.subject()).with_formatted_value(message.into_content()),
-            )),
+    ) -> Result<Result<Self, Message>, FromMessageError> {
+        let kind = message.kind();
+        let flags = message.flags();
+        let content_error = |err| FromMessageError::Content(kind, err);
+        let request = match kind {
+            message::Kind::Call => {
+                let mut call = Call::new(message.subject());
+                if message.flags().contains(message::Flags::RETURN_TYPE) {
+                    call = call.with_return_type();
+                }
+                let mut content = message.into_content();
+                if flags.contains(message::Flags::TRACE_CONTEXT) {
+                    let (trace_context, arguments) = TraceContext::split_content(content)?;
+                    call = call.with_trace_context(trace_context);
+                    content = arguments;
+                }
+                Ok(Self::Call(call.with_formatted_value(content)))
+            }
             message::Kind::Post => Ok(Self::Notification(
                 Post::new(message.subject())
                     .with_formatted_value(message.into_content())
@@ -30,18 +48,43 @@ impl Request {
                     .with_formatted_value(message.into_content())
                     .into(),
             )),
-            message::Kind::Cancel => Ok(Self::Notification(
-                Cancel::new(message.subject(), message.deserialize_content()?).into(),
-            )),
-            message::Kind::Capabilities => Ok(Self::Notification(
-                Capabilities::new(message.subject(), message.deserialize_content()?).into(),
-            )),
+            message::Kind::Cancel => {
+                let call_id = message.deserialize_content().map_err(|err| match err {
+                    format::Error::EmptyValue => FromMessageError::CancelWithoutCallId,
+                    err => content_error(err),
+                })?;
+                Ok(Self::Notification(
+                    Cancel::new(message.subject(), call_id).into(),
+                ))
+            }
+            message::Kind::Capabilities => {
+                let capabilities = if message.content().as_bytes().is_empty() {
+                    capabilities::CapabilitiesMap::new()
+                } else {
+                    message.deserialize_content().map_err(content_error)?
+                };
+                Ok(Self::Notification(
+                    Capabilities::new(message.subject(), capabilities).into(),
+                ))
+            }
             _ => Err(message),
         };
         Ok(request)
     }
 }
 
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum FromMessageError {
+    #[error("the cancel message has no id of the canceled call")]
+    CancelWithoutCallId,
+
+    #[error("the content of the {0} message could not be deserialized")]
+    Content(message::Kind, #[source] format::Error),
+
+    #[error("the trace context of the call message could not be read")]
+    TraceContext(#[from] ParseTraceContextError),
+}
+
 impl From<Call> for Request {
     fn from(value: Call) -> Self {
         Self::Call(value)
@@ -83,7 +126,7 @@ pub(crate) type RequestWithId = WithRequestId<Request>;
 impl RequestWithId {
     pub(crate) fn try_from_message(
         message: Message,
-    ) -> Result<Result<Self, Message>, format::Error> {
+    ) -> Result<Result<Self, Message>, FromMessageError> {
         let id = message.id();
         let request = Request::try_from_message(message)?;
         Ok(request.map(|req| Self::new(id, req)))
@@ -110,9 +153,19 @@ where
     S: Into<Subject> + Clone,
 {
     fn from(call: service::CallWithId<S>) -> Self {
-        Message::call(call.id(), call.subject().clone().into())
-            .set_content(call.into_inner().into_formatted_value())
-            .build()
+        let mut builder = Message::call(call.id(), call.subject().clone().into());
+        if call.inner().return_type_requested() {
+            builder = builder.flag(message::Flags::RETURN_TYPE);
+        }
+        let call = call.into_inner();
+        let content = match call.trace_context() {
+            Some(trace_context) => {
+                builder = builder.flag(message::Flags::TRACE_CONTEXT);
+                trace_context.prefix_content(call.formatted_value())
+            }
+            None => call.into_formatted_value(),
+        };
+        builder.set_content(content).build_unchecked()
     }
 }
 
@@ -210,7 +263,7 @@ where
     fn from(value: service::PostWithId<S>) -> Self {
         Message::post(value.id(), value.subject().clone().into())
             .set_content(value.into_inner().into_formatted_value())
-            .build()
+            .build_unchecked()
     }
 }
 
@@ -224,7 +277,7 @@ where
     fn from(value: service::EventWithId<S>) -> Self {
         Message::event(value.id(), value.subject().clone().into())
             .set_content(value.into_inner().into_formatted_value())
-            .build()
+            .build_unchecked()
     }
 }
 
@@ -241,7 +294,7 @@ where
             value.subject().clone().into(),
             value.inner().call_id(),
         )
-        .build()
+        .build_unchecked()
     }
 }
 
@@ -277,7 +330,134 @@ impl TryFrom<CapabilitiesWithId> for Message {
     fn try_from(value: CapabilitiesWithId) -> Result<Self, Self::Error> {
         Ok(
             Message::capabilities(value.id(), *value.subject(), &value.inner().capabilities)?
-                .build(),
+                .build_unchecked(),
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::object::{ActionId, ObjectId, ServiceId};
+    use assert_matches::assert_matches;
+
+    fn empty_message(kind: message::Kind) -> Message {
+        let subject = Subject::new(ServiceId::new(1), ObjectId::new(2), ActionId::new(3));
+        let builder = match kind {
+            message::Kind::Call => Message::call(message::Id::new(1), subject),
+            message::Kind::Reply => Message::reply(message::Id::new(1), subject),
+            message::Kind::Post => Message::post(message::Id::new(1), subject),
+            message::Kind::Event => Message::event(message::Id::new(1), subject),
+            message::Kind::Cancel => {
+                Message::cancel(message::Id::new(1), subject, message::Id::new(2))
+            }
+            message::Kind::Capabilities => Message::capabilities(
+                message::Id::new(1),
+                subject,
+                &capabilities::CapabilitiesMap::new(),
+            )
+            .unwrap(),
+            kind => unreachable!("no empty {kind} message in these tests"),
+        };
+        builder.payload(bytes::Bytes::new()).build_unchecked()
+    }
+
+    #[test]
+    fn test_request_from_empty_call() {
+        let request = Request::try_from_message(empty_message(message::Kind::Call));
+        let call = assert_matches!(request, Ok(Ok(Request::Call(call))) => call);
+        assert_matches!(call.value::<()>(), Ok(()));
+        assert_matches!(call.value::<i32>(), Err(format::Error::EmptyValue));
+    }
+
+    #[test]
+    fn test_request_from_empty_post_and_event() {
+        let request = Request::try_from_message(empty_message(message::Kind::Post));
+        let post = assert_matches!(
+            request,
+            Ok(Ok(Request::Notification(Notification::Post(post)))) => post
+        );
+        assert!(post.into_formatted_value().as_bytes().is_empty());
+
+        let request = Request::try_from_message(empty_message(message::Kind::Event));
+        let event = assert_matches!(
+            request,
+            Ok(Ok(Request::Notification(Notification::Event(event)))) => event
+        );
+        assert!(event.into_formatted_value().as_bytes().is_empty());
+    }
+
+    #[test]
+    fn test_request_from_empty_cancel() {
+        assert_matches!(
+            Request::try_from_message(empty_message(message::Kind::Cancel)),
+            Err(FromMessageError::CancelWithoutCallId)
+        );
+    }
+
+    #[test]
+    fn test_request_from_empty_capabilities() {
+        let request = Request::try_from_message(empty_message(message::Kind::Capabilities));
+        let capabilities = assert_matches!(
+            request,
+            Ok(Ok(Request::Notification(Notification::Capabilities(capabilities)))) => capabilities
+        );
+        assert_eq!(
+            capabilities::CapabilitiesMap::from(capabilities),
+            capabilities::CapabilitiesMap::new()
+        );
+    }
+
+    #[test]
+    fn test_empty_reply_and_error() {
+        let message = empty_message(message::Kind::Reply);
+        let message =
+            assert_matches!(Request::try_from_message(message), Ok(Err(message)) => message);
+        let reply = Reply::new(message.into_content());
+        assert_matches!(reply.value::<()>(), Ok(()));
+        assert_matches!(reply.value::<String>(), Err(format::Error::EmptyValue));
+
+        let error = Error::with_undecodable_description(bytes::Bytes::new());
+        assert_eq!(error.reason(), "the error has no description");
+        assert_eq!(error.raw_description(), None);
+    }
+
+    #[test]
+    fn test_call_return_type_to_from_message() {
+        let subject = Subject::new(ServiceId::new(1), ObjectId::new(2), ActionId::new(3));
+        let call = WithRequestId::new(RequestId::from(1), Call::new(subject).with_return_type());
+        let message = Message::from(call);
+        assert!(message.flags().contains(message::Flags::RETURN_TYPE));
+        let call = assert_matches!(
+            Request::try_from_message(message),
+            Ok(Ok(Request::Call(call))) => call
+        );
+        assert!(call.return_type_requested());
+
+        let message = Message::from(WithRequestId::new(RequestId::from(2), Call::new(subject)));
+        assert!(!message.flags().contains(message::Flags::RETURN_TYPE));
+    }
+
+    #[test]
+    fn test_call_trace_context_to_from_message() {
+        let subject = Subject::new(ServiceId::new(1), ObjectId::new(2), ActionId::new(3));
+        let trace_context: TraceContext = "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01"
+            .parse()
+            .unwrap();
+        let call = Call::new(subject)
+            .with_trace_context(trace_context)
+            .with_value(&(1i32, "a"))
+            .unwrap();
+        let message = Message::from(WithRequestId::new(RequestId::from(1), call));
+        assert!(message.flags().contains(message::Flags::TRACE_CONTEXT));
+        let call = assert_matches!(
+            Request::try_from_message(message),
+            Ok(Ok(Request::Call(call))) => call
+        );
+        assert_eq!(call.trace_context(), Some(&trace_context));
+        assert_eq!(call.value::<(i32, String)>().unwrap(), (1, "a".to_owned()));
+
+        let message = Message::from(WithRequestId::new(RequestId::from(2), Call::new(subject)));
+        assert!(!message.flags().contains(message::Flags::TRACE_CONTEXT));
+    }
+}
@@ -1,11 +1,16 @@
 use crate::{
-    format,
     messaging::{
-        CallResult, CallTermination, CallWithId, GetSubject, Message, NotificationWithId,
-        RequestId, RequestWithId, Service, Subject, ToRequestId,
+        CallResult, CallTermination, CallWithId, GetSubject, Message, Notification,
+        NotificationWithId, Reply, Request, RequestId, RequestWithId, Service, Subject,
+        ToRequestId,
     },
+    types::object::ServiceId,
 };
 use futures::{stream::FuturesUnordered, FutureExt, Sink, SinkExt, Stream, StreamExt};
+use std::{
+    collections::{HashMap, VecDeque},
+    num::{NonZeroU32, NonZeroUsize},
+};
 use tokio::{pin, select};
 use tracing::{trace, trace_span, Instrument};
 
@@ -13,6 +18,7 @@ pub(crate) async fn serve<St, Si, Svc>(
     requests_stream: St,
     responses_sink: Si,
     mut service: Svc,
+    scheduling: Scheduling,
 ) -> Result<(), Si::Error>
 where
     St: Stream<Item = RequestWithId>,
@@ -22,20 +28,62 @@ where
 {
     let requests_stream = requests_stream.fuse();
     let mut result_futures = FuturesUnordered::new();
+    let mut pending_requests = FairQueue::new();
     pin!(requests_stream, responses_sink);
 
+    let call_service = |service: &mut Svc, request: RequestWithId| {
+        let (id, subject) = (request.to_request_id(), *request.subject());
+        let return_type =
+            matches!(request.inner(), Request::Call(call) if call.return_type_requested());
+        let span = trace_span!("service_call", traceparent = tracing::field::Empty);
+        if let Request::Call(call) = request.inner() {
+            if let Some(trace_context) = call.trace_context() {
+                span.record("traceparent", tracing::field::display(trace_context));
+            }
+        }
+        trace!(?request, "calling service");
+        service
+            .request(request.transpose_id())
+            .instrument(span)
+            .map(move |response| (id, subject, return_type, response))
+    };
+
     loop {
+        while scheduling.has_capacity(result_futures.len()) {
+            match pending_requests.pop() {
+                Some(request) => result_futures.push(call_service(&mut service, request)),
+                None => break,
+            }
+        }
         select! {
             Some(request) = requests_stream.next() => {
-                let (id, subject) = (request.to_request_id(), *request.subject());
-                trace!(?request, "received a new request, calling service");
-                let result_future = service.request(request.transpose_id()).instrument(trace_span!("service_call"));
-                result_futures.push(result_future.map(move |response| (id, subject, response)));
+                trace!(?request, "received a new request");
+                match Scheduled::of(&request) {
+                    Scheduled::Queued(service_id) => {
+                        pending_requests.push(service_id, scheduling.weight(service_id), request);
+                    }
+                    Scheduled::CancelQueued(call_id) => {
+                        match pending_requests.remove(call_id) {
+                            Some(call) => {
+                                trace!(%call_id, "canceled a pending call");
+                                let response = Response {
+                                    id: call_id,
+                                    subject: *call.subject(),
+                                    return_type: false,
+                                    result: Err(CallTermination::Canceled),
+                                };
+                                responses_sink.send(response).await?;
+                            }
+                            None => result_futures.push(call_service(&mut service, request)),
+                        }
+                    }
+                    Scheduled::Immediate => result_futures.push(call_service(&mut service, request)),
+                }
             },
-            Some((id, subject, result)) = result_futures.next() => {
+            Some((id, subject, return_type, result)) = result_futures.next() => {
                 trace!(%id, %subject, "received result of service call");
                 if let Some(result) = result.transpose() {
-                    responses_sink.send(Response { id, subject, result }).await?;
+                    responses_sink.send(Response { id, subject, return_type, result }).await?;
                 }
             },
             else => {
@@ -46,30 +94,184 @@ where
     }
 }
 
+/// The scheduling of the requests that a server receives between the services that it serves.
+///
+/// By default, requests are processed as soon as they are received. When a capacity is set, at
+/// most that number of requests are processed at the same time, and other requests are queued.
+/// Queued requests are processed in turn for each service, in proportion of its weight (1 by
+/// default), so that a service flooded with requests does not starve the others.
+///
+/// A call that is served holds its part of the capacity until it terminates, even while it waits
+/// for a call that it sent to the remote. Services that call the remote from within their calls,
+/// for which the remote may call back, need a capacity larger than the depth of such nested calls,
+/// or the nested calls wait for each other forever.
+#[derive(Default, Clone, PartialEq, Eq, Debug)]
+pub struct Scheduling {
+    capacity: Option<NonZeroUsize>,
+    weights: HashMap<ServiceId, NonZeroU32>,
+}
+
+impl Scheduling {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(mut self, capacity: NonZeroUsize) -> Self {
+        self.capacity = Some(capacity);
+        self
+    }
+
+    pub fn with_weight(mut self, service: ServiceId, weight: NonZeroU32) -> Self {
+        self.weights.insert(service, weight);
+        self
+    }
+
+    pub fn capacity(&self) -> Option<NonZeroUsize> {
+        self.capacity
+    }
+
+    pub fn weight(&self, service: ServiceId) -> NonZeroU32 {
+        self.weights
+            .get(&service)
+            .copied()
+            .unwrap_or(Self::DEFAULT_WEIGHT)
+    }
+
+    const DEFAULT_WEIGHT: NonZeroU32 = match NonZeroU32::new(1) {
+        Some(weight) => weight,
+        None => unreachable!(),
+    };
+
+    fn has_capacity(&self, running: usize) -> bool {
+        self.capacity
+            .is_none_or(|capacity| running < capacity.get())
+    }
+}
+
+/// How a request is scheduled when it is received.
+enum Scheduled {
+    /// Calls and notifications with a payload are queued with the requests of their service.
+    Queued(ServiceId),
+    /// Cancellations are processed right away, and cancel the call if it is still queued.
+    CancelQueued(RequestId),
+    /// Other requests are processed right away.
+    Immediate,
+}
+
+impl Scheduled {
+    fn of(request: &RequestWithId) -> Self {
+        match request.inner() {
+            Request::Call(call) => Self::Queued(call.subject().service()),
+            Request::Notification(Notification::Post(post)) => {
+                Self::Queued(post.subject().service())
+            }
+            Request::Notification(Notification::Event(event)) => {
+                Self::Queued(event.subject().service())
+            }
+            Request::Notification(Notification::Cancel(cancel)) => {
+                Self::CancelQueued(cancel.call_id())
+            }
+            Request::Notification(Notification::Capabilities(_)) => Self::Immediate,
+        }
+    }
+}
+
+/// A queue of requests per service, dequeued with stride scheduling.
+///
+/// Each service has a pass value that advances by a stride, inversely proportional to its weight,
+/// each time one of its requests is dequeued. The request dequeued next is the one of the service
+/// with the lowest pass. A service that becomes active again starts at the pass of the last
+/// dequeued request, so that it cannot claim the turns it did not use while idle.
+#[derive(Debug)]
+struct FairQueue {
+    queues: HashMap<ServiceId, ServiceQueue>,
+    pass: u64,
+}
+
+#[derive(Debug, Default)]
+struct ServiceQueue {
+    requests: VecDeque<RequestWithId>,
+    stride: u64,
+    pass: u64,
+}
+
+impl FairQueue {
+    const STRIDE_UNIT: u64 = 1 << 20;
+
+    fn new() -> Self {
+        Self {
+            queues: HashMap::new(),
+            pass: 0,
+        }
+    }
+
+    fn push(&mut self, service: ServiceId, weight: NonZeroU32, request: RequestWithId) {
+        let queue = self.queues.entry(service).or_default();
+        if queue.requests.is_empty() {
+            queue.pass = queue.pass.max(self.pass);
+        }
+        // The unit is large enough that the rounding of strides is negligible.
+        #[allow(clippy::integer_division)]
+        let stride = Self::STRIDE_UNIT / u64::from(weight.get());
+        queue.stride = stride;
+        queue.requests.push_back(request);
+    }
+
+    fn pop(&mut self) -> Option<RequestWithId> {
+        let (_service, queue) = self
+            .queues
+            .iter_mut()
+            .filter(|(_service, queue)| !queue.requests.is_empty())
+            .min_by_key(|(service, queue)| (queue.pass, **service))?;
+        self.pass = queue.pass;
+        queue.pass += queue.stride;
+        queue.requests.pop_front()
+    }
+
+    /// Removes the queued call with the given id.
+    fn remove(&mut self, call_id: RequestId) -> Option<RequestWithId> {
+        self.queues.values_mut().find_map(|queue| {
+            let index = queue.requests.iter().position(|request| {
+                matches!(request.inner(), Request::Call(_)) && request.to_request_id() == call_id
+            })?;
+            queue.requests.remove(index)
+        })
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct Response<T, E> {
     id: RequestId,
     subject: Subject,
+    /// Whether the caller requested the signature of the returned value.
+    return_type: bool,
     result: CallResult<T, E>,
 }
 
 impl<T, E> TryFrom<Response<T, E>> for Message
 where
-    T: Into<format::Value>,
+    T: Into<Reply>,
     E: ToString,
 {
     type Error = crate::format::Error;
 
     fn try_from(response: Response<T, E>) -> Result<Self, Self::Error> {
         match response.result {
-            Ok(value) => Ok(Message::reply(response.id, response.subject)
-                .set_content(value.into())
-                .build()),
+            Ok(value) => {
+                let (content, flags) = value.into().into_content(response.return_type)?;
+                Ok(Message::reply(response.id, response.subject)
+                    .flag(flags)
+                    .set_content(content)
+                    .build_unchecked())
+            }
             Err(CallTermination::Canceled) => {
-                Ok(Message::canceled(response.id, response.subject).build())
+                Ok(Message::canceled(response.id, response.subject).build_unchecked())
             }
             Err(CallTermination::Error(err)) => {
-                Ok(Message::error(response.id, response.subject, &err.to_string())?.build())
+                Ok(
+                    Message::error(response.id, response.subject, &err.to_string())?
+                        .build_unchecked(),
+                )
             }
         }
     }
@@ -80,19 +282,19 @@ mod tests {
     use super::*;
     use crate::{
         message,
-        messaging::Call,
+        messaging::{self, Call},
         service,
         types::object::{ActionId, ObjectId, ServiceId},
     };
     use assert_matches::assert_matches;
     use futures::{
-        future::{poll_immediate, BoxFuture},
+        future::{self, poll_immediate, BoxFuture},
         FutureExt,
     };
     use std::{collections::HashMap, sync::Arc};
     use tokio::sync::{
         mpsc::{self, error::TryRecvError},
-        Barrier,
+        oneshot, Barrier,
     };
     use tokio_stream::wrappers::ReceiverStream;
     use tokio_util::sync::PollSender;
@@ -146,7 +348,12 @@ mod tests {
 
         let requests_stream = ReceiverStream::new(requests_rx);
         let responses_sink = PollSender::new(responses_tx);
-        let serve = serve(requests_stream, responses_sink, service);
+        let serve = serve(
+            requests_stream,
+            responses_sink,
+            service,
+            Scheduling::default(),
+        );
         pin!(serve);
 
         // Send 3 call requests.
@@ -219,6 +426,135 @@ mod tests {
         assert_matches!(poll_immediate(&mut serve).await, Some(Ok(())));
     }
 
+    /// A service whose calls wait for the test to complete them.
+    #[derive(Debug)]
+    struct PendingService {
+        calls: mpsc::UnboundedSender<(RequestId, oneshot::Sender<()>)>,
+    }
+
+    impl<N> service::Service<CallWithId, N> for PendingService {
+        type CallReply = RequestId;
+        type Error = String;
+        type CallFuture = BoxFuture<'static, CallResult<Self::CallReply, Self::Error>>;
+        type NotifyFuture = BoxFuture<'static, Result<(), Self::Error>>;
+
+        fn call(&mut self, call: CallWithId) -> Self::CallFuture {
+            let id = call.to_request_id();
+            let (complete_tx, complete_rx) = oneshot::channel();
+            self.calls.send((id, complete_tx)).unwrap();
+            async move {
+                complete_rx.await.unwrap();
+                Ok(id)
+            }
+            .boxed()
+        }
+
+        fn notify(&mut self, _notif: N) -> Self::NotifyFuture {
+            future::ok(()).boxed()
+        }
+    }
+
+    fn call_request(id: u32, service: u32) -> RequestWithId {
+        let subject = message::Subject::new(
+            ServiceId::new(service),
+            ObjectId::new(1),
+            ActionId::new(100),
+        );
+        RequestWithId::new(RequestId::from(id), Call::new(subject).into())
+    }
+
+    /// Tests that with a capacity of one request, requests of services are processed one at a
+    /// time, in proportion of the weights of services.
+    #[tokio::test]
+    async fn test_server_scheduling_weighted_fair() {
+        let (requests_tx, requests_rx) = mpsc::channel(8);
+        let (responses_tx, mut responses_rx) = mpsc::channel(8);
+        let (calls_tx, mut calls_rx) = mpsc::unbounded_channel();
+        let scheduling = Scheduling::new()
+            .with_capacity(NonZeroUsize::new(1).unwrap())
+            .with_weight(ServiceId::new(1), NonZeroU32::new(2).unwrap());
+        let serve = serve(
+            ReceiverStream::new(requests_rx),
+            PollSender::new(responses_tx),
+            PendingService { calls: calls_tx },
+            scheduling,
+        );
+        pin!(serve);
+
+        // Service 1 floods the server, service 2 sends fewer requests.
+        for (id, service) in [(1, 1), (2, 1), (3, 1), (4, 1), (5, 2), (6, 2)] {
+            requests_tx.send(call_request(id, service)).await.unwrap();
+        }
+
+        let mut order = vec![];
+        for _ in 0..6 {
+            assert_matches!(poll_immediate(&mut serve).await, None);
+            let (id, complete) = calls_rx.try_recv().unwrap();
+            // Only one request is processed at a time.
+            assert_matches!(calls_rx.try_recv(), Err(TryRecvError::Empty));
+            order.push(id);
+            complete.send(()).unwrap();
+        }
+        assert_matches!(poll_immediate(&mut serve).await, None);
+        assert_eq!(order, [1, 5, 2, 3, 6, 4].map(RequestId::from));
+        for _ in 0..6 {
+            assert_matches!(responses_rx.try_recv(), Ok(Response { result: Ok(_), .. }));
+        }
+
+        drop(requests_tx);
+        assert_matches!(poll_immediate(&mut serve).await, Some(Ok(())));
+    }
+
+    #[tokio::test]
+    async fn test_server_scheduling_cancel_queued_call() {
+        let (requests_tx, requests_rx) = mpsc::channel(8);
+        let (responses_tx, mut responses_rx) = mpsc::channel(8);
+        let (calls_tx, mut calls_rx) = mpsc::unbounded_channel();
+        let serve = serve(
+            ReceiverStream::new(requests_rx),
+            PollSender::new(responses_tx),
+            PendingService { calls: calls_tx },
+            Scheduling::new().with_capacity(NonZeroUsize::new(1).unwrap()),
+        );
+        pin!(serve);
+
+        requests_tx.send(call_request(1, 1)).await.unwrap();
+        requests_tx.send(call_request(2, 1)).await.unwrap();
+        let subject =
+            message::Subject::new(ServiceId::new(1), ObjectId::new(1), ActionId::new(100));
+        requests_tx
+            .send(RequestWithId::new(
+                RequestId::from(3),
+                messaging::Cancel::new(subject, RequestId::from(2)).into(),
+            ))
+            .await
+            .unwrap();
+
+        // The queued call is canceled without reaching the service.
+        assert_matches!(poll_immediate(&mut serve).await, None);
+        assert_matches!(
+            responses_rx.try_recv(),
+            Ok(Response {
+                id: RequestId(2),
+                result: Err(CallTermination::Canceled),
+                ..
+            })
+        );
+        let (id, complete) = calls_rx.try_recv().unwrap();
+        assert_eq!(id, RequestId::from(1));
+        complete.send(()).unwrap();
+        assert_matches!(poll_immediate(&mut serve).await, None);
+        assert_matches!(calls_rx.try_recv(), Err(TryRecvError::Empty));
+        assert_matches!(
+            responses_rx.try_recv(),
+            Ok(Response {
+                id: RequestId(1),
+                result: Ok(RequestId(1)),
+                ..
+            })
+        );
+    }
+
     #[tokio::test]
     async fn test_server_sink_error_stops_task() {
         let (requests_tx, requests_rx) = mpsc::channel(1);
@@ -229,7 +565,12 @@ mod tests {
         let requests_stream = ReceiverStream::new(requests_rx);
         let responses_sink = PollSender::new(responses_tx);
 
-        let serve = serve(requests_stream, responses_sink, service);
+        let serve = serve(
+            requests_stream,
+            responses_sink,
+            service,
+            Scheduling::default(),
+        );
         pin!(serve);
 
         // Drop the sink receiver, this will cause errors from the sender.
@@ -246,4 +587,44 @@ mod tests {
 
         assert_matches!(poll_immediate(&mut serve).await, Some(Err(_err)));
     }
+
+    #[test]
+    fn test_response_into_message_return_type() {
+        let reply = Reply::with_value(&42i32)
+            .unwrap()
+            .with_return_signature("i".parse().unwrap());
+        let response = |return_type| Response::<_, String> {
+            id: RequestId::from(1),
+            subject: Subject::default(),
+            return_type,
+            result: Ok(reply.clone()),
+        };
+
+        // The signature is only sent if the caller requested it.
+        let message = Message::try_from(response(false)).unwrap();
+        assert!(!message.flags().contains(message::Flags::RETURN_TYPE));
+        assert_eq!(message.content().as_bytes().as_ref(), &[42, 0, 0, 0]);
+
+        let message = Message::try_from(response(true)).unwrap();
+        assert!(message.flags().contains(message::Flags::RETURN_TYPE));
+        assert_eq!(
+            message.content().as_bytes().as_ref(),
+            &[1, 0, 0, 0, b'i', 42, 0, 0, 0]
+        );
+        let reply = Reply::from_content_with_return_signature(message.into_content()).unwrap();
+        assert_eq!(reply.return_signature(), Some(&"i".parse().unwrap()));
+        assert_matches!(reply.value::<i32>(), Ok(42));
+    }
+
+    #[test]
+    fn test_reply_with_invalid_return_signature() {
+        assert_matches!(
+            Reply::from_content_with_return_signature([4, 0, 0, 0, b'i'].into()),
+            Err(service::ReturnSignatureError::Truncated)
+        );
+        assert_matches!(
+            Reply::from_content_with_return_signature([1, 0, 0, 0, b'!', 42].into()),
+            Err(service::ReturnSignatureError::Invalid(signature, _)) if signature == "!"
+        );
+    }
 }
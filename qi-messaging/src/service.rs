@@ -1,4 +1,4 @@
-use crate::{format, message};
+use crate::{format, message, types::Signature, TraceContext};
 pub use message::Id as RequestId;
 use pin_project_lite::pin_project;
 use std::{
@@ -87,6 +87,8 @@ impl<C, N> WithRequestId<Request<C, N>> {
 pub struct Call<S> {
     subject: S,
     formatted_value: format::Value,
+    return_type: bool,
+    trace_context: Option<TraceContext>,
 }
 
 pub(crate) type CallWithId<S> = WithRequestId<Call<S>>;
@@ -96,14 +98,53 @@ impl<S> Call<S> {
         Self {
             subject,
             formatted_value: format::Value::new(),
+            return_type: false,
+            trace_context: None,
         }
     }
 
+    /// Requests the signature of the returned value along with the reply of the call, see
+    /// [`Reply::return_signature`].
+    ///
+    /// The call is sent with the `RETURN_TYPE` flag. Remotes that do not know the signature of
+    /// their reply may ignore the request.
+    pub fn with_return_type(mut self) -> Self {
+        self.return_type = true;
+        self
+    }
+
+    /// Returns true if the caller requested the signature of the returned value.
+    pub fn return_type_requested(&self) -> bool {
+        self.return_type
+    }
+
+    /// Attaches the context of the trace of the caller to the call.
+    ///
+    /// The context is only sent to remotes that support it, see [`TraceContext`].
+    pub fn with_trace_context(mut self, trace_context: TraceContext) -> Self {
+        self.trace_context = Some(trace_context);
+        self
+    }
+
+    /// The context of the trace of the caller, if it was attached to the call.
+    pub fn trace_context(&self) -> Option<&TraceContext> {
+        self.trace_context.as_ref()
+    }
+
+    pub(crate) fn without_trace_context(mut self) -> Self {
+        self.trace_context = None;
+        self
+    }
+
     pub(crate) fn with_formatted_value(mut self, formatted_value: format::Value) -> Self {
         self.formatted_value = formatted_value;
         self
     }
 
+    pub(crate) fn formatted_value(&self) -> &format::Value {
+        &self.formatted_value
+    }
+
     pub(crate) fn into_formatted_value(self) -> format::Value {
         self.formatted_value
     }
@@ -122,6 +163,13 @@ impl<S> Call<S> {
     {
         self.formatted_value.to_deserializable()
     }
+
+    pub fn value_seed<'de, T>(&'de self, seed: T) -> Result<T::Value, format::Error>
+    where
+        T: serde::de::DeserializeSeed<'de>,
+    {
+        format::de::from_value_seed(&self.formatted_value, seed)
+    }
 }
 
 impl<S> GetSubject for Call<S> {
@@ -132,6 +180,11 @@ impl<S> GetSubject for Call<S> {
     }
 }
 
+/// A call of a method for which no response is expected, also known as "fire and forget".
+///
+/// Posts are sent in messages of kind [`Kind::Post`](crate::message::Kind::Post). Their subject
+/// is a method, which is executed by the remote as for a [`Call`], but its result, or its failure,
+/// is never sent back. To notify the subscribers of a signal, send an [`Event`] instead.
 #[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Default)]
 pub struct Post<S> {
     subject: S,
@@ -139,18 +192,35 @@ pub struct Post<S> {
 }
 
 impl<S> Post<S> {
-    pub(crate) fn new(subject: S) -> Self {
+    pub fn new(subject: S) -> Self {
         Self {
             subject,
             formatted_value: format::Value::new(),
         }
     }
 
-    pub(crate) fn with_formatted_value(mut self, formatted_value: format::Value) -> Self {
+    /// Sets the arguments of the method.
+    pub fn with_value<T>(mut self, value: &T) -> Result<Self, format::Error>
+    where
+        T: serde::Serialize,
+    {
+        self.formatted_value = format::Value::from_serializable(value)?;
+        Ok(self)
+    }
+
+    /// Sets the arguments of the method, already serialized in the `qi` format.
+    pub fn with_formatted_value(mut self, formatted_value: format::Value) -> Self {
         self.formatted_value = formatted_value;
         self
     }
 
+    pub fn value<'de, T>(&'de self) -> Result<T, format::Error>
+    where
+        T: serde::Deserialize<'de>,
+    {
+        self.formatted_value.to_deserializable()
+    }
+
     pub(crate) fn into_formatted_value(self) -> format::Value {
         self.formatted_value
     }
@@ -166,6 +236,11 @@ impl<S> GetSubject for Post<S> {
     }
 }
 
+/// An emission of a value of a signal to one of its subscribers.
+///
+/// Events are sent in messages of kind [`Kind::Event`](crate::message::Kind::Event). Their subject
+/// is a signal, no response is expected. To call a method without waiting for its result, send a
+/// [`Post`] instead.
 #[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Default)]
 pub struct Event<S> {
     subject: S,
@@ -173,18 +248,34 @@ pub struct Event<S> {
 }
 
 impl<S> Event<S> {
-    pub(crate) fn new(subject: S) -> Self {
+    pub fn new(subject: S) -> Self {
         Self {
             subject,
             formatted_value: format::Value::new(),
         }
     }
 
-    pub(crate) fn with_formatted_value(mut self, formatted_value: format::Value) -> Self {
+    pub fn with_value<T>(mut self, value: &T) -> Result<Self, format::Error>
+    where
+        T: serde::Serialize,
+    {
+        self.formatted_value = format::Value::from_serializable(value)?;
+        Ok(self)
+    }
+
+    /// Sets the value of the event, already serialized in the `qi` format.
+    pub fn with_formatted_value(mut self, formatted_value: format::Value) -> Self {
         self.formatted_value = formatted_value;
         self
     }
 
+    pub fn value<'de, T>(&'de self) -> Result<T, format::Error>
+    where
+        T: serde::Deserialize<'de>,
+    {
+        self.formatted_value.to_deserializable()
+    }
+
     pub(crate) fn into_formatted_value(self) -> format::Value {
         self.formatted_value
     }
@@ -307,23 +398,98 @@ impl<E> CallTermination<E> {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, derive_more::Into)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
 pub struct Reply {
     formatted_value: format::Value,
+    return_signature: Option<Signature>,
 }
 
 impl Reply {
     pub(crate) fn new(formatted_value: format::Value) -> Self {
-        Self { formatted_value }
+        Self {
+            formatted_value,
+            return_signature: None,
+        }
     }
 
     pub fn with_value<T>(value: &T) -> Result<Self, format::Error>
     where
         T: serde::Serialize,
     {
-        Ok(Self {
-            formatted_value: format::Value::from_serializable(value)?,
-        })
+        Ok(Self::new(format::Value::from_serializable(value)?))
+    }
+
+    /// Sets the signature of the returned value.
+    ///
+    /// It is sent along with the reply if the caller requested it, see [`Call::with_return_type`].
+    pub fn with_return_signature(mut self, signature: Signature) -> Self {
+        self.return_signature = Some(signature);
+        self
+    }
+
+    /// The signature of the returned value, if the caller requested it and the remote sent it.
+    pub fn return_signature(&self) -> Option<&Signature> {
+        self.return_signature.as_ref()
+    }
+
+    /// Reads a reply from the content of a message with the `RETURN_TYPE` flag, in which the
+    /// signature of the returned value, as a string, precedes the value.
+    pub(crate) fn from_content_with_return_signature(
+        content: format::Value,
+    ) -> Result<Self, ReturnSignatureError> {
+        use bytes::Buf;
+        let mut bytes = content.to_bytes();
+        let size = if bytes.remaining() >= std::mem::size_of::<u32>() {
+            usize::try_from(bytes.get_u32_le()).ok()
+        } else {
+            None
+        };
+        let signature = match size {
+            Some(size) if size <= bytes.remaining() => bytes.split_to(size),
+            _ => return Err(ReturnSignatureError::Truncated),
+        };
+        let signature = String::from_utf8_lossy(&signature);
+        let signature = signature
+            .parse()
+            .map_err(|err| ReturnSignatureError::Invalid(signature.clone().into_owned(), err))?;
+        Ok(Self::new(format::Value::from_bytes(bytes)).with_return_signature(signature))
+    }
+
+    /// Writes the reply as the content of a message, with the flags that the message must have.
+    ///
+    /// If the caller requested it and the reply has a return signature, the signature precedes the
+    /// value and the message has the `RETURN_TYPE` flag.
+    pub(crate) fn into_content(
+        self,
+        return_type_requested: bool,
+    ) -> Result<(format::Value, message::Flags), format::Error> {
+        match self.return_signature {
+            Some(signature) if return_type_requested => {
+                let signature = format::Value::from_serializable(&signature)?;
+                let mut content = bytes::BytesMut::from(signature.as_bytes().as_ref());
+                content.extend_from_slice(self.formatted_value.as_bytes());
+                Ok((
+                    format::Value::from_bytes(content.freeze()),
+                    message::Flags::RETURN_TYPE,
+                ))
+            }
+            _ => Ok((self.formatted_value, message::Flags::empty())),
+        }
+    }
+
+    /// Reads a reply with a `raw` value from an asynchronous source.
+    ///
+    /// The source is read to its end before the reply is created, so that a reply never holds a
+    /// partial value. If reading fails, the error is returned and there is nothing to send.
+    pub async fn from_async_read<R>(mut source: R) -> std::io::Result<Self>
+    where
+        R: tokio::io::AsyncRead + Unpin,
+    {
+        use tokio::io::AsyncReadExt;
+        let mut buf = Vec::new();
+        source.read_to_end(&mut buf).await?;
+        Self::with_value(&bytes::Bytes::from(buf))
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
     }
 
     pub fn value<'de, T>(&'de self) -> Result<T, format::Error>
@@ -332,19 +498,83 @@ impl Reply {
     {
         self.formatted_value.to_deserializable()
     }
+
+    pub fn value_seed<'de, S>(&'de self, seed: S) -> Result<S::Value, format::Error>
+    where
+        S: serde::de::DeserializeSeed<'de>,
+    {
+        format::de::from_value_seed(&self.formatted_value, seed)
+    }
+
+    pub fn value_with_utf8_policy<'de, T>(
+        &'de self,
+        utf8_policy: format::Utf8Policy,
+    ) -> Result<T, format::Error>
+    where
+        T: serde::Deserialize<'de>,
+    {
+        self.formatted_value
+            .to_deserializable_with_utf8_policy(utf8_policy)
+    }
 }
 
 pub type CallResult<T, E> = Result<T, CallTermination<E>>;
 
-#[derive(
-    Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, thiserror::Error, derive_more::From,
-)]
-#[error("the call request ended with an error: {0}")]
-pub struct Error(pub(crate) String);
+/// The formatted value of the reply, without its return signature.
+impl From<Reply> for format::Value {
+    fn from(reply: Reply) -> Self {
+        reply.formatted_value
+    }
+}
+
+/// The error of reading the return signature that precedes the value of a reply.
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum ReturnSignatureError {
+    #[error("the reply is too short to hold its return signature")]
+    Truncated,
+
+    #[error("the return signature \"{0}\" of the reply is invalid")]
+    Invalid(String, #[source] crate::types::signature::FromStrError),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, thiserror::Error)]
+#[error("the call request ended with an error: {reason}")]
+pub struct Error {
+    pub(crate) reason: String,
+    raw_description: Option<bytes::Bytes>,
+}
 
 impl Error {
+    /// An error of which the description could not be decoded, which is kept as it was received.
+    pub(crate) fn with_undecodable_description(raw_description: bytes::Bytes) -> Self {
+        if raw_description.is_empty() {
+            return Self::from("the error has no description".to_owned());
+        }
+        Self {
+            reason: format!(
+                "the description of the error could not be decoded ({} bytes)",
+                raw_description.len()
+            ),
+            raw_description: Some(raw_description),
+        }
+    }
+
     pub fn reason(&self) -> &str {
-        &self.0
+        &self.reason
+    }
+
+    /// The description of the error as it was received, if it could not be decoded.
+    pub fn raw_description(&self) -> Option<&[u8]> {
+        self.raw_description.as_deref()
+    }
+}
+
+impl From<String> for Error {
+    fn from(reason: String) -> Self {
+        Self {
+            reason,
+            raw_description: None,
+        }
     }
 }
 
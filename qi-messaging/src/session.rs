@@ -1,24 +1,382 @@
+mod config;
+mod connection;
 mod control;
+mod event_sources;
+mod multipath;
+mod payload_log;
+mod result_cache;
 mod router;
+mod slow_calls;
+mod watchdog;
 
+#[cfg(feature = "debug")]
+pub use crate::client::PendingCallState;
 use crate::{
     channel, client, messaging,
-    service::{self, CallResult, GetSubject, WithRequestId},
-    Service,
+    service::{self, CallResult, CallTermination, GetSubject, WithRequestId},
+    Service, SubjectRouter,
+};
+pub use crate::{
+    channel::{ChannelStats, CloseReport, KindStats, TrafficStats},
+    client::CancelFuture,
+    server::Scheduling,
+    service::Reply,
+    RequestId,
+};
+use bytes::Bytes;
+pub use config::{Config, RateLimit, SharedConfig};
+pub use connection::{ConnectionInfo, TlsInfo};
+pub use control::authentication::{
+    AuthState, ClientAuthenticator, NoAuthentication, ServerAuthenticator,
+};
+use control::capabilities::{CapabilitiesMap, CapabilitiesMapExt};
+pub use event_sources::EventSources;
+use futures::{future, FutureExt, Stream, StreamExt, TryFutureExt};
+pub use multipath::Multipath;
+pub use payload_log::PayloadLogger;
+pub use result_cache::{
+    ResultCache, ResultCacheFuture, ResultCacheHandle, ResultCacheStats,
+    DEFAULT_RESULT_CACHE_MAX_ENTRIES,
+};
+pub use slow_calls::{
+    CallLatencyStats, SlowCallDetector, SlowCallEvent, SlowCallFuture, SlowCallHandle,
 };
-pub use crate::{client::CancelFuture, service::Reply, RequestId};
-use futures::{FutureExt, TryFutureExt};
 use std::{
     future::Future,
+    net::SocketAddr,
     pin::Pin,
+    sync::Arc,
     task::{Context, Poll},
+    time::Duration,
 };
-use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    sync::watch,
+};
+use tokio_stream::wrappers::{errors::BroadcastStreamRecvError, BroadcastStream, ReceiverStream};
 use tracing::trace;
+pub use watchdog::DispatchStall;
 
 #[derive(Debug, Clone)]
 pub struct Client {
     client: client::Client,
+    events: channel::Events,
+    reply_chunks: channel::ReplyChunks,
+    event_batches: channel::EventBatches,
+    stats: channel::Stats,
+    close: channel::Close,
+    capabilities: watch::Receiver<CapabilitiesMap>,
+    connection: Arc<ConnectionInfo>,
+    remote_version: channel::RemoteVersion,
+    config: SharedConfig,
+    event_sources: EventSources,
+    stalls: watchdog::Stalls,
+}
+
+impl Client {
+    /// The configuration of the session, which may be updated while the session is running.
+    pub fn config(&self) -> &SharedConfig {
+        &self.config
+    }
+
+    /// The signals of the local end of the session that the remote may publish to.
+    pub fn event_sources(&self) -> &EventSources {
+        &self.event_sources
+    }
+
+    /// The information of the connection of the session, as it was when the session was
+    /// established.
+    pub fn connection_info(&self) -> &ConnectionInfo {
+        &self.connection
+    }
+
+    pub fn local_address(&self) -> Option<SocketAddr> {
+        self.connection.local_address()
+    }
+
+    pub fn remote_address(&self) -> Option<SocketAddr> {
+        self.connection.remote_address()
+    }
+
+    pub fn tls_info(&self) -> Option<&TlsInfo> {
+        self.connection.tls()
+    }
+
+    /// The version of the messaging protocol negotiated with the remote end, which is the version
+    /// of the messages that it sends.
+    ///
+    /// The remote sends messages to establish the session, and messages of a version that this end
+    /// does not support fail the session, so that both ends use this version.
+    pub fn protocol_version(&self) -> u16 {
+        self.remote_version
+            .get()
+            .unwrap_or(crate::message::Version::CURRENT)
+            .into()
+    }
+
+    /// The capabilities resolved between the local and the remote ends of the session.
+    pub fn capabilities(&self) -> CapabilitiesMap {
+        self.capabilities.borrow().clone()
+    }
+
+    /// Returns a receiver of the capabilities resolved between the local and the remote ends of
+    /// the session.
+    ///
+    /// The remote may update its capabilities while the session is running. The receiver is
+    /// notified each time the resolved capabilities change, and the features of the session that
+    /// depend on them, such as streaming call replies or compressed replies, follow the change.
+    pub fn watch_capabilities(&self) -> watch::Receiver<CapabilitiesMap> {
+        self.capabilities.clone()
+    }
+
+    /// Sends a call for which the reply may be streamed by chunks.
+    ///
+    /// Returns the stream of the chunks of the reply with the future of the call. The future
+    /// resolves with the final reply of the call, after which the stream terminates.
+    ///
+    /// The chunks that are not yet received are buffered, up to a bound. If a chunk arrives while
+    /// the buffer is full, the call fails with [`ClientError::ReplyChunksOverflow`] and is
+    /// canceled: the buffered chunks are still yielded by the stream, and the next ones are
+    /// discarded. The caller that awaits the reply before reading the chunks must expect at most
+    /// that many of them.
+    ///
+    /// If the remote does not support streaming call replies, the stream of chunks is empty and
+    /// the whole reply is only received by the future.
+    pub fn call_streaming(&self, call: Call) -> (ReplyChunks, CallFuture) {
+        let call = self.to_messaging_call(call);
+        if self.supports_streaming_call_replies() {
+            let (chunks, call) = self.client.call_streaming(call);
+            (
+                ReplyChunks(ReceiverStream::new(chunks)),
+                CallFuture::new(call, self.config.get().call_timeout()),
+            )
+        } else {
+            let mut client = &self.client;
+            let (_sender, chunks) = tokio::sync::mpsc::channel(1);
+            (
+                ReplyChunks(ReceiverStream::new(chunks)),
+                CallFuture::new(client.call(call), self.config.get().call_timeout()),
+            )
+        }
+    }
+
+    /// Sends a chunk of the reply of a call received from the remote.
+    ///
+    /// The remote receives the chunk through the stream returned by [`Client::call_streaming`].
+    /// Chunks sent before the service returns the reply of the call are received before it.
+    ///
+    /// Fails if the remote does not support streaming call replies, in which case the whole reply
+    /// must be returned at once.
+    pub async fn send_reply_chunk(
+        &self,
+        call: &CallWithId,
+        chunk: Reply,
+    ) -> Result<(), SendReplyChunkError> {
+        if !self.supports_streaming_call_replies() {
+            return Err(SendReplyChunkError::Unsupported);
+        }
+        self.reply_chunks
+            .send(call.id(), (*call.subject()).into(), chunk)
+            .await
+            .map_err(|_err| SessionClosedError(client::Error::DispatchTerminated).into())
+    }
+
+    /// Sends the chunks of the reply of a call received from the remote, as a stream produces
+    /// them. Returns the number of chunks that were sent.
+    ///
+    /// Each chunk is a complete value, it is only framed and written once the stream has produced
+    /// it entirely. If the stream fails, sending stops and the error is returned with the number
+    /// of chunks that were sent. The remote keeps these chunks, and the framing of the session is
+    /// not affected. The service should then terminate the call with an error, so that the remote
+    /// knows that the reply is incomplete.
+    pub async fn send_reply_chunks<S, E>(
+        &self,
+        call: &CallWithId,
+        chunks: S,
+    ) -> Result<usize, SendReplyChunksError<E>>
+    where
+        S: Stream<Item = Result<Reply, E>>,
+    {
+        futures::pin_mut!(chunks);
+        let mut sent = 0;
+        while let Some(chunk) = chunks.next().await {
+            let chunk = chunk.map_err(|error| SendReplyChunksError::Source { sent, error })?;
+            self.send_reply_chunk(call, chunk).await?;
+            sent += 1;
+        }
+        Ok(sent)
+    }
+
+    /// Sends a batch of events to the remote.
+    ///
+    /// The events are encoded together and written at once, which is cheaper than notifying them
+    /// one by one when they are emitted in bursts. They are received in the order of the batch,
+    /// but are not ordered with the notifications sent concurrently on the session.
+    pub async fn notify_events<I>(&self, events: I) -> Result<(), ClientError>
+    where
+        I: IntoIterator<Item = Event>,
+    {
+        let events = events.into_iter().map(|event| {
+            let id = self.client.create_request_id();
+            let subject = (*event.subject()).into();
+            (id, subject, event.into_formatted_value())
+        });
+        self.event_batches
+            .send(events)
+            .await
+            .map_err(|_err| SessionClosedError(client::Error::DispatchTerminated).into())
+    }
+
+    /// The number of responses received for calls that were not waiting for them anymore.
+    ///
+    /// A slow remote may respond to a call after it timed out or was canceled, the response
+    /// crossing the cancellation. Such responses are discarded, they do not terminate the session.
+    pub fn late_replies(&self) -> u64 {
+        self.client.late_replies()
+    }
+
+    /// The number of calls sent on the session that are waiting for their response, including the
+    /// calls of the session control.
+    pub fn ongoing_calls(&self) -> usize {
+        self.client.ongoing_calls()
+    }
+
+    /// The number of calls and notifications that are queued to be sent on the session.
+    pub fn queued_requests(&self) -> usize {
+        self.client.queued_requests()
+    }
+
+    /// Returns a snapshot of the statistics of the messages received and sent on the session, by
+    /// kind.
+    ///
+    /// The statistics are counted as long as the session runs, whether or not they are read, and
+    /// taking a snapshot is cheap enough to be done at any time.
+    pub fn stats(&self) -> ChannelStats {
+        self.stats.snapshot()
+    }
+
+    /// Returns true if the session is closed, after which nothing can be sent on it anymore.
+    pub fn is_closed(&self) -> bool {
+        self.client.is_closed()
+    }
+
+    /// Closes the session.
+    ///
+    /// The calls, replies and events that are queued to be sent are written first, for up to the
+    /// linger duration of the configuration of the session (see [`Config::with_linger`]). The
+    /// connection is then shut down, and the session terminates. The report tells whether some
+    /// messages were dropped because they could not be written in time.
+    ///
+    /// Fails if the session is already closed.
+    pub async fn close(&self) -> Result<CloseReport, SessionClosedError> {
+        self.close
+            .close(self.config.get().linger())
+            .await
+            .map_err(|_err| SessionClosedError(client::Error::DispatchTerminated))
+    }
+
+    /// Returns a stream of the stalls of the dispatch of the session, that occur when the handling
+    /// of its messages, or one of its services, blocks the thread for longer than the threshold of
+    /// the configuration of the session (see [`Config::with_dispatch_stall_threshold`]).
+    ///
+    /// Each stall is also logged as a warning. Only stalls that occur after this call are yielded.
+    pub fn dispatch_stalls(&self) -> impl Stream<Item = DispatchStall> {
+        BroadcastStream::new(self.stalls.subscribe()).filter_map(|stall| {
+            let stall = match stall {
+                Ok(stall) => Some(stall),
+                Err(BroadcastStreamRecvError::Lagged(count)) => {
+                    trace!(
+                        count,
+                        "the dispatch stalls subscriber lagged, some stalls were skipped"
+                    );
+                    None
+                }
+            };
+            future::ready(stall)
+        })
+    }
+
+    fn supports_streaming_call_replies(&self) -> bool {
+        self.capabilities.borrow().has_streaming_call_replies()
+    }
+
+    fn to_messaging_call(&self, call: Call) -> messaging::Call {
+        // The remote would not be able to read the arguments of the call.
+        if call.trace_context().is_some() && !self.capabilities.borrow().has_trace_context() {
+            return call.without_trace_context().into();
+        }
+        call.into()
+    }
+
+    /// Returns a snapshot of the calls sent on the session that are waiting for their response,
+    /// ordered by id.
+    ///
+    /// This is meant for debugging, for instance to find out which calls a deadlocked application
+    /// is waiting on. Calls of the session control, such as the authentication, are not included.
+    #[cfg(feature = "debug")]
+    pub fn pending_calls(&self) -> Vec<PendingCall> {
+        self.client
+            .pending_calls()
+            .into_iter()
+            .filter_map(|call| {
+                Some(PendingCall {
+                    id: call.id,
+                    subject: Subject::from_messaging(call.subject)?,
+                    elapsed: call.elapsed,
+                    state: call.state,
+                })
+            })
+            .collect()
+    }
+
+    /// Returns a stream of the events received on the session for which the subject matches the
+    /// filter.
+    ///
+    /// Each item is the subject of the event associated with its content, serialized in the `qi`
+    /// format. This gives access to the event traffic of the session without knowledge of the
+    /// objects that emit them. Only events received after this call are yielded.
+    pub fn events<F>(&self, mut filter: F) -> impl Stream<Item = (Subject, Bytes)>
+    where
+        F: FnMut(&Subject) -> bool,
+    {
+        self.filter_map_events(move |subject, content| {
+            filter(&subject).then_some((subject, content))
+        })
+    }
+
+    /// Same as [`Client::events`], for the events of which the subject matches a pattern of a
+    /// routing table. Each event is yielded with the value of the most specific pattern that it
+    /// matches.
+    pub fn routed_events<'a, T>(
+        &self,
+        routes: &'a SubjectRouter<T>,
+    ) -> impl Stream<Item = (&'a T, Subject, Bytes)> + 'a {
+        self.filter_map_events(move |subject, content| {
+            let value = routes.get_subject(&subject)?;
+            Some((value, subject, content))
+        })
+    }
+
+    fn filter_map_events<F, T>(&self, mut f: F) -> impl Stream<Item = T>
+    where
+        F: FnMut(Subject, Bytes) -> Option<T>,
+    {
+        BroadcastStream::new(self.events.subscribe()).filter_map(move |event| {
+            let event = match event {
+                Ok((subject, content)) => {
+                    Subject::from_messaging(subject).and_then(|subject| f(subject, content))
+                }
+                Err(BroadcastStreamRecvError::Lagged(count)) => {
+                    trace!(
+                        count,
+                        "the events subscriber lagged, some events were skipped"
+                    );
+                    None
+                }
+            };
+            future::ready(event)
+        })
+    }
 }
 
 impl crate::Service<Call, Notification> for Client {
@@ -45,8 +403,9 @@ impl crate::Service<Call, Notification> for &Client {
     type NotifyFuture = NotifyFuture;
 
     fn call(&mut self, call: Call) -> Self::CallFuture {
+        let call = self.to_messaging_call(call);
         let mut client = &self.client;
-        CallFuture(client.call(call.into()))
+        CallFuture::new(client.call(call), self.config.get().call_timeout())
     }
 
     fn notify(&mut self, notif: Notification) -> Self::NotifyFuture {
@@ -55,6 +414,36 @@ impl crate::Service<Call, Notification> for &Client {
     }
 }
 
+/// A call sent on a session that is waiting for its response, see [`Client::pending_calls`].
+#[cfg(feature = "debug")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingCall {
+    id: RequestId,
+    subject: Subject,
+    elapsed: std::time::Duration,
+    state: PendingCallState,
+}
+
+#[cfg(feature = "debug")]
+impl PendingCall {
+    pub fn id(&self) -> RequestId {
+        self.id
+    }
+
+    pub fn subject(&self) -> Subject {
+        self.subject
+    }
+
+    /// The time elapsed since the call was sent, when the snapshot was taken.
+    pub fn elapsed(&self) -> std::time::Duration {
+        self.elapsed
+    }
+
+    pub fn state(&self) -> PendingCallState {
+        self.state
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 #[error(transparent)]
 pub enum ClientError {
@@ -65,17 +454,48 @@ pub enum ClientError {
     // Format(#[from] format::Error),
     #[error(transparent)]
     Service(#[from] service::Error),
+
+    #[error("the call has had no response for {0:?}")]
+    Timeout(Duration),
+
+    #[error("the reply chunks of the call overflowed their buffer of {0} chunks")]
+    ReplyChunksOverflow(usize),
 }
 
 #[derive(Debug, thiserror::Error)]
 #[error("session is closed")]
 pub struct SessionClosedError(#[source] client::Error);
 
+#[derive(Debug, thiserror::Error)]
+pub enum SendReplyChunkError {
+    #[error("the remote does not support streaming call replies")]
+    Unsupported,
+
+    #[error(transparent)]
+    SessionClosed(#[from] SessionClosedError),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SendReplyChunksError<E> {
+    #[error("the source of the reply chunks failed after {sent} chunks were sent")]
+    Source {
+        sent: usize,
+        #[source]
+        error: E,
+    },
+
+    #[error(transparent)]
+    Send(#[from] SendReplyChunkError),
+}
+
 impl From<client::Error> for ClientError {
     fn from(error: client::Error) -> Self {
         match error {
             client::Error::DispatchTerminated => SessionClosedError(error).into(),
             client::Error::DispatchDroppedResponse => SessionClosedError(error).into(),
+            client::Error::ReplyChunksOverflow => {
+                Self::ReplyChunksOverflow(client::REPLY_CHUNKS_CAPACITY)
+            }
             client::Error::Messaging(err) => Self::Service(err),
         }
     }
@@ -94,14 +514,143 @@ where
     Svc::Error: std::fmt::Display + std::fmt::Debug + Send + Sync + 'static,
     Svc::CallReply: serde::Serialize,
 {
-    // As a client, we can enable the service in the router right away.
-    let (control, control_service) = control::create();
-    let router = router::Router::with_service_enabled(control_service, service);
-    let (mut client, channel_dispatch) = channel::open(io, router);
+    connect_with_scheduling(io, service, Scheduling::default())
+}
+
+/// Same as [`connect`], with a scheduling of the requests received for the service.
+pub fn connect_with_scheduling<IO, Svc>(
+    io: IO,
+    service: Svc,
+    scheduling: Scheduling,
+) -> (
+    impl Future<Output = Result<Client, ConnectError>>,
+    impl Future<Output = Result<(), Error>>,
+)
+where
+    IO: AsyncWrite + AsyncRead,
+    Svc: Service<CallWithId, NotificationWithId>,
+    Svc::Error: std::fmt::Display + std::fmt::Debug + Send + Sync + 'static,
+    Svc::CallReply: serde::Serialize,
+{
+    connect_with_options(io, service, scheduling, NoAuthentication, None)
+}
+
+/// Same as [`connect`], with the information of the connection of the session, such as its
+/// addresses, which the session cannot get from its IO object.
+pub fn connect_with_connection_info<IO, Svc>(
+    io: IO,
+    service: Svc,
+    connection: ConnectionInfo,
+) -> (
+    impl Future<Output = Result<Client, ConnectError>>,
+    impl Future<Output = Result<(), Error>>,
+)
+where
+    IO: AsyncWrite + AsyncRead,
+    Svc: Service<CallWithId, NotificationWithId>,
+    Svc::Error: std::fmt::Display + std::fmt::Debug + Send + Sync + 'static,
+    Svc::CallReply: serde::Serialize,
+{
+    connect_with_options(
+        io,
+        service,
+        Scheduling::default(),
+        NoAuthentication,
+        Some(connection),
+    )
+}
+
+/// Same as [`connect`], with an authenticator of the session to the server end, for servers that
+/// require authentication parameters, such as credentials.
+pub fn connect_with_authenticator<IO, Svc, A>(
+    io: IO,
+    service: Svc,
+    authenticator: A,
+) -> (
+    impl Future<Output = Result<Client, ConnectError>>,
+    impl Future<Output = Result<(), Error>>,
+)
+where
+    IO: AsyncWrite + AsyncRead,
+    Svc: Service<CallWithId, NotificationWithId>,
+    Svc::Error: std::fmt::Display + std::fmt::Debug + Send + Sync + 'static,
+    Svc::CallReply: serde::Serialize,
+    A: ClientAuthenticator,
+{
+    connect_with_options(io, service, Scheduling::default(), authenticator, None)
+}
+
+fn connect_with_options<IO, Svc, A>(
+    io: IO,
+    service: Svc,
+    scheduling: Scheduling,
+    mut authenticator: A,
+    connection: Option<ConnectionInfo>,
+) -> (
+    impl Future<Output = Result<Client, ConnectError>>,
+    impl Future<Output = Result<(), Error>>,
+)
+where
+    IO: AsyncWrite + AsyncRead,
+    Svc: Service<CallWithId, NotificationWithId>,
+    Svc::Error: std::fmt::Display + std::fmt::Debug + Send + Sync + 'static,
+    Svc::CallReply: serde::Serialize,
+    A: ClientAuthenticator,
+{
+    let connection = Arc::new(connection.unwrap_or_default());
+    let config = SharedConfig::default();
+    let event_sources = EventSources::default();
+    let stalls = watchdog::Stalls::new();
+    // As a client, we can enable the service in the router right away. The remote does not
+    // authenticate to us.
+    let (control, control_service) = control::create(Box::new(NoAuthentication));
+    let router = router::Router::with_service_enabled(
+        control_service,
+        service,
+        config.clone(),
+        event_sources.clone(),
+    );
+    let reply_compression = channel::ReplyCompression::new(
+        control.compressed_replies(),
+        channel::ReplyCompression::DEFAULT_THRESHOLD,
+    );
+    let (
+        channel::Handles {
+            mut client,
+            events,
+            reply_chunks,
+            event_batches,
+            stats,
+            close,
+            remote_version,
+        },
+        channel_dispatch,
+    ) = channel::open(io, router, scheduling, reply_compression);
+    let channel_dispatch = watchdog::Watchdog::new(
+        channel_dispatch,
+        config.clone(),
+        Arc::clone(&connection),
+        stalls.clone(),
+    );
 
     let client = async move {
-        control.authenticate_to_remote(&mut client).await?;
-        Ok(Client { client })
+        control
+            .authenticate_to_remote(&mut client, &mut authenticator)
+            .await?;
+        Ok(Client {
+            client,
+            events,
+            reply_chunks,
+            event_batches,
+            stats,
+            close,
+            capabilities: control.capabilities(),
+            connection,
+            remote_version,
+            config,
+            event_sources,
+            stalls,
+        })
     };
     let session = channel_dispatch.map_err(|err| Error(err.into()));
 
@@ -122,7 +671,10 @@ impl From<control::AuthenticateToRemoteError> for ConnectError {
         use control::AuthenticateToRemoteError as AuthError;
         use control::VerifyAuthenticationResultError;
         match error {
-            AuthError::Client(client::Error::Messaging(messaging::Error(message)))
+            AuthError::Client(client::Error::Messaging(messaging::Error {
+                reason: message,
+                ..
+            }))
             | AuthError::VerifyAuthenticationResult(VerifyAuthenticationResultError::Refused(
                 message,
             )) => Self::AuthenticationFailure(message),
@@ -144,12 +696,121 @@ where
     Svc::Error: std::fmt::Display + std::fmt::Debug + Sync + Send + 'static,
     Svc::CallReply: serde::Serialize,
 {
+    listen_with_scheduling(io, service, Scheduling::default())
+}
+
+/// Same as [`listen`], with a scheduling of the requests received for the service.
+pub fn listen_with_scheduling<IO, Svc>(
+    io: IO,
+    service: Svc,
+    scheduling: Scheduling,
+) -> (
+    impl Future<Output = Result<Client, ListenError>>,
+    impl Future<Output = Result<(), Error>>,
+)
+where
+    IO: AsyncWrite + AsyncRead + Send + 'static,
+    Svc: Service<CallWithId, NotificationWithId>,
+    Svc::Error: std::fmt::Display + std::fmt::Debug + Sync + Send + 'static,
+    Svc::CallReply: serde::Serialize,
+{
+    listen_with_options(io, service, scheduling, NoAuthentication, None)
+}
+
+/// Same as [`listen`], with the information of the connection of the session, such as its
+/// addresses, which the session cannot get from its IO object.
+pub fn listen_with_connection_info<IO, Svc>(
+    io: IO,
+    service: Svc,
+    connection: ConnectionInfo,
+) -> (
+    impl Future<Output = Result<Client, ListenError>>,
+    impl Future<Output = Result<(), Error>>,
+)
+where
+    IO: AsyncWrite + AsyncRead + Send + 'static,
+    Svc: Service<CallWithId, NotificationWithId>,
+    Svc::Error: std::fmt::Display + std::fmt::Debug + Sync + Send + 'static,
+    Svc::CallReply: serde::Serialize,
+{
+    listen_with_options(
+        io,
+        service,
+        Scheduling::default(),
+        NoAuthentication,
+        Some(connection),
+    )
+}
+
+/// Same as [`listen`], with an authenticator of the client end of the session. The service is only
+/// enabled once the authenticator accepts the client.
+pub fn listen_with_authenticator<IO, Svc, A>(
+    io: IO,
+    service: Svc,
+    authenticator: A,
+) -> (
+    impl Future<Output = Result<Client, ListenError>>,
+    impl Future<Output = Result<(), Error>>,
+)
+where
+    IO: AsyncWrite + AsyncRead + Send + 'static,
+    Svc: Service<CallWithId, NotificationWithId>,
+    Svc::Error: std::fmt::Display + std::fmt::Debug + Sync + Send + 'static,
+    Svc::CallReply: serde::Serialize,
+    A: ServerAuthenticator + 'static,
+{
+    listen_with_options(io, service, Scheduling::default(), authenticator, None)
+}
+
+fn listen_with_options<IO, Svc, A>(
+    io: IO,
+    service: Svc,
+    scheduling: Scheduling,
+    authenticator: A,
+    connection: Option<ConnectionInfo>,
+) -> (
+    impl Future<Output = Result<Client, ListenError>>,
+    impl Future<Output = Result<(), Error>>,
+)
+where
+    IO: AsyncWrite + AsyncRead + Send + 'static,
+    Svc: Service<CallWithId, NotificationWithId>,
+    Svc::Error: std::fmt::Display + std::fmt::Debug + Sync + Send + 'static,
+    Svc::CallReply: serde::Serialize,
+    A: ServerAuthenticator + 'static,
+{
+    let connection = Arc::new(connection.unwrap_or_default());
     // As a server, we first have to create the router, then wait for a successful
     // authentication to enable access to the service.
 
-    let (mut control, control_service) = control::create();
-    let (router, router_enable_service_sender) = router::Router::new(control_service);
-    let (client, channel_dispatch) = channel::open(io, router);
+    let config = SharedConfig::default();
+    let event_sources = EventSources::default();
+    let stalls = watchdog::Stalls::new();
+    let (mut control, control_service) = control::create(Box::new(authenticator));
+    let (router, router_enable_service_sender) =
+        router::Router::new(control_service, config.clone(), event_sources.clone());
+    let reply_compression = channel::ReplyCompression::new(
+        control.compressed_replies(),
+        channel::ReplyCompression::DEFAULT_THRESHOLD,
+    );
+    let (
+        channel::Handles {
+            client,
+            events,
+            reply_chunks,
+            event_batches,
+            stats,
+            close,
+            remote_version,
+        },
+        channel_dispatch,
+    ) = channel::open(io, router, scheduling, reply_compression);
+    let channel_dispatch = watchdog::Watchdog::new(
+        channel_dispatch,
+        config.clone(),
+        Arc::clone(&connection),
+        stalls.clone(),
+    );
 
     let client = async move {
         control.remote_authentication().await?;
@@ -159,7 +820,20 @@ where
         {
             trace!("failed to enable the service of the session router, the router service is probably terminated.");
         }
-        Ok(Client { client })
+        Ok(Client {
+            client,
+            events,
+            reply_chunks,
+            event_batches,
+            stats,
+            close,
+            capabilities: control.capabilities(),
+            connection,
+            remote_version,
+            config,
+            event_sources,
+            stalls,
+        })
     };
     let session = channel_dispatch.map_err(|err| Error(err.into()));
 
@@ -168,13 +842,21 @@ where
 
 #[derive(Debug, thiserror::Error)]
 pub enum ListenError {
+    #[error("the authenticator refused the client: {0}")]
+    AuthenticationRefused(String),
+
     #[error("the connection was terminated")]
     Terminated(#[source] Box<dyn std::error::Error + Send + Sync + 'static>),
 }
 
 impl From<control::RemoteAuthenticationError> for ListenError {
     fn from(error: control::RemoteAuthenticationError) -> Self {
-        Self::Terminated(error.into())
+        match error {
+            control::RemoteAuthenticationError::Refused(reason) => {
+                Self::AuthenticationRefused(reason)
+            }
+            error => Self::Terminated(error.into()),
+        }
     }
 }
 
@@ -224,6 +906,10 @@ pub mod subject {
             }
         }
 
+        pub fn service_object(&self) -> ServiceObject {
+            self.service_object
+        }
+
         pub fn service(&self) -> ServiceId {
             self.service_object.service
         }
@@ -260,7 +946,14 @@ pub type Call = service::Call<Subject>;
 
 impl From<Call> for messaging::Call {
     fn from(call: Call) -> Self {
-        Self::new((*call.subject()).into()).with_formatted_value(call.into_formatted_value())
+        let mut messaging_call = Self::new((*call.subject()).into());
+        if call.return_type_requested() {
+            messaging_call = messaging_call.with_return_type();
+        }
+        if let Some(trace_context) = call.trace_context() {
+            messaging_call = messaging_call.with_trace_context(*trace_context);
+        }
+        messaging_call.with_formatted_value(call.into_formatted_value())
     }
 }
 
@@ -269,8 +962,15 @@ impl CallWithId {
         match Subject::from_messaging(*call.subject()) {
             Some(subject) => {
                 let id = call.id();
-                let call = Call::new(subject)
-                    .with_formatted_value(call.into_inner().into_formatted_value());
+                let call = call.into_inner();
+                let mut session_call = Call::new(subject);
+                if call.return_type_requested() {
+                    session_call = session_call.with_return_type();
+                }
+                if let Some(trace_context) = call.trace_context() {
+                    session_call = session_call.with_trace_context(*trace_context);
+                }
+                let call = session_call.with_formatted_value(call.into_formatted_value());
                 Ok(Self::new(id, call))
             }
             None => Err(call),
@@ -278,13 +978,33 @@ impl CallWithId {
     }
 }
 
+/// A request for which no response is expected.
+///
+/// Posts and events are both sent without waiting for a response, but they do not have the same
+/// meaning: a post calls a method of an object, an event emits a value of a signal of an object.
+/// They are sent in messages of a distinct kind, see [`Notification::kind`].
 #[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, derive_more::From)]
 pub enum Notification {
+    /// A call of a method, whose result is not sent back.
     Post(Post),
+    /// An emission of a value of a signal.
     Event(Event),
+    /// The cancellation of a call.
     Cancel(Cancel),
 }
 
+impl Notification {
+    /// The kind of the message in which the notification is sent.
+    pub fn kind(&self) -> crate::message::Kind {
+        use crate::message::Kind;
+        match self {
+            Self::Post(_) => Kind::Post,
+            Self::Event(_) => Kind::Event,
+            Self::Cancel(_) => Kind::Cancel,
+        }
+    }
+}
+
 impl GetSubject for Notification {
     type Subject = Subject;
 
@@ -340,6 +1060,7 @@ pub type Post = service::Post<Subject>;
 impl From<Post> for messaging::Post {
     fn from(post: Post) -> Self {
         messaging::Post::new((*post.subject()).into())
+            .with_formatted_value(post.into_formatted_value())
     }
 }
 
@@ -348,6 +1069,7 @@ pub type Event = service::Event<Subject>;
 impl From<Event> for messaging::Event {
     fn from(event: Event) -> Self {
         messaging::Event::new((*event.subject()).into())
+            .with_formatted_value(event.into_formatted_value())
     }
 }
 
@@ -366,13 +1088,23 @@ pub type PostWithId = service::PostWithId<Subject>;
 pub type EventWithId = service::EventWithId<Subject>;
 pub type CancelWithId = service::CancelWithId<Subject>;
 
-#[derive(Debug, derive_more::From)]
+#[derive(Debug)]
 #[must_use = "futures do nothing until polled"]
-pub struct CallFuture(client::CallFuture);
+pub struct CallFuture {
+    inner: client::CallFuture,
+    timeout: Option<(Duration, Pin<Box<tokio::time::Sleep>>)>,
+}
 
 impl CallFuture {
+    fn new(inner: client::CallFuture, timeout: Option<Duration>) -> Self {
+        Self {
+            inner,
+            timeout: timeout.map(|timeout| (timeout, Box::pin(tokio::time::sleep(timeout)))),
+        }
+    }
+
     pub fn cancel(mut self) -> CancelFuture {
-        self.0.cancel()
+        self.inner.cancel()
     }
 }
 
@@ -380,13 +1112,38 @@ impl Future for CallFuture {
     type Output = CallResult<Reply, ClientError>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        self.0.poll_unpin(cx).map_err(|err| err.map_err(Into::into))
+        if let Poll::Ready(result) = self.inner.poll_unpin(cx) {
+            return Poll::Ready(result.map_err(|err| err.map_err(Into::into)));
+        }
+        if let Some((timeout, sleep)) = self.timeout.as_mut() {
+            if sleep.poll_unpin(cx).is_ready() {
+                let timeout = *timeout;
+                self.timeout = None;
+                // The remote is notified that the response is no longer expected.
+                tokio::spawn(self.inner.cancel());
+                return Poll::Ready(Err(CallTermination::Error(ClientError::Timeout(timeout))));
+            }
+        }
+        Poll::Pending
     }
 }
 
 impl service::ToRequestId for CallFuture {
     fn to_request_id(&self) -> RequestId {
-        self.0.to_request_id()
+        self.inner.to_request_id()
+    }
+}
+
+/// The stream of the chunks of the reply of a call, see [`Client::call_streaming`].
+#[derive(Debug)]
+#[must_use = "streams do nothing unless polled"]
+pub struct ReplyChunks(ReceiverStream<Reply>);
+
+impl Stream for ReplyChunks {
+    type Item = Reply;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.0.poll_next_unpin(cx)
     }
 }
 
@@ -523,6 +1280,212 @@ mod tests {
         super::Subject::new(service_object, ActionId::new(1))
     }
 
+    /// Challenges the client to answer with the reverse of a nonce.
+    struct ChallengeServer;
+
+    impl ServerAuthenticator for ChallengeServer {
+        fn authenticate(&mut self, parameters: &CapabilitiesMap) -> AuthState {
+            match parameters.get("auth_answer").and_then(|a| a.as_string()) {
+                None => AuthState::Continue(CapabilitiesMap::from_iter([("auth_nonce", "abc")])),
+                Some(answer) if answer == "cba" => AuthState::Done,
+                Some(_) => AuthState::Error("wrong answer".to_owned()),
+            }
+        }
+    }
+
+    struct ChallengeClient {
+        reverse: bool,
+    }
+
+    impl ClientAuthenticator for ChallengeClient {
+        fn parameters(&mut self) -> CapabilitiesMap {
+            CapabilitiesMap::new()
+        }
+
+        fn continue_with(
+            &mut self,
+            parameters: &CapabilitiesMap,
+        ) -> Result<CapabilitiesMap, String> {
+            let nonce = parameters
+                .get("auth_nonce")
+                .and_then(|nonce| nonce.as_string())
+                .ok_or("no nonce")?;
+            let answer: String = if self.reverse {
+                nonce.chars().rev().collect()
+            } else {
+                nonce.clone()
+            };
+            Ok(CapabilitiesMap::from_iter([("auth_answer", answer)]))
+        }
+    }
+
+    async fn authenticate(
+        client: ChallengeClient,
+    ) -> (
+        Result<super::Client, ConnectError>,
+        Result<super::Client, ListenError>,
+    ) {
+        let (io_client, io_server) = io::duplex(256);
+        let (client, client_dispatch) =
+            connect_with_authenticator(io_client, ServiceFn::new(to_async(to_try(sum))), client);
+        let (server, server_dispatch) = listen_with_authenticator(
+            io_server,
+            ServiceFn::new(to_async(to_try(add_to_string))),
+            ChallengeServer,
+        );
+        spawn(async move {
+            let _res = join!(client_dispatch, server_dispatch);
+        });
+        // The service of the server end is enabled by its client future, once the client end is
+        // authenticated.
+        let (client, server) = join!(client, server);
+        (client, server)
+    }
+
+    #[tokio::test]
+    async fn test_session_authentication_continue() {
+        let (client, server) = authenticate(ChallengeClient { reverse: true }).await;
+        let mut client = client.unwrap();
+        assert!(server.is_ok());
+        let reply = client
+            .call(
+                Call::new(any_service_subject())
+                    .with_value(&(1, 2))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(reply.value::<String>().unwrap(), "3");
+    }
+
+    #[tokio::test]
+    async fn test_session_authentication_refused() {
+        let (client, server) = authenticate(ChallengeClient { reverse: false }).await;
+        assert!(matches!(
+            client,
+            Err(ConnectError::AuthenticationFailure(reason)) if reason == "wrong answer"
+        ));
+        assert!(matches!(
+            server,
+            Err(ListenError::AuthenticationRefused(reason)) if reason == "wrong answer"
+        ));
+    }
+
+    #[test]
+    fn test_notification_message_kind() {
+        use crate::message::{codec::Encoder, Kind};
+        use tokio_util::codec::Encoder as _;
+
+        // The kind of the message follows its magic cookie, id, body size and version.
+        const KIND_OFFSET: usize = 14;
+        let subject = any_service_subject();
+        let notifications: [(Notification, Kind); 2] = [
+            (
+                Post::new(subject).with_value(&1).unwrap().into(),
+                Kind::Post,
+            ),
+            (
+                Event::new(subject).with_value(&1).unwrap().into(),
+                Kind::Event,
+            ),
+        ];
+        for (notif, kind) in notifications {
+            assert_eq!(notif.kind(), kind);
+            let notif = WithRequestId::new(RequestId(1), messaging::Notification::from(notif));
+            let message = crate::message::Message::try_from(notif).unwrap();
+            assert_eq!(message.kind(), kind);
+            let mut buf = bytes::BytesMut::new();
+            Encoder.encode(message, &mut buf).unwrap();
+            assert_eq!(buf[KIND_OFFSET], kind as u8);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_session_pair_post_is_not_an_event() {
+        let TestSessionPair { mut client, server } = TestSessionPair::new().await;
+        let subject = any_service_subject();
+        let events = server.events(|_subject| true);
+        futures::pin_mut!(events);
+
+        client
+            .notify(Post::new(subject).with_value(&1i32).unwrap().into())
+            .await
+            .unwrap();
+        client
+            .notify(Event::new(subject).with_value(&2i32).unwrap().into())
+            .await
+            .unwrap();
+        // Posts are delivered to the service of the session, only the event is published.
+        let (event_subject, content) = events.next().await.unwrap();
+        assert_eq!(event_subject, subject);
+        assert_eq!(content, Bytes::from_static(&[2, 0, 0, 0]));
+    }
+
+    #[tokio::test]
+    async fn test_session_pair_event_sources() {
+        let TestSessionPair { mut client, server } = TestSessionPair::new().await;
+        let subject = any_service_subject();
+        let call = || Call::new(subject).with_value(&(1, 2)).unwrap();
+
+        server
+            .event_sources()
+            .own(crate::SubjectPattern::any().with_service(subject.service()));
+        client
+            .notify(Event::new(subject).with_value(&1i32).unwrap().into())
+            .await
+            .unwrap();
+        client
+            .notify(Post::new(subject).with_value(&2i32).unwrap().into())
+            .await
+            .unwrap();
+        // Requests are served in order, the notifications were handled once the call returns.
+        client.call(call()).await.unwrap();
+        assert_eq!(server.event_sources().rejected(), 2);
+
+        server
+            .config()
+            .update(|config| config.with_remote_event_sources_trusted(true));
+        client
+            .notify(Event::new(subject).with_value(&3i32).unwrap().into())
+            .await
+            .unwrap();
+        client.call(call()).await.unwrap();
+        assert_eq!(server.event_sources().rejected(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_session_pair_stats() {
+        use crate::message::Kind;
+
+        let TestSessionPair { mut client, server } = TestSessionPair::new().await;
+        let before = client.stats();
+        // The authentication is a call of the client to the server.
+        assert_eq!(before.sent().kind(Kind::Call).messages(), 1);
+        assert_eq!(before.received().kind(Kind::Reply).messages(), 1);
+        assert!(before.last_activity().is_some());
+
+        client
+            .call(
+                Call::new(any_service_subject())
+                    .with_value(&(1, 2))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let after = client.stats();
+        assert_eq!(after.sent().kind(Kind::Call).messages(), 2);
+        assert_eq!(after.received().kind(Kind::Reply).messages(), 2);
+        assert!(after.sent().bytes() > before.sent().bytes());
+        assert_eq!(after.errors(), 0);
+        assert_eq!(after.cancellations(), 0);
+        assert!(after.last_activity() >= before.last_activity());
+
+        // The ends of the session see the same messages from each side.
+        let server_stats = server.stats();
+        assert_eq!(server_stats.received().messages(), after.sent().messages());
+        assert_eq!(server_stats.received().bytes(), after.sent().bytes());
+    }
+
     #[tokio::test]
     async fn test_session_pair_call() {
         let TestSessionPair {
@@ -549,4 +1512,601 @@ mod tests {
         let value: i32 = reply.value().unwrap();
         assert_eq!(value, -32204);
     }
+
+    #[tokio::test]
+    async fn test_session_pair_events() {
+        let TestSessionPair { client, mut server } = TestSessionPair::new().await;
+
+        let subject = any_service_subject();
+        let other_subject = super::Subject::new(
+            subject::ServiceObject::new(ServiceId::new(2), ObjectId::new(1)).unwrap(),
+            ActionId::new(1),
+        );
+        let mut events = Box::pin(client.events(move |s| s == &subject));
+
+        server
+            .notify(
+                Event::new(other_subject)
+                    .with_formatted_value([4, 5, 6].into())
+                    .into(),
+            )
+            .await
+            .unwrap();
+        server
+            .notify(
+                Event::new(subject)
+                    .with_formatted_value([1, 2, 3].into())
+                    .into(),
+            )
+            .await
+            .unwrap();
+
+        let (event_subject, content) = events.next().await.unwrap();
+        assert_eq!(event_subject, subject);
+        assert_eq!(content, Bytes::from_static(&[1, 2, 3]));
+    }
+
+    #[tokio::test]
+    async fn test_session_pair_routed_events() {
+        let TestSessionPair { client, mut server } = TestSessionPair::new().await;
+
+        let subject = any_service_subject();
+        let other_subject = super::Subject::new(
+            subject::ServiceObject::new(ServiceId::new(2), ObjectId::new(1)).unwrap(),
+            ActionId::new(1),
+        );
+        let mut routes = SubjectRouter::new();
+        routes.insert(
+            crate::SubjectPattern::any().with_service(ServiceId::new(2)),
+            "service",
+        );
+        routes.insert(crate::SubjectPattern::from(other_subject), "signal");
+        let mut events = Box::pin(client.routed_events(&routes));
+
+        for subject in [subject, other_subject] {
+            server
+                .notify(
+                    Event::new(subject)
+                        .with_formatted_value([1, 2, 3].into())
+                        .into(),
+                )
+                .await
+                .unwrap();
+        }
+
+        let (route, event_subject, content) = events.next().await.unwrap();
+        assert_eq!(*route, "signal");
+        assert_eq!(event_subject, other_subject);
+        assert_eq!(content, Bytes::from_static(&[1, 2, 3]));
+    }
+
+    #[tokio::test]
+    async fn test_session_pair_notify_events() {
+        let TestSessionPair { client, server } = TestSessionPair::new().await;
+
+        let subject = any_service_subject();
+        let mut events = Box::pin(client.events(move |s| s == &subject));
+
+        // The burst is received at once, the session must forward it to its service while it
+        // publishes it to the subscribers of events.
+        server
+            .notify_events(
+                (0u8..32).map(|i| Event::new(subject).with_formatted_value([i, i, i].into())),
+            )
+            .await
+            .unwrap();
+
+        for i in 0u8..32 {
+            let (event_subject, content) = events.next().await.unwrap();
+            assert_eq!(event_subject, subject);
+            assert_eq!(content, Bytes::copy_from_slice(&[i, i, i]));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_session_pair_close() {
+        let events = || {
+            (0u8..2)
+                .map(|i| Event::new(any_service_subject()).with_formatted_value([i; 1024].into()))
+        };
+
+        // Without a linger duration, the queued events are dropped. They cannot be written at once
+        // in the buffer of the connection.
+        let TestSessionPair { client, .. } = TestSessionPair::new().await;
+        client.notify_events(events()).await.unwrap();
+        let report = client.close().await.unwrap();
+        assert_eq!(report.dropped_messages(), 2);
+        assert!(!report.is_complete());
+        assert!(client.close().await.is_err());
+
+        let TestSessionPair { client, .. } = TestSessionPair::new().await;
+        client
+            .config()
+            .update(|config| config.with_linger(Some(Duration::from_secs(10))));
+        client.notify_events(events()).await.unwrap();
+        let report = client.close().await.unwrap();
+        assert!(report.is_complete());
+        assert!(client.close().await.is_err());
+    }
+
+    struct StreamingService {
+        server: watch::Receiver<Option<super::Client>>,
+        // The source of the chunks fails after the last one.
+        failing_source: bool,
+    }
+
+    impl crate::Service<CallWithId, NotificationWithId> for StreamingService {
+        type CallReply = String;
+        type Error = Box<dyn std::error::Error + Sync + Send>;
+        type CallFuture = BoxFuture<'static, CallResult<Self::CallReply, Self::Error>>;
+        type NotifyFuture = BoxFuture<'static, Result<(), Self::Error>>;
+
+        fn call(&mut self, call: CallWithId) -> Self::CallFuture {
+            let mut server = self.server.clone();
+            let failing_source = self.failing_source;
+            async move {
+                let server = server
+                    .wait_for(Option::is_some)
+                    .await
+                    .map_err(|err| CallTermination::Error(err.into()))?
+                    .clone()
+                    .unwrap();
+                let count: i32 = call
+                    .inner()
+                    .value()
+                    .map_err(|err| CallTermination::Error(err.into()))?;
+                let chunks = (0..count).map(|chunk| Ok(Reply::with_value(&chunk).unwrap()));
+                let source_error = failing_source
+                    .then(|| Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof)));
+                let chunks = futures::stream::iter(chunks.chain(source_error));
+                server
+                    .send_reply_chunks(&call, chunks)
+                    .await
+                    .map_err(|err| CallTermination::Error(err.into()))?;
+                Ok("done".to_owned())
+            }
+            .boxed()
+        }
+
+        fn notify(&mut self, _notif: NotificationWithId) -> Self::NotifyFuture {
+            future::ok(()).boxed()
+        }
+    }
+
+    async fn streaming_session_pair(failing_source: bool) -> super::Client {
+        let (io_client, io_server) = io::duplex(256);
+        let client_service = ServiceFn::new(to_async(to_try(sum)));
+        let (client, client_dispatch) = connect(io_client, client_service);
+        let (server_sender, server_receiver) = watch::channel(None);
+        let server_service = StreamingService {
+            server: server_receiver,
+            failing_source,
+        };
+        let (server, server_dispatch) = listen(io_server, server_service);
+        spawn(async move {
+            select! {
+                res = client_dispatch => {
+                    res.unwrap();
+                },
+                res = server_dispatch => {
+                    res.unwrap();
+                }
+            }
+        });
+        let (client, server) = join!(client.map(Result::unwrap), server.map(Result::unwrap));
+        server_sender.send_replace(Some(server));
+        client
+    }
+
+    #[tokio::test]
+    async fn test_session_pair_call_streaming() {
+        let client = streaming_session_pair(false).await;
+        let (chunks, call) =
+            client.call_streaming(Call::new(any_service_subject()).with_value(&3).unwrap());
+        let (chunks, reply) = join!(chunks.collect::<Vec<_>>(), call);
+        let chunks: Vec<i32> = chunks.iter().map(|chunk| chunk.value().unwrap()).collect();
+        assert_eq!(chunks, [0, 1, 2]);
+        let reply: String = reply.unwrap().value().unwrap();
+        assert_eq!(reply, "done");
+    }
+
+    #[tokio::test]
+    async fn test_session_pair_call_streaming_reply_awaited_before_chunks() {
+        let client = streaming_session_pair(false).await;
+        let (chunks, call) =
+            client.call_streaming(Call::new(any_service_subject()).with_value(&3).unwrap());
+        let reply: String = call.await.unwrap().value().unwrap();
+        assert_eq!(reply, "done");
+        let chunks: Vec<i32> = chunks
+            .map(|chunk| chunk.value::<i32>().unwrap())
+            .collect()
+            .await;
+        assert_eq!(chunks, [0, 1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_session_pair_call_streaming_source_error() {
+        let client = streaming_session_pair(true).await;
+        let (chunks, call) =
+            client.call_streaming(Call::new(any_service_subject()).with_value(&2).unwrap());
+        let (chunks, reply) = join!(chunks.collect::<Vec<_>>(), call);
+        let chunks: Vec<i32> = chunks.iter().map(|chunk| chunk.value().unwrap()).collect();
+        assert_eq!(chunks, [0, 1]);
+        assert_matches::assert_matches!(
+            reply,
+            Err(CallTermination::Error(ClientError::Service(_)))
+        );
+
+        // The session framing is intact, the next call goes through.
+        let (_chunks, call) =
+            client.call_streaming(Call::new(any_service_subject()).with_value(&0).unwrap());
+        assert_matches::assert_matches!(call.await, Err(CallTermination::Error(_)));
+    }
+
+    #[tokio::test]
+    async fn test_session_pair_call_streaming_chunks_overflow() {
+        let client = streaming_session_pair(false).await;
+        let count = i32::try_from(client::REPLY_CHUNKS_CAPACITY).unwrap() + 1;
+        let (chunks, call) =
+            client.call_streaming(Call::new(any_service_subject()).with_value(&count).unwrap());
+        // The chunks are not read while the reply is awaited, they overflow their buffer.
+        assert_matches::assert_matches!(
+            call.await,
+            Err(CallTermination::Error(ClientError::ReplyChunksOverflow(capacity)))
+                if capacity == client::REPLY_CHUNKS_CAPACITY
+        );
+        // The buffered chunks are still received.
+        let chunks: Vec<i32> = chunks
+            .map(|chunk| chunk.value::<i32>().unwrap())
+            .collect()
+            .await;
+        assert_eq!(chunks, (0..count - 1).collect::<Vec<_>>());
+
+        // The session is still usable.
+        let (chunks, call) =
+            client.call_streaming(Call::new(any_service_subject()).with_value(&2).unwrap());
+        let (chunks, reply) = join!(chunks.collect::<Vec<_>>(), call);
+        assert_eq!(chunks.len(), 2);
+        let reply: String = reply.unwrap().value().unwrap();
+        assert_eq!(reply, "done");
+    }
+
+    /// A service that answers a call of `n` by calling the remote with `n - 1`, from within the
+    /// call, until `n` is 0.
+    struct PingPongService {
+        session: watch::Receiver<Option<super::Client>>,
+    }
+
+    impl crate::Service<CallWithId, NotificationWithId> for PingPongService {
+        type CallReply = u32;
+        type Error = Box<dyn std::error::Error + Sync + Send>;
+        type CallFuture = BoxFuture<'static, CallResult<Self::CallReply, Self::Error>>;
+        type NotifyFuture = BoxFuture<'static, Result<(), Self::Error>>;
+
+        fn call(&mut self, call: CallWithId) -> Self::CallFuture {
+            let mut session = self.session.clone();
+            async move {
+                let n: u32 = call
+                    .inner()
+                    .value()
+                    .map_err(|err| CallTermination::Error(err.into()))?;
+                if n == 0 {
+                    return Ok(0);
+                }
+                let mut session = session
+                    .wait_for(Option::is_some)
+                    .await
+                    .map_err(|err| CallTermination::Error(err.into()))?
+                    .clone()
+                    .unwrap();
+                let reply = session
+                    .call(call.inner().clone().with_value(&(n - 1)).unwrap())
+                    .await
+                    .map_err(|err| err.map_err(Into::into))?;
+                let pongs: u32 = reply
+                    .value()
+                    .map_err(|err| CallTermination::Error(err.into()))?;
+                Ok(pongs + 1)
+            }
+            .boxed()
+        }
+
+        fn notify(&mut self, _notif: NotificationWithId) -> Self::NotifyFuture {
+            future::ok(()).boxed()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_session_pair_reentrant_calls() {
+        let (io_client, io_server) = io::duplex(256);
+        let (client_sender, client_receiver) = watch::channel(None);
+        let (server_sender, server_receiver) = watch::channel(None);
+        let (client, client_dispatch) = connect(
+            io_client,
+            PingPongService {
+                session: client_receiver,
+            },
+        );
+        let (server, server_dispatch) = listen(
+            io_server,
+            PingPongService {
+                session: server_receiver,
+            },
+        );
+        spawn(async move {
+            select! {
+                res = client_dispatch => {
+                    res.unwrap();
+                },
+                res = server_dispatch => {
+                    res.unwrap();
+                }
+            }
+        });
+        let (client, server) = join!(client.map(Result::unwrap), server.map(Result::unwrap));
+        client_sender.send_replace(Some(client.clone()));
+        server_sender.send_replace(Some(server));
+
+        // Each call is served while the calls that led to it wait for their reply, on both ends of
+        // the session. Several chains of calls are interleaved.
+        let calls = (0..8).map(|_| {
+            let mut client = client.clone();
+            async move {
+                client
+                    .call(Call::new(any_service_subject()).with_value(&16u32).unwrap())
+                    .await
+            }
+        });
+        let replies = tokio::time::timeout(Duration::from_secs(10), future::join_all(calls))
+            .await
+            .expect("the reentrant calls are deadlocked");
+        for reply in replies {
+            let pongs: u32 = reply.unwrap().value().unwrap();
+            assert_eq!(pongs, 16);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_session_pair_call_compressed_reply() {
+        let (io_client, io_server) = io::duplex(256);
+        let client_service = ServiceFn::new(to_async(to_try(sum)));
+        let (client, client_dispatch) = connect(io_client, client_service);
+        let server_service = ServiceFn::new(to_async(to_try(|count: usize| {
+            "getAngles::(mb)".repeat(count)
+        })));
+        let (server, server_dispatch) = listen(io_server, server_service);
+        spawn(async move {
+            select! {
+                res = client_dispatch => {
+                    res.unwrap();
+                },
+                res = server_dispatch => {
+                    res.unwrap();
+                }
+            }
+        });
+        let (mut client, _server) = join!(client.map(Result::unwrap), server.map(Result::unwrap));
+
+        // Both small and large replies, that are compressed, are received unchanged.
+        for count in [1, 1000] {
+            let reply = client
+                .call(Call::new(any_service_subject()).with_value(&count).unwrap())
+                .await
+                .unwrap();
+            let value: String = reply.value().unwrap();
+            assert_eq!(value, "getAngles::(mb)".repeat(count));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_session_pair_config() {
+        let TestSessionPair { mut client, server } = TestSessionPair::new().await;
+        let subject = any_service_subject();
+
+        server.config().update(|config| {
+            config.with_incoming_call_rate_limit(Some(RateLimit::new(
+                std::num::NonZeroU32::new(1).unwrap(),
+                Duration::from_secs(3600),
+            )))
+        });
+        let call = || Call::new(subject).with_value(&(1, 2)).unwrap();
+        assert_matches::assert_matches!(client.call(call()).await, Ok(_));
+        assert_matches::assert_matches!(
+            client.call(call()).await,
+            Err(CallTermination::Error(ClientError::Service(_)))
+        );
+
+        server.config().set(Config::new());
+        assert_matches::assert_matches!(client.call(call()).await, Ok(_));
+
+        server
+            .config()
+            .update(|config| config.with_incoming_calls_refused(true));
+        assert_matches::assert_matches!(
+            client.call(call()).await,
+            Err(CallTermination::Error(ClientError::Service(_)))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_session_pair_call_timeout() {
+        let (io_client, io_server) = io::duplex(256);
+        let (client, client_dispatch) = connect(io_client, ServiceFn::new(to_async(to_try(sum))));
+        let server_service =
+            ServiceFn::new(|()| future::pending::<Result<(), std::convert::Infallible>>());
+        let (server, server_dispatch) = listen(io_server, server_service);
+        spawn(async move {
+            select! {
+                res = client_dispatch => {
+                    res.unwrap();
+                },
+                res = server_dispatch => {
+                    res.unwrap();
+                }
+            }
+        });
+        let (mut client, _server) = join!(client.map(Result::unwrap), server.map(Result::unwrap));
+
+        let timeout = Duration::from_millis(10);
+        client
+            .config()
+            .update(|config| config.with_call_timeout(Some(timeout)));
+        let result = client
+            .call(Call::new(any_service_subject()).with_value(&()).unwrap())
+            .await;
+        assert_matches::assert_matches!(
+            result,
+            Err(CallTermination::Error(ClientError::Timeout(t))) if t == timeout
+        );
+    }
+
+    #[tokio::test]
+    async fn test_session_pair_dispatch_stall() {
+        let (io_client, io_server) = io::duplex(256);
+        let (client, client_dispatch) = connect(io_client, ServiceFn::new(to_async(to_try(sum))));
+        // The service blocks the thread of the dispatch of the session while it serves a call.
+        let server_service = ServiceFn::new(|()| {
+            std::thread::sleep(Duration::from_millis(50));
+            future::ok::<_, std::convert::Infallible>(())
+        });
+        let (server, server_dispatch) = listen(io_server, server_service);
+        spawn(async move {
+            select! {
+                res = client_dispatch => {
+                    res.unwrap();
+                },
+                res = server_dispatch => {
+                    res.unwrap();
+                }
+            }
+        });
+        let (mut client, server) = join!(client.map(Result::unwrap), server.map(Result::unwrap));
+
+        let threshold = Duration::from_millis(10);
+        server
+            .config()
+            .update(|config| config.with_dispatch_stall_threshold(Some(threshold)));
+        let mut stalls = Box::pin(server.dispatch_stalls());
+        client
+            .call(Call::new(any_service_subject()).with_value(&()).unwrap())
+            .await
+            .unwrap();
+        let stall = tokio::time::timeout(Duration::from_secs(5), stalls.next())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stall.threshold(), threshold);
+        assert!(stall.duration() >= Duration::from_millis(50));
+    }
+
+    #[tokio::test]
+    async fn test_session_pair_late_reply() {
+        let (io_client, io_server) = io::duplex(256);
+        let (client, client_dispatch) = connect(io_client, ServiceFn::new(to_async(to_try(sum))));
+        // The service responds after the call timed out on the client side. Its response, either
+        // the reply or the acknowledgement of the cancellation, arrives late.
+        let server_service = ServiceFn::new(|()| async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Ok::<_, std::convert::Infallible>(())
+        });
+        let (server, server_dispatch) = listen(io_server, server_service);
+        let dispatch = spawn(async move {
+            select! {
+                res = client_dispatch => res,
+                res = server_dispatch => res,
+            }
+        });
+        let (mut client, _server) = join!(client.map(Result::unwrap), server.map(Result::unwrap));
+
+        client
+            .config()
+            .update(|config| config.with_call_timeout(Some(Duration::from_millis(10))));
+        let result = client
+            .call(Call::new(any_service_subject()).with_value(&()).unwrap())
+            .await;
+        assert_matches::assert_matches!(
+            result,
+            Err(CallTermination::Error(ClientError::Timeout(_)))
+        );
+
+        // The late reply is discarded and the session goes on.
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while client.late_replies() == 0 {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(client.late_replies(), 1);
+        client
+            .config()
+            .update(|config| config.with_call_timeout(None));
+        let result = client
+            .call(Call::new(any_service_subject()).with_value(&()).unwrap())
+            .await;
+        assert_matches::assert_matches!(result, Ok(_));
+        assert!(!dispatch.is_finished());
+    }
+
+    #[tokio::test]
+    async fn test_session_pair_connection_info() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server_address = listener.local_addr().unwrap();
+        let (io_client, (io_server, client_address)) = join!(
+            tokio::net::TcpStream::connect(server_address).map(Result::unwrap),
+            listener.accept().map(Result::unwrap)
+        );
+        let client_connection = ConnectionInfo::from_tcp_stream(&io_client);
+        let server_connection = ConnectionInfo::from_tcp_stream(&io_server)
+            .with_tls(TlsInfo::new().with_server_name("localhost"));
+        let (client, client_dispatch) = connect_with_connection_info(
+            io_client,
+            ServiceFn::new(to_async(to_try(sum))),
+            client_connection,
+        );
+        let (server, server_dispatch) = listen_with_connection_info(
+            io_server,
+            ServiceFn::new(to_async(to_try(add_to_string))),
+            server_connection,
+        );
+        spawn(async move {
+            select! {
+                res = client_dispatch => {
+                    res.unwrap();
+                },
+                res = server_dispatch => {
+                    res.unwrap();
+                }
+            }
+        });
+        let (client, server) = join!(client.map(Result::unwrap), server.map(Result::unwrap));
+
+        assert_eq!(client.local_address(), Some(client_address));
+        assert_eq!(client.remote_address(), Some(server_address));
+        assert_eq!(server.local_address(), Some(server_address));
+        assert_eq!(server.remote_address(), Some(client_address));
+        assert_eq!(client.tls_info(), None);
+        assert_eq!(
+            server.tls_info().and_then(TlsInfo::server_name),
+            Some("localhost")
+        );
+        assert_eq!(client.protocol_version(), 0);
+        assert_eq!(server.protocol_version(), 0);
+
+        let TestSessionPair { client, .. } = TestSessionPair::new().await;
+        assert_eq!(client.connection_info(), &ConnectionInfo::default());
+    }
+
+    #[test]
+    fn test_call_return_type_to_from_messaging() {
+        let call = Call::new(any_service_subject()).with_return_type();
+        let messaging_call = messaging::Call::from(call);
+        assert!(messaging_call.return_type_requested());
+        let call = CallWithId::from_messaging(messaging::CallWithId::new(
+            RequestId::from(1),
+            messaging_call,
+        ))
+        .unwrap();
+        assert!(call.inner().return_type_requested());
+    }
 }
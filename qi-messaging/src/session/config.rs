@@ -0,0 +1,237 @@
+use std::{num::NonZeroU32, sync::Arc, time::Duration};
+use tokio::sync::watch;
+
+/// Parameters of a session that may be changed while it is running, see [`SharedConfig`].
+///
+/// By default, calls have no timeout, incoming calls are accepted without limit, payloads are not
+/// logged, the dispatch of the session is not watched for stalls, closing the session drops the
+/// messages that are queued to be sent and the remote may not publish to the owned signals of the
+/// local end.
+#[derive(Default, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Config {
+    call_timeout: Option<Duration>,
+    incoming_call_rate_limit: Option<RateLimit>,
+    payload_sampling_period: Option<NonZeroU32>,
+    incoming_calls_refused: bool,
+    dispatch_stall_threshold: Option<Duration>,
+    linger: Option<Duration>,
+    remote_event_sources_trusted: bool,
+}
+
+impl Config {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the duration after which the calls sent on the session fail if they have no response.
+    pub fn with_call_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.call_timeout = timeout;
+        self
+    }
+
+    /// Sets the maximum rate of the calls received by the service of the session. Calls beyond
+    /// this rate are answered with an error, without reaching the service.
+    pub fn with_incoming_call_rate_limit(mut self, limit: Option<RateLimit>) -> Self {
+        self.incoming_call_rate_limit = limit;
+        self
+    }
+
+    /// Sets the sampling period of the payload loggers that follow the configuration of the
+    /// session, or disables them. See [`super::PayloadLogger::with_config`].
+    pub fn with_payload_sampling_period(mut self, period: Option<NonZeroU32>) -> Self {
+        self.payload_sampling_period = period;
+        self
+    }
+
+    /// Sets whether the calls received by the service of the session are refused. Refused calls
+    /// are answered with an error, without reaching the service, for instance while the
+    /// application shuts down.
+    pub fn with_incoming_calls_refused(mut self, refused: bool) -> Self {
+        self.incoming_calls_refused = refused;
+        self
+    }
+
+    /// Sets the duration beyond which the dispatch of the session is considered stalled when it
+    /// does not yield, for instance because a service blocks its thread. See
+    /// [`super::Client::dispatch_stalls`].
+    pub fn with_dispatch_stall_threshold(mut self, threshold: Option<Duration>) -> Self {
+        self.dispatch_stall_threshold = threshold;
+        self
+    }
+
+    /// Sets the duration for which the messages that are queued to be sent are still written when
+    /// the session is closed, or disables it. See [`super::Client::close`].
+    pub fn with_linger(mut self, linger: Option<Duration>) -> Self {
+        self.linger = linger;
+        self
+    }
+
+    /// Sets whether the remote may publish to all the signals of the local end, including the
+    /// owned ones. This suits bridges that relay signals on behalf of other nodes, and are trusted
+    /// to do so. See [`super::EventSources`].
+    pub fn with_remote_event_sources_trusted(mut self, trusted: bool) -> Self {
+        self.remote_event_sources_trusted = trusted;
+        self
+    }
+
+    pub fn call_timeout(&self) -> Option<Duration> {
+        self.call_timeout
+    }
+
+    pub fn incoming_call_rate_limit(&self) -> Option<RateLimit> {
+        self.incoming_call_rate_limit
+    }
+
+    pub fn payload_sampling_period(&self) -> Option<NonZeroU32> {
+        self.payload_sampling_period
+    }
+
+    pub fn incoming_calls_refused(&self) -> bool {
+        self.incoming_calls_refused
+    }
+
+    pub fn dispatch_stall_threshold(&self) -> Option<Duration> {
+        self.dispatch_stall_threshold
+    }
+
+    pub fn linger(&self) -> Option<Duration> {
+        self.linger
+    }
+
+    pub fn remote_event_sources_trusted(&self) -> bool {
+        self.remote_event_sources_trusted
+    }
+}
+
+/// A maximum number of calls over a period of time.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct RateLimit {
+    calls: NonZeroU32,
+    period: Duration,
+}
+
+impl RateLimit {
+    pub fn new(calls: NonZeroU32, period: Duration) -> Self {
+        Self { calls, period }
+    }
+
+    pub fn calls(&self) -> NonZeroU32 {
+        self.calls
+    }
+
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+}
+
+/// The configuration of a session, shared by its components.
+///
+/// Components consult the configuration each time they use it, so that an update takes effect
+/// on a live session without reconnecting. Updates are atomic, components never observe a
+/// partially updated configuration. Clones share the same configuration.
+#[derive(Clone, Debug)]
+pub struct SharedConfig(Arc<watch::Sender<Config>>);
+
+impl SharedConfig {
+    pub fn new(config: Config) -> Self {
+        let (sender, _receiver) = watch::channel(config);
+        Self(Arc::new(sender))
+    }
+
+    /// Returns the current configuration.
+    pub fn get(&self) -> Config {
+        *self.0.borrow()
+    }
+
+    /// Replaces the configuration.
+    pub fn set(&self, config: Config) {
+        self.0.send_replace(config);
+    }
+
+    /// Updates the configuration from its current value.
+    pub fn update<F>(&self, f: F)
+    where
+        F: FnOnce(Config) -> Config,
+    {
+        self.0.send_modify(|config| *config = f(*config));
+    }
+}
+
+impl Default for SharedConfig {
+    fn default() -> Self {
+        Self::new(Config::default())
+    }
+}
+
+/// Limits the rate of calls, by counting them over fixed windows of time.
+#[derive(Debug)]
+pub(super) struct RateLimiter {
+    config: SharedConfig,
+    window_start: tokio::time::Instant,
+    window_calls: u32,
+}
+
+impl RateLimiter {
+    pub(super) fn new(config: SharedConfig) -> Self {
+        Self {
+            config,
+            window_start: tokio::time::Instant::now(),
+            window_calls: 0,
+        }
+    }
+
+    /// Counts a call, and returns whether it is allowed by the current rate limit.
+    pub(super) fn allow(&mut self) -> bool {
+        let limit = match self.config.get().incoming_call_rate_limit() {
+            Some(limit) => limit,
+            None => return true,
+        };
+        let now = tokio::time::Instant::now();
+        if now.duration_since(self.window_start) >= limit.period() {
+            self.window_start = now;
+            self.window_calls = 0;
+        }
+        if self.window_calls >= limit.calls().get() {
+            return false;
+        }
+        self.window_calls += 1;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shared_config_update() {
+        let config = SharedConfig::default();
+        let clone = config.clone();
+        assert_eq!(config.get(), Config::new());
+
+        clone.update(|config| config.with_call_timeout(Some(Duration::from_secs(1))));
+        assert_eq!(config.get().call_timeout(), Some(Duration::from_secs(1)));
+
+        config.set(Config::new());
+        assert_eq!(clone.get(), Config::new());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_rate_limiter() {
+        let config = SharedConfig::default();
+        let mut limiter = RateLimiter::new(config.clone());
+        assert!((0..10).all(|_| limiter.allow()));
+
+        config.update(|config| {
+            config.with_incoming_call_rate_limit(Some(RateLimit::new(
+                NonZeroU32::new(2).unwrap(),
+                Duration::from_secs(1),
+            )))
+        });
+        let allowed: Vec<_> = std::iter::repeat_with(|| limiter.allow()).take(3).collect();
+        assert_eq!(allowed, [true, true, false]);
+
+        tokio::time::advance(Duration::from_secs(1)).await;
+        assert!(limiter.allow());
+    }
+}
@@ -0,0 +1,97 @@
+use std::net::SocketAddr;
+use tokio::net::TcpStream;
+
+/// The information of the connection over which a session is established.
+///
+/// Sessions cannot get it from their IO object, it is given to them when they are established, see
+/// [`connect_with_connection_info`](super::connect_with_connection_info) and
+/// [`listen_with_connection_info`](super::listen_with_connection_info).
+#[derive(Default, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct ConnectionInfo {
+    local_address: Option<SocketAddr>,
+    remote_address: Option<SocketAddr>,
+    tls: Option<TlsInfo>,
+}
+
+impl ConnectionInfo {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The addresses of a TCP stream, which has no TLS layer.
+    pub fn from_tcp_stream(stream: &TcpStream) -> Self {
+        Self {
+            local_address: stream.local_addr().ok(),
+            remote_address: stream.peer_addr().ok(),
+            tls: None,
+        }
+    }
+
+    pub fn with_local_address(mut self, address: SocketAddr) -> Self {
+        self.local_address = Some(address);
+        self
+    }
+
+    pub fn with_remote_address(mut self, address: SocketAddr) -> Self {
+        self.remote_address = Some(address);
+        self
+    }
+
+    pub fn with_tls(mut self, tls: TlsInfo) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    pub fn local_address(&self) -> Option<SocketAddr> {
+        self.local_address
+    }
+
+    pub fn remote_address(&self) -> Option<SocketAddr> {
+        self.remote_address
+    }
+
+    pub fn tls(&self) -> Option<&TlsInfo> {
+        self.tls.as_ref()
+    }
+}
+
+/// Information about the TLS layer of a connection, as resolved by the TLS handshake.
+#[derive(Default, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct TlsInfo {
+    protocol_version: Option<String>,
+    cipher_suite: Option<String>,
+    server_name: Option<String>,
+}
+
+impl TlsInfo {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_protocol_version(mut self, version: impl Into<String>) -> Self {
+        self.protocol_version = Some(version.into());
+        self
+    }
+
+    pub fn with_cipher_suite(mut self, cipher_suite: impl Into<String>) -> Self {
+        self.cipher_suite = Some(cipher_suite.into());
+        self
+    }
+
+    pub fn with_server_name(mut self, server_name: impl Into<String>) -> Self {
+        self.server_name = Some(server_name.into());
+        self
+    }
+
+    pub fn protocol_version(&self) -> Option<&str> {
+        self.protocol_version.as_deref()
+    }
+
+    pub fn cipher_suite(&self) -> Option<&str> {
+        self.cipher_suite.as_deref()
+    }
+
+    pub fn server_name(&self) -> Option<&str> {
+        self.server_name.as_deref()
+    }
+}
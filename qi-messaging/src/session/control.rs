@@ -1,7 +1,7 @@
-mod authentication;
+pub(super) mod authentication;
 pub(super) mod capabilities;
 
-use self::authentication::authenticate;
+use self::authentication::{AuthState, ClientAuthenticator, ServerAuthenticator};
 use crate::{
     client, format, messaging,
     service::{CallResult, CallTermination},
@@ -9,9 +9,9 @@ use crate::{
     GetSubject,
 };
 use capabilities::{CapabilitiesMap, CapabilitiesMapExt};
-use futures::{future, FutureExt, TryFutureExt};
-use std::{future::Future, sync::Arc};
-use tokio::sync::{watch, Mutex};
+use futures::future;
+use std::sync::{Arc, Mutex, PoisonError};
+use tokio::sync::watch;
 use tracing::{instrument, trace};
 
 mod subject {
@@ -66,9 +66,16 @@ mod subject {
 }
 pub(super) use subject::{is_object, is_service, Subject};
 
-pub(super) fn create() -> (Control, Service) {
-    let capabilities = Arc::new(Mutex::new(CapabilitiesMap::new()));
-    let (remote_authenticated_sender, remote_authenticated_receiver) = watch::channel(false);
+pub(super) fn create(authenticator: Box<dyn ServerAuthenticator>) -> (Control, Service) {
+    let (capabilities, _capabilities_receiver) = watch::channel(CapabilitiesMap::new());
+    let (compressed_replies, _compressed_replies_receiver) = watch::channel(false);
+    let capabilities = Arc::new(CapabilitiesSender {
+        remote: Mutex::default(),
+        capabilities,
+        compressed_replies,
+    });
+    let (remote_authenticated_sender, remote_authenticated_receiver) =
+        watch::channel(RemoteAuthentication::Pending);
     (
         Control {
             capabilities: Arc::clone(&capabilities),
@@ -77,56 +84,155 @@ pub(super) fn create() -> (Control, Service) {
         Service {
             capabilities,
             remote_authentication_sender: remote_authenticated_sender,
+            authenticator,
         },
     )
 }
 
+/// Publishes the capabilities resolved between the local and the remote ends, along with the
+/// features of the session that depend on them.
+///
+/// The remote may update its capabilities at any time during the session. Updates are merged
+/// into the capabilities it advertised so far, and the capabilities are resolved again from the
+/// result. Subscribers are only notified if the resolved capabilities change.
+#[derive(Debug)]
+struct CapabilitiesSender {
+    remote: Mutex<CapabilitiesMap>,
+    capabilities: watch::Sender<CapabilitiesMap>,
+    compressed_replies: watch::Sender<bool>,
+}
+
+impl CapabilitiesSender {
+    /// Sets the capabilities advertised by the remote, and publishes the resolved ones.
+    fn set_remote(&self, remote: CapabilitiesMap, resolved: CapabilitiesMap) {
+        *self.lock_remote() = remote;
+        self.send(resolved);
+    }
+
+    /// Merges an update of the capabilities of the remote into the ones it advertised so far, and
+    /// publishes the resolved ones.
+    ///
+    /// If the merged capabilities are not supported, the update is discarded.
+    fn update_remote(
+        &self,
+        update: &CapabilitiesMap,
+    ) -> Result<(), capabilities::ExpectedKeyValueError<bool>> {
+        let mut remote = self.lock_remote();
+        let mut merged = remote.clone();
+        merged.extend(
+            update
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone())),
+        );
+        let resolved = merged.clone().check_intersect_with_local()?;
+        *remote = merged;
+        self.send(resolved);
+        Ok(())
+    }
+
+    fn send(&self, capabilities: CapabilitiesMap) {
+        let compressed_replies = capabilities.has_compressed_replies();
+        self.compressed_replies.send_if_modified(|current| {
+            let modified = *current != compressed_replies;
+            *current = compressed_replies;
+            modified
+        });
+        self.capabilities.send_if_modified(|current| {
+            let modified = *current != capabilities;
+            *current = capabilities;
+            modified
+        });
+    }
+
+    fn lock_remote(&self) -> std::sync::MutexGuard<'_, CapabilitiesMap> {
+        // The map is always left in a consistent state, poisoning can be ignored.
+        self.remote.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
 #[derive(Debug)]
 pub(super) struct Control {
-    capabilities: Arc<Mutex<CapabilitiesMap>>,
-    remote_authentication_receiver: watch::Receiver<bool>,
+    capabilities: Arc<CapabilitiesSender>,
+    remote_authentication_receiver: watch::Receiver<RemoteAuthentication>,
 }
 
 impl Control {
+    const MAX_AUTHENTICATION_ROUNDS: usize = 16;
+
+    /// Returns a receiver of the capabilities resolved between the local and the remote ends.
+    pub(super) fn capabilities(&self) -> watch::Receiver<CapabilitiesMap> {
+        self.capabilities.capabilities.subscribe()
+    }
+
+    /// Returns a receiver of whether the remote end accepts compressed replies.
+    pub(super) fn compressed_replies(&self) -> watch::Receiver<bool> {
+        self.capabilities.compressed_replies.subscribe()
+    }
+
+    /// Authenticates to the remote end, in as many rounds as the remote requires, up to
+    /// [`Self::MAX_AUTHENTICATION_ROUNDS`].
     #[instrument(name = "authenticate", level = "trace", skip_all, ret)]
     pub(super) async fn authenticate_to_remote(
         &self,
         client: &mut client::Client,
+        authenticator: &mut dyn ClientAuthenticator,
     ) -> Result<(), AuthenticateToRemoteError> {
         use crate::service::Service;
-        let authenticate = Authenticate::new_outgoing();
-        let call = authenticate
-            .to_messaging_call()
-            .map_err(AuthenticateToRemoteError::SerializeLocalCapabilities)?;
-        trace!("sending authentication request to server");
-        let reply = client.call(call).await?;
-        let result_capabilities = reply
-            .value()
-            .map_err(AuthenticateToRemoteError::DeserializeRemoteCapabilities)?;
-        trace!(capabilities = ?result_capabilities, "received authentication result and capabilities from server");
-        authentication::verify_result(&result_capabilities)?;
+        let mut parameters = authenticator.parameters();
+        let mut round = 1;
+        let result_capabilities = loop {
+            let call = Authenticate::new_outgoing(parameters)
+                .to_messaging_call()
+                .map_err(AuthenticateToRemoteError::SerializeLocalCapabilities)?;
+            trace!(round, "sending authentication request to server");
+            let reply = client.call(call).await?;
+            let result: CapabilitiesMap = reply
+                .value()
+                .map_err(AuthenticateToRemoteError::DeserializeRemoteCapabilities)?;
+            trace!(capabilities = ?result, "received authentication result and capabilities from server");
+            match AuthState::from_result(&result)? {
+                AuthState::Done => break result,
+                AuthState::Error(reason) => {
+                    return Err(VerifyAuthenticationResultError::Refused(reason).into())
+                }
+                AuthState::Continue(_) if round == Self::MAX_AUTHENTICATION_ROUNDS => {
+                    return Err(AuthenticateToRemoteError::TooManyRounds(round))
+                }
+                AuthState::Continue(challenge) => {
+                    parameters = authenticator
+                        .continue_with(&challenge)
+                        .map_err(AuthenticateToRemoteError::ContinuationRefused)?;
+                    round += 1;
+                }
+            }
+        };
         let capabilities = result_capabilities
+            .clone()
             .check_intersect_with_local()
             .map_err(AuthenticateToRemoteError::MissingRequiredCapabilities)?;
         trace!(
             ?capabilities,
             "resolved capabilities between local and remote"
         );
-        *self.capabilities.lock().await = capabilities;
+        self.capabilities
+            .set_remote(result_capabilities, capabilities);
         Ok(())
     }
 
     #[instrument(name = "authentication", level = "trace", skip_all, ret)]
     pub(super) async fn remote_authentication(&mut self) -> Result<(), RemoteAuthenticationError> {
-        match self
+        let result = self
             .remote_authentication_receiver
             .wait_for(|auth| {
-                trace!(result = auth, "received remote authentication result");
-                *auth
+                trace!(result = ?auth, "received remote authentication result");
+                *auth != RemoteAuthentication::Pending
             })
-            .await
-        {
-            Ok(_ref) => Ok(()),
+            .await;
+        match result.as_deref() {
+            Ok(RemoteAuthentication::Refused(reason)) => {
+                Err(RemoteAuthenticationError::Refused(reason.clone()))
+            }
+            Ok(_auth) => Ok(()),
             Err(_err) => Err(RemoteAuthenticationError::ServiceClosed),
         }
     }
@@ -149,6 +255,12 @@ pub(super) enum AuthenticateToRemoteError {
     #[error("error verifying the authentication result")]
     VerifyAuthenticationResult(#[from] VerifyAuthenticationResultError),
 
+    #[error("the authentication was not done after {0} rounds")]
+    TooManyRounds(usize),
+
+    #[error("the continuation of the authentication was refused, reason is: {0}")]
+    ContinuationRefused(String),
+
     #[error("some required capabilities are missing")]
     MissingRequiredCapabilities(#[from] capabilities::ExpectedKeyValueError<bool>),
 }
@@ -168,36 +280,67 @@ pub(super) use authentication::VerifyResultError as VerifyAuthenticationResultEr
 pub(super) enum RemoteAuthenticationError {
     #[error("control service closed")]
     ServiceClosed,
+
+    #[error("the authentication of the remote was refused, reason is: {0}")]
+    Refused(String),
+}
+
+/// The authentication of the remote end, as decided by the authenticator of the local end.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum RemoteAuthentication {
+    Pending,
+    Done,
+    Refused(String),
 }
 
-#[derive(Debug)]
 pub(super) struct Service {
-    capabilities: Arc<Mutex<CapabilitiesMap>>,
-    remote_authentication_sender: watch::Sender<bool>,
+    capabilities: Arc<CapabilitiesSender>,
+    remote_authentication_sender: watch::Sender<RemoteAuthentication>,
+    authenticator: Box<dyn ServerAuthenticator>,
 }
 
-impl Service {
-    fn authenticate(&self, parameters: &CapabilitiesMap) -> CapabilitiesMap {
-        let reply = authenticate(parameters);
-        self.remote_authentication_sender.send_replace(true);
-        reply
+impl std::fmt::Debug for Service {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Service")
+            .field("capabilities", &self.capabilities)
+            .field(
+                "remote_authentication_sender",
+                &self.remote_authentication_sender,
+            )
+            .finish_non_exhaustive()
     }
+}
 
-    fn update_capabilities(
-        &self,
-        remote: CapabilitiesMap,
-    ) -> impl Future<Output = Result<(), UpdateCapabilitiesError>> {
-        let check_result = remote.check_intersect_with_local();
-        let self_capabilities = Arc::clone(&self.capabilities);
-        async move {
-            match check_result {
-                Ok(capabilities) => {
-                    *self_capabilities.lock_owned().await = capabilities;
-                    Ok(())
-                }
-                Err(err) => Err(UpdateCapabilitiesError(err)),
+impl Service {
+    fn authenticate(&mut self, parameters: &CapabilitiesMap) -> CapabilitiesMap {
+        let state = self.authenticator.authenticate(parameters);
+        trace!(?state, "authenticated a round of the remote");
+        match &state {
+            AuthState::Done => {
+                let mut capabilities = parameters.clone();
+                capabilities.intersect(capabilities::local());
+                self.capabilities
+                    .set_remote(parameters.clone(), capabilities);
+                self.remote_authentication_sender
+                    .send_replace(RemoteAuthentication::Done);
             }
+            AuthState::Error(reason) => {
+                self.remote_authentication_sender
+                    .send_replace(RemoteAuthentication::Refused(reason.clone()));
+            }
+            AuthState::Continue(_) => {}
         }
+        state.into_result()
+    }
+
+    fn update_capabilities(&self, update: &CapabilitiesMap) -> Result<(), UpdateCapabilitiesError> {
+        trace!(
+            ?update,
+            "received an update of the capabilities of the remote"
+        );
+        self.capabilities
+            .update_remote(update)
+            .map_err(UpdateCapabilitiesError)
     }
 }
 
@@ -205,7 +348,7 @@ impl crate::Service<Call, Notification> for Service {
     type CallReply = CapabilitiesMap;
     type Error = Error;
     type CallFuture = future::Ready<CallResult<Self::CallReply, Self::Error>>;
-    type NotifyFuture = future::BoxFuture<'static, Result<(), Self::Error>>;
+    type NotifyFuture = future::Ready<Result<(), Self::Error>>;
 
     fn call(&mut self, call: Call) -> Self::CallFuture {
         match call {
@@ -217,10 +360,10 @@ impl crate::Service<Call, Notification> for Service {
 
     fn notify(&mut self, notif: Notification) -> Self::NotifyFuture {
         match notif {
-            Notification::Capabilities(Capabilities(capabilities)) => self
-                .update_capabilities(capabilities)
-                .map_err(Error::Capabilities)
-                .boxed(),
+            Notification::Capabilities(Capabilities(capabilities)) => future::ready(
+                self.update_capabilities(&capabilities)
+                    .map_err(Error::Capabilities),
+            ),
         }
     }
 }
@@ -248,8 +391,16 @@ pub(super) struct Authenticate(CapabilitiesMap);
 impl Authenticate {
     const SUBJECT: Subject = Subject(ActionId::new(8));
 
-    pub(super) fn new_outgoing() -> Self {
-        Self(capabilities::local().clone())
+    /// The authentication request of a round, with the local capabilities and the parameters of
+    /// the authenticator.
+    pub(super) fn new_outgoing(parameters: CapabilitiesMap) -> Self {
+        let mut capabilities = capabilities::local().clone();
+        capabilities.extend(
+            parameters
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone())),
+        );
+        Self(capabilities)
     }
 
     pub(super) fn to_messaging_call(&self) -> Result<messaging::Call, format::Error> {
@@ -293,3 +444,84 @@ pub(super) enum Error {
 #[derive(Debug, thiserror::Error)]
 #[error("error updating capabilities")]
 pub(super) struct UpdateCapabilitiesError(#[from] capabilities::ExpectedKeyValueError<bool>);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Service as _;
+
+    fn authenticated() -> (Control, Service) {
+        let (control, mut service) = create(Box::new(authentication::NoAuthentication));
+        let parameters = Authenticate::new_outgoing(CapabilitiesMap::new()).into();
+        let _reply = service
+            .call(Call::Authenticate(Authenticate(parameters)))
+            .into_inner()
+            .unwrap();
+        (control, service)
+    }
+
+    fn update(service: &mut Service, update: CapabilitiesMap) -> Result<(), Error> {
+        service
+            .notify(Notification::Capabilities(Capabilities(update)))
+            .into_inner()
+    }
+
+    #[test]
+    fn test_update_capabilities_mid_session() {
+        let (control, mut service) = authenticated();
+        let mut capabilities = control.capabilities();
+        let mut compressed_replies = control.compressed_replies();
+        capabilities.borrow_and_update();
+        compressed_replies.borrow_and_update();
+        assert!(capabilities.borrow().has_streaming_call_replies());
+        assert!(*compressed_replies.borrow());
+
+        // Capabilities absent from the update keep the value previously advertised.
+        update(
+            &mut service,
+            CapabilitiesMap::from_iter([("CompressedReplies", false)]),
+        )
+        .unwrap();
+        assert!(capabilities.has_changed().unwrap());
+        assert!(compressed_replies.has_changed().unwrap());
+        assert!(!capabilities.borrow_and_update().has_compressed_replies());
+        assert!(capabilities.borrow().has_streaming_call_replies());
+        assert!(!*compressed_replies.borrow_and_update());
+
+        // An update that changes nothing does not notify the subscribers.
+        update(
+            &mut service,
+            CapabilitiesMap::from_iter([("CompressedReplies", false)]),
+        )
+        .unwrap();
+        assert!(!capabilities.has_changed().unwrap());
+        assert!(!compressed_replies.has_changed().unwrap());
+    }
+
+    #[test]
+    fn test_update_capabilities_unsupported_is_discarded() {
+        let (control, mut service) = authenticated();
+        let mut capabilities = control.capabilities();
+        let resolved = capabilities.borrow_and_update().clone();
+
+        let result = update(
+            &mut service,
+            CapabilitiesMap::from_iter([
+                ("ClientServerSocket", false),
+                ("CompressedReplies", false),
+            ]),
+        );
+        assert!(matches!(result, Err(Error::Capabilities(_))));
+        assert!(!capabilities.has_changed().unwrap());
+        assert_eq!(*capabilities.borrow(), resolved);
+
+        // The discarded update is not merged into later ones.
+        update(
+            &mut service,
+            CapabilitiesMap::from_iter([("StreamingCallReplies", false)]),
+        )
+        .unwrap();
+        assert!(capabilities.borrow().has_compressed_replies());
+        assert!(!capabilities.borrow().has_streaming_call_replies());
+    }
+}
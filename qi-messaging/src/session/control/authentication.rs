@@ -1,3 +1,14 @@
+//! The authentication of the ends of a session.
+//!
+//! The client end authenticates to the server end by calling the `authenticate` method of the
+//! session control, with its capabilities and its authentication parameters. The server answers
+//! with the state of the authentication, in the `__qi_auth_state` key of the reply. The
+//! authentication may require several rounds: as long as the state is `continue`, the client calls
+//! the method again with the parameters that answer those of the server.
+//!
+//! How parameters are produced and checked is up to the authenticators of each end, see
+//! [`ClientAuthenticator`] and [`ServerAuthenticator`].
+
 use super::capabilities::{self, CapabilitiesMap};
 use crate::types::{Dynamic, Number};
 use num_traits::{FromPrimitive, ToPrimitive};
@@ -39,38 +50,104 @@ enum State {
     Done = 3,
 }
 
-pub(super) fn authenticate(_parameters: &CapabilitiesMap) -> CapabilitiesMap {
-    // TODO: Implement a more restrictive authentication.
-    let mut capabilities = capabilities::local().clone();
-    capabilities.extend([(STATE_KEY, State::Done.to_u32().unwrap())]);
-    capabilities
+/// The state of an authentication, as answered by the server end to each round.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AuthState {
+    /// The client is authenticated.
+    Done,
+    /// The client must answer the parameters with another round of authentication.
+    Continue(CapabilitiesMap),
+    /// The client is refused, for the given reason.
+    Error(String),
+}
+
+impl AuthState {
+    /// Reads the state from the result of a round of authentication.
+    ///
+    /// The parameters of a continuation are the entries of the result, without the state.
+    pub(super) fn from_result(result: &CapabilitiesMap) -> Result<Self, VerifyResultError> {
+        let dynamic_state = result
+            .get(STATE_KEY)
+            .ok_or(VerifyResultError::NoStateValue)?;
+        let state = dynamic_state
+            .as_number()
+            .as_ref()
+            .and_then(Number::as_uint32)
+            .and_then(State::from_u32)
+            .ok_or_else(|| VerifyResultError::StateUnknownValue(dynamic_state.clone()))?;
+        Ok(match state {
+            State::Done => Self::Done,
+            State::Continue => Self::Continue(
+                result
+                    .iter()
+                    .filter(|(key, _value)| key.as_str() != STATE_KEY)
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect(),
+            ),
+            State::Error => Self::Error(
+                result
+                    .get(ERROR_REASON_KEY)
+                    .and_then(Dynamic::as_string)
+                    .map_or("unknown reason", String::as_str)
+                    .to_owned(),
+            ),
+        })
+    }
+
+    /// Writes the state as the result of a round of authentication.
+    ///
+    /// The result of a successful authentication also holds the capabilities of the server end.
+    pub(super) fn into_result(self) -> CapabilitiesMap {
+        let (mut result, state) = match self {
+            Self::Done => (capabilities::local().clone(), State::Done),
+            Self::Continue(parameters) => (parameters, State::Continue),
+            Self::Error(reason) => (
+                CapabilitiesMap::from_iter([(ERROR_REASON_KEY, reason)]),
+                State::Error,
+            ),
+        };
+        result.set_capability(STATE_KEY, state.to_u32().unwrap_or_default());
+        result
+    }
+}
+
+/// The authentication of the client end of a session to the server end.
+pub trait ClientAuthenticator: Send {
+    /// The parameters of the first round of authentication, sent along with the capabilities of
+    /// the client end.
+    fn parameters(&mut self) -> CapabilitiesMap;
+
+    /// Answers the parameters of a continuation of the server end, with the parameters of the next
+    /// round of authentication, or refuses to continue with a reason.
+    fn continue_with(&mut self, parameters: &CapabilitiesMap) -> Result<CapabilitiesMap, String>;
+}
+
+/// The authentication of the client end of a session by the server end.
+pub trait ServerAuthenticator: Send {
+    /// Handles a round of authentication of the client end, from the parameters that it sent along
+    /// with its capabilities.
+    fn authenticate(&mut self, parameters: &CapabilitiesMap) -> AuthState;
+}
+
+/// An authenticator that sends no parameters and accepts any client.
+///
+/// This is the authenticator of the sessions unless another one is set.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct NoAuthentication;
+
+impl ClientAuthenticator for NoAuthentication {
+    fn parameters(&mut self) -> CapabilitiesMap {
+        CapabilitiesMap::new()
+    }
+
+    fn continue_with(&mut self, _parameters: &CapabilitiesMap) -> Result<CapabilitiesMap, String> {
+        Err("the authentication cannot be continued without parameters".to_owned())
+    }
 }
 
-pub(super) fn verify_result(result: &CapabilitiesMap) -> Result<(), VerifyResultError> {
-    let dynamic_state = result
-        .get(STATE_KEY)
-        .ok_or(VerifyResultError::NoStateValue)?
-        .clone();
-    let state = dynamic_state
-        .as_number()
-        .as_ref()
-        .and_then(Number::as_uint32)
-        .and_then(State::from_u32)
-        .ok_or_else(|| VerifyResultError::StateUnknownValue(dynamic_state))?;
-    match state {
-        State::Continue => Err(VerifyResultError::Continue),
-        // Technically the error case should not happen. If an authentication error
-        // occurred, the server should return a call error, not a call reply, and therefore
-        // we should not have a capability map to check.
-        State::Error => {
-            let err = result
-                .get(ERROR_REASON_KEY)
-                .and_then(Dynamic::as_string)
-                .map(String::as_str)
-                .unwrap_or_else(|| "unknown reason");
-            Err(VerifyResultError::Refused(err.to_owned()))
-        }
-        State::Done => Ok(()),
+impl ServerAuthenticator for NoAuthentication {
+    fn authenticate(&mut self, _parameters: &CapabilitiesMap) -> AuthState {
+        AuthState::Done
     }
 }
 
@@ -82,9 +159,48 @@ pub(in crate::session) enum VerifyResultError {
     #[error("the authentication state value has an unknown value \"{0}\"")]
     StateUnknownValue(Dynamic),
 
-    #[error("the authentication is incomplete and must be continued")]
-    Continue,
-
     #[error("the authentication attempt was refused, reason is: {0}")]
     Refused(String),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_auth_state_to_from_result() {
+        let result = AuthState::Done.into_result();
+        assert!(result.has_flag_capability("StreamingCallReplies"));
+        assert_eq!(AuthState::from_result(&result).unwrap(), AuthState::Done);
+
+        let challenge = CapabilitiesMap::from_iter([("auth_challenge", "1234")]);
+        let result = AuthState::Continue(challenge.clone()).into_result();
+        assert_eq!(result.get(STATE_KEY), Some(&Dynamic::from(2u32)));
+        assert_eq!(
+            AuthState::from_result(&result).unwrap(),
+            AuthState::Continue(challenge)
+        );
+
+        let result = AuthState::Error("bad token".to_owned()).into_result();
+        assert_eq!(
+            AuthState::from_result(&result).unwrap(),
+            AuthState::Error("bad token".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_auth_state_from_invalid_result() {
+        assert!(matches!(
+            AuthState::from_result(&CapabilitiesMap::new()),
+            Err(VerifyResultError::NoStateValue)
+        ));
+        assert!(matches!(
+            AuthState::from_result(&CapabilitiesMap::from_iter([(STATE_KEY, 4u32)])),
+            Err(VerifyResultError::StateUnknownValue(_))
+        ));
+        assert_eq!(
+            AuthState::from_result(&CapabilitiesMap::from_iter([(STATE_KEY, 1u32)])).unwrap(),
+            AuthState::Error("unknown reason".to_owned())
+        );
+    }
+}
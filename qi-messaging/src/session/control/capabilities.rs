@@ -1,4 +1,5 @@
 pub(in crate::session) use crate::capabilities::CapabilitiesMap;
+use crate::capabilities::Capability;
 use once_cell::sync::OnceCell;
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
@@ -7,13 +8,18 @@ struct Supported {
     remote_cancelable_calls: bool,
     object_ptr_uid: bool,
     relative_endpoint_uri: bool,
+    streaming_call_replies: bool,
+    compressed_replies: bool,
+    trace_context: bool,
 }
 
 impl Supported {
-    const CLIENT_SERVER_SOCKET: &'static str = "ClientServerSocket";
-    const REMOTE_CANCELABLE_CALLS: &'static str = "RemoteCancelableCalls";
-    const OBJECT_PTR_UID: &'static str = "ObjectPtrUID";
-    const RELATIVE_ENDPOINT_URI: &'static str = "RelativeEndpointURI";
+    // Extension of this implementation, see `crate::session::Client::call_streaming`.
+    const STREAMING_CALL_REPLIES: &'static str = "StreamingCallReplies";
+    // Extension of this implementation, see `crate::channel::ReplyCompression`.
+    const COMPRESSED_REPLIES: &'static str = "CompressedReplies";
+    // Extension of this implementation, see `crate::TraceContext`.
+    const TRACE_CONTEXT: &'static str = "TraceContext";
 
     const fn new() -> Self {
         Self {
@@ -21,24 +27,43 @@ impl Supported {
             remote_cancelable_calls: true,
             object_ptr_uid: true,
             relative_endpoint_uri: true,
+            streaming_call_replies: true,
+            compressed_replies: true,
+            trace_context: true,
         }
     }
 
     fn from_capabilities(map: &CapabilitiesMap) -> Self {
         Self {
-            client_server_socket: map.has_flag_capability(Self::CLIENT_SERVER_SOCKET),
-            remote_cancelable_calls: map.has_flag_capability(Self::REMOTE_CANCELABLE_CALLS),
-            object_ptr_uid: map.has_flag_capability(Self::OBJECT_PTR_UID),
-            relative_endpoint_uri: map.has_flag_capability(Self::RELATIVE_ENDPOINT_URI),
+            client_server_socket: map.has_flag_capability(Capability::ClientServerSocket.key()),
+            remote_cancelable_calls: map
+                .has_flag_capability(Capability::RemoteCancelableCalls.key()),
+            object_ptr_uid: map.has_flag_capability(Capability::ObjectPtrUid.key()),
+            relative_endpoint_uri: map.has_flag_capability(Capability::RelativeEndpointUri.key()),
+            streaming_call_replies: map.has_flag_capability(Self::STREAMING_CALL_REPLIES),
+            compressed_replies: map.has_flag_capability(Self::COMPRESSED_REPLIES),
+            trace_context: map.has_flag_capability(Self::TRACE_CONTEXT),
         }
     }
 
     fn to_capabilities(self) -> CapabilitiesMap {
         CapabilitiesMap::from_iter([
-            (Self::CLIENT_SERVER_SOCKET, self.client_server_socket),
-            (Self::REMOTE_CANCELABLE_CALLS, self.remote_cancelable_calls),
-            (Self::OBJECT_PTR_UID, self.object_ptr_uid),
-            (Self::RELATIVE_ENDPOINT_URI, self.relative_endpoint_uri),
+            (
+                Capability::ClientServerSocket.key(),
+                self.client_server_socket,
+            ),
+            (
+                Capability::RemoteCancelableCalls.key(),
+                self.remote_cancelable_calls,
+            ),
+            (Capability::ObjectPtrUid.key(), self.object_ptr_uid),
+            (
+                Capability::RelativeEndpointUri.key(),
+                self.relative_endpoint_uri,
+            ),
+            (Self::STREAMING_CALL_REPLIES, self.streaming_call_replies),
+            (Self::COMPRESSED_REPLIES, self.compressed_replies),
+            (Self::TRACE_CONTEXT, self.trace_context),
         ])
     }
 }
@@ -54,6 +79,9 @@ pub(crate) trait CapabilitiesMapExt {
     fn check_intersect_with_local(self) -> Result<Self, ExpectedKeyValueError<bool>>
     where
         Self: Sized;
+    fn has_streaming_call_replies(&self) -> bool;
+    fn has_compressed_replies(&self) -> bool;
+    fn has_trace_context(&self) -> bool;
 }
 
 #[derive(PartialEq, Eq, PartialOrd, Ord, Debug, thiserror::Error)]
@@ -71,25 +99,25 @@ impl CapabilitiesMapExt for CapabilitiesMap {
         // TODO: Implement capabilities so that this function always succeeds, so that we may remove it.
         if !supported.client_server_socket {
             return Err(ExpectedKeyValueError(
-                Supported::CLIENT_SERVER_SOCKET.into(),
+                Capability::ClientServerSocket.key().into(),
                 true,
             ));
         }
         if !supported.remote_cancelable_calls {
             return Err(ExpectedKeyValueError(
-                Supported::REMOTE_CANCELABLE_CALLS.into(),
+                Capability::RemoteCancelableCalls.key().into(),
                 true,
             ));
         }
         if !supported.object_ptr_uid {
             return Err(ExpectedKeyValueError(
-                Supported::OBJECT_PTR_UID.into(),
+                Capability::ObjectPtrUid.key().into(),
                 true,
             ));
         }
         if !supported.relative_endpoint_uri {
             return Err(ExpectedKeyValueError(
-                Supported::RELATIVE_ENDPOINT_URI.into(),
+                Capability::RelativeEndpointUri.key().into(),
                 true,
             ));
         }
@@ -100,6 +128,18 @@ impl CapabilitiesMapExt for CapabilitiesMap {
         self.intersect(local()).check_required()?;
         Ok(self)
     }
+
+    fn has_streaming_call_replies(&self) -> bool {
+        Supported::from_capabilities(self).streaming_call_replies
+    }
+
+    fn has_compressed_replies(&self) -> bool {
+        Supported::from_capabilities(self).compressed_replies
+    }
+
+    fn has_trace_context(&self) -> bool {
+        Supported::from_capabilities(self).trace_context
+    }
 }
 
 const LOCAL_SUPPORTED_CAPABILITIES: Supported = Supported::new();
@@ -0,0 +1,113 @@
+use super::Subject;
+use crate::{SubjectPattern, SubjectRouter};
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex, MutexGuard, PoisonError,
+};
+
+/// The signals of the local end of a session, to which the remote may or may not publish.
+///
+/// Only the end of a session that hosts an object emits the values of its signals. The signals
+/// that are declared as owned, see [`EventSources::own`], are protected: the events and the posts
+/// that the remote sends to them are rejected by the dispatch of the session, without reaching
+/// its service. The remote may be permitted to publish to some of them, see
+/// [`EventSources::permit`].
+///
+/// Nothing is owned by default. Bridges that relay the signals of other nodes, and must be
+/// trusted as such, may disable the validation altogether with
+/// [`Config::with_remote_event_sources_trusted`](super::Config::with_remote_event_sources_trusted).
+#[derive(Debug, Clone, Default)]
+pub struct EventSources(Arc<Shared>);
+
+#[derive(Debug, Default)]
+struct Shared {
+    patterns: Mutex<Patterns>,
+    rejected: AtomicU64,
+}
+
+#[derive(Debug, Default)]
+struct Patterns {
+    owned: SubjectRouter<()>,
+    permitted: SubjectRouter<()>,
+}
+
+impl EventSources {
+    /// Declares the signals of the pattern as owned by the local end.
+    pub fn own(&self, signals: SubjectPattern) {
+        self.lock().owned.insert(signals, ());
+    }
+
+    /// Removes a pattern previously declared with [`EventSources::own`], for instance when the
+    /// object that owns the signals is dropped.
+    pub fn disown(&self, signals: SubjectPattern) {
+        self.lock().owned.remove(&signals);
+    }
+
+    /// Permits the remote to publish to the owned signals of the pattern.
+    pub fn permit(&self, signals: SubjectPattern) {
+        self.lock().permitted.insert(signals, ());
+    }
+
+    /// Removes a pattern previously permitted with [`EventSources::permit`].
+    pub fn revoke(&self, signals: SubjectPattern) {
+        self.lock().permitted.remove(&signals);
+    }
+
+    /// Returns true if the remote may publish to the subject, which is either not owned by the
+    /// local end, or is permitted.
+    pub fn is_permitted(&self, subject: &Subject) -> bool {
+        let patterns = self.lock();
+        !patterns.owned.contains_subject(subject) || patterns.permitted.contains_subject(subject)
+    }
+
+    /// The number of events and posts of the remote that were rejected.
+    pub fn rejected(&self) -> u64 {
+        self.0.rejected.load(Ordering::Relaxed)
+    }
+
+    pub(super) fn record_rejected(&self) {
+        self.0.rejected.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Patterns> {
+        // Patterns are only inserted and removed, poisoning can be ignored.
+        self.0
+            .patterns
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        session::subject::ServiceObject,
+        types::object::{ActionId, ObjectId, ServiceId},
+    };
+
+    fn subject(action: u32) -> Subject {
+        let service_object = ServiceObject::new(ServiceId::new(1), ObjectId::new(1)).unwrap();
+        Subject::new(service_object, ActionId::new(action))
+    }
+
+    #[test]
+    fn test_event_sources_is_permitted() {
+        let sources = EventSources::default();
+        assert!(sources.is_permitted(&subject(100)));
+
+        let service = SubjectPattern::any().with_service(ServiceId::new(1));
+        sources.own(service);
+        assert!(!sources.is_permitted(&subject(100)));
+        assert!(!sources.is_permitted(&subject(101)));
+
+        sources.permit(service.with_action(ActionId::new(101)));
+        assert!(!sources.is_permitted(&subject(100)));
+        assert!(sources.is_permitted(&subject(101)));
+
+        sources.revoke(service.with_action(ActionId::new(101)));
+        assert!(!sources.is_permitted(&subject(101)));
+        sources.disown(service);
+        assert!(sources.is_permitted(&subject(100)));
+    }
+}
@@ -0,0 +1,280 @@
+use super::{Call, CallFuture, Client, ClientError, Notification, NotifyFuture, Reply};
+use crate::{
+    service::{CallResult, CallTermination, GetSubject},
+    Service, SubjectPattern,
+};
+use futures::{future::BoxFuture, FutureExt};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use tracing::debug;
+
+/// A client of a peer that is reachable through several sessions, for instance a robot that is
+/// connected over both WiFi and Ethernet.
+///
+/// Requests are sent on the active session, which is the primary one at first. A standby session
+/// is kept connected, and becomes the active one as soon as the active session is closed. Calls
+/// that were waiting for their response on the closed session fail, except for those whose subject
+/// matches a pattern of idempotent calls (see [`Multipath::with_idempotent_calls`]), which are
+/// replayed on the new active session. Other calls may have been executed by the peer, and only the
+/// caller can decide if they may be sent again.
+///
+/// After a failover, there is no standby session until one is set with [`Multipath::set_standby`].
+/// Clones share the same sessions.
+#[derive(Debug, Clone)]
+pub struct Multipath {
+    paths: Arc<Mutex<Paths>>,
+    idempotent_calls: Arc<[SubjectPattern]>,
+}
+
+impl Multipath {
+    pub fn new(primary: Client) -> Self {
+        Self {
+            paths: Arc::new(Mutex::new(Paths {
+                active: primary,
+                standby: None,
+                failovers: 0,
+            })),
+            idempotent_calls: Arc::new([]),
+        }
+    }
+
+    pub fn with_standby(self, standby: Client) -> Self {
+        self.set_standby(standby);
+        self
+    }
+
+    /// Adds a pattern of the subjects of the calls that may be replayed on the standby session if
+    /// the active session is closed before they are responded to.
+    pub fn with_idempotent_calls(mut self, pattern: SubjectPattern) -> Self {
+        let mut patterns = self.idempotent_calls.to_vec();
+        patterns.push(pattern);
+        self.idempotent_calls = patterns.into();
+        self
+    }
+
+    /// Sets the session that becomes the active one when the active session is closed, and
+    /// returns the previous standby session, if any.
+    pub fn set_standby(&self, standby: Client) -> Option<Client> {
+        self.lock().standby.replace(standby)
+    }
+
+    /// Returns the session on which requests are sent, after a failover if it was closed.
+    pub fn active(&self) -> Client {
+        self.lock().active().clone()
+    }
+
+    /// Returns true if a standby session is ready to take over.
+    pub fn has_standby(&self) -> bool {
+        self.lock()
+            .standby
+            .as_ref()
+            .is_some_and(|standby| !standby.is_closed())
+    }
+
+    /// The number of times the standby session took over a closed session.
+    pub fn failovers(&self) -> u64 {
+        self.lock().failovers
+    }
+
+    fn is_idempotent(&self, call: &Call) -> bool {
+        self.idempotent_calls
+            .iter()
+            .any(|pattern| pattern.matches_subject(call.subject()))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Paths> {
+        // The paths are always left in a consistent state, poisoning can be ignored.
+        self.paths.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[derive(Debug)]
+struct Paths {
+    active: Client,
+    standby: Option<Client>,
+    failovers: u64,
+}
+
+impl Paths {
+    fn active(&mut self) -> &Client {
+        if self.active.is_closed() {
+            let standby = self.standby.take().filter(|standby| !standby.is_closed());
+            if let Some(standby) = standby {
+                debug!(
+                    remote_address = ?standby.remote_address(),
+                    "the active session is closed, switching over to the standby session"
+                );
+                self.active = standby;
+                self.failovers += 1;
+            }
+        }
+        &self.active
+    }
+}
+
+impl Service<Call, Notification> for Multipath {
+    type CallReply = Reply;
+    type Error = ClientError;
+    type CallFuture = BoxFuture<'static, CallResult<Reply, ClientError>>;
+    type NotifyFuture = NotifyFuture;
+
+    fn call(&mut self, call: Call) -> Self::CallFuture {
+        let mut this = &*self;
+        this.call(call)
+    }
+
+    fn notify(&mut self, notif: Notification) -> Self::NotifyFuture {
+        let mut this = &*self;
+        this.notify(notif)
+    }
+}
+
+impl Service<Call, Notification> for &Multipath {
+    type CallReply = Reply;
+    type Error = ClientError;
+    type CallFuture = BoxFuture<'static, CallResult<Reply, ClientError>>;
+    type NotifyFuture = NotifyFuture;
+
+    fn call(&mut self, call: Call) -> Self::CallFuture {
+        let replay = self.is_idempotent(&call).then(|| call.clone());
+        let future = send_call(&self.active(), call);
+        let this = Multipath::clone(self);
+        async move {
+            let result = future.await;
+            match (result, replay) {
+                (Err(CallTermination::Error(ClientError::SessionClosed(err))), Some(call)) => {
+                    let active = this.active();
+                    if active.is_closed() {
+                        return Err(CallTermination::Error(ClientError::SessionClosed(err)));
+                    }
+                    debug!(subject = ?call.subject(), "replaying a call on the standby session");
+                    send_call(&active, call).await
+                }
+                (result, _) => result,
+            }
+        }
+        .boxed()
+    }
+
+    fn notify(&mut self, notif: Notification) -> Self::NotifyFuture {
+        let mut active = &self.active();
+        active.notify(notif)
+    }
+}
+
+fn send_call(client: &Client, call: Call) -> CallFuture {
+    let mut client = client;
+    client.call(call)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        session::{self, subject::ServiceObject, CallWithId, NotificationWithId, Subject},
+        types::object::{ActionId, ObjectId, ServiceId},
+    };
+    use futures::future;
+    use std::time::Duration;
+    use tokio::{io, join, select, spawn, task::JoinHandle};
+
+    /// A service that replies to calls with a value, or never replies to them.
+    struct Answer(Option<i32>);
+
+    impl Service<CallWithId, NotificationWithId> for Answer {
+        type CallReply = i32;
+        type Error = String;
+        type CallFuture = BoxFuture<'static, CallResult<i32, String>>;
+        type NotifyFuture = future::Ready<Result<(), String>>;
+
+        fn call(&mut self, _call: CallWithId) -> Self::CallFuture {
+            match self.0 {
+                Some(value) => future::ok(value).boxed(),
+                None => future::pending().boxed(),
+            }
+        }
+
+        fn notify(&mut self, _notif: NotificationWithId) -> Self::NotifyFuture {
+            future::ok(())
+        }
+    }
+
+    /// Returns the client of a session with a peer that answers calls, and the task that
+    /// dispatches the messages of the session, which closes it when aborted.
+    async fn path(answer: Option<i32>) -> (Client, JoinHandle<()>) {
+        let (io_client, io_server) = io::duplex(256);
+        let (client, client_dispatch) = session::connect(io_client, Answer(None));
+        let (server, server_dispatch) = session::listen(io_server, Answer(answer));
+        let dispatch = spawn(async move {
+            select! {
+                _res = client_dispatch => {},
+                _res = server_dispatch => {},
+            }
+        });
+        let (client, _server) = join!(client, server);
+        (client.unwrap(), dispatch)
+    }
+
+    fn subject(action: u32) -> Subject {
+        let service_object = ServiceObject::new(ServiceId::new(1), ObjectId::new(1)).unwrap();
+        Subject::new(service_object, ActionId::new(action))
+    }
+
+    async fn closed(dispatch: JoinHandle<()>) {
+        dispatch.abort();
+        let _res = dispatch.await;
+    }
+
+    #[tokio::test]
+    async fn test_multipath_failover() {
+        let (primary, primary_dispatch) = path(Some(1)).await;
+        let (standby, _standby_dispatch) = path(Some(2)).await;
+        let mut multipath = Multipath::new(primary).with_standby(standby);
+        assert!(multipath.has_standby());
+
+        let reply = multipath.call(Call::new(subject(1))).await.unwrap();
+        assert_eq!(reply.value::<i32>().unwrap(), 1);
+
+        closed(primary_dispatch).await;
+        let reply = multipath.call(Call::new(subject(1))).await.unwrap();
+        assert_eq!(reply.value::<i32>().unwrap(), 2);
+        assert_eq!(multipath.failovers(), 1);
+        assert!(!multipath.has_standby());
+    }
+
+    #[tokio::test]
+    async fn test_multipath_replays_pending_idempotent_calls() {
+        let (primary, primary_dispatch) = path(None).await;
+        let (standby, _standby_dispatch) = path(Some(2)).await;
+        let mut multipath = Multipath::new(primary)
+            .with_standby(standby)
+            .with_idempotent_calls(SubjectPattern::any().with_action(ActionId::new(1)));
+
+        let idempotent = multipath.call(Call::new(subject(1)));
+        let other = multipath.call(Call::new(subject(2)));
+        let (idempotent, other, ()) = join!(
+            tokio::time::timeout(Duration::from_secs(5), idempotent),
+            tokio::time::timeout(Duration::from_secs(5), other),
+            async {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                closed(primary_dispatch).await;
+            }
+        );
+        assert_eq!(idempotent.unwrap().unwrap().value::<i32>().unwrap(), 2);
+        assert_matches::assert_matches!(
+            other.unwrap(),
+            Err(CallTermination::Error(ClientError::SessionClosed(_)))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_multipath_without_standby() {
+        let (primary, primary_dispatch) = path(Some(1)).await;
+        let mut multipath = Multipath::new(primary).with_idempotent_calls(SubjectPattern::any());
+        closed(primary_dispatch).await;
+        assert_matches::assert_matches!(
+            multipath.call(Call::new(subject(1))).await,
+            Err(CallTermination::Error(ClientError::SessionClosed(_)))
+        );
+        assert_eq!(multipath.failovers(), 0);
+    }
+}
@@ -0,0 +1,294 @@
+use super::{Call, CallWithId, NotificationWithId, SharedConfig, Subject};
+use crate::{
+    service::{GetSubject, Service},
+    types::{
+        dynamic::DynamicSeed,
+        object::{ActionId, ServiceId},
+        Dynamic, Type, ValuePath,
+    },
+};
+use std::{
+    collections::{HashMap, HashSet},
+    num::NonZeroU32,
+};
+use tracing::{debug, Level};
+
+/// A service that traces the payloads of a sample of the calls it receives before forwarding them
+/// to an inner service.
+///
+/// One call out of every `sampling_period` is traced. The payload is decoded as a dynamic value if
+/// a type is attached to the action of the call, otherwise only its raw bytes are traced. Payloads
+/// of redacted services or actions are never traced, and parts of the payloads of an action may
+/// be redacted instead, see [`PayloadLogger::with_redacted_paths`].
+///
+/// Payloads are traced at the `debug` level. They are neither sampled nor decoded while this level
+/// is disabled.
+///
+/// The sampling period may follow the configuration of a session instead, so that logging can be
+/// tuned or disabled at runtime, see [`PayloadLogger::with_config`].
+#[derive(Debug)]
+pub struct PayloadLogger<S> {
+    inner: S,
+    sampling_period: NonZeroU32,
+    config: Option<SharedConfig>,
+    call_count: u32,
+    types: HashMap<(ServiceId, ActionId), Type>,
+    redacted_services: HashSet<ServiceId>,
+    redacted_actions: HashSet<(ServiceId, ActionId)>,
+    redacted_paths: HashMap<(ServiceId, ActionId), Vec<ValuePath>>,
+}
+
+impl<S> PayloadLogger<S> {
+    pub fn new(inner: S, sampling_period: NonZeroU32) -> Self {
+        Self {
+            inner,
+            sampling_period,
+            config: None,
+            call_count: 0,
+            types: HashMap::new(),
+            redacted_services: HashSet::new(),
+            redacted_actions: HashSet::new(),
+            redacted_paths: HashMap::new(),
+        }
+    }
+
+    /// Attaches the type of the payload of the calls to an action of a service.
+    pub fn with_type(mut self, service: ServiceId, action: ActionId, t: Type) -> Self {
+        self.types.insert((service, action), t);
+        self
+    }
+
+    /// Redacts the payloads of all the calls to a service.
+    pub fn with_redacted_service(mut self, service: ServiceId) -> Self {
+        self.redacted_services.insert(service);
+        self
+    }
+
+    /// Redacts the payloads of the calls to an action of a service.
+    pub fn with_redacted_action(mut self, service: ServiceId, action: ActionId) -> Self {
+        self.redacted_actions.insert((service, action));
+        self
+    }
+
+    /// Redacts parts of the payloads of the calls to an action of a service, see
+    /// [`Value::redact`](crate::types::Value::redact).
+    ///
+    /// Paths are only applied to payloads that are decoded with the type attached to the action,
+    /// see [`PayloadLogger::with_type`]. Without a type, the payloads are redacted altogether.
+    pub fn with_redacted_paths<I>(mut self, service: ServiceId, action: ActionId, paths: I) -> Self
+    where
+        I: IntoIterator<Item = ValuePath>,
+    {
+        self.redacted_paths
+            .entry((service, action))
+            .or_default()
+            .extend(paths);
+        self
+    }
+
+    /// Uses the payload sampling period of a session configuration, instead of the period the
+    /// logger was created with. Payloads are not traced while the configuration has no period.
+    pub fn with_config(mut self, config: SharedConfig) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    fn sampling_period(&self) -> Option<NonZeroU32> {
+        match &self.config {
+            Some(config) => config.get().payload_sampling_period(),
+            None => Some(self.sampling_period),
+        }
+    }
+
+    fn sample(&mut self) -> bool {
+        let sampling_period = match self.sampling_period() {
+            Some(period) => period,
+            None => return false,
+        };
+        let sampled = self.call_count == 0;
+        self.call_count = (self.call_count + 1) % sampling_period.get();
+        sampled
+    }
+
+    fn is_redacted(&self, subject: &Subject) -> bool {
+        self.redacted_services.contains(&subject.service())
+            || self
+                .redacted_actions
+                .contains(&(subject.service(), subject.action()))
+    }
+
+    fn payload(&self, call: &Call) -> Payload {
+        let subject = call.subject();
+        if self.is_redacted(subject) {
+            return Payload::Redacted;
+        }
+        let key = (subject.service(), subject.action());
+        let redacted_paths = self.redacted_paths.get(&key);
+        match self.types.get(&key) {
+            Some(t) => match call.value_seed(DynamicSeed::new(Some(t.clone()))) {
+                Ok(value) => match redacted_paths {
+                    Some(paths) => {
+                        Payload::Value(Dynamic::from_value(value.into_value().redact(paths)))
+                    }
+                    None => Payload::Value(value),
+                },
+                Err(err) => Payload::Undecodable(err.to_string()),
+            },
+            None if redacted_paths.is_some() => Payload::Redacted,
+            None => Payload::Bytes(call.formatted_value().as_bytes().to_vec()),
+        }
+    }
+}
+
+impl<S> Service<CallWithId, NotificationWithId> for PayloadLogger<S>
+where
+    S: Service<CallWithId, NotificationWithId>,
+{
+    type CallReply = S::CallReply;
+    type Error = S::Error;
+    type CallFuture = S::CallFuture;
+    type NotifyFuture = S::NotifyFuture;
+
+    fn call(&mut self, call: CallWithId) -> Self::CallFuture {
+        // The payload is only decoded and copied if it is traced.
+        if tracing::enabled!(Level::DEBUG) && self.sample() {
+            let id = call.id();
+            let subject = call.inner().subject();
+            match self.payload(call.inner()) {
+                Payload::Redacted => debug!(%id, ?subject, "call payload redacted"),
+                Payload::Value(value) => debug!(%id, ?subject, %value, "call payload"),
+                Payload::Bytes(bytes) => debug!(%id, ?subject, ?bytes, "call payload"),
+                Payload::Undecodable(error) => {
+                    debug!(%id, ?subject, %error, "call payload could not be decoded")
+                }
+            }
+        }
+        self.inner.call(call)
+    }
+
+    fn notify(&mut self, notif: NotificationWithId) -> Self::NotifyFuture {
+        self.inner.notify(notif)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Payload {
+    Redacted,
+    Value(Dynamic),
+    Bytes(Vec<u8>),
+    Undecodable(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        session::subject::ServiceObject,
+        types::{object::ObjectId, Value},
+    };
+    use futures::future;
+
+    #[derive(Debug)]
+    struct NoopService;
+
+    impl Service<CallWithId, NotificationWithId> for NoopService {
+        type CallReply = ();
+        type Error = std::convert::Infallible;
+        type CallFuture = future::Ready<crate::CallResult<(), Self::Error>>;
+        type NotifyFuture = future::Ready<Result<(), Self::Error>>;
+
+        fn call(&mut self, _call: CallWithId) -> Self::CallFuture {
+            future::ok(())
+        }
+
+        fn notify(&mut self, _notif: NotificationWithId) -> Self::NotifyFuture {
+            future::ok(())
+        }
+    }
+
+    fn call(service: u32, action: u32) -> Call {
+        let service_object =
+            ServiceObject::new(ServiceId::from(service), ObjectId::from(1)).unwrap();
+        let subject = Subject::new(service_object, ActionId::from(action));
+        Call::new(subject).with_value(&"secret").unwrap()
+    }
+
+    #[test]
+    fn test_payload_logger_sample() {
+        let mut logger = PayloadLogger::new(NoopService, NonZeroU32::new(3).unwrap());
+        let sampled: Vec<_> = std::iter::repeat_with(|| logger.sample()).take(7).collect();
+        assert_eq!(sampled, [true, false, false, true, false, false, true]);
+    }
+
+    #[test]
+    fn test_payload_logger_sample_with_config() {
+        let config = SharedConfig::default();
+        let mut logger = PayloadLogger::new(NoopService, NonZeroU32::new(1).unwrap())
+            .with_config(config.clone());
+        assert!(!logger.sample());
+
+        config.update(|config| config.with_payload_sampling_period(NonZeroU32::new(2)));
+        let sampled: Vec<_> = std::iter::repeat_with(|| logger.sample()).take(4).collect();
+        assert_eq!(sampled, [true, false, true, false]);
+    }
+
+    #[test]
+    fn test_payload_logger_call_debug_disabled() {
+        // Without a subscriber, the debug level is disabled: calls are not sampled.
+        let mut logger = PayloadLogger::new(NoopService, NonZeroU32::new(2).unwrap());
+        let _res = logger.call(CallWithId::new(crate::RequestId(1), call(2, 100)));
+        assert_eq!(logger.call_count, 0);
+    }
+
+    #[test]
+    fn test_payload_logger_payload() {
+        let logger = PayloadLogger::new(NoopService, NonZeroU32::new(1).unwrap())
+            .with_type(ServiceId::from(2), ActionId::from(100), Type::String)
+            .with_type(
+                ServiceId::from(2),
+                ActionId::from(101),
+                Type::List(Some(Box::new(Type::String))),
+            )
+            .with_redacted_service(ServiceId::from(3))
+            .with_redacted_action(ServiceId::from(2), ActionId::from(102));
+
+        assert_eq!(
+            logger.payload(&call(2, 100)),
+            Payload::Value(Dynamic::String("secret".to_owned()))
+        );
+        assert_matches::assert_matches!(logger.payload(&call(2, 101)), Payload::Undecodable(_));
+        assert_eq!(logger.payload(&call(2, 102)), Payload::Redacted);
+        assert_eq!(logger.payload(&call(3, 100)), Payload::Redacted);
+        assert_eq!(
+            logger.payload(&call(4, 100)),
+            Payload::Bytes(call(4, 100).formatted_value().as_bytes().to_vec())
+        );
+    }
+
+    #[test]
+    fn test_payload_logger_payload_redacted_paths() {
+        let logger = PayloadLogger::new(NoopService, NonZeroU32::new(1).unwrap())
+            .with_type(ServiceId::from(2), ActionId::from(100), Type::String)
+            .with_redacted_paths(ServiceId::from(2), ActionId::from(100), [ValuePath::new()])
+            .with_redacted_paths(
+                ServiceId::from(2),
+                ActionId::from(101),
+                ["0.token".parse().unwrap()],
+            );
+
+        assert_eq!(
+            logger.payload(&call(2, 100)),
+            Payload::Value(Dynamic::String(Value::REDACTED.to_owned()))
+        );
+        // The paths of an action without a type cannot be applied.
+        assert_eq!(logger.payload(&call(2, 101)), Payload::Redacted);
+    }
+}
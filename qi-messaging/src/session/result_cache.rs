@@ -0,0 +1,521 @@
+use super::{CallWithId, NotificationWithId};
+use crate::{
+    service::{CallResult, GetSubject, Service},
+    types::object::{ActionId, ObjectId, ServiceId},
+};
+use bytes::Bytes;
+use pin_project_lite::pin_project;
+use std::{
+    collections::{BTreeSet, HashMap},
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard, PoisonError,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::time::Instant;
+
+/// A service that caches the replies of the calls to some actions of an inner service.
+///
+/// Actions are made cacheable with a time to live, see [`ResultCache::with_cacheable`]. A call to
+/// a cacheable action with the same object and the same arguments as a previous call is answered
+/// with the reply of that call, as long as it is not expired, without calling the inner service.
+/// Only successful replies are cached.
+///
+/// This suits getters of values that rarely change, such as summaries. The cache may be
+/// invalidated and observed through a [`ResultCacheHandle`], for instance when the values change.
+///
+/// The cache holds at most [`DEFAULT_RESULT_CACHE_MAX_ENTRIES`] replies by default, see
+/// [`ResultCache::with_max_entries`]. Expired replies are removed when they are looked up or when
+/// a reply is cached; once the cache is full, caching a reply evicts the one that expires first.
+#[derive(Debug)]
+pub struct ResultCache<S, R> {
+    inner: S,
+    ttls: HashMap<(ServiceId, ActionId), Duration>,
+    shared: Arc<Shared<R>>,
+}
+
+/// The default maximum number of replies held by a [`ResultCache`].
+pub const DEFAULT_RESULT_CACHE_MAX_ENTRIES: usize = 1024;
+
+impl<S, R> ResultCache<S, R> {
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            ttls: HashMap::new(),
+            shared: Arc::new(Shared {
+                entries: Mutex::new(Entries {
+                    replies: HashMap::new(),
+                    expiries: BTreeSet::new(),
+                    max: DEFAULT_RESULT_CACHE_MAX_ENTRIES,
+                }),
+                hits: AtomicU64::new(0),
+                misses: AtomicU64::new(0),
+                evictions: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Sets the maximum number of replies held by the cache. A maximum of 0 disables the cache.
+    ///
+    /// If the cache holds more replies, the ones that expire first are evicted.
+    pub fn with_max_entries(self, max: usize) -> Self {
+        let mut entries = self.shared.lock_entries();
+        entries.max = max;
+        let evicted = entries.evict(max);
+        drop(entries);
+        self.shared
+            .evictions
+            .fetch_add(evicted as u64, Ordering::Relaxed);
+        self
+    }
+
+    /// Caches the replies of the calls to an action of a service for the given duration.
+    pub fn with_cacheable(mut self, service: ServiceId, action: ActionId, ttl: Duration) -> Self {
+        self.ttls.insert((service, action), ttl);
+        self
+    }
+
+    /// Returns a handle to invalidate the cache and read its statistics, that can be kept after
+    /// the service is given to a session.
+    pub fn handle(&self) -> ResultCacheHandle<R> {
+        ResultCacheHandle(Arc::clone(&self.shared))
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S, R> Service<CallWithId, NotificationWithId> for ResultCache<S, R>
+where
+    S: Service<CallWithId, NotificationWithId, CallReply = R>,
+    R: Clone,
+{
+    type CallReply = R;
+    type Error = S::Error;
+    type CallFuture = ResultCacheFuture<S::CallFuture, R>;
+    type NotifyFuture = S::NotifyFuture;
+
+    fn call(&mut self, call: CallWithId) -> Self::CallFuture {
+        let subject = *call.inner().subject();
+        let ttl = match self.ttls.get(&(subject.service(), subject.action())) {
+            Some(ttl) => *ttl,
+            None => return ResultCacheFuture::call(self.inner.call(call), None),
+        };
+        let key = Key {
+            service: subject.service(),
+            object: subject.object(),
+            action: subject.action(),
+            arguments: call.inner().formatted_value().to_bytes(),
+        };
+        if let Some(reply) = self.shared.get(&key) {
+            self.shared.hits.fetch_add(1, Ordering::Relaxed);
+            return ResultCacheFuture::hit(reply);
+        }
+        self.shared.misses.fetch_add(1, Ordering::Relaxed);
+        let store = Store {
+            shared: Arc::clone(&self.shared),
+            key,
+            ttl,
+        };
+        ResultCacheFuture::call(self.inner.call(call), Some(store))
+    }
+
+    fn notify(&mut self, notif: NotificationWithId) -> Self::NotifyFuture {
+        self.inner.notify(notif)
+    }
+}
+
+/// A handle to the cache of a [`ResultCache`] service.
+#[derive(Debug)]
+pub struct ResultCacheHandle<R>(Arc<Shared<R>>);
+
+impl<R> ResultCacheHandle<R> {
+    /// Removes the cached replies of the calls to an action of a service.
+    pub fn invalidate(&self, service: ServiceId, action: ActionId) {
+        self.0
+            .lock_entries()
+            .retain(|key| (key.service, key.action) != (service, action));
+    }
+
+    /// Removes the cached replies of the calls to all the actions of a service.
+    pub fn invalidate_service(&self, service: ServiceId) {
+        self.0.lock_entries().retain(|key| key.service != service);
+    }
+
+    /// Removes all the cached replies.
+    pub fn clear(&self) {
+        self.0.lock_entries().retain(|_key| false);
+    }
+
+    pub fn stats(&self) -> ResultCacheStats {
+        ResultCacheStats {
+            hits: self.0.hits.load(Ordering::Relaxed),
+            misses: self.0.misses.load(Ordering::Relaxed),
+            evictions: self.0.evictions.load(Ordering::Relaxed),
+            entries: self.0.lock_entries().replies.len(),
+        }
+    }
+}
+
+impl<R> Clone for ResultCacheHandle<R> {
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
+}
+
+/// The statistics of a [`ResultCache`], see [`ResultCacheHandle::stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ResultCacheStats {
+    hits: u64,
+    misses: u64,
+    evictions: u64,
+    entries: usize,
+}
+
+impl ResultCacheStats {
+    /// The number of calls to cacheable actions that were answered from the cache.
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// The number of calls to cacheable actions that were forwarded to the inner service.
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+
+    /// The ratio of the calls to cacheable actions that were answered from the cache, or
+    /// nothing if there were no such calls.
+    pub fn hit_rate(&self) -> Option<f64> {
+        let calls = self.hits + self.misses;
+        (calls > 0).then(|| self.hits as f64 / calls as f64)
+    }
+
+    /// The number of replies that were evicted from the cache before they expired, because it was
+    /// full, see [`ResultCache::with_max_entries`].
+    pub fn evictions(&self) -> u64 {
+        self.evictions
+    }
+
+    /// The number of cached replies, including the expired ones that were not removed yet.
+    pub fn entries(&self) -> usize {
+        self.entries
+    }
+}
+
+#[derive(Debug)]
+struct Shared<R> {
+    entries: Mutex<Entries<R>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+}
+
+impl<R> Shared<R> {
+    fn lock_entries(&self) -> MutexGuard<'_, Entries<R>> {
+        // Entries are always left consistent, poisoning can be ignored.
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn get(&self, key: &Key) -> Option<R>
+    where
+        R: Clone,
+    {
+        let mut entries = self.lock_entries();
+        let entry = entries.replies.get(key)?;
+        if entry.expiry > Instant::now() {
+            return Some(entry.reply.clone());
+        }
+        entries.remove(key);
+        None
+    }
+
+    fn insert(&self, key: Key, reply: R, ttl: Duration) {
+        let now = Instant::now();
+        let mut entries = self.lock_entries();
+        entries.remove_expired(now);
+        entries.remove(&key);
+        let max = entries.max;
+        if max == 0 {
+            return;
+        }
+        // Makes room for the reply.
+        let evicted = entries.evict(max - 1);
+        self.evictions.fetch_add(evicted as u64, Ordering::Relaxed);
+        let expiry = now + ttl;
+        entries.expiries.insert((expiry, key.clone()));
+        entries.replies.insert(key, Entry { reply, expiry });
+    }
+}
+
+/// The cached replies, with an index of their expiries, so that the expired replies and the ones
+/// to evict are found without going through all the replies.
+#[derive(Debug)]
+struct Entries<R> {
+    replies: HashMap<Key, Entry<R>>,
+    expiries: BTreeSet<(Instant, Key)>,
+    max: usize,
+}
+
+impl<R> Entries<R> {
+    fn remove(&mut self, key: &Key) {
+        if let Some(entry) = self.replies.remove(key) {
+            self.expiries.remove(&(entry.expiry, key.clone()));
+        }
+    }
+
+    /// Removes the replies that expired, which are the first ones of the index.
+    fn remove_expired(&mut self, now: Instant) {
+        while self
+            .expiries
+            .iter()
+            .next()
+            .is_some_and(|(expiry, _key)| *expiry <= now)
+        {
+            self.pop_first();
+        }
+    }
+
+    /// Removes the replies that expire first until at most `len` remain, and returns how many
+    /// were removed.
+    fn evict(&mut self, len: usize) -> usize {
+        let mut evicted = 0;
+        while self.replies.len() > len {
+            self.pop_first();
+            evicted += 1;
+        }
+        evicted
+    }
+
+    fn pop_first(&mut self) {
+        if let Some(first) = self.expiries.iter().next().cloned() {
+            self.expiries.remove(&first);
+            self.replies.remove(&first.1);
+        }
+    }
+
+    fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&Key) -> bool,
+    {
+        self.replies.retain(|key, _entry| f(key));
+        self.expiries.retain(|(_expiry, key)| f(key));
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct Key {
+    service: ServiceId,
+    object: ObjectId,
+    action: ActionId,
+    arguments: Bytes,
+}
+
+#[derive(Debug)]
+struct Entry<R> {
+    reply: R,
+    expiry: Instant,
+}
+
+#[derive(Debug)]
+struct Store<R> {
+    shared: Arc<Shared<R>>,
+    key: Key,
+    ttl: Duration,
+}
+
+pin_project! {
+    /// The future of a call to a [`ResultCache`] service.
+    #[derive(Debug)]
+    #[must_use = "futures do nothing until polled"]
+    pub struct ResultCacheFuture<F, R> {
+        #[pin]
+        state: State<F, R>,
+    }
+}
+
+pin_project! {
+    #[project = StateProj]
+    #[derive(Debug)]
+    enum State<F, R> {
+        Hit {
+            reply: Option<R>,
+        },
+        Call {
+            #[pin]
+            inner: F,
+            store: Option<Store<R>>,
+        },
+    }
+}
+
+impl<F, R> ResultCacheFuture<F, R> {
+    fn hit(reply: R) -> Self {
+        Self {
+            state: State::Hit { reply: Some(reply) },
+        }
+    }
+
+    fn call(inner: F, store: Option<Store<R>>) -> Self {
+        Self {
+            state: State::Call { inner, store },
+        }
+    }
+}
+
+impl<F, R, E> Future for ResultCacheFuture<F, R>
+where
+    F: Future<Output = CallResult<R, E>>,
+    R: Clone,
+{
+    type Output = CallResult<R, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project().state.project() {
+            StateProj::Hit { reply } => match reply.take() {
+                Some(reply) => Poll::Ready(Ok(reply)),
+                None => Poll::Pending,
+            },
+            StateProj::Call { inner, store } => {
+                let result = futures::ready!(inner.poll(cx));
+                if let (Ok(reply), Some(store)) = (&result, store.take()) {
+                    store.shared.insert(store.key, reply.clone(), store.ttl);
+                }
+                Poll::Ready(result)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::{subject::ServiceObject, Call, Subject};
+    use futures::future;
+
+    /// Replies to each call with the number of calls it received.
+    #[derive(Debug, Default)]
+    struct CountingService(u32);
+
+    impl Service<CallWithId, NotificationWithId> for CountingService {
+        type CallReply = u32;
+        type Error = std::convert::Infallible;
+        type CallFuture = future::Ready<CallResult<u32, Self::Error>>;
+        type NotifyFuture = future::Ready<Result<(), Self::Error>>;
+
+        fn call(&mut self, _call: CallWithId) -> Self::CallFuture {
+            self.0 += 1;
+            future::ok(self.0)
+        }
+
+        fn notify(&mut self, _notif: NotificationWithId) -> Self::NotifyFuture {
+            future::ok(())
+        }
+    }
+
+    fn call(action: u32, argument: i32) -> CallWithId {
+        let service_object = ServiceObject::new(ServiceId::from(2), ObjectId::from(1)).unwrap();
+        let subject = Subject::new(service_object, ActionId::from(action));
+        CallWithId::new(
+            crate::RequestId(1),
+            Call::new(subject).with_value(&argument).unwrap(),
+        )
+    }
+
+    async fn reply(cache: &mut ResultCache<CountingService, u32>, call: CallWithId) -> u32 {
+        match cache.call(call).await {
+            Ok(reply) => reply,
+            Err(_err) => unreachable!(),
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_result_cache() {
+        let mut cache = ResultCache::new(CountingService::default()).with_cacheable(
+            ServiceId::from(2),
+            ActionId::from(100),
+            Duration::from_secs(10),
+        );
+        let handle = cache.handle();
+        assert_eq!(handle.stats().hit_rate(), None);
+
+        assert_eq!(reply(&mut cache, call(100, 1)).await, 1);
+        assert_eq!(reply(&mut cache, call(100, 1)).await, 1);
+        // Calls with other arguments or to other actions are not served from the cache.
+        assert_eq!(reply(&mut cache, call(100, 2)).await, 2);
+        assert_eq!(reply(&mut cache, call(101, 1)).await, 3);
+        assert_eq!(reply(&mut cache, call(101, 1)).await, 4);
+
+        let stats = handle.stats();
+        assert_eq!(stats.hits(), 1);
+        assert_eq!(stats.misses(), 2);
+        assert_eq!(stats.entries(), 2);
+        assert_eq!(stats.hit_rate(), Some(1. / 3.));
+
+        tokio::time::advance(Duration::from_secs(11)).await;
+        assert_eq!(reply(&mut cache, call(100, 1)).await, 5);
+        assert_eq!(reply(&mut cache, call(100, 1)).await, 5);
+        // The expired entry of the other arguments was removed.
+        assert_eq!(handle.stats().entries(), 1);
+
+        handle.invalidate(ServiceId::from(2), ActionId::from(100));
+        assert_eq!(handle.stats().entries(), 0);
+        assert_eq!(reply(&mut cache, call(100, 1)).await, 6);
+        handle.invalidate_service(ServiceId::from(2));
+        assert_eq!(reply(&mut cache, call(100, 1)).await, 7);
+        handle.clear();
+        assert_eq!(handle.stats().entries(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_result_cache_max_entries() {
+        let mut cache = ResultCache::new(CountingService::default())
+            .with_cacheable(
+                ServiceId::from(2),
+                ActionId::from(100),
+                Duration::from_secs(10),
+            )
+            .with_cacheable(
+                ServiceId::from(2),
+                ActionId::from(101),
+                Duration::from_secs(20),
+            )
+            .with_max_entries(2);
+        let handle = cache.handle();
+
+        assert_eq!(reply(&mut cache, call(101, 1)).await, 1);
+        assert_eq!(reply(&mut cache, call(100, 1)).await, 2);
+        // The cache is full, the reply that expires first is evicted.
+        assert_eq!(reply(&mut cache, call(101, 2)).await, 3);
+        let stats = handle.stats();
+        assert_eq!(stats.entries(), 2);
+        assert_eq!(stats.evictions(), 1);
+        assert_eq!(reply(&mut cache, call(100, 1)).await, 4);
+        assert_eq!(reply(&mut cache, call(101, 2)).await, 3);
+
+        // Expired replies are removed before evicting others.
+        tokio::time::advance(Duration::from_secs(15)).await;
+        assert_eq!(reply(&mut cache, call(100, 2)).await, 5);
+        let stats = handle.stats();
+        assert_eq!(stats.entries(), 2);
+        assert_eq!(stats.evictions(), 2);
+        assert_eq!(reply(&mut cache, call(101, 2)).await, 3);
+
+        // Reducing the maximum evicts the replies that expire first.
+        let mut cache = cache.with_max_entries(1);
+        assert_eq!(handle.stats().entries(), 1);
+        assert_eq!(reply(&mut cache, call(100, 2)).await, 5);
+        let mut cache = cache.with_max_entries(0);
+        assert_eq!(handle.stats().entries(), 0);
+        assert_eq!(reply(&mut cache, call(100, 2)).await, 6);
+        assert_eq!(reply(&mut cache, call(100, 2)).await, 7);
+    }
+}
@@ -1,4 +1,4 @@
-use super::{control, Service};
+use super::{config, control, EventSources, GetSubject, Service};
 use crate::{
     format,
     messaging::{self, CallWithId, NotificationWithId},
@@ -12,33 +12,52 @@ use std::{
     task::{Context, Poll},
 };
 use tokio::sync::oneshot;
+use tracing::debug;
 
 #[derive(Debug)]
 pub(super) struct Router<S> {
     control: control::Service,
     service: Option<S>,
     enable_service_receiver: Option<oneshot::Receiver<EnableService<S>>>,
+    rate_limiter: config::RateLimiter,
+    config: config::SharedConfig,
+    event_sources: EventSources,
 }
 
 /// Routes request between a control service and a client service.
 impl<S> Router<S> {
-    pub(super) fn new(control: control::Service) -> (Self, oneshot::Sender<EnableService<S>>) {
+    pub(super) fn new(
+        control: control::Service,
+        config: config::SharedConfig,
+        event_sources: EventSources,
+    ) -> (Self, oneshot::Sender<EnableService<S>>) {
         let (enable_service_sender, enable_service_receiver) = oneshot::channel();
         (
             Self {
                 control,
                 service: None,
                 enable_service_receiver: Some(enable_service_receiver),
+                rate_limiter: config::RateLimiter::new(config.clone()),
+                config,
+                event_sources,
             },
             enable_service_sender,
         )
     }
 
-    pub(super) fn with_service_enabled(control: control::Service, service: S) -> Self {
+    pub(super) fn with_service_enabled(
+        control: control::Service,
+        service: S,
+        config: config::SharedConfig,
+        event_sources: EventSources,
+    ) -> Self {
         Self {
             control,
             service: Some(service),
             enable_service_receiver: None,
+            rate_limiter: config::RateLimiter::new(config.clone()),
+            config,
+            event_sources,
         }
     }
 
@@ -58,6 +77,17 @@ impl<S> Router<S> {
             }
         }
     }
+
+    /// Returns true if the remote may send the notification, see `EventSources`.
+    fn is_permitted(&self, notif: &super::Notification) -> bool {
+        match notif {
+            super::Notification::Post(_) | super::Notification::Event(_) => {
+                self.config.get().remote_event_sources_trusted()
+                    || self.event_sources.is_permitted(notif.subject())
+            }
+            super::Notification::Cancel(_) => true,
+        }
+    }
 }
 
 impl<S> Service<CallWithId, NotificationWithId> for Router<S>
@@ -84,6 +114,13 @@ where
         };
 
         if let Some(service) = self.service.as_mut() {
+            // Calls of the control service are never refused nor limited.
+            if self.config.get().incoming_calls_refused() {
+                return CallFuture::Refused;
+            }
+            if !self.rate_limiter.allow() {
+                return CallFuture::RateLimited;
+            }
             if let Ok(call) = super::CallWithId::from_messaging(call) {
                 return CallFuture::Service {
                     inner: service.call(call),
@@ -106,9 +143,17 @@ where
             }
             Err(notif) => notif,
         };
-        if let Some(service) = self.service.as_mut() {
-            let notif_with_id = messaging::NotificationWithId::new(id, notif);
-            if let Ok(notif) = super::NotificationWithId::from_messaging(notif_with_id) {
+        let notif_with_id = messaging::NotificationWithId::new(id, notif);
+        if let Ok(notif) = super::NotificationWithId::from_messaging(notif_with_id) {
+            if !self.is_permitted(notif.inner()) {
+                debug!(
+                    subject = ?notif.inner().subject(),
+                    "rejected a notification to a signal that the remote may not publish to"
+                );
+                self.event_sources.record_rejected();
+                return NotifyFuture::Rejected;
+            }
+            if let Some(service) = self.service.as_mut() {
                 return NotifyFuture::Service {
                     inner: service.notify(notif),
                 };
@@ -135,6 +180,15 @@ pub(super) enum Error<S> {
 
     #[error("the request could not be handled")]
     UnhandledRequest,
+
+    #[error("the rate limit of calls is exceeded")]
+    RateLimited,
+
+    #[error("calls are refused")]
+    Refused,
+
+    #[error("the remote is not permitted to publish to this signal")]
+    EventSourceRejected,
 }
 
 pin_project! {
@@ -153,6 +207,8 @@ pin_project! {
             error: Option<format::Error>
         },
         UnhandledRequest,
+        RateLimited,
+        Refused,
     }
 }
 
@@ -182,6 +238,8 @@ where
                 None => Poll::Pending,
             },
             CallFutureProj::UnhandledRequest => Poll::Ready(Err(Error::UnhandledRequest.into())),
+            CallFutureProj::RateLimited => Poll::Ready(Err(Error::RateLimited.into())),
+            CallFutureProj::Refused => Poll::Ready(Err(Error::Refused.into())),
         }
     }
 }
@@ -199,6 +257,7 @@ pin_project! {
             inner: S
         },
         UnhandledRequest,
+        Rejected,
     }
 }
 
@@ -219,6 +278,7 @@ where
                 Poll::Ready(Ok(()))
             }
             NotifyFutureProj::UnhandledRequest => Poll::Ready(Err(Error::UnhandledRequest)),
+            NotifyFutureProj::Rejected => Poll::Ready(Err(Error::EventSourceRejected)),
         }
     }
 }
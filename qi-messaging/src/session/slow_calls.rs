@@ -0,0 +1,576 @@
+use super::{Call, Notification};
+use crate::{
+    service::{CallResult, GetSubject},
+    types::object::{ActionId, ServiceId},
+    Service,
+};
+use pin_project_lite::pin_project;
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::{sync::broadcast, time::Instant};
+use tracing::{debug, warn};
+
+/// A service that measures the latency of the calls to an inner service, such as the client of a
+/// session, and reports the slow ones.
+///
+/// The latency of a call lasts from its sending to its response, and is measured per action of a
+/// service. A call is slow if its latency exceeds the threshold of its action, see
+/// [`SlowCallDetector::with_threshold`] and [`SlowCallDetector::with_action_threshold`].
+///
+/// The latencies of the calls to each action are sampled in a reservoir of a fixed size, see
+/// [`SlowCallDetector::with_reservoir_size`], from which their 99th percentile is estimated. Once
+/// enough latencies are sampled, an action is degraded while this percentile exceeds the threshold
+/// set with [`SlowCallDetector::with_p99_threshold`].
+///
+/// Slow calls and degradations are logged as warnings, and published to the subscribers of the
+/// [`SlowCallHandle`] of the detector, along with the statistics of the actions. Calls that are
+/// dropped before their response are not measured.
+#[derive(Debug)]
+pub struct SlowCallDetector<S> {
+    inner: S,
+    threshold: Option<Duration>,
+    action_thresholds: HashMap<(ServiceId, ActionId), Duration>,
+    p99_threshold: Option<Duration>,
+    shared: Arc<Shared>,
+}
+
+impl<S> SlowCallDetector<S> {
+    pub fn new(inner: S) -> Self {
+        let (events, _receiver) = broadcast::channel(CHANNEL_SIZE);
+        Self {
+            inner,
+            threshold: None,
+            action_thresholds: HashMap::new(),
+            p99_threshold: None,
+            shared: Arc::new(Shared {
+                latencies: Mutex::new(Latencies {
+                    actions: HashMap::new(),
+                    random: RANDOM_SEED,
+                }),
+                reservoir_size: DEFAULT_RESERVOIR_SIZE,
+                events,
+            }),
+        }
+    }
+
+    /// Sets the latency above which calls are slow, for all the actions that have no threshold
+    /// of their own. No call is slow by default.
+    pub fn with_threshold(mut self, threshold: Option<Duration>) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Sets the latency above which the calls to an action of a service are slow.
+    pub fn with_action_threshold(
+        mut self,
+        service: ServiceId,
+        action: ActionId,
+        threshold: Duration,
+    ) -> Self {
+        self.action_thresholds.insert((service, action), threshold);
+        self
+    }
+
+    /// Sets the 99th percentile of the latencies of an action above which it is degraded. Actions
+    /// are never degraded by default.
+    pub fn with_p99_threshold(mut self, threshold: Option<Duration>) -> Self {
+        self.p99_threshold = threshold;
+        self
+    }
+
+    /// Sets the number of latencies sampled per action, 128 by default.
+    ///
+    /// It must be set before the handle of the detector is taken, and is ignored otherwise.
+    pub fn with_reservoir_size(mut self, size: usize) -> Self {
+        if let Some(shared) = Arc::get_mut(&mut self.shared) {
+            shared.reservoir_size = size;
+        }
+        self
+    }
+
+    /// Returns a handle to read the statistics of the detector and subscribe to its events, that
+    /// can be kept after the service is given away.
+    pub fn handle(&self) -> SlowCallHandle {
+        SlowCallHandle(Arc::clone(&self.shared))
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S> Service<Call, Notification> for SlowCallDetector<S>
+where
+    S: Service<Call, Notification>,
+{
+    type CallReply = S::CallReply;
+    type Error = S::Error;
+    type CallFuture = SlowCallFuture<S::CallFuture>;
+    type NotifyFuture = S::NotifyFuture;
+
+    fn call(&mut self, call: Call) -> Self::CallFuture {
+        let subject = call.subject();
+        let key = (subject.service(), subject.action());
+        let measure = Measure {
+            shared: Arc::clone(&self.shared),
+            key,
+            threshold: self.action_thresholds.get(&key).copied().or(self.threshold),
+            p99_threshold: self.p99_threshold,
+        };
+        SlowCallFuture {
+            inner: self.inner.call(call),
+            start: Instant::now(),
+            measure: Some(measure),
+        }
+    }
+
+    fn notify(&mut self, notif: Notification) -> Self::NotifyFuture {
+        self.inner.notify(notif)
+    }
+}
+
+/// A handle to the measures of a [`SlowCallDetector`].
+#[derive(Debug, Clone)]
+pub struct SlowCallHandle(Arc<Shared>);
+
+impl SlowCallHandle {
+    /// Returns the statistics of the calls to an action of a service, if any was measured.
+    pub fn stats(&self, service: ServiceId, action: ActionId) -> Option<CallLatencyStats> {
+        self.0
+            .lock_latencies()
+            .actions
+            .get(&(service, action))
+            .map(ActionLatencies::stats)
+    }
+
+    /// Returns the statistics of the calls to all the actions that were measured.
+    pub fn all_stats(&self) -> HashMap<(ServiceId, ActionId), CallLatencyStats> {
+        self.0
+            .lock_latencies()
+            .actions
+            .iter()
+            .map(|(key, action)| (*key, action.stats()))
+            .collect()
+    }
+
+    /// Subscribes to the slow calls and the degradations of the actions.
+    ///
+    /// Events are kept in a bounded buffer until received, the oldest ones are lost if the
+    /// subscriber lags behind.
+    pub fn subscribe(&self) -> broadcast::Receiver<SlowCallEvent> {
+        self.0.events.subscribe()
+    }
+}
+
+/// The statistics of the latencies of the calls to an action, see [`SlowCallHandle::stats`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct CallLatencyStats {
+    calls: u64,
+    slow_calls: u64,
+    samples: Vec<Duration>,
+}
+
+impl CallLatencyStats {
+    /// The number of calls whose latency was measured.
+    pub fn calls(&self) -> u64 {
+        self.calls
+    }
+
+    /// The number of calls whose latency exceeded the threshold of the action.
+    pub fn slow_calls(&self) -> u64 {
+        self.slow_calls
+    }
+
+    /// The sampled latencies, in ascending order.
+    ///
+    /// They are a uniform sample of the latencies of all the calls, the size of which is at most
+    /// the size of the reservoir of the detector.
+    pub fn samples(&self) -> &[Duration] {
+        &self.samples
+    }
+
+    /// Estimates the latency below which are the given quantile of the calls, between 0 and 1,
+    /// from the samples. Returns nothing if there are no samples.
+    pub fn quantile(&self, quantile: f64) -> Option<Duration> {
+        quantile_of_sorted(&self.samples, quantile)
+    }
+
+    /// Estimates the median of the latencies, see [`CallLatencyStats::quantile`].
+    pub fn p50(&self) -> Option<Duration> {
+        self.quantile(0.5)
+    }
+
+    /// Estimates the 99th percentile of the latencies, see [`CallLatencyStats::quantile`].
+    pub fn p99(&self) -> Option<Duration> {
+        self.quantile(0.99)
+    }
+}
+
+/// A slow call or a change of the degradation of an action, reported by a
+/// [`SlowCallDetector`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SlowCallEvent {
+    /// A call lasted longer than the threshold of its action.
+    SlowCall {
+        service: ServiceId,
+        action: ActionId,
+        latency: Duration,
+        threshold: Duration,
+    },
+    /// The 99th percentile of the latencies of an action exceeded the threshold. It is reported
+    /// once, until the action recovers.
+    Degraded {
+        service: ServiceId,
+        action: ActionId,
+        p99: Duration,
+        threshold: Duration,
+    },
+    /// The 99th percentile of the latencies of a degraded action went back under the threshold.
+    Recovered {
+        service: ServiceId,
+        action: ActionId,
+        p99: Duration,
+        threshold: Duration,
+    },
+}
+
+const DEFAULT_RESERVOIR_SIZE: usize = 128;
+/// The number of samples of an action from which their 99th percentile is compared to the
+/// threshold. Below it, the percentile is the slowest of a few calls.
+const MIN_P99_SAMPLES: usize = 32;
+const CHANNEL_SIZE: usize = 16;
+// Any non-zero value seeds the generator of the indices of the reservoir.
+const RANDOM_SEED: u64 = 0x2545_f491_4f6c_dd1d;
+
+#[derive(Debug)]
+struct Shared {
+    latencies: Mutex<Latencies>,
+    reservoir_size: usize,
+    events: broadcast::Sender<SlowCallEvent>,
+}
+
+impl Shared {
+    fn lock_latencies(&self) -> MutexGuard<'_, Latencies> {
+        // Latencies are updated at once, poisoning can be ignored.
+        self.latencies
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn record(&self, measure: &Measure, latency: Duration) {
+        let (service, action) = measure.key;
+        let mut events = Vec::new();
+        {
+            let mut latencies = self.lock_latencies();
+            let random = latencies.next_random();
+            let action_latencies = latencies.actions.entry(measure.key).or_default();
+            action_latencies.sample(latency, self.reservoir_size, random);
+            if let Some(threshold) = measure.threshold.filter(|threshold| latency > *threshold) {
+                action_latencies.slow_calls += 1;
+                events.push(SlowCallEvent::SlowCall {
+                    service,
+                    action,
+                    latency,
+                    threshold,
+                });
+            }
+            let p99 = (action_latencies.samples.len() >= MIN_P99_SAMPLES)
+                .then(|| action_latencies.stats().p99())
+                .flatten();
+            if let (Some(p99), Some(threshold)) = (p99, measure.p99_threshold) {
+                let degraded = p99 > threshold;
+                if degraded != action_latencies.degraded {
+                    action_latencies.degraded = degraded;
+                    events.push(if degraded {
+                        SlowCallEvent::Degraded {
+                            service,
+                            action,
+                            p99,
+                            threshold,
+                        }
+                    } else {
+                        SlowCallEvent::Recovered {
+                            service,
+                            action,
+                            p99,
+                            threshold,
+                        }
+                    });
+                }
+            }
+        }
+        for event in events {
+            match event {
+                SlowCallEvent::SlowCall {
+                    latency, threshold, ..
+                } => warn!(
+                    %service, %action, ?latency, ?threshold,
+                    "a call lasted longer than the threshold of its action"
+                ),
+                SlowCallEvent::Degraded { p99, threshold, .. } => warn!(
+                    %service, %action, ?p99, ?threshold,
+                    "the 99th percentile of the latencies of an action exceeds its threshold"
+                ),
+                SlowCallEvent::Recovered { p99, threshold, .. } => debug!(
+                    %service, %action, ?p99, ?threshold,
+                    "the 99th percentile of the latencies of an action is back under its threshold"
+                ),
+            }
+            // An error means there are no subscribers, which is fine.
+            let _res = self.events.send(event);
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Latencies {
+    actions: HashMap<(ServiceId, ActionId), ActionLatencies>,
+    random: u64,
+}
+
+impl Latencies {
+    /// A xorshift generator, random enough to pick the samples of the reservoirs.
+    fn next_random(&mut self) -> u64 {
+        self.random ^= self.random << 13;
+        self.random ^= self.random >> 7;
+        self.random ^= self.random << 17;
+        self.random
+    }
+}
+
+#[derive(Debug, Default)]
+struct ActionLatencies {
+    calls: u64,
+    slow_calls: u64,
+    samples: Vec<Duration>,
+    degraded: bool,
+}
+
+impl ActionLatencies {
+    /// Samples a latency in the reservoir, so that each of the latencies measured so far has the
+    /// same probability to be in it.
+    fn sample(&mut self, latency: Duration, reservoir_size: usize, random: u64) {
+        self.calls += 1;
+        if self.samples.len() < reservoir_size {
+            self.samples.push(latency);
+        } else {
+            let index = random % self.calls;
+            if let Some(sample) = usize::try_from(index)
+                .ok()
+                .and_then(|index| self.samples.get_mut(index))
+            {
+                *sample = latency;
+            }
+        }
+    }
+
+    fn stats(&self) -> CallLatencyStats {
+        let mut samples = self.samples.clone();
+        samples.sort_unstable();
+        CallLatencyStats {
+            calls: self.calls,
+            slow_calls: self.slow_calls,
+            samples,
+        }
+    }
+}
+
+/// Returns the nearest-rank quantile of sorted values.
+fn quantile_of_sorted(sorted: &[Duration], quantile: f64) -> Option<Duration> {
+    let rank = (sorted.len() as f64 * quantile.clamp(0., 1.)).ceil() as usize;
+    sorted
+        .get(rank.saturating_sub(1).min(sorted.len().checked_sub(1)?))
+        .copied()
+}
+
+#[derive(Debug)]
+struct Measure {
+    shared: Arc<Shared>,
+    key: (ServiceId, ActionId),
+    threshold: Option<Duration>,
+    p99_threshold: Option<Duration>,
+}
+
+pin_project! {
+    /// The future of a call to a [`SlowCallDetector`] service.
+    #[derive(Debug)]
+    #[must_use = "futures do nothing until polled"]
+    pub struct SlowCallFuture<F> {
+        #[pin]
+        inner: F,
+        start: Instant,
+        measure: Option<Measure>,
+    }
+}
+
+impl<F, R, E> Future for SlowCallFuture<F>
+where
+    F: Future<Output = CallResult<R, E>>,
+{
+    type Output = CallResult<R, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let result = futures::ready!(this.inner.poll(cx));
+        if let Some(measure) = this.measure.take() {
+            measure.shared.record(&measure, this.start.elapsed());
+        }
+        Poll::Ready(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        session::{subject::ServiceObject, Subject},
+        types::object::ObjectId,
+    };
+    use futures::future::{self, BoxFuture, FutureExt};
+    use tokio::sync::broadcast::error::TryRecvError;
+
+    /// Replies to each call after as many milliseconds as its argument.
+    #[derive(Debug, Default)]
+    struct DelayService;
+
+    impl Service<Call, Notification> for DelayService {
+        type CallReply = ();
+        type Error = std::convert::Infallible;
+        type CallFuture = BoxFuture<'static, CallResult<(), Self::Error>>;
+        type NotifyFuture = future::Ready<Result<(), Self::Error>>;
+
+        fn call(&mut self, call: Call) -> Self::CallFuture {
+            let delay: u64 = call.formatted_value().to_deserializable().unwrap();
+            tokio::time::sleep(Duration::from_millis(delay))
+                .map(Ok)
+                .boxed()
+        }
+
+        fn notify(&mut self, _notif: Notification) -> Self::NotifyFuture {
+            future::ok(())
+        }
+    }
+
+    const SERVICE: ServiceId = ServiceId::new(2);
+
+    async fn call(detector: &mut SlowCallDetector<DelayService>, action: u32, delay: u64) {
+        let service_object = ServiceObject::new(SERVICE, ObjectId::new(1)).unwrap();
+        let call = Call::new(Subject::new(service_object, ActionId::new(action)))
+            .with_value(&delay)
+            .unwrap();
+        let _res = detector.call(call).await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_slow_call_detector_thresholds() {
+        let mut detector = SlowCallDetector::new(DelayService)
+            .with_threshold(Some(Duration::from_millis(100)))
+            .with_action_threshold(SERVICE, ActionId::new(101), Duration::from_millis(500));
+        let handle = detector.handle();
+        let mut events = handle.subscribe();
+
+        call(&mut detector, 100, 50).await;
+        call(&mut detector, 100, 200).await;
+        call(&mut detector, 101, 300).await;
+        assert_eq!(
+            events.try_recv(),
+            Ok(SlowCallEvent::SlowCall {
+                service: SERVICE,
+                action: ActionId::new(100),
+                latency: Duration::from_millis(200),
+                threshold: Duration::from_millis(100),
+            })
+        );
+        assert_eq!(events.try_recv(), Err(TryRecvError::Empty));
+
+        let stats = handle.stats(SERVICE, ActionId::new(100)).unwrap();
+        assert_eq!(stats.calls(), 2);
+        assert_eq!(stats.slow_calls(), 1);
+        assert_eq!(
+            stats.samples(),
+            [Duration::from_millis(50), Duration::from_millis(200)]
+        );
+        let stats = handle.stats(SERVICE, ActionId::new(101)).unwrap();
+        assert_eq!(stats.slow_calls(), 0);
+        assert_eq!(handle.all_stats().len(), 2);
+        assert!(handle.stats(SERVICE, ActionId::new(102)).is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_slow_call_detector_p99() {
+        let mut detector = SlowCallDetector::new(DelayService)
+            .with_p99_threshold(Some(Duration::from_millis(20)))
+            .with_reservoir_size(MIN_P99_SAMPLES);
+        let handle = detector.handle();
+        let mut events = handle.subscribe();
+
+        call(&mut detector, 100, 30).await;
+        for _ in 2..MIN_P99_SAMPLES {
+            call(&mut detector, 100, 10).await;
+        }
+        // Too few calls were sampled for the slowest one to count as the 99th percentile.
+        assert_eq!(events.try_recv(), Err(TryRecvError::Empty));
+        call(&mut detector, 100, 10).await;
+        assert_eq!(
+            events.try_recv(),
+            Ok(SlowCallEvent::Degraded {
+                service: SERVICE,
+                action: ActionId::new(100),
+                p99: Duration::from_millis(30),
+                threshold: Duration::from_millis(20),
+            })
+        );
+        let stats = handle.stats(SERVICE, ActionId::new(100)).unwrap();
+        assert_eq!(stats.p99(), Some(Duration::from_millis(30)));
+        assert_eq!(stats.p50(), Some(Duration::from_millis(10)));
+
+        // The slow call is eventually replaced in the reservoir by the fast ones.
+        for _ in 0..10_000 {
+            call(&mut detector, 100, 10).await;
+        }
+        assert_eq!(
+            events.try_recv(),
+            Ok(SlowCallEvent::Recovered {
+                service: SERVICE,
+                action: ActionId::new(100),
+                p99: Duration::from_millis(10),
+                threshold: Duration::from_millis(20),
+            })
+        );
+        let stats = handle.stats(SERVICE, ActionId::new(100)).unwrap();
+        assert_eq!(stats.calls(), 10_000 + MIN_P99_SAMPLES as u64);
+        assert_eq!(stats.samples().len(), MIN_P99_SAMPLES);
+    }
+
+    #[test]
+    fn test_quantile_of_sorted() {
+        let samples: Vec<_> = (1..=100).map(Duration::from_millis).collect();
+        assert_eq!(
+            quantile_of_sorted(&samples, 0.99),
+            Some(Duration::from_millis(99))
+        );
+        assert_eq!(
+            quantile_of_sorted(&samples, 0.5),
+            Some(Duration::from_millis(50))
+        );
+        assert_eq!(
+            quantile_of_sorted(&samples, 0.),
+            Some(Duration::from_millis(1))
+        );
+        assert_eq!(
+            quantile_of_sorted(&samples, 1.),
+            Some(Duration::from_millis(100))
+        );
+        assert_eq!(quantile_of_sorted(&[], 0.5), None);
+    }
+}
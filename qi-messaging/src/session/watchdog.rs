@@ -0,0 +1,117 @@
+use super::{ConnectionInfo, SharedConfig};
+use pin_project_lite::pin_project;
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+use tokio::sync::broadcast;
+use tracing::warn;
+
+/// A stall of the dispatch of a session, see [`super::Client::dispatch_stalls`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DispatchStall {
+    duration: Duration,
+    threshold: Duration,
+}
+
+impl DispatchStall {
+    /// The duration during which the dispatch of the session could not make progress.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// The threshold of the configuration of the session that the duration exceeded.
+    pub fn threshold(&self) -> Duration {
+        self.threshold
+    }
+}
+
+/// The stalls of the dispatch of a session, published to the subscribers of the session.
+#[derive(Debug, Clone)]
+pub(super) struct Stalls(broadcast::Sender<DispatchStall>);
+
+impl Stalls {
+    const CHANNEL_SIZE: usize = 16;
+
+    pub(super) fn new() -> Self {
+        let (sender, _receiver) = broadcast::channel(Self::CHANNEL_SIZE);
+        Self(sender)
+    }
+
+    pub(super) fn subscribe(&self) -> broadcast::Receiver<DispatchStall> {
+        self.0.subscribe()
+    }
+
+    fn publish(&self, stall: DispatchStall) {
+        // An error means there are no subscribers, which is fine.
+        let _res = self.0.send(stall);
+    }
+}
+
+pin_project! {
+    /// Measures the duration of each poll of the dispatch of a session.
+    ///
+    /// The services of the session, and the handling of its messages, are polled by its dispatch.
+    /// A poll that lasts longer than the threshold of the configuration of the session means that
+    /// some code blocked the thread instead of awaiting, which delays all the messages of the
+    /// session.
+    #[derive(Debug)]
+    #[must_use = "futures do nothing until polled"]
+    pub(super) struct Watchdog<F> {
+        #[pin]
+        dispatch: F,
+        config: SharedConfig,
+        connection: Arc<ConnectionInfo>,
+        stalls: Stalls,
+    }
+}
+
+impl<F> Watchdog<F> {
+    pub(super) fn new(
+        dispatch: F,
+        config: SharedConfig,
+        connection: Arc<ConnectionInfo>,
+        stalls: Stalls,
+    ) -> Self {
+        Self {
+            dispatch,
+            config,
+            connection,
+            stalls,
+        }
+    }
+}
+
+impl<F> Future for Watchdog<F>
+where
+    F: Future,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let threshold = match this.config.get().dispatch_stall_threshold() {
+            Some(threshold) => threshold,
+            None => return this.dispatch.poll(cx),
+        };
+        let start = Instant::now();
+        let poll = this.dispatch.poll(cx);
+        let duration = start.elapsed();
+        if duration > threshold {
+            warn!(
+                ?duration,
+                ?threshold,
+                remote_address = ?this.connection.remote_address(),
+                "the dispatch of the session stalled, a service may be blocking its thread"
+            );
+            this.stalls.publish(DispatchStall {
+                duration,
+                threshold,
+            });
+        }
+        poll
+    }
+}
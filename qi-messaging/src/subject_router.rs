@@ -0,0 +1,259 @@
+use crate::{
+    session,
+    types::object::{ActionId, ObjectId, ServiceId},
+};
+use std::collections::HashMap;
+
+/// A pattern of subjects.
+///
+/// Each of the service, object and action of the pattern is either a value, that only matches
+/// subjects with that value, or a wildcard, that matches any subject.
+#[derive(Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct SubjectPattern {
+    service: Option<ServiceId>,
+    object: Option<ObjectId>,
+    action: Option<ActionId>,
+}
+
+impl SubjectPattern {
+    /// Returns a pattern that matches any subject.
+    pub fn any() -> Self {
+        Self::default()
+    }
+
+    /// Returns a pattern that only matches the given subject.
+    pub fn exact(service: ServiceId, object: ObjectId, action: ActionId) -> Self {
+        Self::any()
+            .with_service(service)
+            .with_object(object)
+            .with_action(action)
+    }
+
+    pub fn with_service(mut self, service: ServiceId) -> Self {
+        self.service = Some(service);
+        self
+    }
+
+    pub fn with_object(mut self, object: ObjectId) -> Self {
+        self.object = Some(object);
+        self
+    }
+
+    pub fn with_action(mut self, action: ActionId) -> Self {
+        self.action = Some(action);
+        self
+    }
+
+    pub fn service(&self) -> Option<ServiceId> {
+        self.service
+    }
+
+    pub fn object(&self) -> Option<ObjectId> {
+        self.object
+    }
+
+    pub fn action(&self) -> Option<ActionId> {
+        self.action
+    }
+
+    pub fn matches(&self, service: ServiceId, object: ObjectId, action: ActionId) -> bool {
+        self.service.is_none_or(|value| value == service)
+            && self.object.is_none_or(|value| value == object)
+            && self.action.is_none_or(|value| value == action)
+    }
+
+    pub fn matches_subject(&self, subject: &session::Subject) -> bool {
+        self.matches(subject.service(), subject.object(), subject.action())
+    }
+
+    /// The patterns that match a subject, from the most specific to the least specific.
+    ///
+    /// A value of the service takes precedence over a value of the object, which takes precedence
+    /// over a value of the action.
+    fn matching(
+        service: ServiceId,
+        object: ObjectId,
+        action: ActionId,
+    ) -> impl Iterator<Item = Self> {
+        (0..8u8).map(move |wildcards| Self {
+            service: (wildcards & 0b100 == 0).then_some(service),
+            object: (wildcards & 0b010 == 0).then_some(object),
+            action: (wildcards & 0b001 == 0).then_some(action),
+        })
+    }
+}
+
+impl From<session::Subject> for SubjectPattern {
+    fn from(subject: session::Subject) -> Self {
+        Self::exact(subject.service(), subject.object(), subject.action())
+    }
+}
+
+/// A routing table that associates values to patterns of subjects.
+///
+/// Looking up a subject returns the value of the most specific pattern that matches it (see
+/// [`SubjectPattern`]). A lookup costs at most one hash table access per combination of
+/// wildcards, independently of the number of routes.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SubjectRouter<T> {
+    routes: HashMap<SubjectPattern, T>,
+}
+
+impl<T> SubjectRouter<T> {
+    pub fn new() -> Self {
+        Self {
+            routes: HashMap::new(),
+        }
+    }
+
+    /// Adds a route, and returns the value that was previously associated to the same pattern,
+    /// if any.
+    pub fn insert(&mut self, pattern: SubjectPattern, value: T) -> Option<T> {
+        self.routes.insert(pattern, value)
+    }
+
+    pub fn remove(&mut self, pattern: &SubjectPattern) -> Option<T> {
+        self.routes.remove(pattern)
+    }
+
+    /// Returns a mutable reference to the value of a pattern, inserting the default value if the
+    /// pattern has no route yet.
+    pub fn entry_or_default(&mut self, pattern: SubjectPattern) -> &mut T
+    where
+        T: Default,
+    {
+        self.routes.entry(pattern).or_default()
+    }
+
+    /// Returns the value of the most specific pattern that matches the subject.
+    pub fn get(&self, service: ServiceId, object: ObjectId, action: ActionId) -> Option<&T> {
+        if self.routes.is_empty() {
+            return None;
+        }
+        SubjectPattern::matching(service, object, action)
+            .find_map(|pattern| self.routes.get(&pattern))
+    }
+
+    pub fn get_subject(&self, subject: &session::Subject) -> Option<&T> {
+        self.get(subject.service(), subject.object(), subject.action())
+    }
+
+    pub fn contains_subject(&self, subject: &session::Subject) -> bool {
+        self.get_subject(subject).is_some()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&SubjectPattern, &T)> {
+        self.routes.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.routes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+}
+
+impl<T> Default for SubjectRouter<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> FromIterator<(SubjectPattern, T)> for SubjectRouter<T> {
+    fn from_iter<I: IntoIterator<Item = (SubjectPattern, T)>>(iter: I) -> Self {
+        Self {
+            routes: iter.into_iter().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(service: u32, object: u32, action: u32) -> (ServiceId, ObjectId, ActionId) {
+        (
+            ServiceId::new(service),
+            ObjectId::new(object),
+            ActionId::new(action),
+        )
+    }
+
+    #[test]
+    fn test_subject_pattern_matches() {
+        let (service, object, action) = ids(42, 1, 100);
+        assert!(SubjectPattern::any().matches(service, object, action));
+        assert!(SubjectPattern::exact(service, object, action).matches(service, object, action));
+        assert!(SubjectPattern::any().with_service(service).matches(
+            service,
+            ObjectId::new(2),
+            ActionId::new(101)
+        ));
+        assert!(!SubjectPattern::any().with_service(service).matches(
+            ServiceId::new(43),
+            object,
+            action
+        ));
+        assert!(!SubjectPattern::any()
+            .with_object(object)
+            .with_action(action)
+            .matches(service, object, ActionId::new(101)));
+    }
+
+    #[test]
+    fn test_subject_router_get_most_specific() {
+        let (service, object, action) = ids(42, 1, 100);
+        let router: SubjectRouter<_> = [
+            (SubjectPattern::any(), "any"),
+            (SubjectPattern::any().with_action(action), "action"),
+            (SubjectPattern::any().with_service(service), "service"),
+            (
+                SubjectPattern::any()
+                    .with_service(service)
+                    .with_action(action),
+                "service action",
+            ),
+            (SubjectPattern::exact(service, object, action), "exact"),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(router.get(service, object, action), Some(&"exact"));
+        assert_eq!(
+            router.get(service, ObjectId::new(2), action),
+            Some(&"service action")
+        );
+        assert_eq!(
+            router.get(service, object, ActionId::new(101)),
+            Some(&"service")
+        );
+        assert_eq!(
+            router.get(ServiceId::new(43), object, action),
+            Some(&"action")
+        );
+        assert_eq!(
+            router.get(ServiceId::new(43), object, ActionId::new(101)),
+            Some(&"any")
+        );
+    }
+
+    #[test]
+    fn test_subject_router_insert_remove() {
+        let (service, object, action) = ids(42, 1, 100);
+        let mut router = SubjectRouter::new();
+        assert!(router.is_empty());
+        assert_eq!(router.get(service, object, action), None);
+
+        let any_action = SubjectPattern::any().with_service(service);
+        assert_eq!(router.insert(any_action, 1), None);
+        assert_eq!(router.insert(any_action, 2), Some(1));
+        assert_eq!(router.len(), 1);
+        assert_eq!(router.get(service, object, action), Some(&2));
+        assert_eq!(router.get(ServiceId::new(43), object, action), None);
+
+        assert_eq!(router.remove(&any_action), Some(2));
+        assert_eq!(router.get(service, object, action), None);
+    }
+}
@@ -0,0 +1,223 @@
+use crate::format;
+use bytes::{Buf, BufMut, BytesMut};
+use std::{fmt::Write, str::FromStr};
+
+/// The context of a distributed trace, in the format of the W3C `traceparent` header.
+///
+/// A trace context may be attached to a call, see [`Call::with_trace_context`], so that the spans of
+/// the service that serves the call belong to the trace of the caller. The context of a call served
+/// by a session is recorded in the `traceparent` field of the `service_call` span, in which the
+/// service is called.
+///
+/// # Encoding
+///
+/// This is an extension of this implementation, that other implementations may support as
+/// follows:
+/// - The ends of a session that support it advertise the `TraceContext` capability. A trace
+///   context is only sent to a remote that advertised it.
+/// - The message of a call with a trace context has the flag `0b00001000`.
+/// - The content of such a message starts with the trace context, as a string in the `qi` format
+///   (its size as a little endian `u32`, then its bytes), followed by the arguments of the call.
+/// - The string is a `traceparent` of version `00`: `00-{trace-id}-{parent-id}-{trace-flags}`,
+///   where the fields are written in lowercase hexadecimal, on 32, 16 and 2 digits.
+///
+/// [`Call::with_trace_context`]: crate::session::Call::with_trace_context
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TraceContext {
+    trace_id: [u8; 16],
+    parent_id: [u8; 8],
+    flags: u8,
+}
+
+impl TraceContext {
+    const VERSION: &'static str = "00";
+    const SAMPLED: u8 = 0b0000_0001;
+
+    /// Creates a context, or nothing if the trace id or the parent id is all zeros, which the
+    /// format forbids.
+    pub fn new(trace_id: [u8; 16], parent_id: [u8; 8], flags: u8) -> Option<Self> {
+        let is_zero = |bytes: &[u8]| bytes.iter().all(|byte| *byte == 0);
+        if is_zero(&trace_id) || is_zero(&parent_id) {
+            return None;
+        }
+        Some(Self {
+            trace_id,
+            parent_id,
+            flags,
+        })
+    }
+
+    pub fn trace_id(&self) -> [u8; 16] {
+        self.trace_id
+    }
+
+    /// The id of the span of the caller.
+    pub fn parent_id(&self) -> [u8; 8] {
+        self.parent_id
+    }
+
+    pub fn flags(&self) -> u8 {
+        self.flags
+    }
+
+    /// Returns true if the caller may have recorded its part of the trace.
+    pub fn is_sampled(&self) -> bool {
+        self.flags & Self::SAMPLED != 0
+    }
+
+    /// Writes the context at the start of the content of a call.
+    pub(crate) fn prefix_content(&self, content: &format::Value) -> format::Value {
+        let traceparent = self.to_string();
+        let mut buf = BytesMut::with_capacity(
+            std::mem::size_of::<u32>() + traceparent.len() + content.as_bytes().len(),
+        );
+        // The size of a trace parent always fits.
+        buf.put_u32_le(traceparent.len() as u32);
+        buf.put_slice(traceparent.as_bytes());
+        buf.put_slice(content.as_bytes());
+        format::Value::from_bytes(buf.freeze())
+    }
+
+    /// Reads the context at the start of the content of a call, and returns it with the rest of
+    /// the content.
+    pub(crate) fn split_content(
+        content: format::Value,
+    ) -> Result<(Self, format::Value), ParseTraceContextError> {
+        let mut bytes = content.to_bytes();
+        if bytes.len() < std::mem::size_of::<u32>() {
+            return Err(ParseTraceContextError::Truncated);
+        }
+        let size = usize::try_from(bytes.get_u32_le())
+            .map_err(|_err| ParseTraceContextError::Truncated)?;
+        if bytes.len() < size {
+            return Err(ParseTraceContextError::Truncated);
+        }
+        let traceparent = bytes.split_to(size);
+        let context = std::str::from_utf8(&traceparent)
+            .map_err(|_err| ParseTraceContextError::Invalid)?
+            .parse()?;
+        Ok((context, format::Value::from_bytes(bytes)))
+    }
+}
+
+impl std::fmt::Display for TraceContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(Self::VERSION)?;
+        f.write_char('-')?;
+        write_hex(f, &self.trace_id)?;
+        f.write_char('-')?;
+        write_hex(f, &self.parent_id)?;
+        f.write_char('-')?;
+        write_hex(f, &[self.flags])
+    }
+}
+
+impl FromStr for TraceContext {
+    type Err = ParseTraceContextError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fields = s.split('-');
+        let mut next_field = || fields.next().ok_or(ParseTraceContextError::Invalid);
+        let version = next_field()?;
+        parse_hex::<1>(version)?;
+        if version != Self::VERSION {
+            return Err(ParseTraceContextError::UnsupportedVersion(
+                version.to_owned(),
+            ));
+        }
+        let trace_id = parse_hex(next_field()?)?;
+        let parent_id = parse_hex(next_field()?)?;
+        let [flags] = parse_hex(next_field()?)?;
+        if fields.next().is_some() {
+            return Err(ParseTraceContextError::Invalid);
+        }
+        Self::new(trace_id, parent_id, flags).ok_or(ParseTraceContextError::Invalid)
+    }
+}
+
+fn write_hex(f: &mut std::fmt::Formatter<'_>, bytes: &[u8]) -> std::fmt::Result {
+    bytes.iter().try_for_each(|byte| write!(f, "{byte:02x}"))
+}
+
+fn parse_hex<const N: usize>(field: &str) -> Result<[u8; N], ParseTraceContextError> {
+    let digits = field.as_bytes();
+    if digits.len() != N * 2 {
+        return Err(ParseTraceContextError::Invalid);
+    }
+    let digit = |digit: u8| match digit {
+        b'0'..=b'9' => Ok(digit - b'0'),
+        b'a'..=b'f' => Ok(digit - b'a' + 10),
+        _ => Err(ParseTraceContextError::Invalid),
+    };
+    let mut bytes = [0; N];
+    for (byte, pair) in bytes.iter_mut().zip(digits.chunks_exact(2)) {
+        *byte = digit(pair[0])? << 4 | digit(pair[1])?;
+    }
+    Ok(bytes)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ParseTraceContextError {
+    #[error("the content of the call is too short for its trace context")]
+    Truncated,
+
+    #[error("the trace context is not a valid \"traceparent\"")]
+    Invalid,
+
+    #[error("the version \"{0}\" of the trace context is not supported")]
+    UnsupportedVersion(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRACEPARENT: &str = "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01";
+
+    #[test]
+    fn test_trace_context_to_from_str() {
+        let context: TraceContext = TRACEPARENT.parse().unwrap();
+        assert_eq!(context.trace_id()[..2], [0x0a, 0xf7]);
+        assert_eq!(context.parent_id()[7], 0x31);
+        assert!(context.is_sampled());
+        assert_eq!(context.to_string(), TRACEPARENT);
+
+        for invalid in [
+            "",
+            "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331",
+            "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01-00",
+            "00-0AF7651916CD43DD8448EB211C80319C-b7ad6b7169203331-01",
+            "00-00000000000000000000000000000000-b7ad6b7169203331-01",
+            "00-0af7651916cd43dd8448eb211c80319c-b7ad6b716920333-01",
+        ] {
+            assert_eq!(
+                invalid.parse::<TraceContext>(),
+                Err(ParseTraceContextError::Invalid),
+                "{invalid}"
+            );
+        }
+        assert_eq!(
+            "01-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01".parse::<TraceContext>(),
+            Err(ParseTraceContextError::UnsupportedVersion("01".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_trace_context_prefix_split_content() {
+        let context: TraceContext = TRACEPARENT.parse().unwrap();
+        let content = format::to_value(&(1i32, "a")).unwrap();
+        let prefixed = context.prefix_content(&content);
+        // The trace context is a string in the `qi` format.
+        let (traceparent, _args): (String, (i32, String)) = prefixed.to_deserializable().unwrap();
+        assert_eq!(traceparent, TRACEPARENT);
+        assert_eq!(
+            TraceContext::split_content(prefixed).unwrap(),
+            (context, content)
+        );
+
+        assert_eq!(
+            TraceContext::split_content(format::Value::from([55, 0, 0, 0, b'0'])),
+            Err(ParseTraceContextError::Truncated)
+        );
+    }
+}
@@ -31,7 +31,6 @@
     clippy::mod_module_files,
     clippy::str_to_string,
     clippy::string_slice,
-    clippy::string_to_string,
     clippy::todo,
     clippy::try_err,
     clippy::unimplemented,
@@ -39,6 +38,8 @@
     clippy::unneeded_field_pattern,
     clippy::use_debug
 )]
+// Errors keep the failures of calls by value, which makes them large.
+#![allow(clippy::result_large_err)]
 // Deny warnings in doc test.
 #![doc(test(attr(deny(warnings))))]
 #![doc = include_str!("../README.md")]
@@ -51,9 +52,12 @@ pub mod transport;
 
 pub use iri_string::types::UriString as Uri;
 pub use messaging::CallResult;
-pub use node::Node;
-pub use object::Object;
+pub use node::{Node, NodeBuilder};
+pub use object::{Object, ServedObject};
 use qi_format as format;
 use qi_messaging as messaging;
 use qi_types as value;
-pub use service_directory::{ServiceDirectory, ServiceInfo};
+pub use service_directory::{ServiceDirectory, ServiceEvent, ServiceInfo};
+pub use transport::Endpoints;
+#[cfg(feature = "server")]
+pub use transport::ServeConfig;
@@ -1,52 +1,1074 @@
+mod diagnostics;
+mod io_runtime;
+mod meta_object_cache;
+mod sessions;
+
+#[cfg(feature = "server")]
+use crate::transport::{Listener, ServeConfig};
 use crate::{
-    messaging::{self, session, CallResult},
-    object,
+    format,
+    messaging::{self, session, CallResult, GetSubject},
+    object::{self, ServedObject},
     service_directory::{self, BoxServiceDirectory},
-    transport::{self, Transport},
-    Uri,
+    signal,
+    transport::{self, Endpoints, Transport},
+    value::{
+        self,
+        object::{ActionId, MetaObject, ObjectId, ServiceId},
+        ValuePath,
+    },
+    ServiceInfo, Uri,
+};
+use diagnostics::LastError;
+pub use diagnostics::{Diagnostics, SessionDiagnostics, SessionState};
+use futures::{
+    future::{self, BoxFuture, Either},
+    FutureExt,
+};
+use io_runtime::IoRuntime;
+pub use io_runtime::IoRuntimeShutdownError;
+pub use meta_object_cache::MetaObjectCache;
+use sessions::CloseSession;
+pub use sessions::SessionHandle;
+#[cfg(feature = "server")]
+use sessions::{ListenerSession, ListenerSessions};
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::Duration,
 };
-use futures::future::BoxFuture;
-use tokio::spawn;
+#[cfg(feature = "server")]
+use tokio::sync::{mpsc, watch};
+use tokio::{spawn, sync::oneshot, task::JoinHandle};
 use tracing::{instrument, trace, trace_span, Instrument};
 
 pub struct Node {
+    session: session::Client,
     service_directory: BoxServiceDirectory<'static>,
+    // Services registered by this node, by name. The id is unknown while the registration is
+    // pending.
+    registered_services: Mutex<HashMap<String, Option<ServiceId>>>,
+    // The main objects of the registered services, with the subscriptions of remote clients to
+    // their signals, shared with the messaging services of the sessions that serve them.
+    served_services: ServedServices,
+    // The error that terminated the session, recorded by its dispatch task.
+    session_error: LastError,
+    meta_object_cache: MetaObjectCache,
+    // The timeout of the requests of the meta objects of services, see
+    // `NodeBuilder::meta_object_timeout`.
+    meta_object_timeout: Duration,
+    // The endpoints advertised along with the registered services, see `NodeBuilder::serve`.
+    endpoints: Vec<Uri>,
+    // Accepts the connections to the services of the node and serves their sessions, see
+    // `NodeBuilder::serve`.
+    #[cfg(feature = "server")]
+    serve_task: Option<ServeTask>,
+    // Closes the session, see `Node::shutdown` and `SessionHandle::disconnect`.
+    close_session: CloseSession,
+    // The dispatch task of the session, which terminates with it.
+    dispatch_task: JoinHandle<()>,
+    // The parts of the capabilities that are redacted from the diagnostics, see
+    // `NodeBuilder::redact_diagnostics`.
+    diagnostics_redaction: Arc<[ValuePath]>,
+    // Kept so that a dedicated runtime runs as long as the node.
+    _io_runtime: Option<IoRuntime>,
 }
 
+/// By convention, the id of the service of the main object served by a peer, over a direct
+/// connection without a service directory, see [`Node::serve_peer_service`].
+pub const PEER_SERVICE_ID: ServiceId = ServiceId::new(1);
+
 impl Node {
-    #[instrument(level = "trace", skip_all, ret)]
+    /// Returns a builder of a node, to set how it connects.
+    pub fn builder() -> NodeBuilder {
+        NodeBuilder::new()
+    }
+
     pub async fn to_namespace(uri: Uri) -> CallResult<Self, ToNamespaceError> {
-        let transport = Transport::connect(uri)
-            .await
-            .map_err(ToNamespaceError::TransportFromUri)?;
-        let service = MessagingService;
-        let (session_client, session) = session::connect(transport, service);
-
-        spawn(
-            async move {
-                if let Err(err) = session.await {
-                    trace!(
-                        error = &err as &dyn std::error::Error,
-                        "session terminated with an error"
-                    )
+        NodeBuilder::new().to_namespace(uri).await
+    }
+
+    /// Connects to a namespace that is reachable at several addresses.
+    ///
+    /// The endpoints remember the address that worked, so that passing them again, for instance
+    /// to reconnect, tries it first.
+    pub async fn connect_to_space(endpoints: &Endpoints) -> CallResult<Self, ToNamespaceError> {
+        NodeBuilder::new().connect_to_space(endpoints).await
+    }
+
+    /// Connects directly to a peer that serves objects without a service directory.
+    ///
+    /// Services of the peer cannot be looked up by name, they are accessed by an agreed id with
+    /// [`Node::service_with_id`], by convention [`PEER_SERVICE_ID`]. The service directory of the
+    /// node is unavailable. The peer accepts the connection with [`NodeBuilder::accept_peer`].
+    pub async fn to_peer(uri: Uri) -> Result<Self, ToPeerError> {
+        NodeBuilder::new().to_peer(uri).await
+    }
+
+    pub fn service_directory(&self) -> &BoxServiceDirectory<'static> {
+        &self.service_directory
+    }
+
+    /// The configuration of the session of the node, which may be updated while the node is
+    /// connected.
+    pub fn config(&self) -> &session::SharedConfig {
+        self.session.config()
+    }
+
+    /// Shuts the node down, so that the other participants of the namespace do not see its
+    /// services anymore.
+    ///
+    /// The calls received by the node are refused from then on. The services registered by the
+    /// node are unregistered, see [`Node::unregister_service`], then the session of the node and
+    /// the sessions that it serves are closed, and the shutdown waits for them to terminate. The
+    /// timeout covers the whole shutdown: if the services are not unregistered in time, the
+    /// sessions are closed anyway.
+    ///
+    /// If a service cannot be unregistered, the other services are still unregistered, and the
+    /// first error is returned.
+    #[instrument(level = "trace", skip(self), ret)]
+    pub async fn shutdown(mut self, timeout: Duration) -> Result<(), ShutdownError> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        self.config()
+            .update(|config| config.with_incoming_calls_refused(true));
+        let result = match tokio::time::timeout_at(deadline, self.unregister_services()).await {
+            Ok(result) => result,
+            Err(_elapsed) => Err(ShutdownError::TimedOut(timeout)),
+        };
+        match tokio::time::timeout_at(deadline, self.close_sessions()).await {
+            Ok(()) => result,
+            Err(_elapsed) => result.and(Err(ShutdownError::TimedOut(timeout))),
+        }
+    }
+
+    /// Closes the session of the node and the sessions that it serves, and waits for them to
+    /// terminate.
+    async fn close_sessions(&mut self) {
+        self.close_session.close();
+        let dispatch_task = &mut self.dispatch_task;
+        let session_terminated = async move {
+            // The task only fails if it panicked, in which case the session is terminated too.
+            let _res = dispatch_task.await;
+        };
+        #[cfg(feature = "server")]
+        let session_terminated = {
+            let serve_task = self.serve_task.as_mut();
+            future::join(session_terminated, async move {
+                if let Some(serve_task) = serve_task {
+                    serve_task.close().await;
+                }
+            })
+        };
+        session_terminated.await;
+    }
+
+    async fn unregister_services(&self) -> Result<(), ShutdownError> {
+        // Services of which the registration is pending have no id to unregister yet, the
+        // service directory removes them when the session is closed.
+        let names: Vec<_> = self
+            .registered_services()
+            .iter()
+            .filter_map(|(name, id)| id.map(|_id| name.clone()))
+            .collect();
+        let mut result = Ok(());
+        for name in names {
+            if let Err(error) = self.unregister_service(&name).await {
+                trace!(
+                    service = %name,
+                    error = &error as &dyn std::error::Error,
+                    "failed to unregister a service during the shutdown"
+                );
+                if result.is_ok() {
+                    result = Err(ShutdownError::UnregisterService { name, error });
                 }
             }
-            .instrument(trace_span!(parent: None, "dispatch")),
-        );
+        }
+        result
+    }
+
+    /// Returns a snapshot of the state of the connections of the node.
+    ///
+    /// This is meant to be exposed by the application, for instance on a health endpoint, rather
+    /// than scraping the logs of the node. Sensitive capabilities may be redacted, see
+    /// [`NodeBuilder::redact_diagnostics`].
+    ///
+    /// The sessions are the ones of [`Node::sessions`], in the same order.
+    pub fn diagnostics(&self) -> Diagnostics {
+        #[cfg_attr(not(feature = "server"), allow(unused_mut))]
+        let mut sessions = vec![SessionDiagnostics::new(
+            &self.session,
+            &self.session_error,
+            &self.diagnostics_redaction,
+        )];
+        #[cfg(feature = "server")]
+        sessions.extend(self.listener_sessions().into_iter().map(|session| {
+            SessionDiagnostics::new(
+                &session.client,
+                &session.last_error,
+                &self.diagnostics_redaction,
+            )
+        }));
+        Diagnostics::new(sessions)
+    }
+
+    /// Returns handles to the sessions of the node, to introspect them or to close them.
+    ///
+    /// The first session is the one to the namespace or to the peer that the node connected to.
+    /// It is followed by the sessions served by the listener of the node, if any, see
+    /// [`NodeBuilder::serve`], that are not closed. The node serves the services that it
+    /// registered over all of them.
+    pub fn sessions(&self) -> Vec<SessionHandle> {
+        let services: Vec<_> = self
+            .registered_services()
+            .iter()
+            .filter_map(|(name, id)| id.map(|id| (name.clone(), id)))
+            .collect();
+        #[cfg_attr(not(feature = "server"), allow(unused_mut))]
+        let mut sessions = vec![SessionHandle::new(
+            self.session.clone(),
+            self.session_error.clone(),
+            self.close_session.clone(),
+            Arc::clone(&self.diagnostics_redaction),
+            services.clone(),
+        )];
+        #[cfg(feature = "server")]
+        sessions.extend(self.listener_sessions().into_iter().map(|session| {
+            SessionHandle::new(
+                session.client,
+                session.last_error,
+                session.close,
+                Arc::clone(&self.diagnostics_redaction),
+                services.clone(),
+            )
+        }));
+        sessions
+    }
+
+    #[cfg(feature = "server")]
+    fn listener_sessions(&self) -> Vec<ListenerSession> {
+        self.serve_task
+            .as_ref()
+            .map(|serve_task| serve_task.sessions.list())
+            .unwrap_or_default()
+    }
+
+    /// The cache of the meta objects of the services of the node, see
+    /// [`NodeBuilder::meta_object_cache`].
+    pub fn meta_object_cache(&self) -> &MetaObjectCache {
+        &self.meta_object_cache
+    }
+
+    /// Returns a client of the main object of the service with this name.
+    ///
+    /// The meta object of the object is taken from the cache of the node if it is there and if the
+    /// session of the node shares the `MetaObjectCache` capability, and is cached otherwise, see
+    /// [`MetaObjectCache`].
+    #[instrument(level = "trace", skip(self), ret)]
+    pub async fn service(&self, name: &str) -> CallResult<object::Client, ServiceError> {
+        let info = self
+            .service_directory
+            .resolve(name)
+            .await
+            .map_err(|err| err.map_err(ServiceError::ServiceDirectory))?;
+        let cached_meta_object = info
+            .object_uid
+            .filter(|_uid| self.shares_meta_object_cache())
+            .and_then(|uid| self.meta_object_cache.get(name, uid));
+        let is_cached = cached_meta_object.is_some();
+        let object = object::Client::connect_with_meta_object(
+            self.session.clone(),
+            info.service_id,
+            object::client::SERVICE_MAIN_OBJECT,
+            cached_meta_object,
+            self.meta_object_timeout,
+        )
+        .await
+        .map_err(|err| err.map_err(ServiceError::ConnectObject))?;
+        if let (false, Some(uid), Some(meta_object)) =
+            (is_cached, info.object_uid, object.meta_object())
+        {
+            self.meta_object_cache
+                .insert(name, uid, meta_object.clone());
+        }
+        Ok(object)
+    }
+
+    /// Whether the session of the node shares the capability of caching meta objects, see
+    /// [`MetaObjectCache`].
+    fn shares_meta_object_cache(&self) -> bool {
+        self.session
+            .capabilities()
+            .capability(messaging::Capability::MetaObjectCache)
+            .unwrap_or_default()
+    }
+
+    /// Returns a client of the main object of the service with this id, without looking it up in
+    /// the service directory.
+    #[instrument(level = "trace", skip(self), ret)]
+    pub async fn service_with_id(
+        &self,
+        id: ServiceId,
+    ) -> CallResult<object::Client, object::client::ConnectError> {
+        object::Client::connect_with_meta_object(
+            self.session.clone(),
+            id,
+            object::client::SERVICE_MAIN_OBJECT,
+            None,
+            self.meta_object_timeout,
+        )
+        .await
+    }
+
+    /// Registers a service in the service directory, and returns the id that the directory
+    /// assigned to it.
+    ///
+    /// The object is served as the main object of the service, until the service is unregistered.
+    pub async fn register_service<O>(
+        &self,
+        name: &str,
+        object: O,
+    ) -> CallResult<ServiceId, RegisterServiceError>
+    where
+        O: ServedObject + 'static,
+    {
+        self.register(name, None, Arc::new(object)).await
+    }
+
+    /// Registers a service in the service directory with a fixed id, see
+    /// [`Node::register_service`].
+    ///
+    /// This is required to reimplement a service that is known by its id. The registration fails
+    /// if this node already registered a service with this name or id, or if the directory does
+    /// not accept the id.
+    pub async fn register_service_with_id<O>(
+        &self,
+        name: &str,
+        id: ServiceId,
+        object: O,
+    ) -> CallResult<(), RegisterServiceError>
+    where
+        O: ServedObject + 'static,
+    {
+        if is_reserved_service_id(id) {
+            return Err(RegisterServiceError::ReservedId(id).into());
+        }
+        self.register(name, Some(id), Arc::new(object)).await?;
+        Ok(())
+    }
+
+    async fn register(
+        &self,
+        name: &str,
+        id: Option<ServiceId>,
+        object: Arc<dyn ServedObject>,
+    ) -> CallResult<ServiceId, RegisterServiceError> {
+        self.reserve_service(name, id)?;
+        let info = ServiceInfo {
+            name: name.to_owned(),
+            service_id: id.unwrap_or_default(),
+            process_id: std::process::id(),
+            endpoints: self.endpoints.clone(),
+            ..Default::default()
+        };
+        let assigned_id = match self.service_directory.register_service(info).await {
+            Ok(assigned_id) => assigned_id,
+            Err(err) => {
+                self.registered_services().remove(name);
+                return Err(err.map_err(RegisterServiceError::ServiceDirectory));
+            }
+        };
+        if let Some(requested_id) = id.filter(|&id| id != assigned_id) {
+            // The registration under another id is not wanted, it is canceled.
+            if let Err(err) = self.service_directory.unregister_service(assigned_id).await {
+                trace!(
+                    error = &err as &dyn std::error::Error,
+                    "failed to unregister a service that was assigned another id"
+                );
+            }
+            self.registered_services().remove(name);
+            return Err(RegisterServiceError::IdRejected {
+                requested: requested_id,
+                assigned: assigned_id,
+            }
+            .into());
+        }
+        self.registered_services()
+            .insert(name.to_owned(), Some(assigned_id));
+        if let Some(service_object) =
+            session::subject::ServiceObject::new(assigned_id, object::client::SERVICE_MAIN_OBJECT)
+        {
+            let subscriptions = signal::SubscriptionSet::new(self.session.clone(), service_object);
+            let service = ServedService {
+                object,
+                subscriptions: Arc::new(subscriptions),
+            };
+            self.served_services().insert(assigned_id, service);
+        }
+        Ok(assigned_id)
+    }
+
+    /// Serves an object as the main object of a service with this id, without registering it in
+    /// a service directory.
+    ///
+    /// This is how the objects of a node are served to a peer over a direct connection, see
+    /// [`Node::to_peer`] and [`NodeBuilder::accept_peer`], by convention with the id
+    /// [`PEER_SERVICE_ID`]. The service is served as long as the node, and fails if this node
+    /// already serves a service with this id.
+    pub fn serve_peer_service<O>(
+        &self,
+        id: ServiceId,
+        object: O,
+    ) -> Result<(), RegisterServiceError>
+    where
+        O: ServedObject + 'static,
+    {
+        let service_object =
+            session::subject::ServiceObject::new(id, object::client::SERVICE_MAIN_OBJECT)
+                .ok_or(RegisterServiceError::ReservedId(id))?;
+        if self
+            .registered_services()
+            .values()
+            .any(|&registered| registered == Some(id))
+        {
+            return Err(RegisterServiceError::IdAlreadyRegistered(id));
+        }
+        let mut served_services = self.served_services();
+        if served_services.contains_key(&id) {
+            return Err(RegisterServiceError::IdAlreadyRegistered(id));
+        }
+        let subscriptions = signal::SubscriptionSet::new(self.session.clone(), service_object);
+        let service = ServedService {
+            object: Arc::new(object),
+            subscriptions: Arc::new(subscriptions),
+        };
+        served_services.insert(id, service);
+        Ok(())
+    }
+
+    /// Returns the subscriptions to the signals of the main object of a service registered by
+    /// this node.
+    pub fn subscriptions(&self, id: ServiceId) -> Option<Arc<signal::SubscriptionSet>> {
+        self.served_services()
+            .get(&id)
+            .map(|service| Arc::clone(&service.subscriptions))
+    }
+
+    /// Unregisters a service that this node registered.
+    ///
+    /// The subscriptions to the signals of the service are closed first: the events being emitted
+    /// are sent, then no event of the service is sent anymore. The service is then unregistered
+    /// from the service directory, which notifies its own subscribers with its `serviceRemoved`
+    /// signal. This is the last indication that remote clients receive about the service. Their
+    /// later calls to `registerEvent` and `unregisterEvent` on the object of the service fail, as
+    /// it is not served by this node anymore.
+    ///
+    /// The subscriptions stay closed even if the service directory fails to unregister the
+    /// service, in which case the name of the service stays reserved by this node.
+    pub async fn unregister_service(&self, name: &str) -> CallResult<(), UnregisterServiceError> {
+        let id = match self.registered_services().get(name) {
+            Some(Some(id)) => *id,
+            Some(None) => {
+                return Err(UnregisterServiceError::RegistrationPending(name.to_owned()).into())
+            }
+            None => return Err(UnregisterServiceError::NotRegistered(name.to_owned()).into()),
+        };
+        let service = self.served_services().remove(&id);
+        if let Some(ServedService { subscriptions, .. }) = service {
+            let links = subscriptions.close().await;
+            trace!(
+                service = %id,
+                subscribers = links.len(),
+                "closed the subscriptions to the signals of the service"
+            );
+        }
+        self.service_directory
+            .unregister_service(id)
+            .await
+            .map_err(|err| err.map_err(UnregisterServiceError::ServiceDirectory))?;
+        self.registered_services().remove(name);
+        Ok(())
+    }
+
+    /// Checks that the service does not collide with the services registered by this node, and
+    /// reserves its name and id until its registration terminates.
+    fn reserve_service(
+        &self,
+        name: &str,
+        id: Option<ServiceId>,
+    ) -> Result<(), RegisterServiceError> {
+        reserve_service(&mut self.registered_services(), name, id)
+    }
+
+    fn registered_services(&self) -> MutexGuard<'_, HashMap<String, Option<ServiceId>>> {
+        self.registered_services
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn served_services(&self) -> MutexGuard<'_, HashMap<ServiceId, ServedService>> {
+        self.served_services
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// A builder of a [`Node`], to set how it connects.
+///
+/// By default, the input and output of the sessions of the node are driven by tasks of the runtime
+/// on which the node connects.
+#[derive(Debug, Clone)]
+pub struct NodeBuilder {
+    io_runtime: Option<IoRuntime>,
+    meta_object_cache: MetaObjectCache,
+    meta_object_timeout: Duration,
+    #[cfg(feature = "server")]
+    serve_config: Option<ServeConfig>,
+    diagnostics_redaction: Vec<ValuePath>,
+}
+
+impl Default for NodeBuilder {
+    fn default() -> Self {
+        Self {
+            io_runtime: None,
+            meta_object_cache: MetaObjectCache::default(),
+            meta_object_timeout: object::client::DEFAULT_META_OBJECT_TIMEOUT,
+            #[cfg(feature = "server")]
+            serve_config: None,
+            diagnostics_redaction: Vec::new(),
+        }
+    }
+}
+
+impl NodeBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Drives the input and output of the sessions of the node on the runtime of this handle.
+    ///
+    /// Reading and writing messages are then isolated from the work of the application, so that a
+    /// busy application runtime does not delay calls and events. The cost is that messages are
+    /// passed between the runtimes, which adds a wake up of a thread of the other runtime for each
+    /// of them. The runtime must run as long as the node is used, otherwise its sessions stall.
+    pub fn io_runtime(mut self, handle: tokio::runtime::Handle) -> Self {
+        self.io_runtime = Some(IoRuntime::from_handle(handle));
+        self
+    }
+
+    /// Drives the input and output of the sessions of the node on a current-thread runtime that
+    /// runs on a thread dedicated to the node, and stops when the node is dropped.
+    ///
+    /// This is the same as [`NodeBuilder::io_runtime`] with a runtime that only runs the IO of the
+    /// node, which therefore does not depend on the load of any other runtime. Fails if the thread
+    /// or the runtime cannot be created.
+    pub fn dedicated_io_runtime(mut self) -> std::io::Result<Self> {
+        self.io_runtime = Some(IoRuntime::dedicated()?);
+        Ok(self)
+    }
 
-        let session_client = session_client
+    /// Shares a cache of meta objects with the node.
+    ///
+    /// Each node has its own cache by default. Sharing a cache between the nodes that successively
+    /// connect to a namespace, for instance to reconnect, saves requesting again the meta objects
+    /// of the services that did not restart in the meantime, on sessions that share the
+    /// `MetaObjectCache` capability.
+    pub fn meta_object_cache(mut self, cache: MetaObjectCache) -> Self {
+        self.meta_object_cache = cache;
+        self
+    }
+
+    /// Sets the maximum duration of the requests of the meta objects of the services that the node
+    /// connects to, which is [`DEFAULT_META_OBJECT_TIMEOUT`] by default.
+    ///
+    /// The client of a service whose meta object is not received in time is still usable, see
+    /// [`object::Client`].
+    ///
+    /// [`DEFAULT_META_OBJECT_TIMEOUT`]: object::client::DEFAULT_META_OBJECT_TIMEOUT
+    pub fn meta_object_timeout(mut self, timeout: Duration) -> Self {
+        self.meta_object_timeout = timeout;
+        self
+    }
+
+    /// Listens for connections to the services that the node registers, and advertises them with
+    /// the endpoints derived from the configuration.
+    ///
+    /// This only applies to nodes connected to a namespace, the endpoints of the services being
+    /// registered in its service directory. By default, a node does not listen, and its services
+    /// are only reachable through its session to the namespace.
+    #[cfg(feature = "server")]
+    pub fn serve(mut self, config: ServeConfig) -> Self {
+        self.serve_config = Some(config);
+        self
+    }
+
+    /// Redacts a part of the capabilities of the sessions in the diagnostics of the node, see
+    /// [`Node::diagnostics`].
+    ///
+    /// The capabilities are redacted as a map of their values by name, the first segment of the
+    /// path being the name of a capability, see [`Value::redact`](crate::value::Value::redact).
+    /// This is meant for capabilities that carry sensitive values, such as authentication tokens.
+    pub fn redact_diagnostics(mut self, path: ValuePath) -> Self {
+        self.diagnostics_redaction.push(path);
+        self
+    }
+
+    #[instrument(level = "trace", skip_all, ret)]
+    pub async fn to_namespace(self, uri: Uri) -> CallResult<Node, ToNamespaceError> {
+        let session_error = LastError::default();
+        let dispatch_error = session_error.clone();
+        let served_services = ServedServices::default();
+        let service = MessagingService::new(Arc::clone(&served_services));
+        let (close_session, session_closed) = oneshot::channel();
+        let (session_client, dispatch_task) = self
+            .run_io(async move {
+                let transport = Transport::connect(uri)
+                    .await
+                    .map_err(ToNamespaceError::TransportFromUri)?;
+                connect_session(transport, service, dispatch_error, session_closed)
+                    .await
+                    .map_err(ToNamespaceError::SessionConnect)
+            })
             .await
-            .map_err(ToNamespaceError::SessionConnect)?;
-        let sd_client = service_directory::Client::connect(session_client)
+            .map_err(ToNamespaceError::IoRuntime)??;
+        self.namespace_node(
+            session_client,
+            dispatch_task,
+            session_error,
+            close_session,
+            served_services,
+        )
+        .await
+    }
+
+    /// Connects to a namespace that is reachable at several addresses, see
+    /// [`Node::connect_to_space`].
+    #[instrument(level = "trace", skip_all, ret)]
+    pub async fn connect_to_space(
+        self,
+        endpoints: &Endpoints,
+    ) -> CallResult<Node, ToNamespaceError> {
+        let endpoints = endpoints.clone();
+        let session_error = LastError::default();
+        let dispatch_error = session_error.clone();
+        let served_services = ServedServices::default();
+        let service = MessagingService::new(Arc::clone(&served_services));
+        let (close_session, session_closed) = oneshot::channel();
+        let (session_client, dispatch_task) = self
+            .run_io(async move {
+                let transport = Transport::connect_endpoints(&endpoints)
+                    .await
+                    .map_err(ToNamespaceError::Endpoints)?;
+                connect_session(transport, service, dispatch_error, session_closed)
+                    .await
+                    .map_err(ToNamespaceError::SessionConnect)
+            })
+            .await
+            .map_err(ToNamespaceError::IoRuntime)??;
+        self.namespace_node(
+            session_client,
+            dispatch_task,
+            session_error,
+            close_session,
+            served_services,
+        )
+        .await
+    }
+
+    /// Connects directly to a peer that serves objects without a service directory, see
+    /// [`Node::to_peer`].
+    #[instrument(level = "trace", skip_all, ret)]
+    pub async fn to_peer(self, uri: Uri) -> Result<Node, ToPeerError> {
+        let session_error = LastError::default();
+        let dispatch_error = session_error.clone();
+        let served_services = ServedServices::default();
+        let service = MessagingService::new(Arc::clone(&served_services));
+        let (close_session, session_closed) = oneshot::channel();
+        let (session_client, dispatch_task) = self
+            .run_io(async move {
+                let transport = Transport::connect(uri).await?;
+                Ok::<_, ToPeerError>(
+                    connect_session(transport, service, dispatch_error, session_closed).await?,
+                )
+            })
+            .await??;
+        Ok(self.peer_node(
+            session_client,
+            dispatch_task,
+            session_error,
+            close_session,
+            served_services,
+        ))
+    }
+
+    /// Accepts a connection from a peer on a listener, see [`Node::to_peer`].
+    ///
+    /// The node serves its objects to the peer with [`Node::serve_peer_service`], and may access
+    /// the objects that the peer serves too. Each accepted connection is a node of its own, a
+    /// listener may accept several of them.
+    #[cfg(feature = "server")]
+    #[instrument(level = "trace", skip_all, ret)]
+    pub async fn accept_peer(self, listener: &Listener) -> Result<Node, ToPeerError> {
+        let (stream, address) = listener.accept().await.map_err(ToPeerError::Accept)?;
+        trace!(%address, "accepted the connection of a peer");
+        let session_error = LastError::default();
+        let dispatch_error = session_error.clone();
+        let served_services = ServedServices::default();
+        let service = MessagingService::new(Arc::clone(&served_services));
+        let (close_session, session_closed) = oneshot::channel();
+        let (session_client, dispatch_task) = self
+            .run_io(listen_session(
+                Transport::Tcp(stream),
+                service,
+                dispatch_error,
+                session_closed,
+            ))
+            .await??;
+        Ok(self.peer_node(
+            session_client,
+            dispatch_task,
+            session_error,
+            close_session,
+            served_services,
+        ))
+    }
+
+    fn peer_node(
+        self,
+        session_client: session::Client,
+        dispatch_task: JoinHandle<()>,
+        session_error: LastError,
+        close_session: oneshot::Sender<()>,
+        served_services: ServedServices,
+    ) -> Node {
+        Node {
+            session: session_client,
+            service_directory: Box::new(service_directory::Unavailable),
+            registered_services: Mutex::default(),
+            served_services,
+            session_error,
+            meta_object_cache: self.meta_object_cache,
+            meta_object_timeout: self.meta_object_timeout,
+            endpoints: Vec::new(),
+            #[cfg(feature = "server")]
+            serve_task: None,
+            close_session: CloseSession::new(close_session),
+            dispatch_task,
+            diagnostics_redaction: self.diagnostics_redaction.into(),
+            _io_runtime: self.io_runtime,
+        }
+    }
+
+    async fn namespace_node(
+        self,
+        session_client: session::Client,
+        dispatch_task: JoinHandle<()>,
+        session_error: LastError,
+        close_session: oneshot::Sender<()>,
+        served_services: ServedServices,
+    ) -> CallResult<Node, ToNamespaceError> {
+        let sd_client = service_directory::Client::connect(session_client.clone())
             .await
             .map_err(|err| err.map_err(ToNamespaceError::ConnectServiceDirectoryClient))?;
-        let service_directory = Box::new(sd_client);
+        #[cfg(feature = "server")]
+        let (endpoints, serve_task) = match self.serve_config.clone() {
+            Some(config) => {
+                let (endpoints, task) = self
+                    .run_io(serve(config, Arc::clone(&served_services)))
+                    .await
+                    .map_err(ToNamespaceError::IoRuntime)?
+                    .map_err(ToNamespaceError::Listen)?;
+                (endpoints, Some(task))
+            }
+            None => (Vec::new(), None),
+        };
+        #[cfg(not(feature = "server"))]
+        let endpoints = Vec::new();
+        Ok(Node {
+            session: session_client,
+            service_directory: Box::new(sd_client),
+            registered_services: Mutex::default(),
+            served_services,
+            session_error,
+            meta_object_cache: self.meta_object_cache,
+            meta_object_timeout: self.meta_object_timeout,
+            endpoints,
+            #[cfg(feature = "server")]
+            serve_task,
+            close_session: CloseSession::new(close_session),
+            dispatch_task,
+            diagnostics_redaction: self.diagnostics_redaction.into(),
+            _io_runtime: self.io_runtime,
+        })
+    }
 
-        Ok(Self { service_directory })
+    /// Runs a future that connects a session on the IO runtime, if any, so that the connection
+    /// and the dispatch task of the session belong to it.
+    async fn run_io<F>(&self, future: F) -> Result<F::Output, IoRuntimeShutdownError>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        match &self.io_runtime {
+            Some(io_runtime) => io_runtime.run(future).await,
+            None => Ok(future.await),
+        }
     }
+}
 
-    pub fn service_directory(&self) -> &BoxServiceDirectory<'static> {
-        &self.service_directory
+/// Reserves the name and the id of a service among the services registered by a node, by name.
+fn reserve_service(
+    services: &mut HashMap<String, Option<ServiceId>>,
+    name: &str,
+    id: Option<ServiceId>,
+) -> Result<(), RegisterServiceError> {
+    if services.contains_key(name) {
+        return Err(RegisterServiceError::NameAlreadyRegistered(name.to_owned()));
+    }
+    if let Some(id) = id {
+        // Pending registrations with a fixed id are looked up by their requested id.
+        if services.values().any(|&registered| registered == Some(id)) {
+            return Err(RegisterServiceError::IdAlreadyRegistered(id));
+        }
+    }
+    services.insert(name.to_owned(), id);
+    Ok(())
+}
+
+/// The ids of the control service of sessions and of the service directory cannot be used by
+/// other services.
+fn is_reserved_service_id(id: ServiceId) -> bool {
+    id == ServiceId::default() || id == service_directory::SERVICE_ID
+}
+
+async fn connect_session(
+    transport: Transport,
+    service: MessagingService,
+    session_error: LastError,
+    closed: oneshot::Receiver<()>,
+) -> Result<(session::Client, JoinHandle<()>), session::ConnectError> {
+    let connection = transport.connection_info();
+    let (session_client, session) =
+        session::connect_with_connection_info(transport, service, connection);
+    let dispatch_task = spawn_dispatch(session, session_error, closed);
+    Ok((session_client.await?, dispatch_task))
+}
+
+/// Same as [`connect_session`], for the listening end of a session.
+#[cfg(feature = "server")]
+async fn listen_session(
+    transport: Transport,
+    service: MessagingService,
+    session_error: LastError,
+    closed: oneshot::Receiver<()>,
+) -> Result<(session::Client, JoinHandle<()>), session::ListenError> {
+    let connection = transport.connection_info();
+    let (session_client, session) =
+        session::listen_with_connection_info(transport, service, connection);
+    let dispatch_task = spawn_dispatch(session, session_error, closed);
+    Ok((session_client.await?, dispatch_task))
+}
+
+/// Spawns the task that dispatches the messages of the session of a node, until it terminates or
+/// until it is closed.
+fn spawn_dispatch<S>(
+    session: S,
+    session_error: LastError,
+    closed: oneshot::Receiver<()>,
+) -> JoinHandle<()>
+where
+    S: Future<Output = Result<(), session::Error>> + Send + 'static,
+{
+    spawn(
+        async move {
+            // The session is closed by dropping it, which closes its transport. If the node is
+            // dropped without being shut down, the session goes on for the clients of its objects.
+            futures::pin_mut!(session);
+            let result = match future::select(session, closed).await {
+                Either::Left((result, _closed)) => result,
+                Either::Right((Ok(()), _session)) => {
+                    trace!("session closed by the shutdown of the node");
+                    Ok(())
+                }
+                Either::Right((Err(_dropped), session)) => session.await,
+            };
+            if let Err(err) = result {
+                trace!(
+                    error = &err as &dyn std::error::Error,
+                    "session terminated with an error"
+                );
+                session_error.set(&err);
+            }
+        }
+        .instrument(trace_span!(parent: None, "dispatch")),
+    )
+}
+
+/// The delays before accepting connections again after a failure, which is often persistent, such
+/// as the exhaustion of the file descriptors of the process. The delay doubles with each failure
+/// in a row.
+#[cfg(feature = "server")]
+const ACCEPT_RETRY_DELAY_MIN: Duration = Duration::from_millis(10);
+#[cfg(feature = "server")]
+const ACCEPT_RETRY_DELAY_MAX: Duration = Duration::from_secs(1);
+
+/// Binds a listener and spawns the task that serves the services of the node to the sessions of
+/// its connections. Returns the endpoints to advertise with the task.
+#[cfg(feature = "server")]
+async fn serve(
+    config: ServeConfig,
+    services: ServedServices,
+) -> std::io::Result<(Vec<Uri>, ServeTask)> {
+    let listener = config.listen().await?;
+    let endpoints = listener.endpoints().to_vec();
+    trace!(address = %listener.local_address(), ?endpoints, "listening for connections");
+    let (close, closed) = watch::channel(());
+    let (session_guard, sessions_terminated) = mpsc::channel(1);
+    let sessions = ListenerSessions::default();
+    let served_sessions = sessions.clone();
+    let task = spawn(
+        async move {
+            let mut retry_delay = ACCEPT_RETRY_DELAY_MIN;
+            loop {
+                match listener.accept().await {
+                    Ok((stream, address)) => {
+                        retry_delay = ACCEPT_RETRY_DELAY_MIN;
+                        spawn(
+                            serve_session(
+                                Transport::Tcp(stream),
+                                Arc::clone(&services),
+                                served_sessions.clone(),
+                                closed.clone(),
+                                session_guard.clone(),
+                            )
+                            .instrument(trace_span!(parent: None, "served_session", %address)),
+                        );
+                    }
+                    Err(err) => {
+                        trace!(
+                            error = &err as &dyn std::error::Error,
+                            ?retry_delay,
+                            "failed to accept a connection"
+                        );
+                        tokio::time::sleep(retry_delay).await;
+                        retry_delay = (retry_delay * 2).min(ACCEPT_RETRY_DELAY_MAX);
+                    }
+                }
+            }
+        }
+        .instrument(trace_span!(parent: None, "serve")),
+    );
+    Ok((
+        endpoints,
+        ServeTask {
+            task,
+            sessions,
+            close,
+            sessions_terminated,
+        },
+    ))
+}
+
+/// Serves the services of the node to a session until it terminates, or until it is closed by the
+/// shutdown of the node or through its handle. The session is added to the sessions of the
+/// listener once it is established. The guard is dropped when the session terminates.
+#[cfg(feature = "server")]
+async fn serve_session(
+    transport: Transport,
+    services: ServedServices,
+    sessions: ListenerSessions,
+    mut closed: watch::Receiver<()>,
+    _guard: mpsc::Sender<()>,
+) {
+    let connection = transport.connection_info();
+    // The subscriptions to the signals of the services are bound to the client of the session,
+    // which the messaging service waits for.
+    let (client_sender, client) = watch::channel(None);
+    let service = MessagingService::served(services, client);
+    let (session_client, session) =
+        session::listen_with_connection_info(transport, service, connection);
+    let last_error = LastError::default();
+    let (close_session, session_closed) = oneshot::channel();
+    let session_client = session_client.inspect(|result| {
+        if let Ok(client) = result {
+            client_sender.send_replace(Some(client.clone()));
+            sessions.insert(ListenerSession {
+                client: client.clone(),
+                last_error: last_error.clone(),
+                close: CloseSession::new(close_session),
+            });
+        }
+    });
+    let session = future::join(session_client, session);
+    let node_closed = async move {
+        // If the node is dropped without being shut down, the session goes on.
+        if closed.changed().await.is_err() {
+            future::pending::<()>().await;
+        }
+    };
+    let session_closed = async move {
+        // The handles of the session are dropped once it is closed.
+        if session_closed.await.is_err() {
+            future::pending::<()>().await;
+        }
+    };
+    futures::pin_mut!(node_closed, session_closed);
+    let closed = future::select(node_closed, session_closed);
+    futures::pin_mut!(session, closed);
+    let (client_result, result) = match future::select(session, closed).await {
+        Either::Left((results, _closed)) => results,
+        Either::Right((_closed, _session)) => {
+            // The session is closed by dropping it, which closes its transport.
+            trace!("served session closed by the node");
+            return;
+        }
+    };
+    if let Err(err) = client_result {
+        trace!(
+            error = &err as &dyn std::error::Error,
+            "failed to establish a served session"
+        );
+    }
+    if let Err(err) = result {
+        trace!(
+            error = &err as &dyn std::error::Error,
+            "served session terminated with an error"
+        );
+        last_error.set(&err);
+    }
+}
+
+/// Accepts the connections to the services of a node and serves their sessions.
+///
+/// The node stops accepting connections when it is dropped, the sessions that it serves go on.
+#[cfg(feature = "server")]
+#[derive(Debug)]
+struct ServeTask {
+    task: JoinHandle<()>,
+    // The established sessions, see `Node::sessions`.
+    sessions: ListenerSessions,
+    // Notifies the served sessions that they are closed.
+    close: watch::Sender<()>,
+    // Each served session holds a sender of the channel, which is closed once they all terminated.
+    sessions_terminated: mpsc::Receiver<()>,
+}
+
+#[cfg(feature = "server")]
+impl ServeTask {
+    /// Stops accepting connections, closes the served sessions and waits for them to terminate.
+    async fn close(&mut self) {
+        // Aborting the task drops its sender of the channel of the terminated sessions.
+        self.task.abort();
+        let _res = (&mut self.task).await;
+        let _res = self.close.send(());
+        let _res = self.sessions_terminated.recv().await;
+    }
+}
+
+#[cfg(feature = "server")]
+impl Drop for ServeTask {
+    fn drop(&mut self) {
+        self.task.abort();
     }
 }
 
@@ -61,34 +1083,987 @@ pub enum ToNamespaceError {
     #[error("failed to create a transport for this URI")]
     TransportFromUri(#[from] transport::ConnectFromUriError),
 
+    #[error(transparent)]
+    Endpoints(#[from] transport::ConnectEndpointsError),
+
     #[error(transparent)]
     SessionConnect(#[from] session::ConnectError),
 
     #[error("failed to connect the client of the service directory main object")]
     ConnectServiceDirectoryClient(#[from] object::client::ConnectError),
+
+    #[error(transparent)]
+    IoRuntime(#[from] IoRuntimeShutdownError),
+
+    #[cfg(feature = "server")]
+    #[error("failed to listen for connections to the services of the node")]
+    Listen(#[source] std::io::Error),
 }
 
 #[derive(Debug, thiserror::Error)]
-pub enum ServiceError {}
+pub enum ToPeerError {
+    #[error("failed to create a transport for this URI")]
+    TransportFromUri(#[from] transport::ConnectFromUriError),
 
-#[derive(Debug)]
-struct MessagingService;
+    #[error(transparent)]
+    SessionConnect(#[from] session::ConnectError),
+
+    #[cfg(feature = "server")]
+    #[error("failed to accept the connection of a peer")]
+    Accept(#[source] std::io::Error),
+
+    #[cfg(feature = "server")]
+    #[error(transparent)]
+    SessionListen(#[from] session::ListenError),
+
+    #[error(transparent)]
+    IoRuntime(#[from] IoRuntimeShutdownError),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ServiceError {
+    #[error("failed to get the service information from the service directory")]
+    ServiceDirectory(#[from] service_directory::Error),
+
+    #[error("failed to connect the client of the service main object")]
+    ConnectObject(#[from] object::client::ConnectError),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RegisterServiceError {
+    #[error("the service id {0} is reserved")]
+    ReservedId(ServiceId),
+
+    #[error("a service named \"{0}\" is already registered by this node")]
+    NameAlreadyRegistered(String),
+
+    #[error("a service with id {0} is already registered by this node")]
+    IdAlreadyRegistered(ServiceId),
+
+    #[error("the service directory failed to register the service")]
+    ServiceDirectory(#[from] service_directory::Error),
+
+    #[error(
+        "the service directory assigned the id {assigned} instead of the requested id {requested}"
+    )]
+    IdRejected {
+        requested: ServiceId,
+        assigned: ServiceId,
+    },
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum UnregisterServiceError {
+    #[error("no service named \"{0}\" is registered by this node")]
+    NotRegistered(String),
+
+    #[error("the registration of the service named \"{0}\" is pending")]
+    RegistrationPending(String),
+
+    #[error("the service directory failed to unregister the service")]
+    ServiceDirectory(#[from] service_directory::Error),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ShutdownError {
+    #[error("the shutdown did not complete in {0:?}")]
+    TimedOut(Duration),
+
+    #[error("failed to unregister the service named \"{name}\"")]
+    UnregisterService {
+        name: String,
+        #[source]
+        error: messaging::CallTermination<UnregisterServiceError>,
+    },
+}
+
+/// The main objects of the services registered by a node with the subscriptions to their
+/// signals, by service id.
+type ServedServices = Arc<Mutex<HashMap<ServiceId, ServedService>>>;
+
+#[derive(Clone)]
+struct ServedService {
+    object: Arc<dyn ServedObject>,
+    subscriptions: Arc<signal::SubscriptionSet>,
+}
+
+impl std::fmt::Debug for ServedService {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ServedService")
+            .field("subscriptions", &self.subscriptions)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Serves the main objects of the services registered by a node.
+///
+/// The requests of the meta object and the subscriptions to the signals, with the replay of their
+/// last values, are answered for all objects, the calls of the other methods are dispatched to the objects, see [`ServedObject`].
+///
+/// Once a service is unregistered, the calls to its object fail, which tells the subscribers that
+/// unsubscribe from its signals that it was removed.
+#[derive(Debug, Default)]
+struct MessagingService {
+    services: ServedServices,
+    // The session served by the listener of the node, if the service is not the one of the
+    // session of the node.
+    #[cfg(feature = "server")]
+    served_session: Option<ServedSession>,
+}
+
+impl MessagingService {
+    fn new(services: ServedServices) -> Self {
+        Self {
+            services,
+            #[cfg(feature = "server")]
+            served_session: None,
+        }
+    }
+
+    /// The messaging service of a session served by the listener of the node, see
+    /// [`ServedSession`].
+    #[cfg(feature = "server")]
+    fn served(services: ServedServices, client: watch::Receiver<Option<session::Client>>) -> Self {
+        Self {
+            services,
+            served_session: Some(ServedSession {
+                client,
+                subscriptions: Arc::default(),
+            }),
+        }
+    }
+
+    /// Returns the set of the subscribers on the session of this service to the signals of a
+    /// served service, given the set of the node.
+    fn session_subscriptions(
+        &self,
+        service: ServiceId,
+        subscriptions: Arc<signal::SubscriptionSet>,
+    ) -> BoxFuture<'static, Result<Arc<signal::SubscriptionSet>, signal::ClosedError>> {
+        #[cfg(feature = "server")]
+        if let Some(served_session) = self.served_session.clone() {
+            return async move { served_session.subscriptions(service, subscriptions).await }
+                .boxed();
+        }
+        #[cfg(not(feature = "server"))]
+        let _service = service;
+        future::ok(subscriptions).boxed()
+    }
+
+    fn service(&self, subject: &session::Subject) -> Result<ServedService, MessagingServiceError> {
+        if subject.object() != object::client::SERVICE_MAIN_OBJECT {
+            return Err(MessagingServiceError::ObjectNotFound(
+                subject.service(),
+                subject.object(),
+            ));
+        }
+        self.services
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&subject.service())
+            .cloned()
+            .ok_or_else(|| MessagingServiceError::ServiceNotFound(subject.service()))
+    }
+}
 
 impl messaging::Service<session::CallWithId, session::NotificationWithId> for MessagingService {
-    type CallReply = ();
+    type CallReply = MessagingServiceReply;
     type Error = MessagingServiceError;
     type CallFuture = BoxFuture<'static, CallResult<Self::CallReply, Self::Error>>;
-    type NotifyFuture = BoxFuture<'static, Result<(), Self::Error>>;
+    type NotifyFuture = future::Ready<Result<(), Self::Error>>;
 
     fn call(&mut self, call: session::CallWithId) -> Self::CallFuture {
-        todo!()
+        let call = call.into_inner();
+        let subject = *call.subject();
+        let ServedService {
+            object,
+            subscriptions,
+        } = match self.service(&subject) {
+            Ok(service) => service,
+            Err(err) => return future::err(err.into()).boxed(),
+        };
+        match subject.action() {
+            object::client::ACTION_ID_REGISTER_EVENT => {
+                let args = call.value::<(ServiceId, ActionId, signal::Link)>();
+                let subscriptions = self.session_subscriptions(subject.service(), subscriptions);
+                async move {
+                    let (_service, signal, link) =
+                        args.map_err(MessagingServiceError::Arguments)?;
+                    let subscriptions =
+                        subscriptions.await.map_err(MessagingServiceError::Closed)?;
+                    subscriptions
+                        .subscribe(signal, link)
+                        .await
+                        .map_err(MessagingServiceError::Closed)?;
+                    Ok(MessagingServiceReply::Link(link))
+                }
+                .boxed()
+            }
+            object::client::ACTION_ID_REGISTER_EVENT_WITH_REPLAY => {
+                let args = call.value::<(ServiceId, ActionId, signal::Link, u32)>();
+                let subscriptions = self.session_subscriptions(subject.service(), subscriptions);
+                async move {
+                    let (_service, signal, link, count) =
+                        args.map_err(MessagingServiceError::Arguments)?;
+                    let subscriptions =
+                        subscriptions.await.map_err(MessagingServiceError::Closed)?;
+                    let count = usize::try_from(count).unwrap_or(usize::MAX);
+                    let replayed = subscriptions
+                        .subscribe_with_replay(signal, link, count)
+                        .await
+                        .map_err(MessagingServiceError::Closed)?;
+                    let replayed = replayed.iter().map(format::Value::to_bytes).collect();
+                    Ok(MessagingServiceReply::LinkWithReplay(link, replayed))
+                }
+                .boxed()
+            }
+            object::client::ACTION_ID_UNREGISTER_EVENT => {
+                let args = call.value::<(ServiceId, ActionId, signal::Link)>();
+                let subscriptions = self.session_subscriptions(subject.service(), subscriptions);
+                async move {
+                    let (_service, signal, link) =
+                        args.map_err(MessagingServiceError::Arguments)?;
+                    // The subscriptions of a closed session are dropped with it.
+                    if let Ok(subscriptions) = subscriptions.await {
+                        subscriptions.unsubscribe(signal, link).await;
+                    }
+                    Ok(MessagingServiceReply::Unit)
+                }
+                .boxed()
+            }
+            object::client::ACTION_ID_METAOBJECT => {
+                let meta_object = Box::new(object.meta_object().clone());
+                future::ok(MessagingServiceReply::MetaObject(meta_object)).boxed()
+            }
+            action => {
+                let method = match object.meta_object().methods.get(&action) {
+                    Some(method) => method,
+                    None => {
+                        return future::err(MessagingServiceError::ActionNotFound(action).into())
+                            .boxed()
+                    }
+                };
+                let parameters = method.parameters_signature.clone().into_type();
+                let args = match call.value_seed(value::dynamic::DynamicSeed::new(parameters)) {
+                    Ok(args) => args,
+                    Err(err) => {
+                        return future::err(MessagingServiceError::Arguments(err).into()).boxed()
+                    }
+                };
+                object
+                    .call(action, args)
+                    .map(|result| {
+                        result
+                            .map(MessagingServiceReply::Value)
+                            .map_err(|err| err.map_err(MessagingServiceError::Method))
+                    })
+                    .boxed()
+            }
+        }
     }
 
-    fn notify(&mut self, notif: session::NotificationWithId) -> Self::NotifyFuture {
-        todo!()
+    fn notify(&mut self, _notif: session::NotificationWithId) -> Self::NotifyFuture {
+        // Posts of methods are not dispatched to the objects, and calls are replied to before
+        // they may be canceled.
+        future::ok(())
     }
 }
 
+/// A session served by the listener of a node.
+///
+/// The subscribers on the session to the signals of a service are held by a session set of the
+/// set of the node, see [`signal::SubscriptionSet::on_session`], so that the values emitted by
+/// the node are sent to them.
+#[cfg(feature = "server")]
+#[derive(Debug, Clone)]
+struct ServedSession {
+    // The client of the session, once it is established.
+    client: watch::Receiver<Option<session::Client>>,
+    // The session sets of the services, by id, along with the set of the node they belong to, which
+    // changes if the service is registered again with the same id.
+    subscriptions: Arc<Mutex<HashMap<ServiceId, SessionSubscriptions>>>,
+}
+
+#[cfg(feature = "server")]
+type SessionSubscriptions = (Arc<signal::SubscriptionSet>, Arc<signal::SubscriptionSet>);
+
+#[cfg(feature = "server")]
+impl ServedSession {
+    async fn subscriptions(
+        &self,
+        service: ServiceId,
+        node_subscriptions: Arc<signal::SubscriptionSet>,
+    ) -> Result<Arc<signal::SubscriptionSet>, signal::ClosedError> {
+        if let Some(subscriptions) = self.session_subscriptions(service, &node_subscriptions) {
+            return Ok(subscriptions);
+        }
+        let mut client = self.client.clone();
+        let client = client
+            .wait_for(Option::is_some)
+            .await
+            .map_err(|_closed| signal::ClosedError)?
+            .clone()
+            .ok_or(signal::ClosedError)?;
+        let subscriptions = node_subscriptions.on_session(client).await?;
+        // Concurrent subscriptions to the service share the first session set that was recorded,
+        // the others stay empty.
+        let mut sessions_subscriptions = self
+            .subscriptions
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some(subscriptions) =
+            find_session_subscriptions(&sessions_subscriptions, service, &node_subscriptions)
+        {
+            return Ok(subscriptions);
+        }
+        sessions_subscriptions.insert(service, (node_subscriptions, Arc::clone(&subscriptions)));
+        Ok(subscriptions)
+    }
+
+    fn session_subscriptions(
+        &self,
+        service: ServiceId,
+        node_subscriptions: &Arc<signal::SubscriptionSet>,
+    ) -> Option<Arc<signal::SubscriptionSet>> {
+        let sessions_subscriptions = self
+            .subscriptions
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        find_session_subscriptions(&sessions_subscriptions, service, node_subscriptions)
+    }
+}
+
+#[cfg(feature = "server")]
+fn find_session_subscriptions(
+    sessions_subscriptions: &HashMap<ServiceId, SessionSubscriptions>,
+    service: ServiceId,
+    node_subscriptions: &Arc<signal::SubscriptionSet>,
+) -> Option<Arc<signal::SubscriptionSet>> {
+    sessions_subscriptions
+        .get(&service)
+        .filter(|(node, _session)| Arc::ptr_eq(node, node_subscriptions))
+        .map(|(_node, session)| Arc::clone(session))
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(untagged)]
+enum MessagingServiceReply {
+    Link(signal::Link),
+    LinkWithReplay(signal::Link, Vec<value::Raw>),
+    MetaObject(Box<MetaObject>),
+    Value(value::Value),
+    Unit,
+}
+
 #[derive(Debug, thiserror::Error)]
-#[error("messaging service error")]
-struct MessagingServiceError;
+enum MessagingServiceError {
+    #[error("no service with id {0} is served by this node")]
+    ServiceNotFound(ServiceId),
+
+    #[error("the service {0} has no object with id {1}")]
+    ObjectNotFound(ServiceId, ObjectId),
+
+    #[error("the object has no method with action id {0}")]
+    ActionNotFound(ActionId),
+
+    #[error("invalid arguments")]
+    Arguments(#[source] format::Error),
+
+    #[error(transparent)]
+    Method(Box<dyn std::error::Error + Send + Sync>),
+
+    #[error(transparent)]
+    Closed(signal::ClosedError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_reserved_service_id() {
+        assert!(is_reserved_service_id(ServiceId::new(0)));
+        assert!(is_reserved_service_id(service_directory::SERVICE_ID));
+        assert!(!is_reserved_service_id(ServiceId::new(2)));
+    }
+
+    #[test]
+    fn test_reserve_service() {
+        let mut services = HashMap::new();
+        reserve_service(&mut services, "A", None).unwrap();
+        reserve_service(&mut services, "B", Some(ServiceId::new(10))).unwrap();
+        assert!(matches!(
+            reserve_service(&mut services, "A", Some(ServiceId::new(11))),
+            Err(RegisterServiceError::NameAlreadyRegistered(name)) if name == "A"
+        ));
+        assert!(matches!(
+            reserve_service(&mut services, "C", Some(ServiceId::new(10))),
+            Err(RegisterServiceError::IdAlreadyRegistered(id)) if id == ServiceId::new(10)
+        ));
+
+        // The id of a completed registration is reserved as well.
+        services.insert("A".to_owned(), Some(ServiceId::new(2)));
+        assert!(matches!(
+            reserve_service(&mut services, "C", Some(ServiceId::new(2))),
+            Err(RegisterServiceError::IdAlreadyRegistered(_))
+        ));
+        reserve_service(&mut services, "C", Some(ServiceId::new(3))).unwrap();
+        assert_eq!(services.len(), 3);
+    }
+
+    #[cfg(feature = "server")]
+    const ACTION_ID_ADD: ActionId = ActionId::new(100);
+    #[cfg(feature = "server")]
+    const ACTION_ID_SUMMED: ActionId = ActionId::new(102);
+
+    /// An object with an "add" method, that sums its two arguments, and a "summed" signal.
+    #[cfg(feature = "server")]
+    struct Adder(MetaObject);
+
+    #[cfg(feature = "server")]
+    impl Adder {
+        fn new() -> Self {
+            let mut builder = MetaObject::builder();
+            builder.add_method(
+                ACTION_ID_ADD,
+                "add",
+                "(ii)".parse::<value::Signature>().unwrap(),
+                value::Type::Int32,
+            );
+            builder.add_signal(ACTION_ID_SUMMED, "summed", value::Type::UInt32);
+            Self(builder.build())
+        }
+    }
+
+    #[cfg(feature = "server")]
+    impl ServedObject for Adder {
+        fn meta_object(&self) -> &MetaObject {
+            &self.0
+        }
+
+        fn call(
+            &self,
+            _action: ActionId,
+            args: value::Dynamic,
+        ) -> BoxFuture<'static, CallResult<value::Value, Box<dyn std::error::Error + Send + Sync>>>
+        {
+            let args = args.into_value();
+            let sum = args
+                .as_tuple()
+                .map(|args| {
+                    args.elements()
+                        .iter()
+                        .filter_map(|arg| arg.as_number()?.as_int32())
+                        .sum::<i32>()
+                })
+                .ok_or_else(|| messaging::CallTermination::Error("not a tuple".into()));
+            future::ready(sum.map(value::Value::from)).boxed()
+        }
+    }
+
+    #[cfg(feature = "server")]
+    #[tokio::test]
+    async fn test_node_serves_a_peer() {
+        let listener = ServeConfig::new(([127, 0, 0, 1], 0).into())
+            .listen()
+            .await
+            .unwrap();
+        let uri = listener.endpoints()[0].clone();
+        let (server, client) =
+            future::join(Node::builder().accept_peer(&listener), Node::to_peer(uri)).await;
+        let (server, client) = (server.unwrap(), client.unwrap());
+        server
+            .serve_peer_service(PEER_SERVICE_ID, Adder::new())
+            .unwrap();
+        assert!(matches!(
+            server.serve_peer_service(PEER_SERVICE_ID, Adder::new()),
+            Err(RegisterServiceError::IdAlreadyRegistered(id)) if id == PEER_SERVICE_ID
+        ));
+        assert!(matches!(
+            server.serve_peer_service(ServiceId::new(0), Adder::new()),
+            Err(RegisterServiceError::ReservedId(_))
+        ));
+
+        let object = client.service_with_id(PEER_SERVICE_ID).await.unwrap();
+        assert_eq!(object.meta_object(), Some(&Adder::new().0));
+        let sum: i32 = object.call("add", (1, 2)).await.unwrap();
+        assert_eq!(sum, 3);
+        // The session is symmetric: the accepting node may access the services of the other one.
+        client
+            .serve_peer_service(PEER_SERVICE_ID, Adder::new())
+            .unwrap();
+        let object = server.service_with_id(PEER_SERVICE_ID).await.unwrap();
+        let sum: i32 = object.call("add", (3, 4)).await.unwrap();
+        assert_eq!(sum, 7);
+    }
+
+    /// A node connected to a local peer, that registers its services to an in-memory directory.
+    #[cfg(all(feature = "server", feature = "discovery"))]
+    async fn node_with_directory(directory: service_directory::ServiceDirectoryImpl) -> Node {
+        node_with_peer(directory).await.0
+    }
+
+    /// Same as [`node_with_directory`], with the client of the session of the peer.
+    #[cfg(all(feature = "server", feature = "discovery"))]
+    async fn node_with_peer(
+        directory: service_directory::ServiceDirectoryImpl,
+    ) -> (Node, session::Client) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let (stream, accepted) =
+            future::join(tokio::net::TcpStream::connect(address), listener.accept()).await;
+        let (peer, peer_session) =
+            session::listen(accepted.unwrap().0, MessagingService::default());
+        spawn(async move {
+            let _res = peer_session.await;
+        });
+        let session_error = LastError::default();
+        let served_services = ServedServices::default();
+        let (close_session, session_closed) = oneshot::channel();
+        let (session, peer) = future::join(
+            connect_session(
+                Transport::Tcp(stream.unwrap()),
+                MessagingService::new(Arc::clone(&served_services)),
+                session_error.clone(),
+                session_closed,
+            ),
+            peer,
+        )
+        .await;
+        let (session, dispatch_task) = session.unwrap();
+        let node = Node {
+            session,
+            service_directory: Box::new(directory),
+            registered_services: Mutex::default(),
+            served_services,
+            session_error,
+            meta_object_cache: MetaObjectCache::default(),
+            meta_object_timeout: object::client::DEFAULT_META_OBJECT_TIMEOUT,
+            endpoints: Vec::new(),
+            serve_task: None,
+            close_session: CloseSession::new(close_session),
+            dispatch_task,
+            diagnostics_redaction: Arc::new([]),
+            _io_runtime: None,
+        };
+        (node, peer.unwrap())
+    }
+
+    #[cfg(all(feature = "server", feature = "discovery"))]
+    #[tokio::test]
+    async fn test_node_shutdown_closes_sessions() {
+        use tokio::io::AsyncReadExt;
+
+        let mut node = node_with_directory(service_directory::ServiceDirectoryImpl::new()).await;
+        let (endpoints, serve_task) = serve(
+            ServeConfig::new(([127, 0, 0, 1], 0).into()),
+            Arc::clone(&node.served_services),
+        )
+        .await
+        .unwrap();
+        node.serve_task = Some(serve_task);
+        node.register_service("A", Adder::new()).await.unwrap();
+        let session = node.sessions().remove(0);
+
+        // A connection to the services of the node, over which a session is established then
+        // left idle.
+        let Transport::Tcp(mut served) = Transport::connect(endpoints[0].clone()).await.unwrap();
+        {
+            let (client, session) = session::connect(&mut served, MessagingService::default());
+            futures::pin_mut!(client, session);
+            assert!(matches!(
+                future::select(client, session).await,
+                Either::Left((Ok(_), _))
+            ));
+        }
+
+        node.shutdown(Duration::from_secs(1)).await.unwrap();
+        // The sessions are terminated once the shutdown returns.
+        assert!(session.is_closed());
+        let mut buf = Vec::new();
+        let read = tokio::time::timeout(Duration::from_secs(1), served.read_to_end(&mut buf));
+        assert!(matches!(read.await, Ok(Ok(_))));
+    }
+
+    #[cfg(all(feature = "server", feature = "discovery"))]
+    #[tokio::test]
+    async fn test_node_serves_its_services_to_the_sessions_of_its_listener() {
+        use futures::StreamExt;
+        use messaging::Service;
+
+        const SIGNAL: ActionId = ActionId::new(100);
+        let link = signal::Link::from(1);
+        let mut node = node_with_directory(service_directory::ServiceDirectoryImpl::new()).await;
+        let (endpoints, serve_task) = serve(
+            ServeConfig::new(([127, 0, 0, 1], 0).into()),
+            Arc::clone(&node.served_services),
+        )
+        .await
+        .unwrap();
+        node.serve_task = Some(serve_task);
+        let id = node.register_service("A", Adder::new()).await.unwrap();
+
+        let Transport::Tcp(served) = Transport::connect(endpoints[0].clone()).await.unwrap();
+        let (client, session) = session::connect(served, MessagingService::default());
+        spawn(async move {
+            let _res = session.await;
+        });
+        let mut client = client.await.unwrap();
+
+        // The methods of the object are served to the session.
+        let object = object::Client::connect_with_meta_object(
+            client.clone(),
+            id,
+            object::client::SERVICE_MAIN_OBJECT,
+            None,
+            object::client::DEFAULT_META_OBJECT_TIMEOUT,
+        )
+        .await
+        .unwrap();
+        let sum: i32 = object.call("add", (1, 2)).await.unwrap();
+        assert_eq!(sum, 3);
+
+        // The values emitted by the node are sent to the subscribers on the session.
+        let subject = session::Subject::new(
+            session::subject::ServiceObject::new(id, object::client::SERVICE_MAIN_OBJECT).unwrap(),
+            SIGNAL,
+        );
+        let mut events = Box::pin(client.events(move |event_subject| *event_subject == subject));
+        let reply = client
+            .call(event_registration_call(
+                id,
+                object::client::ACTION_ID_REGISTER_EVENT,
+                SIGNAL,
+                link,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(reply.value::<signal::Link>().unwrap(), link);
+        let subscriptions = node.subscriptions(id).unwrap();
+        subscriptions.emit(SIGNAL, &42u32).await.unwrap();
+        let (_subject, content) = events.next().await.unwrap();
+        assert_eq!(
+            format::from_value::<u32>(&format::Value::from_bytes(content)).unwrap(),
+            42
+        );
+
+        // Once unsubscribed, the session receives no more values.
+        client
+            .call(event_registration_call(
+                id,
+                object::client::ACTION_ID_UNREGISTER_EVENT,
+                SIGNAL,
+                link,
+            ))
+            .await
+            .unwrap();
+        subscriptions.emit(SIGNAL, &43u32).await.unwrap();
+        let next = tokio::time::timeout(Duration::from_millis(100), events.next());
+        assert!(next.await.is_err());
+    }
+
+    #[cfg(all(feature = "server", feature = "discovery"))]
+    #[tokio::test]
+    async fn test_node_sessions_and_diagnostics() {
+        use tokio::io::AsyncReadExt;
+
+        /// Waits until the node has this number of sessions.
+        async fn wait_sessions(node: &Node, count: usize) -> Vec<SessionHandle> {
+            loop {
+                let sessions = node.sessions();
+                if sessions.len() == count {
+                    return sessions;
+                }
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        }
+
+        let mut node = node_with_directory(service_directory::ServiceDirectoryImpl::new()).await;
+        let (endpoints, serve_task) = serve(
+            ServeConfig::new(([127, 0, 0, 1], 0).into()),
+            Arc::clone(&node.served_services),
+        )
+        .await
+        .unwrap();
+        node.serve_task = Some(serve_task);
+        let id = node.register_service("A", Adder::new()).await.unwrap();
+        let sessions = node.sessions();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].services(), [("A".to_owned(), id)]);
+
+        // A session served by the listener of the node follows the session of the node.
+        let Transport::Tcp(mut served) = Transport::connect(endpoints[0].clone()).await.unwrap();
+        let served_address = served.local_addr().unwrap();
+        {
+            let (client, session) = session::connect(&mut served, MessagingService::default());
+            futures::pin_mut!(client, session);
+            assert!(matches!(
+                future::select(client, session).await,
+                Either::Left((Ok(_), _))
+            ));
+        }
+        let sessions = tokio::time::timeout(Duration::from_secs(1), wait_sessions(&node, 2))
+            .await
+            .unwrap();
+        assert_eq!(sessions[1].remote_address(), Some(served_address));
+        assert_eq!(sessions[1].services(), [("A".to_owned(), id)]);
+
+        let diagnostics = node.diagnostics();
+        assert_eq!(diagnostics.sessions().len(), 2);
+        assert_eq!(
+            diagnostics.sessions()[1].remote_address(),
+            Some(served_address)
+        );
+        assert!(diagnostics.is_healthy());
+
+        // A served session that is disconnected is not a session of the node anymore.
+        assert!(sessions[1].disconnect());
+        let mut buf = Vec::new();
+        let read = tokio::time::timeout(Duration::from_secs(1), served.read_to_end(&mut buf));
+        assert!(matches!(read.await, Ok(Ok(_))));
+        tokio::time::timeout(Duration::from_secs(1), wait_sessions(&node, 1))
+            .await
+            .unwrap();
+        assert_eq!(node.diagnostics().sessions().len(), 1);
+
+        // The node is not healthy once its own session is closed.
+        assert!(sessions[0].disconnect());
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while node.diagnostics().is_healthy() {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(
+            node.diagnostics().sessions()[0].state(),
+            SessionState::Closed
+        );
+    }
+
+    #[cfg(all(feature = "server", feature = "discovery"))]
+    #[tokio::test]
+    async fn test_node_replays_the_last_values_of_signals() {
+        use futures::StreamExt;
+
+        let (node, peer) = node_with_peer(service_directory::ServiceDirectoryImpl::new()).await;
+        let id = node.register_service("A", Adder::new()).await.unwrap();
+        let subscriptions = node.subscriptions(id).unwrap();
+        subscriptions.set_replay_capacity(ACTION_ID_SUMMED, 2);
+        for value in 1..=3u32 {
+            subscriptions.emit(ACTION_ID_SUMMED, &value).await.unwrap();
+        }
+
+        // The last values are replayed with the reply to the registration, then the next values
+        // are received as events.
+        let client = object::Client::connect_with_meta_object(
+            peer,
+            id,
+            object::client::SERVICE_MAIN_OBJECT,
+            None,
+            object::client::DEFAULT_META_OBJECT_TIMEOUT,
+        )
+        .await
+        .unwrap();
+        let (replayed, mut stream) = client
+            .subscribe_with_replay::<u32>("summed", 5)
+            .await
+            .unwrap();
+        assert_eq!(replayed, [2, 3]);
+        subscriptions.emit(ACTION_ID_SUMMED, &4u32).await.unwrap();
+        assert_eq!(stream.next().await.unwrap().unwrap(), 4);
+
+        // The count bounds the replayed values.
+        let (replayed, _stream) = client
+            .subscribe_with_replay::<u32>("summed", 1)
+            .await
+            .unwrap();
+        assert_eq!(replayed, [4]);
+        let (replayed, _stream) = client
+            .subscribe_with_replay::<u32>("summed", 0)
+            .await
+            .unwrap();
+        assert!(replayed.is_empty());
+    }
+
+    /// A call of an action of the main object of a service, with the arguments of the
+    /// registration to an event.
+    #[cfg(all(feature = "server", feature = "discovery"))]
+    fn event_registration_call(
+        service: ServiceId,
+        action: ActionId,
+        signal: ActionId,
+        link: signal::Link,
+    ) -> session::Call {
+        let service_object =
+            session::subject::ServiceObject::new(service, object::client::SERVICE_MAIN_OBJECT)
+                .unwrap();
+        session::Call::new(session::Subject::new(service_object, action))
+            .with_value(&(service, signal, link))
+            .unwrap()
+    }
+
+    #[cfg(all(feature = "server", feature = "discovery"))]
+    #[tokio::test]
+    async fn test_node_unregister_service_while_emitting() {
+        use futures::StreamExt;
+        use messaging::Service;
+
+        const SIGNAL: ActionId = ActionId::new(100);
+        let link = signal::Link::from(1);
+        let (node, peer) = node_with_peer(service_directory::ServiceDirectoryImpl::new()).await;
+        let id = node.register_service("A", Adder::new()).await.unwrap();
+        let subscriptions = node.subscriptions(id).unwrap();
+
+        // The peer subscribes to a signal of the main object of the service.
+        let subject = session::Subject::new(
+            session::subject::ServiceObject::new(id, object::client::SERVICE_MAIN_OBJECT).unwrap(),
+            SIGNAL,
+        );
+        let mut events = Box::pin(peer.events(move |event_subject| *event_subject == subject));
+        let mut caller = &peer;
+        let reply = caller
+            .call(event_registration_call(
+                id,
+                object::client::ACTION_ID_REGISTER_EVENT,
+                SIGNAL,
+                link,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(reply.value::<signal::Link>().unwrap(), link);
+
+        // Values are emitted until the subscriptions are closed, concurrently with the
+        // unregistration of the service. Each value is received before the next one is emitted,
+        // for the subscriber to keep up with the traffic.
+        let (started, emitting) = oneshot::channel();
+        let emitter = spawn(async move {
+            let value = |(_subject, content)| {
+                format::from_value::<u32>(&format::Value::from_bytes(content)).unwrap()
+            };
+            let mut started = Some(started);
+            let mut emitted = 0u32;
+            while subscriptions.emit(SIGNAL, &emitted).await.is_ok() {
+                assert_eq!(events.next().await.map(value), Some(emitted));
+                emitted += 1;
+                if let Some(started) = started.take() {
+                    let _res = started.send(());
+                }
+            }
+            (emitted, events)
+        });
+        emitting.await.unwrap();
+        node.unregister_service("A").await.unwrap();
+        let (emitted, mut events) = emitter.await.unwrap();
+        assert!(emitted > 0);
+
+        // The service was removed, which the subscriber learns when it unsubscribes. The reply
+        // follows any event sent by the node before it.
+        let result = caller
+            .call(event_registration_call(
+                id,
+                object::client::ACTION_ID_UNREGISTER_EVENT,
+                SIGNAL,
+                link,
+            ))
+            .await;
+        assert!(matches!(
+            result,
+            Err(messaging::CallTermination::Error(
+                session::ClientError::Service(_)
+            ))
+        ));
+        // No event follows the unregistration.
+        assert!(events.next().now_or_never().is_none());
+    }
+
+    #[cfg(all(feature = "server", feature = "discovery"))]
+    #[tokio::test]
+    async fn test_node_register_service_with_id() {
+        use service_directory::ServiceDirectory;
+
+        let directory = service_directory::ServiceDirectoryImpl::new();
+        let node = node_with_directory(directory.clone()).await;
+        node.register_service_with_id("A", ServiceId::new(10), Adder::new())
+            .await
+            .unwrap();
+        assert_eq!(
+            directory.resolve("A").await.unwrap().service_id,
+            ServiceId::new(10)
+        );
+        assert!(matches!(
+            node.register_service_with_id("B", service_directory::SERVICE_ID, Adder::new())
+                .await,
+            Err(messaging::CallTermination::Error(
+                RegisterServiceError::ReservedId(_)
+            ))
+        ));
+
+        // The id is taken by the service of another node, the directory assigns another one.
+        let other = node_with_directory(directory.clone()).await;
+        assert!(matches!(
+            other
+                .register_service_with_id("B", ServiceId::new(10), Adder::new())
+                .await,
+            Err(messaging::CallTermination::Error(RegisterServiceError::IdRejected {
+                requested,
+                assigned,
+            })) if requested == ServiceId::new(10) && assigned == ServiceId::new(11)
+        ));
+        // The registration under the assigned id is canceled, and the name is free again.
+        let names: Vec<_> = directory
+            .services()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|service| service.name)
+            .collect();
+        assert_eq!(names, ["A"]);
+        assert_eq!(
+            other.register_service("B", Adder::new()).await.unwrap(),
+            ServiceId::new(11)
+        );
+    }
+
+    #[cfg(all(feature = "server", feature = "discovery"))]
+    #[tokio::test]
+    async fn test_node_serves_the_object_of_a_service() {
+        let (node, peer) = node_with_peer(service_directory::ServiceDirectoryImpl::new()).await;
+        node.register_service_with_id("A", ServiceId::new(10), Adder::new())
+            .await
+            .unwrap();
+
+        // The meta object is served by the node, the methods by the object.
+        let client = object::Client::connect_with_meta_object(
+            peer.clone(),
+            ServiceId::new(10),
+            object::client::SERVICE_MAIN_OBJECT,
+            None,
+            object::client::DEFAULT_META_OBJECT_TIMEOUT,
+        )
+        .await
+        .unwrap();
+        assert_eq!(client.meta_object(), Some(&Adder::new().0));
+        let sum: i32 = client.call("add", (1, 2)).await.unwrap();
+        assert_eq!(sum, 3);
+        // The calls of the methods that the object does not have fail on the node.
+        let service_object = session::subject::ServiceObject::new(
+            ServiceId::new(10),
+            object::client::SERVICE_MAIN_OBJECT,
+        )
+        .unwrap();
+        let call = session::Call::new(session::Subject::new(service_object, ActionId::new(101)))
+            .with_value(&(1, 2))
+            .unwrap();
+        assert!(matches!(
+            messaging::Service::call(&mut &peer, call).await,
+            Err(messaging::CallTermination::Error(
+                session::ClientError::Service(_)
+            ))
+        ));
+
+        // Once the service is unregistered, its object is not served anymore.
+        node.unregister_service("A").await.unwrap();
+        assert!(client.call::<_, i32>("add", (1, 2)).await.is_err());
+    }
+}
@@ -0,0 +1,154 @@
+use crate::{
+    messaging::session,
+    value::{Value, ValuePath},
+};
+use std::{
+    collections::BTreeMap,
+    net::SocketAddr,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+/// A snapshot of the state of the connections of a node, see [`super::Node::diagnostics`].
+///
+/// It is meant to be exposed by applications, for instance on a health endpoint, and may be
+/// serialized to any format supported by `serde`, such as JSON.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct Diagnostics {
+    sessions: Vec<SessionDiagnostics>,
+}
+
+impl Diagnostics {
+    pub(super) fn new(sessions: Vec<SessionDiagnostics>) -> Self {
+        Self { sessions }
+    }
+
+    pub fn sessions(&self) -> &[SessionDiagnostics] {
+        &self.sessions
+    }
+
+    /// Returns true if all the sessions of the node are connected.
+    pub fn is_healthy(&self) -> bool {
+        self.sessions
+            .iter()
+            .all(|session| session.state == SessionState::Connected)
+    }
+}
+
+/// A snapshot of the state of a session of a node.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct SessionDiagnostics {
+    state: SessionState,
+    local_address: Option<SocketAddr>,
+    remote_address: Option<SocketAddr>,
+    ongoing_calls: usize,
+    queued_requests: usize,
+    late_replies: u64,
+    last_error: Option<String>,
+    capabilities: BTreeMap<String, String>,
+}
+
+impl SessionDiagnostics {
+    pub(super) fn new(
+        session: &session::Client,
+        last_error: &LastError,
+        redaction: &[ValuePath],
+    ) -> Self {
+        let state = if session.is_closed() {
+            SessionState::Closed
+        } else {
+            SessionState::Connected
+        };
+        let capabilities = Value::Map(
+            session
+                .capabilities()
+                .iter()
+                .map(|(key, value)| {
+                    (
+                        Value::String(key.clone()),
+                        Value::Dynamic(Box::new(value.clone())),
+                    )
+                })
+                .collect(),
+        )
+        .redact(redaction);
+        let capabilities = capabilities
+            .as_map()
+            .into_iter()
+            .flat_map(|capabilities| capabilities.iter())
+            .filter_map(|(key, value)| Some((key.as_string()?.clone(), value.to_string())))
+            .collect();
+        Self {
+            state,
+            local_address: session.local_address(),
+            remote_address: session.remote_address(),
+            ongoing_calls: session.ongoing_calls(),
+            queued_requests: session.queued_requests(),
+            late_replies: session.late_replies(),
+            last_error: last_error.get(),
+            capabilities,
+        }
+    }
+
+    pub fn state(&self) -> SessionState {
+        self.state
+    }
+
+    pub fn local_address(&self) -> Option<SocketAddr> {
+        self.local_address
+    }
+
+    pub fn remote_address(&self) -> Option<SocketAddr> {
+        self.remote_address
+    }
+
+    /// The number of calls sent on the session that are waiting for their response.
+    pub fn ongoing_calls(&self) -> usize {
+        self.ongoing_calls
+    }
+
+    /// The number of calls and notifications that are queued to be sent on the session.
+    pub fn queued_requests(&self) -> usize {
+        self.queued_requests
+    }
+
+    /// The number of responses received for calls that were not waiting for them anymore.
+    pub fn late_replies(&self) -> u64 {
+        self.late_replies
+    }
+
+    /// The description of the error that terminated the session, if any.
+    pub fn last_error(&self) -> Option<&str> {
+        self.last_error.as_deref()
+    }
+
+    /// The capabilities resolved with the remote, with their values formatted and redacted.
+    pub fn capabilities(&self) -> &BTreeMap<String, String> {
+        &self.capabilities
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionState {
+    Connected,
+    Closed,
+}
+
+/// The last error of a session, recorded by the task that dispatches its messages.
+#[derive(Debug, Clone, Default)]
+pub(super) struct LastError(Arc<Mutex<Option<String>>>);
+
+impl LastError {
+    pub(super) fn set(&self, error: &dyn std::error::Error) {
+        *self.lock() = Some(error.to_string());
+    }
+
+    fn get(&self) -> Option<String> {
+        self.lock().clone()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<String>> {
+        // The error is always left in a consistent state, poisoning can be ignored.
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
@@ -0,0 +1,57 @@
+use std::{future::Future, sync::Arc, thread};
+use tokio::{runtime, sync::oneshot};
+
+/// The runtime that drives the input and output of the sessions of a node.
+#[derive(Debug, Clone)]
+pub(super) struct IoRuntime {
+    handle: runtime::Handle,
+    // Keeps the thread of a dedicated runtime running, until the last clone is dropped.
+    _dedicated: Option<Arc<oneshot::Sender<()>>>,
+}
+
+impl IoRuntime {
+    pub(super) fn from_handle(handle: runtime::Handle) -> Self {
+        Self {
+            handle,
+            _dedicated: None,
+        }
+    }
+
+    /// Starts a current-thread runtime on a new thread, which runs until the runtime is dropped.
+    pub(super) fn dedicated() -> std::io::Result<Self> {
+        let runtime = runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let handle = runtime.handle().clone();
+        let (stop, stopped) = oneshot::channel::<()>();
+        thread::Builder::new()
+            .name("qi-io".to_owned())
+            .spawn(move || {
+                // The sender is never used, it stops the runtime by being dropped.
+                let _result = runtime.block_on(stopped);
+            })?;
+        Ok(Self {
+            handle,
+            _dedicated: Some(Arc::new(stop)),
+        })
+    }
+
+    /// Runs a future on the runtime, and waits for its output.
+    ///
+    /// Tasks that the future spawns also run on the runtime.
+    pub(super) async fn run<F>(&self, future: F) -> Result<F::Output, IoRuntimeShutdownError>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        match self.handle.spawn(future).await {
+            Ok(output) => Ok(output),
+            Err(err) if err.is_panic() => std::panic::resume_unwind(err.into_panic()),
+            Err(_err) => Err(IoRuntimeShutdownError),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("the IO runtime of the node is shut down")]
+pub struct IoRuntimeShutdownError;
@@ -0,0 +1,221 @@
+use crate::value::object::{MetaObject, ObjectUid};
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+/// A cache of the meta objects of the services of a namespace, see
+/// [`super::NodeBuilder::meta_object_cache`].
+///
+/// Meta objects are cached by the name of their service, the uid of its main object, as advertised
+/// by the service directory, and their own hash. A service that is restarted has a new uid, its
+/// meta object is then requested again. A meta object that is requested again and changed has
+/// another hash, it replaces the cached one: the cache keeps a single meta object by service and
+/// uid.
+///
+/// The cache honors the `MetaObjectCache` capability of sessions, with which both ends agree that
+/// the meta objects that they exchange may be cached: a node reuses the cached meta objects only
+/// on a session that shares this capability. On other sessions, the meta objects are requested
+/// each time, and the cache is only updated with them.
+///
+/// The cache is a handle: clones share the same entries, which lets several nodes, such as the
+/// ones that successively reconnect to a namespace, share the meta objects that they received.
+#[derive(Debug, Clone, Default)]
+pub struct MetaObjectCache {
+    entries: Arc<Mutex<Entries>>,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    meta_objects: HashMap<Key, MetaObject>,
+    // The hash of the cached meta object of each service and uid.
+    hashes: HashMap<(String, ObjectUid), u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Key {
+    service: String,
+    object_uid: ObjectUid,
+    hash: u64,
+}
+
+impl Entries {
+    fn key(&self, service: &str, object_uid: ObjectUid) -> Option<Key> {
+        let hash = *self.hashes.get(&(service.to_owned(), object_uid))?;
+        Some(Key {
+            service: service.to_owned(),
+            object_uid,
+            hash,
+        })
+    }
+}
+
+impl MetaObjectCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the cached meta object of the main object of a service.
+    pub fn get(&self, service: &str, object_uid: ObjectUid) -> Option<MetaObject> {
+        let entries = self.lock_entries();
+        let key = entries.key(service, object_uid)?;
+        entries.meta_objects.get(&key).cloned()
+    }
+
+    /// Returns whether this meta object is the cached one of the main object of a service.
+    pub fn contains(&self, service: &str, object_uid: ObjectUid, meta_object: &MetaObject) -> bool {
+        let key = Key {
+            service: service.to_owned(),
+            object_uid,
+            hash: meta_object_hash(meta_object),
+        };
+        self.lock_entries().meta_objects.contains_key(&key)
+    }
+
+    /// Caches the meta object of the main object of a service, replacing the previous one if it
+    /// differs.
+    ///
+    /// Returns true if the meta object was not cached yet, or if it differs from the cached one.
+    pub fn insert(&self, service: &str, object_uid: ObjectUid, meta_object: MetaObject) -> bool {
+        let hash = meta_object_hash(&meta_object);
+        let mut entries = self.lock_entries();
+        let previous = entries
+            .hashes
+            .insert((service.to_owned(), object_uid), hash);
+        if previous == Some(hash) {
+            return false;
+        }
+        if let Some(previous) = previous {
+            entries.meta_objects.remove(&Key {
+                service: service.to_owned(),
+                object_uid,
+                hash: previous,
+            });
+        }
+        let key = Key {
+            service: service.to_owned(),
+            object_uid,
+            hash,
+        };
+        entries.meta_objects.insert(key, meta_object);
+        true
+    }
+
+    /// Removes the cached meta object of the main object of a service.
+    pub fn invalidate(&self, service: &str, object_uid: ObjectUid) -> bool {
+        let mut entries = self.lock_entries();
+        match entries.key(service, object_uid) {
+            Some(key) => {
+                entries.hashes.remove(&(service.to_owned(), object_uid));
+                entries.meta_objects.remove(&key).is_some()
+            }
+            None => false,
+        }
+    }
+
+    /// Removes the cached meta objects of a service, whatever the uid of its object.
+    pub fn invalidate_service(&self, service: &str) {
+        let mut entries = self.lock_entries();
+        entries.hashes.retain(|(name, _uid), _hash| name != service);
+        entries
+            .meta_objects
+            .retain(|key, _entry| key.service != service);
+    }
+
+    /// Removes all the cached meta objects.
+    pub fn clear(&self) {
+        let mut entries = self.lock_entries();
+        entries.hashes.clear();
+        entries.meta_objects.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock_entries().meta_objects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock_entries().meta_objects.is_empty()
+    }
+
+    fn lock_entries(&self) -> MutexGuard<'_, Entries> {
+        // Entries are always left consistent, poisoning can be ignored.
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+fn meta_object_hash(meta_object: &MetaObject) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    meta_object.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::{self, object::ActionId};
+
+    fn meta_object(method: &str) -> MetaObject {
+        let mut builder = MetaObject::builder();
+        builder.add_method(
+            ActionId::new(100),
+            method,
+            value::Signature::new(None),
+            value::Type::Int32,
+        );
+        builder.build()
+    }
+
+    fn uid(dword: u32) -> ObjectUid {
+        ObjectUid::new([dword; 5])
+    }
+
+    #[test]
+    fn test_meta_object_cache_hit() {
+        let cache = MetaObjectCache::new();
+        assert_eq!(cache.get("A", uid(1)), None);
+        assert!(cache.insert("A", uid(1), meta_object("f")));
+        assert_eq!(cache.get("A", uid(1)), Some(meta_object("f")));
+        assert!(cache.contains("A", uid(1), &meta_object("f")));
+        // Entries are by service and uid.
+        assert_eq!(cache.get("A", uid(2)), None);
+        assert_eq!(cache.get("B", uid(1)), None);
+        // Clones share the entries.
+        assert_eq!(cache.clone().get("A", uid(1)), Some(meta_object("f")));
+        // The same meta object is not inserted again.
+        assert!(!cache.insert("A", uid(1), meta_object("f")));
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn test_meta_object_cache_replace_on_change() {
+        let cache = MetaObjectCache::new();
+        cache.insert("A", uid(1), meta_object("f"));
+        assert!(cache.insert("A", uid(1), meta_object("g")));
+        assert_eq!(cache.get("A", uid(1)), Some(meta_object("g")));
+        assert!(!cache.contains("A", uid(1), &meta_object("f")));
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn test_meta_object_cache_invalidation() {
+        let cache = MetaObjectCache::new();
+        cache.insert("A", uid(1), meta_object("f"));
+        cache.insert("A", uid(2), meta_object("f"));
+        cache.insert("B", uid(3), meta_object("f"));
+
+        assert!(cache.invalidate("A", uid(1)));
+        assert!(!cache.invalidate("A", uid(1)));
+        assert_eq!(cache.get("A", uid(1)), None);
+        assert_eq!(cache.len(), 2);
+
+        cache.invalidate_service("A");
+        assert_eq!(cache.get("A", uid(2)), None);
+        assert_eq!(cache.get("B", uid(3)), Some(meta_object("f")));
+
+        cache.clear();
+        assert!(cache.is_empty());
+        // An invalidated meta object is cached again.
+        assert!(cache.insert("B", uid(3), meta_object("f")));
+    }
+}
@@ -0,0 +1,145 @@
+use super::diagnostics::{LastError, SessionDiagnostics};
+use crate::{
+    messaging::{
+        session::{self, ChannelStats},
+        CapabilitiesMap,
+    },
+    value::{object::ServiceId, ValuePath},
+};
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+use tokio::sync::oneshot;
+
+/// A handle to a session of a node, see [`super::Node::sessions`].
+///
+/// The handle gives access to the live state of the session, such as its statistics, and may
+/// close the session. The services that the node served over the session are the ones it had
+/// registered when the handle was created.
+#[derive(Debug, Clone)]
+pub struct SessionHandle {
+    client: session::Client,
+    last_error: LastError,
+    close: CloseSession,
+    diagnostics_redaction: Arc<[ValuePath]>,
+    services: Vec<(String, ServiceId)>,
+}
+
+impl SessionHandle {
+    pub(super) fn new(
+        client: session::Client,
+        last_error: LastError,
+        close: CloseSession,
+        diagnostics_redaction: Arc<[ValuePath]>,
+        services: Vec<(String, ServiceId)>,
+    ) -> Self {
+        Self {
+            client,
+            last_error,
+            close,
+            diagnostics_redaction,
+            services,
+        }
+    }
+
+    pub fn local_address(&self) -> Option<SocketAddr> {
+        self.client.local_address()
+    }
+
+    pub fn remote_address(&self) -> Option<SocketAddr> {
+        self.client.remote_address()
+    }
+
+    /// The capabilities resolved with the remote.
+    pub fn capabilities(&self) -> CapabilitiesMap {
+        self.client.capabilities()
+    }
+
+    /// The services registered by the node, by name, that are served over the session.
+    pub fn services(&self) -> &[(String, ServiceId)] {
+        &self.services
+    }
+
+    /// Returns a snapshot of the statistics of the messages of the session.
+    pub fn stats(&self) -> ChannelStats {
+        self.client.stats()
+    }
+
+    /// Returns a snapshot of the state of the session, redacted as the diagnostics of the node.
+    pub fn diagnostics(&self) -> SessionDiagnostics {
+        SessionDiagnostics::new(&self.client, &self.last_error, &self.diagnostics_redaction)
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.client.is_closed()
+    }
+
+    /// Closes the session, for instance to disconnect a misbehaving peer.
+    ///
+    /// The services of the node are not unregistered beforehand, the remote drops them with the
+    /// session. Returns false if the session was already closed, by a previous call or by the
+    /// shutdown of the node.
+    pub fn disconnect(&self) -> bool {
+        self.close.close()
+    }
+}
+
+/// Closes a session, once, on behalf of the node or of any of its handles.
+#[derive(Debug, Clone)]
+pub(super) struct CloseSession(Arc<Mutex<Option<oneshot::Sender<()>>>>);
+
+impl CloseSession {
+    pub(super) fn new(sender: oneshot::Sender<()>) -> Self {
+        Self(Arc::new(Mutex::new(Some(sender))))
+    }
+
+    pub(super) fn close(&self) -> bool {
+        match self.lock().take() {
+            // The session may have already terminated, in which case there is nothing to close.
+            Some(sender) => sender.send(()).is_ok(),
+            None => false,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<oneshot::Sender<()>>> {
+        // The sender is taken at once, poisoning can be ignored.
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// The sessions served by the listener of a node, see [`super::NodeBuilder::serve`].
+///
+/// Sessions are added once they are established, and are dropped once they are closed.
+#[cfg(feature = "server")]
+#[derive(Debug, Clone, Default)]
+pub(super) struct ListenerSessions(Arc<Mutex<Vec<ListenerSession>>>);
+
+#[cfg(feature = "server")]
+#[derive(Debug, Clone)]
+pub(super) struct ListenerSession {
+    pub(super) client: session::Client,
+    pub(super) last_error: LastError,
+    pub(super) close: CloseSession,
+}
+
+#[cfg(feature = "server")]
+impl ListenerSessions {
+    pub(super) fn insert(&self, session: ListenerSession) {
+        let mut sessions = self.lock();
+        sessions.retain(|session| !session.client.is_closed());
+        sessions.push(session);
+    }
+
+    /// Returns the sessions that are not closed.
+    pub(super) fn list(&self) -> Vec<ListenerSession> {
+        let mut sessions = self.lock();
+        sessions.retain(|session| !session.client.is_closed());
+        sessions.clone()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<ListenerSession>> {
+        // Sessions are added and removed at once, poisoning can be ignored.
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
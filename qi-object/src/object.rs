@@ -21,7 +21,7 @@ pub trait Object {
         service: ServiceId,
         event: ActionId,
         link: signal::Link,
-    ) -> BoxFuture<CallResult<signal::Link, Self::Error>>;
+    ) -> BoxFuture<'_, CallResult<signal::Link, Self::Error>>;
 
     fn register_event_with_signature(
         &mut self,
@@ -29,47 +29,75 @@ pub trait Object {
         event: ActionId,
         link: signal::Link,
         signature: Signature,
-    ) -> BoxFuture<CallResult<signal::Link, Self::Error>>;
+    ) -> BoxFuture<'_, CallResult<signal::Link, Self::Error>>;
 
     fn unregister_event(
         &mut self,
         service: ServiceId,
         event: ActionId,
         link: signal::Link,
-    ) -> BoxFuture<CallResult<(), Self::Error>>;
+    ) -> BoxFuture<'_, CallResult<(), Self::Error>>;
 
-    fn meta_object(&self, id: ObjectId) -> BoxFuture<CallResult<MetaObject, Self::Error>>;
+    fn meta_object(&self, id: ObjectId) -> BoxFuture<'_, CallResult<MetaObject, Self::Error>>;
 
-    fn property(&self, name: value::Dynamic) -> BoxFuture<CallResult<value::Dynamic, Self::Error>>;
+    fn property(
+        &self,
+        name: value::Dynamic,
+    ) -> BoxFuture<'_, CallResult<value::Dynamic, Self::Error>>;
 
     fn set_property(
         &mut self,
         name: value::Dynamic,
         value: value::Dynamic,
-    ) -> BoxFuture<CallResult<(), Self::Error>>;
+    ) -> BoxFuture<'_, CallResult<(), Self::Error>>;
 
-    fn properties(&self) -> BoxFuture<CallResult<Vec<String>, Self::Error>>;
+    fn properties(&self) -> BoxFuture<'_, CallResult<Vec<String>, Self::Error>>;
 
     fn call<T>(
         &mut self,
         action: BoundAction,
         value: T,
-    ) -> BoxFuture<CallResult<Value, Self::Error>>
+    ) -> BoxFuture<'_, CallResult<Value, Self::Error>>
     where
         T: serde::Serialize; // TODO: T: Value
 
-    fn post<T>(&mut self, action: BoundAction, value: T) -> BoxFuture<Result<(), Self::Error>>
+    fn post<T>(&mut self, action: BoundAction, value: T) -> BoxFuture<'_, Result<(), Self::Error>>
     where
         T: serde::Serialize; // TODO: T: Value
 
-    fn event<T>(&mut self, action: BoundAction, value: T) -> BoxFuture<Result<(), Self::Error>>
+    fn event<T>(&mut self, action: BoundAction, value: T) -> BoxFuture<'_, Result<(), Self::Error>>
     where
         T: serde::Serialize; // TODO: T: Value
 }
 
+/// An object served by a node to remote clients, see [`crate::Node::register_service`].
+///
+/// The node answers the calls of the methods common to all objects, such as the requests of the
+/// meta object and the registrations to signals. It dispatches the calls of the other methods of
+/// the meta object to the object, with their arguments typed after the parameters signature of
+/// the method.
+pub trait ServedObject: Send + Sync {
+    /// The meta object of the object, sent to the clients that request it.
+    fn meta_object(&self) -> &MetaObject;
+
+    /// Calls a method of the meta object of the object. The returned value is sent as the reply
+    /// of the call, and must match the return signature of the method.
+    fn call(
+        &self,
+        action: ActionId,
+        args: value::Dynamic,
+    ) -> BoxFuture<'static, CallResult<Value, Box<dyn std::error::Error + Send + Sync>>>;
+}
+
 #[derive(Debug)]
 pub struct BoundAction(ActionId);
 
+impl BoundAction {
+    pub fn action(&self) -> ActionId {
+        self.0
+    }
+}
+
 // static OBJECT_META_OBJECT: OnceCell<MetaObject> = OnceCell::new();
 //
 // fn bound_object_meta_object() -> &'static MetaObject {
@@ -5,27 +5,52 @@ use crate::{
         session::{self, Subject},
         CallResult, CallTermination, Service,
     },
-    value::object::{ActionId, MetaObject, ObjectId, ObjectUid, ServiceId},
+    value::{
+        self,
+        dynamic::DynamicSeed,
+        object::{ActionId, MetaMethod, MetaObject, ObjectId, ServiceId},
+        ty::DynamicGetType,
+        Dynamic, Raw, Signature, Type, Value,
+    },
 };
-use futures::{ready, FutureExt};
+use futures::{ready, stream::BoxStream, Stream, StreamExt};
+use once_cell::sync::OnceCell;
 use pin_project_lite::pin_project;
 use std::{
     fmt::Debug,
     future::Future,
     marker::PhantomData,
     pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
     task::{Context, Poll},
+    time::Duration,
 };
-use tracing::{instrument, trace_span, Instrument};
+use tracing::{debug, instrument, trace, trace_span, warn, Instrument};
+
+pub(crate) const SERVICE_MAIN_OBJECT: ObjectId = ObjectId::new(1);
 
-const SERVICE_MAIN_OBJECT: ObjectId = ObjectId::new(1);
+/// The default maximum duration of a request of the meta object of an object, see
+/// [`Client::with_meta_object_timeout`].
+///
+/// Some services never answer this request, which must not block the client.
+pub const DEFAULT_META_OBJECT_TIMEOUT: Duration = Duration::from_secs(5);
 
+/// A client of a remote object.
+///
+/// The meta object of the object is requested when the client connects. If this request times
+/// out or if the object replies with an error, the client is still usable: methods may be called
+/// by their action id with [`Client::call_action`], and the meta object is requested again when it
+/// is needed. The connection fails if the session fails.
 #[derive(Debug, Clone)]
 pub struct Client {
     client: session::Client,
     subject_service_object: session::subject::ServiceObject,
-    meta_object: MetaObject,
-    object_uid: ObjectUid,
+    meta_object: Arc<OnceCell<MetaObject>>,
+    meta_object_timeout: Duration,
+    validate_arguments: bool,
 }
 
 fn call_action<Args, R>(
@@ -45,31 +70,57 @@ where
 }
 
 impl Client {
-    #[instrument(level = "trace", ret)]
     pub(crate) async fn connect(
         client: session::Client,
         service_id: ServiceId,
         object_id: ObjectId,
     ) -> CallResult<Self, ConnectError> {
-        let subject_service_object = session::subject::ServiceObject::new(service_id, object_id)
-            .ok_or(ConnectError::Subject(service_id, object_id))?;
-
-        let meta_object = call_action(
-            &client,
-            subject_service_object,
-            ACTION_ID_METAOBJECT,
+        Self::connect_with_meta_object(
+            client,
+            service_id,
             object_id,
+            None,
+            DEFAULT_META_OBJECT_TIMEOUT,
         )
-        .instrument(trace_span!("get_meta_object"))
         .await
-        .map_err(|err| err.map_err(ConnectError::GetServiceDirectoryMetaObject))?;
+    }
 
-        Ok(Self {
+    /// Connects to an object of which the meta object may already be known, in which case it is
+    /// not requested.
+    #[instrument(level = "trace", skip(meta_object), ret)]
+    pub(crate) async fn connect_with_meta_object(
+        client: session::Client,
+        service_id: ServiceId,
+        object_id: ObjectId,
+        meta_object: Option<MetaObject>,
+        meta_object_timeout: Duration,
+    ) -> CallResult<Self, ConnectError> {
+        let subject_service_object = session::subject::ServiceObject::new(service_id, object_id)
+            .ok_or(ConnectError::Subject(service_id, object_id))?;
+
+        let this = Self {
             client,
             subject_service_object,
-            meta_object,
-            object_uid: ObjectUid::default(), // TODO: Generate an object UID
-        })
+            meta_object: Arc::new(meta_object.map(OnceCell::with_value).unwrap_or_default()),
+            meta_object_timeout,
+            validate_arguments: false,
+        };
+        match this.fetch_meta_object().await {
+            Ok(_meta_object) => {}
+            // Failing to get the meta object does not prevent using the object.
+            Err(CallTermination::Error(
+                CallError::MetaObjectTimeout | CallError::Client(session::ClientError::Timeout(_)),
+            )) => debug!("the request of the meta object of the object timed out"),
+            // The object may not exist, which is only known by the error of the remote.
+            Err(CallTermination::Error(
+                err @ (CallError::Client(session::ClientError::Service(_)) | CallError::Format(_)),
+            )) => warn!(
+                error = &err as &dyn std::error::Error,
+                "failed to get the meta object of the object"
+            ),
+            Err(err) => return Err(err.map_err(ConnectError::MetaObject)),
+        }
+        Ok(this)
     }
 
     pub(crate) async fn connect_to_service_object(
@@ -79,31 +130,373 @@ impl Client {
         Self::connect(client, service_id, SERVICE_MAIN_OBJECT).await
     }
 
-    pub(crate) fn call<Args, R>(&self, name: &str, args: Args) -> CallFuture<R>
+    /// Returns a client of an object received from this object, for instance as the return value
+    /// of a call.
+    ///
+    /// The meta object of the object is requested with the same timeout as the one of this
+    /// client.
+    pub async fn object(&self, object: &value::object::Object) -> CallResult<Self, ConnectError> {
+        Self::connect_with_meta_object(
+            self.client.clone(),
+            object.service_id,
+            object.object_id,
+            None,
+            self.meta_object_timeout,
+        )
+        .await
+    }
+
+    /// Calls a method of the object by its name, as found in the meta object.
+    ///
+    /// The meta object is requested to the object if it has not been received yet, for instance
+    /// because its request failed when the client connected.
+    pub async fn call<Args, R>(&self, name: &str, args: Args) -> CallResult<R, CallError>
     where
         Args: serde::Serialize,
+        R: serde::de::DeserializeOwned,
     {
-        let method = self
-            .meta_object
+        let action = self
+            .fetch_meta_object()
+            .await?
             .methods
             .iter()
-            .find(|(_action, method)| method.name == name);
-        let action = match method {
-            Some((action, _method)) => *action,
-            None => return CallFuture::new_method_not_found(name),
-        };
-        call_action(&self.client, self.subject_service_object, action, args)
+            .find(|(_action, method)| method.name == name)
+            .map(|(action, _method)| *action)
+            .ok_or_else(|| CallTermination::Error(CallError::MethodNotFound(name.to_owned())))?;
+        call_action(&self.client, self.subject_service_object, action, args).await
     }
 
-    pub(crate) fn call_action<Args, R>(&self, action: ActionId, args: Args) -> CallFuture<R>
+    /// Calls a method of the object by its action id.
+    ///
+    /// The arguments must be serialized as the parameters signature of the method, and the return
+    /// value is deserialized as `R`. This does not require the meta object of the object, but if
+    /// it has been received, the call fails if the object has no such method.
+    pub fn call_action<Args, R>(&self, action: ActionId, args: Args) -> CallFuture<R>
     where
         Args: serde::Serialize,
     {
-        if !self.meta_object.methods.contains_key(&action) {
-            return CallFuture::new_action_not_found(action);
+        if let Some(meta_object) = self.meta_object.get() {
+            if !meta_object.methods.contains_key(&action) {
+                return CallFuture::new_action_not_found(action);
+            }
         }
         call_action(&self.client, self.subject_service_object, action, args)
     }
+
+    /// Posts a call of a method of the object by its action id, without waiting for its result.
+    ///
+    /// The method is executed by the remote object, but its result, or its failure, is never sent
+    /// back: this returns as soon as the post is sent. As with [`Client::call_action`], if the
+    /// meta object has been received, the post fails if the object has no such method. Posts
+    /// target methods only, the values of signals are emitted by their object as events.
+    pub async fn post_action<Args>(&self, action: ActionId, args: Args) -> Result<(), CallError>
+    where
+        Args: serde::Serialize,
+    {
+        if let Some(meta_object) = self.meta_object.get() {
+            if !meta_object.methods.contains_key(&action) {
+                return Err(CallError::ActionNotFound(action));
+            }
+        }
+        let subject = Subject::new(self.subject_service_object, action);
+        let post = session::Post::new(subject).with_value(&args)?;
+        let mut client = &self.client;
+        client.notify(post.into()).await?;
+        Ok(())
+    }
+
+    /// Enables the validation of the arguments of calls made with [`Client::call_value`].
+    ///
+    /// Arguments that do not match the parameters signature of the method in the meta object are
+    /// rejected locally with [`CallError::InvalidArguments`], instead of being sent to the remote
+    /// object which would fail to deserialize them.
+    pub fn with_argument_validation(mut self) -> Self {
+        self.validate_arguments = true;
+        self
+    }
+
+    /// Sets the maximum duration of the requests of the meta object of the object made by this
+    /// client, which is [`DEFAULT_META_OBJECT_TIMEOUT`] by default.
+    ///
+    /// This applies to the requests made once the client is connected, see
+    /// [`NodeBuilder::meta_object_timeout`](crate::node::NodeBuilder::meta_object_timeout) for the
+    /// one made when it connects.
+    pub fn with_meta_object_timeout(mut self, timeout: Duration) -> Self {
+        self.meta_object_timeout = timeout;
+        self
+    }
+
+    /// Returns the meta object of the object, if it has been received.
+    pub fn meta_object(&self) -> Option<&MetaObject> {
+        self.meta_object.get()
+    }
+
+    /// Returns the meta object of the object, requesting it to the object if it has not been
+    /// received yet.
+    pub async fn fetch_meta_object(&self) -> CallResult<&MetaObject, CallError> {
+        if let Some(meta_object) = self.meta_object.get() {
+            return Ok(meta_object);
+        }
+        let request = call_action::<_, MetaObject>(
+            &self.client,
+            self.subject_service_object,
+            ACTION_ID_METAOBJECT,
+            self.subject_service_object.object(),
+        )
+        // Descriptions are sometimes not valid UTF-8, which must not prevent using the object.
+        .with_utf8_policy(format::Utf8Policy::Lossy)
+        .instrument(trace_span!("get_meta_object"));
+        let meta_object = tokio::time::timeout(self.meta_object_timeout, request)
+            .await
+            .map_err(|_elapsed| CallTermination::Error(CallError::MetaObjectTimeout))??;
+        // Another request may have received the meta object concurrently, they are equivalent.
+        Ok(self.meta_object.get_or_init(|| meta_object))
+    }
+
+    /// Calls a method of the object, with its return value typed after its signature in the
+    /// meta object.
+    ///
+    /// The arguments must be serialized as the parameters signature of the method.
+    pub async fn call_dynamic<Args>(
+        &self,
+        action: ActionId,
+        args: Args,
+    ) -> CallResult<Dynamic, CallError>
+    where
+        Args: serde::Serialize,
+    {
+        let method = self
+            .fetch_meta_object()
+            .await?
+            .methods
+            .get(&action)
+            .ok_or(CallTermination::Error(CallError::ActionNotFound(action)))?;
+        let call = session::Call::new(Subject::new(self.subject_service_object, action))
+            .with_return_type()
+            .with_value(&args)
+            .map_err(|err| CallTermination::Error(CallError::Format(err)))?;
+        let mut client = &self.client;
+        let reply = client
+            .call(call)
+            .await
+            .map_err(|err| err.map_err(CallError::Client))?;
+        // The signature sent by the remote along with the reply is more precise than the one of
+        // the meta object, for methods that return a `dynamic` value.
+        let return_type = reply
+            .return_signature()
+            .unwrap_or(&method.return_signature)
+            .clone()
+            .into_type();
+        let value = reply
+            .value_seed(DynamicSeed::new(return_type))
+            .map_err(|err| CallTermination::Error(CallError::Format(err)))?;
+        Ok(value)
+    }
+
+    /// Subscribes to a signal of the object.
+    ///
+    /// Returns the stream of the values emitted by the signal, deserialized as `T`. The
+    /// subscription is terminated when the stream is dropped.
+    pub async fn subscribe<T>(&self, signal: &str) -> CallResult<SignalStream<T>, CallError> {
+        let (_replayed, stream) = self.register_event(signal, None).await?;
+        Ok(stream)
+    }
+
+    /// Subscribes to a signal of the object, and returns up to `count` of its last values, from
+    /// the oldest, along with the stream of its next values, see [`Client::subscribe`].
+    ///
+    /// The last values are kept by objects that replay the signal, see
+    /// [`SubscriptionSet::set_replay_capacity`](crate::signal::SubscriptionSet::set_replay_capacity),
+    /// and are sent with the reply to the registration, see
+    /// [`ACTION_ID_REGISTER_EVENT_WITH_REPLAY`]. If other subscribers to the signal share the
+    /// session, the stream may yield some of the replayed values too.
+    pub async fn subscribe_with_replay<T>(
+        &self,
+        signal: &str,
+        count: u32,
+    ) -> CallResult<(Vec<T>, SignalStream<T>), CallError>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let (replayed, stream) = self.register_event(signal, Some(count)).await?;
+        let replayed = replayed
+            .into_iter()
+            .map(|value| format::from_value(&format::Value::from_bytes(value)))
+            .collect::<Result<_, _>>()
+            .map_err(|err| CallTermination::Error(CallError::Format(err)))?;
+        Ok((replayed, stream))
+    }
+
+    async fn register_event<T>(
+        &self,
+        signal: &str,
+        replay: Option<u32>,
+    ) -> CallResult<(Vec<Raw>, SignalStream<T>), CallError> {
+        let action = self
+            .fetch_meta_object()
+            .await?
+            .signals
+            .iter()
+            .find(|(_action, meta_signal)| meta_signal.name == signal)
+            .map(|(action, _meta_signal)| *action)
+            .ok_or_else(|| CallTermination::Error(CallError::SignalNotFound(signal.to_owned())))?;
+
+        // Events are received from the start of the registration, so that none is missed.
+        let subject = Subject::new(self.subject_service_object, action);
+        let events = self
+            .client
+            .events(move |event_subject| *event_subject == subject)
+            .boxed();
+        static NEXT_LINK: AtomicU64 = AtomicU64::new(1);
+        let link = signal::Link::from(NEXT_LINK.fetch_add(1, Ordering::Relaxed));
+        let service = self.subject_service_object.service();
+        let (link, replayed) = match replay {
+            None => {
+                let link = call_action(
+                    &self.client,
+                    self.subject_service_object,
+                    ACTION_ID_REGISTER_EVENT,
+                    (service, action, link),
+                )
+                .await?;
+                (link, Vec::new())
+            }
+            Some(count) => {
+                call_action(
+                    &self.client,
+                    self.subject_service_object,
+                    ACTION_ID_REGISTER_EVENT_WITH_REPLAY,
+                    (service, action, link, count),
+                )
+                .await?
+            }
+        };
+        let stream = SignalStream {
+            events,
+            registration: Some(SignalRegistration {
+                client: self.client.clone(),
+                service_object: self.subject_service_object,
+                action,
+                link,
+            }),
+            phantom: PhantomData,
+        };
+        Ok((replayed, stream))
+    }
+
+    /// Calls a method of the object with arguments as a value, with its return value typed after
+    /// its signature in the meta object.
+    ///
+    /// If argument validation is enabled (see [`Client::with_argument_validation`]), the
+    /// arguments are checked against the parameters signature of the method before being sent.
+    pub async fn call_value(
+        &self,
+        action: ActionId,
+        args: Value,
+    ) -> CallResult<Dynamic, CallError> {
+        if self.validate_arguments {
+            let method = self
+                .fetch_meta_object()
+                .await?
+                .methods
+                .get(&action)
+                .ok_or(CallTermination::Error(CallError::ActionNotFound(action)))?;
+            validate_arguments(method, &args).map_err(CallTermination::Error)?;
+        }
+        self.call_dynamic(action, args).await
+    }
+}
+
+/// The stream of the values emitted by a signal of an object, see [`Client::subscribe`].
+pub struct SignalStream<T> {
+    events: BoxStream<'static, (Subject, Raw)>,
+    registration: Option<SignalRegistration>,
+    phantom: PhantomData<fn() -> T>,
+}
+
+#[derive(Debug)]
+struct SignalRegistration {
+    client: session::Client,
+    service_object: session::subject::ServiceObject,
+    action: ActionId,
+    link: signal::Link,
+}
+
+impl<T> Stream for SignalStream<T>
+where
+    T: serde::de::DeserializeOwned,
+{
+    type Item = Result<T, format::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let value = ready!(self.events.poll_next_unpin(cx))
+            .map(|(_subject, content)| format::from_value(&format::Value::from_bytes(content)));
+        Poll::Ready(value)
+    }
+}
+
+impl<T> Debug for SignalStream<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SignalStream")
+            .field("registration", &self.registration)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T> Drop for SignalStream<T> {
+    fn drop(&mut self) {
+        if let Some(SignalRegistration {
+            client,
+            service_object,
+            action,
+            link,
+        }) = self.registration.take()
+        {
+            let unregister = call_action::<_, ()>(
+                &client,
+                service_object,
+                ACTION_ID_UNREGISTER_EVENT,
+                (service_object.service(), action, link),
+            );
+            // This is a best effort, the remote object terminates the subscription anyway when
+            // the session is closed.
+            tokio::spawn(async move {
+                if let Err(err) = unregister.await {
+                    trace!(error = ?err, "failed to unregister from a signal");
+                }
+            });
+        }
+    }
+}
+
+fn validate_arguments(method: &MetaMethod, args: &Value) -> Result<(), CallError> {
+    let expected = &method.parameters_signature;
+    let valid =
+        match (expected.clone().into_type(), args) {
+            // A dynamic signature accepts any value.
+            (None, _) => true,
+            // The conversion of tuple types ignores their elements, that are checked one by one.
+            (Some(Type::Tuple(parameters)), Value::Tuple(args)) => {
+                parameters.len() == args.len()
+                    && parameters.element_types().iter().zip(args.elements()).all(
+                        |(parameter, arg)| match parameter {
+                            // A dynamic parameter accepts any value.
+                            None => true,
+                            Some(parameter) => arg.has_type(Some(parameter)),
+                        },
+                    )
+            }
+            (Some(t), args) => args.has_type(Some(&t)),
+        };
+    if valid {
+        Ok(())
+    } else {
+        Err(CallError::InvalidArguments {
+            method: method.name.clone(),
+            expected: expected.clone(),
+            provided: Signature::new(args.dynamic_type()),
+        })
+    }
 }
 
 pin_project! {
@@ -111,9 +504,6 @@ pin_project! {
     #[must_use = "futures do nothing until polled"]
     #[project = CallFutureProj]
     pub enum CallFuture<R> {
-        MethodNotFound {
-            name: String
-        },
         ActionNotFound {
             action: ActionId
         },
@@ -123,16 +513,13 @@ pin_project! {
         Call {
             #[pin]
             call: session::CallFuture,
+            utf8_policy: format::Utf8Policy,
             phantom: PhantomData<R>,
         },
     }
 }
 
 impl<R> CallFuture<R> {
-    fn new_method_not_found(name: impl Into<String>) -> Self {
-        CallFuture::MethodNotFound { name: name.into() }
-    }
-
     fn new_action_not_found(action: impl Into<ActionId>) -> Self {
         CallFuture::ActionNotFound {
             action: action.into(),
@@ -146,9 +533,18 @@ impl<R> CallFuture<R> {
     fn new_call(call: session::CallFuture) -> Self {
         Self::Call {
             call,
+            utf8_policy: format::Utf8Policy::default(),
             phantom: PhantomData,
         }
     }
+
+    /// Sets the policy for strings of the reply that are not valid UTF-8.
+    fn with_utf8_policy(mut self, policy: format::Utf8Policy) -> Self {
+        if let Self::Call { utf8_policy, .. } = &mut self {
+            *utf8_policy = policy;
+        }
+        self
+    }
 }
 
 impl<R> Future for CallFuture<R>
@@ -164,15 +560,16 @@ where
                 Some(err) => Poll::Ready(Err(CallTermination::Error(CallError::Format(err)))),
                 None => Poll::Pending,
             },
-            CallFutureProj::MethodNotFound { name } => Poll::Ready(Err(CallTermination::Error(
-                CallError::MethodNotFound(name.clone()),
-            ))),
             CallFutureProj::ActionNotFound { action } => Poll::Ready(Err(CallTermination::Error(
                 CallError::ActionNotFound(*action),
             ))),
-            CallFutureProj::Call { call, .. } => {
+            CallFutureProj::Call {
+                call, utf8_policy, ..
+            } => {
                 let reply = ready!(call.poll(cx).map_err(|err| err.map_err(CallError::Client)))?;
-                let result = reply.value().map_err(CallError::Format)?;
+                let result = reply
+                    .value_with_utf8_policy(*utf8_policy)
+                    .map_err(CallError::Format)?;
                 Poll::Ready(Ok(result))
             }
         }
@@ -190,28 +587,57 @@ pub enum CallError {
     #[error("no function named \"{0}\" was found")]
     MethodNotFound(String),
 
+    #[error("no signal named \"{0}\" was found")]
+    SignalNotFound(String),
+
+    #[error("the request of the meta object of the object timed out")]
+    MetaObjectTimeout,
+
     #[error("format error")]
     Format(#[from] format::Error),
+
+    #[error(
+        "invalid arguments for method \"{method}\", expected signature \"{expected}\" but \
+         arguments have signature \"{provided}\""
+    )]
+    InvalidArguments {
+        method: String,
+        expected: Signature,
+        provided: Signature,
+    },
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum ConnectError {
-    #[error("failure to get the service directory meta object")]
-    GetServiceDirectoryMetaObject(#[from] CallError),
-
     #[error("service subject(service: \"{0}\", object: \"{1}\") is invalid")]
     Subject(ServiceId, ObjectId),
+
+    #[error("failed to get the meta object of the object")]
+    MetaObject(#[source] CallError),
 }
 
-const ACTION_ID_REGISTER_EVENT: ActionId = ActionId::new(0);
-const ACTION_ID_UNREGISTER_EVENT: ActionId = ActionId::new(1);
-const ACTION_ID_METAOBJECT: ActionId = ActionId::new(2);
-const ACTION_ID_TERMINATE: ActionId = ActionId::new(3);
-const ACTION_ID_PROPERTY: ActionId = ActionId::new(5); // not a typo, there is no action 4
-const ACTION_ID_SET_PROPERTY: ActionId = ActionId::new(6);
-const ACTION_ID_PROPERTIES: ActionId = ActionId::new(7);
-const ACTION_ID_REGISTER_EVENT_WITH_SIGNATURE: ActionId = ActionId::new(8);
-const UNRESERVED_ACTION_START_ID: ActionId = ActionId::new(100);
+/// The action of objects that registers a link to one of their signals, with the arguments
+/// `(service, signal, link)`, and that returns the registered link.
+pub const ACTION_ID_REGISTER_EVENT: ActionId = ActionId::new(0);
+/// The action of objects that unregisters a link from one of their signals, with the arguments
+/// `(service, signal, link)`.
+pub const ACTION_ID_UNREGISTER_EVENT: ActionId = ActionId::new(1);
+/// The action of objects that returns their meta object, with the id of the object as argument.
+pub(crate) const ACTION_ID_METAOBJECT: ActionId = ActionId::new(2);
+/// The action of objects that registers a link to one of their signals and replays its last
+/// values, with the arguments `(service, signal, link, count)`, and that returns the registered
+/// link with up to `count` of the last values of the signal, from the oldest, each as the raw
+/// content of the event that would carry it.
+///
+/// This action is an extension of this library, in the range of the actions reserved for the
+/// objects: the objects of other implementations do not have it.
+pub const ACTION_ID_REGISTER_EVENT_WITH_REPLAY: ActionId = ActionId::new(9);
+// const ACTION_ID_TERMINATE: ActionId = ActionId::new(3);
+// const ACTION_ID_PROPERTY: ActionId = ActionId::new(5); // not a typo, there is no action 4
+// const ACTION_ID_SET_PROPERTY: ActionId = ActionId::new(6);
+// const ACTION_ID_PROPERTIES: ActionId = ActionId::new(7);
+// const ACTION_ID_REGISTER_EVENT_WITH_SIGNATURE: ActionId = ActionId::new(8);
+// const UNRESERVED_ACTION_START_ID: ActionId = ActionId::new(100);
 
 // const ACTION_OBJECT_IS_STATS_ENABLED: ActionId = ActionId::new(80);
 // const ACTION_OBJECT_ENABLE_STATS: ActionId = ActionId::new(81);
@@ -220,3 +646,253 @@ const UNRESERVED_ACTION_START_ID: ActionId = ActionId::new(100);
 // const ACTION_OBJECT_IS_TRACE_ENABLED: ActionId = ActionId::new(84);
 // const ACTION_OBJECT_ENABLE_TRACE: ActionId = ActionId::new(85);
 // const ACTION_OBJECT_TRACE_OBJECT: ActionId = ActionId::new(86);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        messaging::GetSubject,
+        value::{object::MetaObjectBuilder, Tuple},
+    };
+    use futures::{
+        future::{self, BoxFuture},
+        FutureExt,
+    };
+    use std::sync::atomic::AtomicUsize;
+    use tokio::{spawn, task::JoinHandle};
+
+    fn method(parameters_signature: &str) -> MetaMethod {
+        let mut builder = MetaObjectBuilder::new();
+        let uid = ActionId::new(100);
+        builder.add_method(
+            uid,
+            "move",
+            parameters_signature.parse::<Signature>().unwrap(),
+            Type::Unit,
+        );
+        builder.build().methods.remove(&uid).unwrap()
+    }
+
+    fn args<const N: usize>(values: [Value; N]) -> Value {
+        Value::Tuple(Tuple::from_vec(values.into()))
+    }
+
+    #[test]
+    fn test_validate_arguments_tuple() {
+        let method = method("(is)");
+        assert_matches_ok(validate_arguments(
+            &method,
+            &args([Value::from(1i32), Value::from("x".to_owned())]),
+        ));
+        let error = validate_arguments(&method, &args([Value::from("x".to_owned())])).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid arguments for method \"move\", expected signature \"(is)\" but arguments \
+             have signature \"(s)\""
+        );
+        // The elements are checked, not only the size of the tuple.
+        let error = validate_arguments(
+            &method,
+            &args([Value::from("x".to_owned()), Value::from(1i32)]),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            CallError::InvalidArguments { provided, .. } if provided.to_string() == "(si)"
+        ));
+        // The arguments are a tuple, a single value is not accepted for a tuple of one element.
+        let method = self::method("(i)");
+        assert_matches_ok(validate_arguments(&method, &args([Value::from(1i32)])));
+        assert!(validate_arguments(&method, &Value::from(1i32)).is_err());
+    }
+
+    #[test]
+    fn test_validate_arguments_no_parameters() {
+        let method = method("()");
+        assert_matches_ok(validate_arguments(&method, &args([])));
+        assert_matches_ok(validate_arguments(&method, &Value::Unit));
+        let error = validate_arguments(&method, &args([Value::from(true)])).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid arguments for method \"move\", expected signature \"()\" but arguments \
+             have signature \"(b)\""
+        );
+    }
+
+    #[test]
+    fn test_validate_arguments_dynamic() {
+        // A dynamic parameter accepts a value of any type.
+        let method = method("(mi)");
+        assert_matches_ok(validate_arguments(
+            &method,
+            &args([Value::from("x".to_owned()), Value::from(1i32)]),
+        ));
+        assert_matches_ok(validate_arguments(
+            &method,
+            &args([Value::from(true), Value::from(1i32)]),
+        ));
+        assert!(
+            validate_arguments(&method, &args([Value::from(true), Value::from(true)])).is_err()
+        );
+
+        // A dynamic signature accepts any arguments.
+        let method = self::method("m");
+        assert_matches_ok(validate_arguments(&method, &Value::from(1i32)));
+        assert_matches_ok(validate_arguments(&method, &args([Value::from(true)])));
+    }
+
+    fn assert_matches_ok(result: Result<(), CallError>) {
+        if let Err(err) = result {
+            panic!("unexpected error: {err}");
+        }
+    }
+
+    const ACTION_ID_ADD: ActionId = ActionId::new(100);
+
+    /// How a remote object fails the first requests of its meta object.
+    #[derive(Debug, Clone, Copy)]
+    enum MetaObjectFailure {
+        NoReply,
+        Error,
+    }
+
+    /// An object with an "add" method, that fails the first requests of its meta object.
+    #[derive(Debug, Clone)]
+    struct RemoteObject {
+        failure: MetaObjectFailure,
+        failed_requests: usize,
+        meta_object_requests: Arc<AtomicUsize>,
+    }
+
+    impl RemoteObject {
+        fn new(failure: MetaObjectFailure, failed_requests: usize) -> Self {
+            Self {
+                failure,
+                failed_requests,
+                meta_object_requests: Arc::default(),
+            }
+        }
+
+        fn meta_object() -> MetaObject {
+            let mut builder = MetaObjectBuilder::new();
+            builder.add_method(
+                ACTION_ID_ADD,
+                "add",
+                "(ii)".parse::<Signature>().unwrap(),
+                Type::Int32,
+            );
+            builder.build()
+        }
+    }
+
+    #[derive(serde::Serialize)]
+    #[serde(untagged)]
+    enum RemoteReply {
+        MetaObject(Box<MetaObject>),
+        Sum(i32),
+    }
+
+    impl Service<session::CallWithId, session::NotificationWithId> for RemoteObject {
+        type CallReply = RemoteReply;
+        type Error = String;
+        type CallFuture = BoxFuture<'static, CallResult<RemoteReply, String>>;
+        type NotifyFuture = future::Ready<Result<(), String>>;
+
+        fn call(&mut self, call: session::CallWithId) -> Self::CallFuture {
+            let call = call.into_inner();
+            if call.subject().action() != ACTION_ID_METAOBJECT {
+                let (a, b): (i32, i32) = call.value().unwrap();
+                return future::ok(RemoteReply::Sum(a + b)).boxed();
+            }
+            let request = self.meta_object_requests.fetch_add(1, Ordering::Relaxed);
+            if request >= self.failed_requests {
+                return future::ok(RemoteReply::MetaObject(Box::new(Self::meta_object()))).boxed();
+            }
+            match self.failure {
+                MetaObjectFailure::NoReply => future::pending().boxed(),
+                MetaObjectFailure::Error => {
+                    future::err(CallTermination::Error("object not found".to_owned())).boxed()
+                }
+            }
+        }
+
+        fn notify(&mut self, _notif: session::NotificationWithId) -> Self::NotifyFuture {
+            future::ok(())
+        }
+    }
+
+    /// Returns a client of a session to the remote object, and the task of its session.
+    async fn connect_remote(remote: RemoteObject) -> (session::Client, JoinHandle<()>) {
+        let (io, remote_io) = tokio::io::duplex(4096);
+        let (remote_client, remote_session) = session::listen(remote_io, remote.clone());
+        let remote_task = spawn(async move {
+            let _res = remote_session.await;
+        });
+        let (client, session) = session::connect(io, remote);
+        spawn(async move {
+            let _res = session.await;
+        });
+        let (client, remote_client) = future::join(client, remote_client).await;
+        drop(remote_client.unwrap());
+        (client.unwrap(), remote_task)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_client_connect_meta_object_timeout() {
+        let remote = RemoteObject::new(MetaObjectFailure::NoReply, 1);
+        let requests = Arc::clone(&remote.meta_object_requests);
+        let (session, _remote_task) = connect_remote(remote).await;
+        let client = Client::connect_with_meta_object(
+            session,
+            ServiceId::new(10),
+            SERVICE_MAIN_OBJECT,
+            None,
+            Duration::from_secs(1),
+        )
+        .await
+        .unwrap();
+        assert!(client.meta_object().is_none());
+        assert_eq!(requests.load(Ordering::Relaxed), 1);
+
+        // Methods can be called by their action id without the meta object.
+        let sum: i32 = client.call_action(ACTION_ID_ADD, (1, 2)).await.unwrap();
+        assert_eq!(sum, 3);
+
+        // Calling a method by its name requests the meta object again.
+        let sum: i32 = client.call("add", (3, 4)).await.unwrap();
+        assert_eq!(sum, 7);
+        assert_eq!(requests.load(Ordering::Relaxed), 2);
+        assert!(client.meta_object().is_some());
+        assert!(matches!(
+            client.call::<_, i32>("sub", (3, 4)).await,
+            Err(CallTermination::Error(CallError::MethodNotFound(name))) if name == "sub"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_client_connect_meta_object_error() {
+        let remote = RemoteObject::new(MetaObjectFailure::Error, 1);
+        let (session, _remote_task) = connect_remote(remote).await;
+        let client = Client::connect(session, ServiceId::new(10), SERVICE_MAIN_OBJECT)
+            .await
+            .unwrap();
+        assert!(client.meta_object().is_none());
+        let sum: i32 = client.call("add", (1, 2)).await.unwrap();
+        assert_eq!(sum, 3);
+    }
+
+    #[tokio::test]
+    async fn test_client_connect_session_closed() {
+        let remote = RemoteObject::new(MetaObjectFailure::NoReply, 0);
+        let (session, remote_task) = connect_remote(remote).await;
+        remote_task.abort();
+        let _res = remote_task.await;
+        let result = Client::connect(session, ServiceId::new(10), SERVICE_MAIN_OBJECT).await;
+        assert!(matches!(
+            result,
+            Err(CallTermination::Error(ConnectError::MetaObject(
+                CallError::Client(session::ClientError::SessionClosed(_))
+            )))
+        ));
+    }
+}
@@ -4,19 +4,55 @@ use crate::{
     value::object::{ActionId, ObjectUid, ServiceId},
     Uri,
 };
-use futures::{future::BoxFuture, FutureExt, TryFutureExt};
+#[cfg(feature = "discovery")]
+use futures::channel::mpsc;
+use futures::{
+    future::{self, BoxFuture},
+    stream::{self, BoxStream},
+    FutureExt, StreamExt, TryFutureExt,
+};
+#[cfg(feature = "discovery")]
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+use tracing::warn;
 
+/// The directory of the services of a namespace.
+///
+/// Application code that looks up services should depend on this trait rather than on a
+/// particular directory, so that it may be tested against an in-memory directory, such as the one
+/// of `qi::testing`, without any connection.
 pub trait ServiceDirectory {
-    fn service(&self, name: &str) -> BoxFuture<'static, CallResult<ServiceInfo, Error>>;
+    /// Resolves a service by its name.
+    fn resolve(&self, name: &str) -> BoxFuture<'static, CallResult<ServiceInfo, Error>>;
     fn services(&self) -> BoxFuture<'static, CallResult<Vec<ServiceInfo>, Error>>;
 
-    // fn register_service(&mut self, info: ServiceInfo) -> Self::RegisterServiceFuture;
-    // fn unregister_service(&mut self, index: ServiceId) -> Self::UnregisterServiceFuture;
+    /// Registers a service, and returns the id that the directory assigned to it.
+    ///
+    /// If the service id of the information is set (not 0), the directory is requested to use
+    /// it, but it may assign another one.
+    fn register_service(
+        &self,
+        info: ServiceInfo,
+    ) -> BoxFuture<'static, CallResult<ServiceId, Error>>;
+    fn unregister_service(&self, id: ServiceId) -> BoxFuture<'static, CallResult<(), Error>>;
+
+    /// Watches the services that are registered to and unregistered from the directory.
+    ///
+    /// The stream yields the changes that occur after it is returned, until it is dropped.
+    fn watch(&self) -> BoxFuture<'static, CallResult<BoxStream<'static, ServiceEvent>, Error>>;
+
     // fn service_ready(&mut self, index: ServiceId) -> Self::ServiceReadyFuture;
     // fn update_service_info(&mut self, info: ServiceInfo) -> Self::UpdateServiceInfoFuture;
     // fn machine_id(&self) -> Self::MachineIdFuture;
-    // fn subscribe_service_added(&self) -> Self::SubscribeServiceFuture;
-    // fn subscribe_service_removed(&self) -> Self::SubscribeServiceFuture;
+}
+
+/// A change of the services of a directory, see [`ServiceDirectory::watch`].
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ServiceEvent {
+    Added { id: ServiceId, name: String },
+    Removed { id: ServiceId, name: String },
 }
 
 #[derive(
@@ -27,20 +63,124 @@ pub struct ServiceIdName {
     name: String,
 }
 
-#[derive(Debug)]
-pub struct ServiceDirectoryImpl;
+/// The service directory of a namespace, that holds the services in memory.
+///
+/// Services are assigned ids in order of registration, starting after the id of the directory
+/// itself, unless they request a free id. Clones share the same services and watchers.
+#[cfg(feature = "discovery")]
+#[derive(Debug, Clone, Default)]
+pub struct ServiceDirectoryImpl {
+    state: Arc<Mutex<State>>,
+}
+
+#[cfg(feature = "discovery")]
+#[derive(Debug, Default)]
+struct State {
+    services: BTreeMap<ServiceId, ServiceInfo>,
+    watchers: Vec<mpsc::UnboundedSender<ServiceEvent>>,
+}
 
+#[cfg(feature = "discovery")]
+impl ServiceDirectoryImpl {
+    /// The id of the first service registered to the directory, after the directory itself.
+    const FIRST_SERVICE_ID: u32 = 2;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        // The state is always left consistent, poisoning can be ignored.
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(feature = "discovery")]
+impl State {
+    fn register(&mut self, mut info: ServiceInfo) -> Result<ServiceId, Error> {
+        if self
+            .services
+            .values()
+            .any(|service| service.name == info.name)
+        {
+            return Err(Error::ServiceAlreadyRegistered(info.name));
+        }
+        if info.service_id == ServiceId::default() || self.services.contains_key(&info.service_id) {
+            let next = self
+                .services
+                .keys()
+                .next_back()
+                .map_or(ServiceDirectoryImpl::FIRST_SERVICE_ID, |&id| {
+                    u32::from(id) + 1
+                });
+            info.service_id = ServiceId::new(next.max(ServiceDirectoryImpl::FIRST_SERVICE_ID));
+        }
+        let id = info.service_id;
+        self.publish(ServiceEvent::Added {
+            id,
+            name: info.name.clone(),
+        });
+        self.services.insert(id, info);
+        Ok(id)
+    }
+
+    fn unregister(&mut self, id: ServiceId) -> Result<(), Error> {
+        let info = self
+            .services
+            .remove(&id)
+            .ok_or(Error::ServiceIdNotFound(id))?;
+        self.publish(ServiceEvent::Removed {
+            id,
+            name: info.name,
+        });
+        Ok(())
+    }
+
+    fn publish(&mut self, event: ServiceEvent) {
+        // Watchers that dropped their stream are forgotten.
+        self.watchers
+            .retain(|watcher| watcher.unbounded_send(event.clone()).is_ok());
+    }
+}
+
+#[cfg(feature = "discovery")]
 impl ServiceDirectory for ServiceDirectoryImpl {
-    fn service(&self, name: &str) -> BoxFuture<'static, CallResult<ServiceInfo, Error>> {
-        todo!()
+    fn resolve(&self, name: &str) -> BoxFuture<'static, CallResult<ServiceInfo, Error>> {
+        let service = self
+            .lock()
+            .services
+            .values()
+            .find(|service| service.name == name)
+            .cloned()
+            .ok_or_else(|| Error::ServiceNotFound(name.to_owned()).into());
+        future::ready(service).boxed()
     }
 
     fn services(&self) -> BoxFuture<'static, CallResult<Vec<ServiceInfo>, Error>> {
-        todo!()
+        future::ok(self.lock().services.values().cloned().collect()).boxed()
+    }
+
+    fn register_service(
+        &self,
+        info: ServiceInfo,
+    ) -> BoxFuture<'static, CallResult<ServiceId, Error>> {
+        let result = self.lock().register(info).map_err(Into::into);
+        future::ready(result).boxed()
+    }
+
+    fn unregister_service(&self, id: ServiceId) -> BoxFuture<'static, CallResult<(), Error>> {
+        let result = self.lock().unregister(id).map_err(Into::into);
+        future::ready(result).boxed()
+    }
+
+    fn watch(&self) -> BoxFuture<'static, CallResult<BoxStream<'static, ServiceEvent>, Error>> {
+        let (sender, receiver) = mpsc::unbounded();
+        self.lock().watchers.push(sender);
+        future::ok(receiver.boxed()).boxed()
     }
 }
 
-const SERVICE_ID: ServiceId = ServiceId::new(1);
+pub(crate) const SERVICE_ID: ServiceId = ServiceId::new(1);
 
 // struct Meta {
 //     object: MetaObject,
@@ -85,19 +225,21 @@ const ACTION_SD_SERVICE: ActionId = ActionId::new(100);
 const ACTION_SD_SERVICES: ActionId = ActionId::new(101);
 const ACTION_SD_REGISTER_SERVICE: ActionId = ActionId::new(102);
 const ACTION_SD_UNREGISTER_SERVICE: ActionId = ActionId::new(103);
-const ACTION_SD_SERVICE_READY: ActionId = ActionId::new(104);
-const ACTION_SD_UPDATE_SERVICE_INFO: ActionId = ActionId::new(105);
-const ACTION_SD_SERVICE_ADDED: ActionId = ActionId::new(106);
-const ACTION_SD_SERVICE_REMOVED: ActionId = ActionId::new(107);
-const ACTION_SD_MACHINE_ID: ActionId = ActionId::new(108);
+// const ACTION_SD_SERVICE_READY: ActionId = ActionId::new(104);
+// const ACTION_SD_UPDATE_SERVICE_INFO: ActionId = ActionId::new(105);
+// const ACTION_SD_SERVICE_ADDED: ActionId = ActionId::new(106);
+// const ACTION_SD_SERVICE_REMOVED: ActionId = ActionId::new(107);
+// const ACTION_SD_MACHINE_ID: ActionId = ActionId::new(108);
 
+/// A client of the service directory of a namespace, see [`Client::connect`].
 #[derive(Debug, Clone)]
 pub struct Client {
     object: object::Client,
 }
 
 impl Client {
-    pub(crate) async fn connect(
+    /// Connects to the service directory of the namespace of a session.
+    pub async fn connect(
         session: session::Client,
     ) -> CallResult<Self, object::client::ConnectError> {
         let object = object::Client::connect_to_service_object(session, SERVICE_ID).await?;
@@ -106,7 +248,7 @@ impl Client {
 }
 
 impl ServiceDirectory for Client {
-    fn service(&self, name: &str) -> BoxFuture<'static, CallResult<ServiceInfo, Error>> {
+    fn resolve(&self, name: &str) -> BoxFuture<'static, CallResult<ServiceInfo, Error>> {
         let call = self.object.call_action(ACTION_SD_SERVICE, name);
         call.map_err(|err| err.map_err(Error::ClientCall)).boxed()
     }
@@ -115,6 +257,81 @@ impl ServiceDirectory for Client {
         let call = self.object.call_action(ACTION_SD_SERVICES, ());
         call.map_err(|err| err.map_err(Error::ClientCall)).boxed()
     }
+
+    fn register_service(
+        &self,
+        info: ServiceInfo,
+    ) -> BoxFuture<'static, CallResult<ServiceId, Error>> {
+        let call = self.object.call_action(ACTION_SD_REGISTER_SERVICE, info);
+        call.map_err(|err| err.map_err(Error::ClientCall)).boxed()
+    }
+
+    fn unregister_service(&self, id: ServiceId) -> BoxFuture<'static, CallResult<(), Error>> {
+        let call = self.object.call_action(ACTION_SD_UNREGISTER_SERVICE, id);
+        call.map_err(|err| err.map_err(Error::ClientCall)).boxed()
+    }
+
+    fn watch(&self) -> BoxFuture<'static, CallResult<BoxStream<'static, ServiceEvent>, Error>> {
+        let object = self.object.clone();
+        async move {
+            let added = object
+                .subscribe::<(ServiceId, String)>("serviceAdded")
+                .await
+                .map_err(|err| err.map_err(Error::ClientCall))?
+                .map(|event| event.map(|(id, name)| ServiceEvent::Added { id, name }));
+            let removed = object
+                .subscribe::<(ServiceId, String)>("serviceRemoved")
+                .await
+                .map_err(|err| err.map_err(Error::ClientCall))?
+                .map(|event| event.map(|(id, name)| ServiceEvent::Removed { id, name }));
+            let events = stream::select(added, removed).filter_map(|event| {
+                future::ready(match event {
+                    Ok(event) => Some(event),
+                    Err(err) => {
+                        warn!(
+                            error = &err as &dyn std::error::Error,
+                            "discarding an undecodable event of the service directory"
+                        );
+                        None
+                    }
+                })
+            });
+            Ok(events.boxed())
+        }
+        .boxed()
+    }
+}
+
+/// The service directory of a node that is not connected to any, see
+/// [`Node::to_peer`](crate::Node::to_peer).
+///
+/// All requests fail with [`Error::Unavailable`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Unavailable;
+
+impl ServiceDirectory for Unavailable {
+    fn resolve(&self, _name: &str) -> BoxFuture<'static, CallResult<ServiceInfo, Error>> {
+        future::err(Error::Unavailable.into()).boxed()
+    }
+
+    fn services(&self) -> BoxFuture<'static, CallResult<Vec<ServiceInfo>, Error>> {
+        future::err(Error::Unavailable.into()).boxed()
+    }
+
+    fn register_service(
+        &self,
+        _info: ServiceInfo,
+    ) -> BoxFuture<'static, CallResult<ServiceId, Error>> {
+        future::err(Error::Unavailable.into()).boxed()
+    }
+
+    fn unregister_service(&self, _id: ServiceId) -> BoxFuture<'static, CallResult<(), Error>> {
+        future::err(Error::Unavailable.into()).boxed()
+    }
+
+    fn watch(&self) -> BoxFuture<'static, CallResult<BoxStream<'static, ServiceEvent>, Error>> {
+        future::err(Error::Unavailable.into()).boxed()
+    }
 }
 
 pub type BoxServiceDirectory<'a> = Box<dyn ServiceDirectory + 'a + Send + Sync>;
@@ -123,6 +340,18 @@ pub type BoxServiceDirectory<'a> = Box<dyn ServiceDirectory + 'a + Send + Sync>;
 pub enum Error {
     #[error(transparent)]
     ClientCall(#[from] object::client::CallError),
+
+    #[error("no service directory is available")]
+    Unavailable,
+
+    #[error("no service named \"{0}\" is registered")]
+    ServiceNotFound(String),
+
+    #[error("no service with id {0} is registered")]
+    ServiceIdNotFound(ServiceId),
+
+    #[error("a service named \"{0}\" is already registered")]
+    ServiceAlreadyRegistered(String),
 }
 
 #[derive(
@@ -218,3 +447,50 @@ pub struct MachineId(String);
     derive_more::Display,
 )]
 pub struct SessionId(String);
+
+#[cfg(all(test, feature = "discovery"))]
+mod tests {
+    use super::*;
+    use crate::messaging::CallTermination;
+
+    fn info(name: &str) -> ServiceInfo {
+        ServiceInfo {
+            name: name.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_service_directory_impl_watch() {
+        let directory = ServiceDirectoryImpl::new();
+        let a = directory.register_service(info("A")).await.unwrap();
+        let mut events = directory.watch().await.unwrap();
+        let dropped = directory.watch().await.unwrap();
+        drop(dropped);
+
+        // Only the changes that occur after the watch are yielded.
+        let b = directory.register_service(info("B")).await.unwrap();
+        directory.unregister_service(a).await.unwrap();
+        assert!(matches!(
+            directory.unregister_service(a).await,
+            Err(CallTermination::Error(Error::ServiceIdNotFound(_)))
+        ));
+        assert_eq!(
+            events.next().await,
+            Some(ServiceEvent::Added {
+                id: b,
+                name: "B".to_owned()
+            })
+        );
+        assert_eq!(
+            events.next().await,
+            Some(ServiceEvent::Removed {
+                id: a,
+                name: "A".to_owned()
+            })
+        );
+        assert!(events.next().now_or_never().is_none());
+        // The dropped watcher is forgotten.
+        assert_eq!(directory.lock().watchers.len(), 1);
+    }
+}
@@ -1,10 +1,20 @@
 use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
     marker::PhantomData,
     pin::Pin,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
     task::{Context, Poll},
+    time::Duration,
 };
 
+use crate::{
+    format,
+    messaging::{self, session, Service},
+    value::object::ActionId,
+};
 use futures::StreamExt;
+use tokio::{sync::RwLock, time::Instant};
+use tracing::{trace, warn};
 
 #[derive(
     Debug,
@@ -23,8 +33,12 @@ use futures::StreamExt;
 )]
 pub struct Link(u64);
 
+// The streams of the values of signals, to local or remote objects, are not implemented yet:
+// polling them panics, so they stay private until they are. The values of the signals of remote
+// objects are streamed by `object::client::SignalStream`.
+#[allow(dead_code)]
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
-pub struct Subscription<T> {
+struct Subscription<T> {
     link: Link,
     phantom: PhantomData<T>,
 }
@@ -32,13 +46,15 @@ pub struct Subscription<T> {
 impl<T> futures::Stream for Subscription<T> {
     type Item = T;
 
-    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    #[allow(clippy::todo)]
+    fn poll_next(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         todo!()
     }
 }
 
+#[allow(dead_code)]
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
-pub struct SubscriptionClient<T> {
+struct SubscriptionClient<T> {
     link: Link,
     phantom: PhantomData<T>,
 }
@@ -46,13 +62,15 @@ pub struct SubscriptionClient<T> {
 impl<T> futures::Stream for SubscriptionClient<T> {
     type Item = T;
 
-    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    #[allow(clippy::todo)]
+    fn poll_next(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         todo!()
     }
 }
 
+#[allow(dead_code)]
 #[derive(Debug)]
-pub enum AnySubscription<T> {
+enum AnySubscription<T> {
     Local(Subscription<T>),
     Client(SubscriptionClient<T>),
 }
@@ -70,3 +88,619 @@ where
         }
     }
 }
+
+/// The signals of an object, with their configuration.
+///
+/// This is derived for enumerations of which each variant is a signal, with the `qi::Signals`
+/// macro. See [`SubscriptionSet::configure`].
+pub trait Signals: Sized + 'static {
+    /// All the signals of the object.
+    const ALL: &'static [Self];
+
+    /// The action of the signal in the object.
+    fn action(&self) -> ActionId;
+
+    /// The number of last values of the signal that are kept to be replayed to late subscribers,
+    /// see [`SubscriptionSet::set_replay_capacity`].
+    fn replay_capacity(&self) -> usize {
+        0
+    }
+}
+
+/// The links of the remote subscribers to the signals of an object served by this node.
+///
+/// Once the set is closed, its links are dropped and no event is emitted anymore. Closing waits
+/// for the emissions in progress, so that no event is sent after [`SubscriptionSet::close`]
+/// returns.
+///
+/// Signals may keep their last emitted values in a replay buffer, see
+/// [`SubscriptionSet::set_replay_capacity`], so that late subscribers receive the current state
+/// of the object without calling a getter.
+///
+/// The last activity of each link is tracked, so that the links that subscribers leak, by never
+/// using nor unsubscribing them while their session lives on, may be collected, see
+/// [`SubscriptionSet::set_stale_link_window`]. Links are active when they are subscribed and when
+/// [`SubscriptionSet::touch`] is called: only their subscribers keep them alive, sending them
+/// events does not.
+///
+/// The set holds the subscribers on its session. The subscribers on the other sessions through
+/// which the object is served, such as the sessions of the listener of a node, are held by the
+/// session sets of this set, to which its values are emitted too.
+#[derive(Debug)]
+pub struct SubscriptionSet {
+    session: session::Client,
+    service_object: session::subject::ServiceObject,
+    state: RwLock<SubscriptionSetState>,
+    // Shared with the session sets.
+    replay: Arc<Mutex<HashMap<ActionId, ReplayBuffer>>>,
+    stale_link_window: Arc<Mutex<Option<Duration>>>,
+}
+
+#[derive(Debug, Default)]
+struct SubscriptionSetState {
+    closed: bool,
+    // The links of each signal, with their last activity.
+    links: HashMap<ActionId, BTreeMap<Link, Instant>>,
+    // The sets of the subscribers on other sessions, see `SubscriptionSet::on_session`.
+    session_sets: Vec<Arc<SubscriptionSet>>,
+}
+
+impl SubscriptionSet {
+    pub fn new(session: session::Client, service_object: session::subject::ServiceObject) -> Self {
+        Self {
+            session,
+            service_object,
+            state: RwLock::default(),
+            replay: Arc::default(),
+            stale_link_window: Arc::default(),
+        }
+    }
+
+    /// Returns a set of the subscribers to the object on another session, such as a session
+    /// served by the listener of the node.
+    ///
+    /// The session set shares the configuration and the replay buffers of this set. The values
+    /// emitted by this set are emitted to its subscribers too, and it is closed with this set.
+    /// Session sets are dropped by this set once their session is closed.
+    #[cfg(feature = "server")]
+    pub(crate) async fn on_session(
+        &self,
+        session: session::Client,
+    ) -> Result<Arc<SubscriptionSet>, ClosedError> {
+        let mut state = self.state.write().await;
+        if state.closed {
+            return Err(ClosedError);
+        }
+        state
+            .session_sets
+            .retain(|session_set| !session_set.session.is_closed());
+        let session_set = Arc::new(Self {
+            session,
+            service_object: self.service_object,
+            state: RwLock::default(),
+            replay: Arc::clone(&self.replay),
+            stale_link_window: Arc::clone(&self.stale_link_window),
+        });
+        state.session_sets.push(Arc::clone(&session_set));
+        Ok(session_set)
+    }
+
+    /// Applies the configuration of each signal of an object, such as its replay capacity.
+    pub fn configure<S>(&self)
+    where
+        S: Signals,
+    {
+        for signal in S::ALL {
+            self.set_replay_capacity(signal.action(), signal.replay_capacity());
+        }
+    }
+
+    /// Sets the number of last values of a signal that are kept to be replayed to the subscribers
+    /// that request them, see [`SubscriptionSet::subscribe_with_replay`].
+    ///
+    /// A capacity of 0 disables the replay of the signal, which is the default. Reducing the
+    /// capacity drops the oldest values.
+    pub fn set_replay_capacity(&self, signal: ActionId, capacity: usize) {
+        let mut replay = self.replay();
+        if capacity == 0 {
+            replay.remove(&signal);
+            return;
+        }
+        let buffer = replay.entry(signal).or_insert_with(|| ReplayBuffer {
+            capacity,
+            values: VecDeque::with_capacity(capacity),
+        });
+        buffer.capacity = capacity;
+        buffer.truncate();
+    }
+
+    /// Sets the duration after which the links that had no activity are stale, or disables the
+    /// collection of stale links, which is the default.
+    ///
+    /// The activity of a link is its subscription, and each use of the link by its subscriber that
+    /// the owner of the set records, see [`SubscriptionSet::touch`]. The events sent to a link are
+    /// not an activity, as they are sent whether or not its subscriber still listens. Stale links
+    /// are removed by [`SubscriptionSet::collect_stale_links`], and when a link is subscribed.
+    pub fn set_stale_link_window(&self, window: Option<Duration>) {
+        *self.stale_link_window() = window;
+    }
+
+    /// Adds the link of a subscriber to a signal.
+    pub async fn subscribe(&self, signal: ActionId, link: Link) -> Result<(), ClosedError> {
+        let mut state = self.state.write().await;
+        if state.closed {
+            return Err(ClosedError);
+        }
+        self.remove_stale_links(&mut state);
+        state
+            .links
+            .entry(signal)
+            .or_default()
+            .insert(link, Instant::now());
+        Ok(())
+    }
+
+    /// Records an activity of the link of a subscriber to a signal, so that it does not become
+    /// stale. Returns whether it is subscribed.
+    ///
+    /// This is meant for the uses of a link that the owner of the set observes, such as an
+    /// acknowledgment by the subscriber of the events it received.
+    pub async fn touch(&self, signal: ActionId, link: Link) -> bool {
+        let mut state = self.state.write().await;
+        match state
+            .links
+            .get_mut(&signal)
+            .and_then(|links| links.get_mut(&link))
+        {
+            Some(activity) => {
+                *activity = Instant::now();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The last activity of the link of a subscriber to a signal, if it is subscribed.
+    pub async fn link_activity(&self, signal: ActionId, link: Link) -> Option<Instant> {
+        let state = self.state.read().await;
+        state.links.get(&signal)?.get(&link).copied()
+    }
+
+    /// Removes the links that are stale, including those of the session sets, and returns them.
+    ///
+    /// Nothing is collected if no window is set (see [`SubscriptionSet::set_stale_link_window`]),
+    /// or if the session is closed, in which case all its links are dropped with it anyway. Each
+    /// collected link is logged as a warning, as it most likely reveals a subscriber that leaks
+    /// its links.
+    pub async fn collect_stale_links(&self) -> Vec<(ActionId, Link)> {
+        let mut state = self.state.write().await;
+        let mut stale = self.remove_stale_links(&mut state);
+        for session_set in &state.session_sets {
+            let mut session_state = session_set.state.write().await;
+            stale.extend(session_set.remove_stale_links(&mut session_state));
+        }
+        stale
+    }
+
+    fn remove_stale_links(&self, state: &mut SubscriptionSetState) -> Vec<(ActionId, Link)> {
+        let window = *self.stale_link_window();
+        let window = match window {
+            Some(window) if !self.session.is_closed() => window,
+            _ => return Vec::new(),
+        };
+        let now = Instant::now();
+        let mut stale = Vec::new();
+        state.links.retain(|signal, links| {
+            links.retain(|link, activity| {
+                let idle = now.saturating_duration_since(*activity);
+                if idle < window {
+                    return true;
+                }
+                warn!(
+                    %signal,
+                    ?link,
+                    ?idle,
+                    "collecting a stale link of a subscriber to a signal"
+                );
+                stale.push((*signal, *link));
+                false
+            });
+            !links.is_empty()
+        });
+        stale
+    }
+
+    /// Adds the link of a subscriber to a signal, and returns up to `count` of the last values of
+    /// the signal, from the oldest.
+    ///
+    /// With a count of 1, the subscriber gets the last value of the signal, if any. The values are
+    /// returned rather than sent as events, because events are not addressed to a link: the other
+    /// subscribers to the signal on the same session would receive them as well. They are
+    /// delivered to the new subscriber only, with the reply to its registration, see
+    /// [`ACTION_ID_REGISTER_EVENT_WITH_REPLAY`](crate::object::client::ACTION_ID_REGISTER_EVENT_WITH_REPLAY).
+    /// Values emitted after this call may reach the subscriber before them.
+    pub async fn subscribe_with_replay(
+        &self,
+        signal: ActionId,
+        link: Link,
+        count: usize,
+    ) -> Result<Vec<format::Value>, ClosedError> {
+        // The write lock prevents emissions until the values are taken from the buffer.
+        let mut state = self.state.write().await;
+        if state.closed {
+            return Err(ClosedError);
+        }
+        self.remove_stale_links(&mut state);
+        state
+            .links
+            .entry(signal)
+            .or_default()
+            .insert(link, Instant::now());
+        let values = match self.replay().get(&signal) {
+            Some(buffer) => {
+                let skipped = buffer.values.len().saturating_sub(count);
+                buffer.values.iter().skip(skipped).cloned().collect()
+            }
+            None => Vec::new(),
+        };
+        Ok(values)
+    }
+
+    /// Removes the link of a subscriber to a signal, and returns whether it was subscribed.
+    pub async fn unsubscribe(&self, signal: ActionId, link: Link) -> bool {
+        let mut state = self.state.write().await;
+        let links = match state.links.get_mut(&signal) {
+            Some(links) => links,
+            None => return false,
+        };
+        let removed = links.remove(&link).is_some();
+        if links.is_empty() {
+            state.links.remove(&signal);
+        }
+        removed
+    }
+
+    /// Emits a value of a signal to its subscribers.
+    ///
+    /// Nothing is sent if the signal has no subscriber, but the value is still kept for replay if
+    /// the signal has a replay buffer. Fails if the set is closed.
+    pub async fn emit<T>(&self, signal: ActionId, value: &T) -> Result<(), EmitError>
+    where
+        T: serde::Serialize,
+    {
+        // The read lock is held while the event is sent, so that closing the set waits for it.
+        let state = self.state.read().await;
+        if state.closed {
+            return Err(EmitError::Closed(ClosedError));
+        }
+        let value = format::Value::from_serializable(value)?;
+        if let Some(buffer) = self.replay().get_mut(&signal) {
+            buffer.values.push_back(value.clone());
+            buffer.truncate();
+        }
+        self.send_to_session_sets(&state, signal, &[&value]).await;
+        if !state.links.contains_key(&signal) {
+            return Ok(());
+        }
+        self.send_event(signal, value).await
+    }
+
+    /// Emits several values of a signal to its subscribers, in order.
+    ///
+    /// The values are sent as a single batch of events, which is cheaper than emitting them one by
+    /// one when a burst of values is published, such as the updates of each joint of a robot.
+    /// Otherwise, this behaves like [`SubscriptionSet::emit`] for each value. If a value fails to
+    /// serialize, none is emitted.
+    pub async fn emit_many<'a, T, I>(&self, signal: ActionId, values: I) -> Result<(), EmitError>
+    where
+        T: serde::Serialize + 'a,
+        I: IntoIterator<Item = &'a T>,
+    {
+        let state = self.state.read().await;
+        if state.closed {
+            return Err(EmitError::Closed(ClosedError));
+        }
+        let values = values
+            .into_iter()
+            .map(format::Value::from_serializable)
+            .collect::<Result<Vec<_>, _>>()?;
+        if let Some(buffer) = self.replay().get_mut(&signal) {
+            buffer.values.extend(values.iter().cloned());
+            buffer.truncate();
+        }
+        self.send_to_session_sets(&state, signal, &values.iter().collect::<Vec<_>>())
+            .await;
+        if !state.links.contains_key(&signal) {
+            return Ok(());
+        }
+        self.send_events(signal, values).await
+    }
+
+    /// Sends the values of a signal to its subscribers on the sessions of the session sets.
+    ///
+    /// These sessions come and go independently of the session of this set: the failure to send
+    /// to one of them does not fail the emission.
+    async fn send_to_session_sets(
+        &self,
+        state: &SubscriptionSetState,
+        signal: ActionId,
+        values: &[&format::Value],
+    ) {
+        for session_set in &state.session_sets {
+            let session_state = session_set.state.read().await;
+            if session_state.closed || !session_state.links.contains_key(&signal) {
+                continue;
+            }
+            let values = values.iter().map(|&value| value.clone()).collect();
+            if let Err(err) = session_set.send_events(signal, values).await {
+                trace!(
+                    error = &err as &dyn std::error::Error,
+                    "failed to send the events of a signal to the subscribers of a session"
+                );
+            }
+        }
+    }
+
+    async fn send_event(&self, signal: ActionId, value: format::Value) -> Result<(), EmitError> {
+        let subject = session::Subject::new(self.service_object, signal);
+        let event = session::Event::new(subject).with_formatted_value(value);
+        let mut client = &self.session;
+        client.notify(event.into()).await?;
+        Ok(())
+    }
+
+    async fn send_events(
+        &self,
+        signal: ActionId,
+        values: Vec<format::Value>,
+    ) -> Result<(), EmitError> {
+        let subject = session::Subject::new(self.service_object, signal);
+        let events = values
+            .into_iter()
+            .map(|value| session::Event::new(subject).with_formatted_value(value));
+        self.session.notify_events(events).await?;
+        Ok(())
+    }
+
+    /// Closes the set and its session sets, after the emissions in progress terminate, and
+    /// returns the links of the subscribers that they contained.
+    pub async fn close(&self) -> Vec<(ActionId, Link)> {
+        let mut state = self.state.write().await;
+        state.closed = true;
+        self.replay().clear();
+        let mut links: Vec<_> = std::mem::take(&mut state.links)
+            .into_iter()
+            .flat_map(|(signal, links)| links.into_keys().map(move |link| (signal, link)))
+            .collect();
+        for session_set in std::mem::take(&mut state.session_sets) {
+            links.extend(Box::pin(session_set.close()).await);
+        }
+        links
+    }
+
+    pub async fn is_closed(&self) -> bool {
+        self.state.read().await.closed
+    }
+
+    fn replay(&self) -> MutexGuard<'_, HashMap<ActionId, ReplayBuffer>> {
+        self.replay.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn stale_link_window(&self) -> MutexGuard<'_, Option<Duration>> {
+        // The window is only ever replaced, poisoning can be ignored.
+        self.stale_link_window
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// The last values of a signal, from the oldest.
+#[derive(Debug)]
+struct ReplayBuffer {
+    capacity: usize,
+    values: VecDeque<format::Value>,
+}
+
+impl ReplayBuffer {
+    fn truncate(&mut self) {
+        while self.values.len() > self.capacity {
+            self.values.pop_front();
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("the subscriptions to the signals of the object are closed")]
+pub struct ClosedError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum EmitError {
+    #[error(transparent)]
+    Closed(#[from] ClosedError),
+
+    #[error("failed to serialize the value of the signal")]
+    Format(#[from] format::Error),
+
+    #[error("failed to send the event of the signal")]
+    Send(#[from] messaging::session::ClientError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        messaging::CallResult,
+        value::object::{ObjectId, ServiceId},
+    };
+    use futures::{future, FutureExt};
+    use tokio::spawn;
+
+    const POSTURE: ActionId = ActionId::new(100);
+    const MOVED: ActionId = ActionId::new(101);
+
+    enum MotionSignal {
+        Posture,
+        Moved,
+    }
+
+    impl Signals for MotionSignal {
+        const ALL: &'static [Self] = &[Self::Posture, Self::Moved];
+
+        fn action(&self) -> ActionId {
+            match self {
+                Self::Posture => POSTURE,
+                Self::Moved => MOVED,
+            }
+        }
+
+        fn replay_capacity(&self) -> usize {
+            match self {
+                Self::Posture => 2,
+                Self::Moved => 0,
+            }
+        }
+    }
+
+    /// The service of the sessions of the tests, that is never called.
+    struct NoService;
+
+    impl Service<session::CallWithId, session::NotificationWithId> for NoService {
+        type CallReply = ();
+        type Error = String;
+        type CallFuture = future::Ready<CallResult<(), String>>;
+        type NotifyFuture = future::Ready<Result<(), String>>;
+
+        fn call(&mut self, _call: session::CallWithId) -> Self::CallFuture {
+            future::err(messaging::CallTermination::Error("no service".to_owned()))
+        }
+
+        fn notify(&mut self, _notif: session::NotificationWithId) -> Self::NotifyFuture {
+            future::ok(())
+        }
+    }
+
+    /// A subscription set of an object served on a session, with the client of the subscribers
+    /// on the other side of the session.
+    async fn subscription_set() -> (SubscriptionSet, session::Client) {
+        let (io, peer_io) = tokio::io::duplex(4096);
+        let (peer, peer_session) = session::listen(peer_io, NoService);
+        spawn(async move {
+            let _res = peer_session.await;
+        });
+        let (client, session) = session::connect(io, NoService);
+        spawn(async move {
+            let _res = session.await;
+        });
+        let (client, peer) = future::join(client, peer).await;
+        let service_object =
+            session::subject::ServiceObject::new(ServiceId::new(10), ObjectId::new(1)).unwrap();
+        (
+            SubscriptionSet::new(client.unwrap(), service_object),
+            peer.unwrap(),
+        )
+    }
+
+    fn values(values: &[format::Value]) -> Vec<u32> {
+        values
+            .iter()
+            .map(|value| format::from_value(value).unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_collect_stale_links() {
+        let (subscriptions, _peer) = subscription_set().await;
+        tokio::time::pause();
+        let (posture, moved, touched) = (Link::from(1), Link::from(2), Link::from(3));
+        subscriptions.subscribe(POSTURE, posture).await.unwrap();
+        subscriptions.subscribe(MOVED, moved).await.unwrap();
+        subscriptions.subscribe(MOVED, touched).await.unwrap();
+        assert!(subscriptions.collect_stale_links().await.is_empty());
+
+        // Nothing is stale before the window is set.
+        tokio::time::advance(Duration::from_secs(60)).await;
+        assert!(subscriptions.collect_stale_links().await.is_empty());
+        subscriptions.set_stale_link_window(Some(Duration::from_secs(10)));
+
+        // Touching a link is an activity, sending events to the links of a signal is not.
+        subscriptions.emit(POSTURE, &1u32).await.unwrap();
+        assert!(subscriptions.touch(MOVED, touched).await);
+        assert_eq!(
+            subscriptions.link_activity(MOVED, touched).await,
+            Some(Instant::now())
+        );
+        let mut stale = subscriptions.collect_stale_links().await;
+        stale.sort_unstable();
+        assert_eq!(stale, [(POSTURE, posture), (MOVED, moved)]);
+        assert!(!subscriptions.touch(MOVED, moved).await);
+
+        // Links stay active as long as their subscribers touch them, whatever is sent to them.
+        let posture = Link::from(4);
+        subscriptions.subscribe(POSTURE, posture).await.unwrap();
+        tokio::time::advance(Duration::from_secs(6)).await;
+        subscriptions.emit(POSTURE, &2u32).await.unwrap();
+        assert!(subscriptions.touch(POSTURE, posture).await);
+        tokio::time::advance(Duration::from_secs(6)).await;
+        subscriptions.emit(POSTURE, &3u32).await.unwrap();
+        let stale = subscriptions.collect_stale_links().await;
+        assert_eq!(stale, [(MOVED, touched)]);
+        assert!(subscriptions
+            .link_activity(POSTURE, posture)
+            .await
+            .is_some());
+
+        tokio::time::advance(Duration::from_secs(6)).await;
+        let stale = subscriptions.collect_stale_links().await;
+        assert_eq!(stale, [(POSTURE, posture)]);
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_with_replay() {
+        let (subscriptions, peer) = subscription_set().await;
+        subscriptions.configure::<MotionSignal>();
+        let mut events = Box::pin(peer.events(|subject| subject.action() == POSTURE));
+        let event_value = |(_subject, content)| {
+            format::from_value::<u32>(&format::Value::from_bytes(content)).unwrap()
+        };
+
+        subscriptions
+            .subscribe(POSTURE, Link::from(1))
+            .await
+            .unwrap();
+        for value in [1u32, 2, 3] {
+            subscriptions.emit(POSTURE, &value).await.unwrap();
+            assert_eq!(events.next().await.map(event_value), Some(value));
+        }
+
+        // The last values are returned to the late subscriber, they are not sent to the others.
+        let replay = subscriptions
+            .subscribe_with_replay(POSTURE, Link::from(2), 5)
+            .await
+            .unwrap();
+        assert_eq!(values(&replay), [2, 3]);
+        let replay = subscriptions
+            .subscribe_with_replay(POSTURE, Link::from(3), 1)
+            .await
+            .unwrap();
+        assert_eq!(values(&replay), [3]);
+        subscriptions.emit(POSTURE, &4u32).await.unwrap();
+        assert_eq!(events.next().await.map(event_value), Some(4));
+        assert!(events.next().now_or_never().is_none());
+
+        // Signals without a replay capacity replay nothing.
+        subscriptions.emit(MOVED, &1u32).await.unwrap();
+        let replay = subscriptions
+            .subscribe_with_replay(MOVED, Link::from(4), 5)
+            .await
+            .unwrap();
+        assert!(replay.is_empty());
+
+        subscriptions.close().await;
+        assert!(matches!(
+            subscriptions
+                .subscribe_with_replay(POSTURE, Link::from(5), 1)
+                .await,
+            Err(ClosedError)
+        ));
+    }
+}
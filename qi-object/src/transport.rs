@@ -1,9 +1,20 @@
+#[cfg(feature = "server")]
+mod listener;
+
+#[cfg(feature = "server")]
+pub use listener::{Listener, ServeConfig};
 use std::{
     pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
     task::{Context, Poll},
+    time::Duration,
 };
 
-use crate::Uri;
+use crate::{messaging::session, Uri};
+use futures::{stream::FuturesUnordered, StreamExt};
 use tokio::{
     io::{AsyncRead, AsyncWrite},
     net::TcpStream,
@@ -11,12 +22,21 @@ use tokio::{
 
 const DEFAULT_TCP_PORT: u16 = 9559;
 
+/// The longest delay between two rounds of connection attempts to endpoints.
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
 #[derive(Debug)]
 pub(crate) enum Transport {
     Tcp(TcpStream),
 }
 
 impl Transport {
+    pub(crate) fn connection_info(&self) -> session::ConnectionInfo {
+        match self {
+            Transport::Tcp(stream) => session::ConnectionInfo::from_tcp_stream(stream),
+        }
+    }
+
     pub(crate) async fn connect(uri: Uri) -> Result<Self, ConnectFromUriError> {
         match uri.scheme_str() {
             "tcp" => {
@@ -41,6 +61,150 @@ impl Transport {
             )),
         }
     }
+
+    /// Connects to the first reachable address of the endpoints, and remembers it in the
+    /// endpoints.
+    ///
+    /// If none of the addresses can be connected to, they are all tried again after the backoff
+    /// delay of the endpoints, as many times as they allow.
+    pub(crate) async fn connect_endpoints(
+        endpoints: &Endpoints,
+    ) -> Result<Self, ConnectEndpointsError> {
+        let mut errors = Vec::new();
+        let mut delay = endpoints.backoff;
+        for retry in 0..=endpoints.retries {
+            if retry > 0 {
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(MAX_BACKOFF);
+            }
+            if let Some(transport) = Self::connect_endpoints_once(endpoints, &mut errors).await {
+                return Ok(transport);
+            }
+        }
+        Err(ConnectEndpointsError(errors))
+    }
+
+    async fn connect_endpoints_once(
+        endpoints: &Endpoints,
+        errors: &mut Vec<(Uri, ConnectFromUriError)>,
+    ) -> Option<Self> {
+        let mut addresses = endpoints.ordered();
+        let mut attempts = FuturesUnordered::new();
+        loop {
+            if attempts.is_empty() {
+                let (index, uri) = addresses.next()?;
+                attempts.push(connect_attempt(index, uri));
+            }
+            let attempt = match endpoints.race_delay {
+                Some(delay) if addresses.len() > 0 => {
+                    match tokio::time::timeout(delay, attempts.next()).await {
+                        Ok(attempt) => attempt,
+                        Err(_elapsed) => {
+                            // The pending attempts keep running, the next address joins the race.
+                            if let Some((index, uri)) = addresses.next() {
+                                attempts.push(connect_attempt(index, uri));
+                            }
+                            continue;
+                        }
+                    }
+                }
+                _ => attempts.next().await,
+            };
+            match attempt {
+                Some((index, _uri, Ok(transport))) => {
+                    endpoints.last_connected.store(index, Ordering::Relaxed);
+                    return Some(transport);
+                }
+                Some((_index, uri, Err(err))) => errors.push((uri, err)),
+                None => {}
+            }
+        }
+    }
+}
+
+async fn connect_attempt(
+    index: usize,
+    uri: Uri,
+) -> (usize, Uri, Result<Transport, ConnectFromUriError>) {
+    let result = Transport::connect(uri.clone()).await;
+    (index, uri, result)
+}
+
+/// The addresses at which a namespace is reachable, in order of preference.
+///
+/// A robot may for instance be reachable both by Ethernet and by WiFi. Connections try the address
+/// that last worked first, then the others in order. Clones share the address that last worked,
+/// so that the following connections, such as reconnections, start with it.
+#[derive(Debug, Clone)]
+pub struct Endpoints {
+    uris: Vec<Uri>,
+    last_connected: Arc<AtomicUsize>,
+    race_delay: Option<Duration>,
+    retries: u32,
+    backoff: Duration,
+}
+
+impl Endpoints {
+    pub fn new(primary: Uri) -> Self {
+        Self {
+            uris: vec![primary],
+            last_connected: Arc::default(),
+            race_delay: None,
+            retries: 0,
+            backoff: Duration::ZERO,
+        }
+    }
+
+    /// Adds an address that is tried if the previous ones cannot be connected to.
+    pub fn with_fallback(mut self, uri: Uri) -> Self {
+        self.uris.push(uri);
+        self
+    }
+
+    /// Sets the delay after which the next address is tried while a connection attempt is still
+    /// pending, in which case the first attempt that succeeds is used.
+    ///
+    /// By default, an address is only tried after the attempt on the previous one failed.
+    pub fn with_race_delay(mut self, delay: Option<Duration>) -> Self {
+        self.race_delay = delay;
+        self
+    }
+
+    /// Sets how many times the addresses are tried again when none of them could be connected
+    /// to, and the delay before the first retry. The delay doubles on each following retry, up to
+    /// 30 seconds.
+    ///
+    /// By default, the addresses are tried once.
+    pub fn with_retries(mut self, retries: u32, backoff: Duration) -> Self {
+        self.retries = retries;
+        self.backoff = backoff;
+        self
+    }
+
+    pub fn uris(&self) -> &[Uri] {
+        &self.uris
+    }
+
+    /// The address of the last successful connection, or the primary address if there was none.
+    pub fn last_connected(&self) -> &Uri {
+        let index = self.last_connected.load(Ordering::Relaxed);
+        &self.uris[index]
+    }
+
+    fn ordered(&self) -> std::vec::IntoIter<(usize, Uri)> {
+        let first = self.last_connected.load(Ordering::Relaxed);
+        std::iter::once(first)
+            .chain((0..self.uris.len()).filter(|&index| index != first))
+            .map(|index| (index, self.uris[index].clone()))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+}
+
+impl From<Uri> for Endpoints {
+    fn from(uri: Uri) -> Self {
+        Self::new(uri)
+    }
 }
 
 impl AsyncWrite for Transport {
@@ -115,3 +279,89 @@ pub enum ConnectFromUriError {
     #[error("unrecognized URI scheme \"{0}\"")]
     UnrecognizedUriScheme(String),
 }
+
+/// None of the addresses of the endpoints could be connected to. Contains the error of each
+/// attempt, in order.
+#[derive(Debug, thiserror::Error)]
+#[error("failed to connect to any of the addresses of the endpoints")]
+pub struct ConnectEndpointsError(pub Vec<(Uri, ConnectFromUriError)>);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+    use tokio::net::TcpListener;
+
+    fn tcp_uri(address: SocketAddr) -> Uri {
+        Uri::try_from(format!("tcp://{address}")).unwrap()
+    }
+
+    /// The URI of a local address at which nothing listens.
+    async fn dead_uri() -> Uri {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        tcp_uri(listener.local_addr().unwrap())
+    }
+
+    fn error_uris(err: &ConnectEndpointsError) -> Vec<&Uri> {
+        err.0.iter().map(|(uri, _err)| uri).collect()
+    }
+
+    #[tokio::test]
+    async fn test_connect_endpoints_fallback() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let live = tcp_uri(listener.local_addr().unwrap());
+        let dead = dead_uri().await;
+        let endpoints = Endpoints::new(dead.clone()).with_fallback(live.clone());
+        assert_eq!(endpoints.last_connected(), &dead);
+
+        let Transport::Tcp(stream) = Transport::connect_endpoints(&endpoints).await.unwrap();
+        let (accepted, _address) = listener.accept().await.unwrap();
+        assert_eq!(stream.local_addr().unwrap(), accepted.peer_addr().unwrap());
+
+        // Clones share the address that worked, which the following connections try first.
+        let reconnect = endpoints.clone();
+        assert_eq!(reconnect.last_connected(), &live);
+        let ordered: Vec<_> = reconnect.ordered().map(|(_index, uri)| uri).collect();
+        assert_eq!(ordered, [live, dead]);
+    }
+
+    #[tokio::test]
+    async fn test_connect_endpoints_order() {
+        let first = dead_uri().await;
+        let second = dead_uri().await;
+        let endpoints = Endpoints::new(first.clone()).with_fallback(second.clone());
+        let err = Transport::connect_endpoints(&endpoints).await.unwrap_err();
+        assert_eq!(error_uris(&err), [&first, &second]);
+        assert_eq!(endpoints.last_connected(), &first);
+    }
+
+    #[tokio::test]
+    async fn test_connect_endpoints_race() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let live = tcp_uri(listener.local_addr().unwrap());
+        let dead = dead_uri().await;
+        let endpoints = Endpoints::new(dead)
+            .with_fallback(live.clone())
+            .with_race_delay(Some(Duration::ZERO));
+        Transport::connect_endpoints(&endpoints).await.unwrap();
+        assert_eq!(endpoints.last_connected(), &live);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_connect_endpoints_backoff() {
+        let first = dead_uri().await;
+        let second = dead_uri().await;
+        let endpoints = Endpoints::new(first.clone())
+            .with_fallback(second.clone())
+            .with_retries(2, Duration::from_secs(1));
+        let start = tokio::time::Instant::now();
+        let err = Transport::connect_endpoints(&endpoints).await.unwrap_err();
+        // Retries wait for 1 then 2 seconds.
+        assert!(start.elapsed() >= Duration::from_secs(3));
+        assert!(start.elapsed() < Duration::from_secs(4));
+        assert_eq!(
+            error_uris(&err),
+            [&first, &second, &first, &second, &first, &second]
+        );
+    }
+}
@@ -0,0 +1,188 @@
+use super::DEFAULT_TCP_PORT;
+use crate::Uri;
+use futures::future::poll_fn;
+use std::{
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket},
+    task::Poll,
+};
+use tokio::net::{TcpListener, TcpStream};
+
+/// How a node listens for connections to the services that it serves, and how it advertises them.
+///
+/// The endpoints that a node registers in the service directory along with its services are the
+/// addresses at which other nodes connect to them. They are not necessarily the address on which
+/// the node listens: a node that listens on all the interfaces of its host must advertise an
+/// address of one of these interfaces, and a node behind a NAT must advertise its public address.
+///
+/// The advertised endpoints are, in order:
+/// - the endpoints set with [`ServeConfig::with_advertised_endpoint`], if any;
+/// - otherwise the bind address if it is a specific address;
+/// - otherwise, if the node listens on all interfaces, for each IP version, the address of the
+///   interface through which the host routes packets to other hosts, if any, then the loopback
+///   addresses.
+///
+/// The interfaces of the host are not enumerated: a host with several routed interfaces, such as
+/// one on several networks, only advertises the one of its default route. Its other addresses
+/// must be set with [`ServeConfig::with_advertised_endpoint`].
+///
+/// Addresses of the preferred IP version are advertised first, see
+/// [`ServeConfig::with_prefer_ipv6`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServeConfig {
+    bind: SocketAddr,
+    advertised_endpoints: Vec<Uri>,
+    prefer_ipv6: bool,
+}
+
+impl ServeConfig {
+    /// Listens on this address. An unspecified IPv6 address, such as `[::]:9559`, listens on all
+    /// the interfaces of the host for both IPv4 and IPv6.
+    pub fn new(bind: SocketAddr) -> Self {
+        Self {
+            bind,
+            advertised_endpoints: Vec::new(),
+            prefer_ipv6: false,
+        }
+    }
+
+    /// Advertises this endpoint instead of the ones derived from the addresses of the host.
+    ///
+    /// Each call adds an endpoint, the first one being the preferred one.
+    pub fn with_advertised_endpoint(mut self, endpoint: Uri) -> Self {
+        self.advertised_endpoints.push(endpoint);
+        self
+    }
+
+    /// Advertises the IPv6 addresses of the host before its IPv4 addresses.
+    pub fn with_prefer_ipv6(mut self, prefer_ipv6: bool) -> Self {
+        self.prefer_ipv6 = prefer_ipv6;
+        self
+    }
+
+    pub fn bind(&self) -> SocketAddr {
+        self.bind
+    }
+
+    pub fn advertised_endpoints(&self) -> &[Uri] {
+        &self.advertised_endpoints
+    }
+
+    pub fn prefer_ipv6(&self) -> bool {
+        self.prefer_ipv6
+    }
+
+    /// Binds a listener as configured.
+    pub async fn listen(&self) -> io::Result<Listener> {
+        let listeners = match self.bind.ip() {
+            IpAddr::V6(ip) if ip.is_unspecified() => {
+                let v6 = TcpListener::bind(self.bind).await?;
+                let port = v6.local_addr()?.port();
+                // Depending on the system, the IPv6 socket may already accept IPv4 connections, in
+                // which case the address is taken.
+                match TcpListener::bind((Ipv4Addr::UNSPECIFIED, port)).await {
+                    Ok(v4) => vec![v6, v4],
+                    Err(err) if err.kind() == io::ErrorKind::AddrInUse => vec![v6],
+                    Err(err) => return Err(err),
+                }
+            }
+            _ => vec![TcpListener::bind(self.bind).await?],
+        };
+        let local_address = listeners[0].local_addr()?;
+        let endpoints = if self.advertised_endpoints.is_empty() {
+            derived_endpoints(local_address, self.prefer_ipv6)
+        } else {
+            self.advertised_endpoints.clone()
+        };
+        Ok(Listener {
+            listeners,
+            local_address,
+            endpoints,
+        })
+    }
+}
+
+impl Default for ServeConfig {
+    fn default() -> Self {
+        Self::new(SocketAddr::from((Ipv6Addr::UNSPECIFIED, DEFAULT_TCP_PORT)))
+    }
+}
+
+/// A listener of connections, bound as set by a [`ServeConfig`].
+#[derive(Debug)]
+pub struct Listener {
+    listeners: Vec<TcpListener>,
+    local_address: SocketAddr,
+    endpoints: Vec<Uri>,
+}
+
+impl Listener {
+    /// The address on which the listener is bound, with the port assigned by the system if the
+    /// configuration did not set one.
+    pub fn local_address(&self) -> SocketAddr {
+        self.local_address
+    }
+
+    /// The endpoints to advertise for the services served through this listener.
+    pub fn endpoints(&self) -> &[Uri] {
+        &self.endpoints
+    }
+
+    /// Accepts a connection, on any of the addresses of the listener.
+    pub async fn accept(&self) -> io::Result<(TcpStream, SocketAddr)> {
+        poll_fn(|cx| {
+            for listener in &self.listeners {
+                if let Poll::Ready(result) = listener.poll_accept(cx) {
+                    return Poll::Ready(result);
+                }
+            }
+            Poll::Pending
+        })
+        .await
+    }
+}
+
+fn derived_endpoints(local_address: SocketAddr, prefer_ipv6: bool) -> Vec<Uri> {
+    let port = local_address.port();
+    let mut addresses = if local_address.ip().is_unspecified() {
+        let mut addresses = Vec::new();
+        addresses.extend(routed_address(IpAddr::V4(PROBE_V4)));
+        addresses.push(IpAddr::V4(Ipv4Addr::LOCALHOST));
+        // A listener on an unspecified IPv4 address does not accept IPv6 connections.
+        if local_address.is_ipv6() {
+            addresses.extend(routed_address(IpAddr::V6(PROBE_V6)));
+            addresses.push(IpAddr::V6(Ipv6Addr::LOCALHOST));
+        }
+        addresses
+    } else {
+        vec![local_address.ip()]
+    };
+    addresses.sort_by_key(|address| (address.is_loopback(), address.is_ipv6() != prefer_ipv6));
+    addresses
+        .into_iter()
+        .filter_map(|address| {
+            // A socket address always forms a valid URI.
+            Uri::try_from(format!("tcp://{}", SocketAddr::new(address, port))).ok()
+        })
+        .collect()
+}
+
+// Addresses reserved for documentation, that are never reached: no packet is sent to them.
+const PROBE_V4: Ipv4Addr = Ipv4Addr::new(192, 0, 2, 1);
+const PROBE_V6: Ipv6Addr = Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1);
+
+/// Returns the address of the interface through which the host routes packets to other hosts, if
+/// it has a route for this IP version.
+///
+/// Connecting a UDP socket only selects the route to the address, which is enough to find the
+/// address of the interface, without the need to enumerate the interfaces of the host.
+fn routed_address(probe: IpAddr) -> Option<IpAddr> {
+    let unspecified = match probe {
+        IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    };
+    let socket = UdpSocket::bind((unspecified, 0)).ok()?;
+    socket.connect((probe, DEFAULT_TCP_PORT)).ok()?;
+    let address = socket.local_addr().ok()?.ip();
+    (!address.is_unspecified() && !address.is_loopback()).then_some(address)
+}
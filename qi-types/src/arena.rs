@@ -0,0 +1,419 @@
+//! Values allocated in an arena.
+//!
+//! Deserializing a [`Value`] allocates every string, list, map and tuple of the value on its own,
+//! which is costly for large values. A [`ValueRef`] instead borrows all of its data from an
+//! [`Arena`], that is released at once when it is dropped, and from the deserialized data when the
+//! deserializer allows it.
+//!
+//! The arena does not run destructors, so values that own resources, such as objects, cannot be
+//! allocated in it.
+
+use crate::{
+    num_bool::*, ty::Type, Dynamic, FormatterExt, List, Map, Raw, Signature, Tuple, Value,
+};
+use bumpalo::collections::Vec as ArenaVec;
+pub use bumpalo::Bump as Arena;
+
+/// A value of the `qi` type system which data is borrowed from an arena.
+///
+/// See [`Value`] for the owned equivalent.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ValueRef<'a> {
+    Unit,
+    Bool(bool),
+    Number(Number),
+    String(&'a str),
+    Raw(&'a [u8]),
+    Option(Option<&'a ValueRef<'a>>),
+    List(&'a [ValueRef<'a>]),
+    Map(&'a [(ValueRef<'a>, ValueRef<'a>)]),
+    Tuple(&'a [ValueRef<'a>]),
+    Dynamic(&'a ValueRef<'a>),
+}
+
+impl<'a> ValueRef<'a> {
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_number(&self) -> Option<Number> {
+        match self {
+            Self::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&'a str> {
+        match self {
+            Self::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_raw(&self) -> Option<&'a [u8]> {
+        match self {
+            Self::Raw(r) => Some(r),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&'a [ValueRef<'a>]> {
+        match self {
+            Self::List(l) => Some(l),
+            _ => None,
+        }
+    }
+
+    pub fn as_map(&self) -> Option<&'a [(ValueRef<'a>, ValueRef<'a>)]> {
+        match self {
+            Self::Map(m) => Some(m),
+            _ => None,
+        }
+    }
+
+    pub fn as_tuple(&self) -> Option<&'a [ValueRef<'a>]> {
+        match self {
+            Self::Tuple(t) => Some(t),
+            _ => None,
+        }
+    }
+
+    /// Copies the value out of the arena.
+    pub fn to_value(&self) -> Value {
+        match self {
+            Self::Unit => Value::Unit,
+            Self::Bool(b) => Value::Bool(*b),
+            Self::Number(n) => Value::Number(*n),
+            Self::String(s) => Value::String((*s).to_owned()),
+            Self::Raw(r) => Value::Raw(Raw::copy_from_slice(r)),
+            Self::Option(o) => Value::Option(Box::new(o.map(ValueRef::to_value))),
+            Self::List(l) => Value::List(l.iter().map(ValueRef::to_value).collect::<List<_>>()),
+            Self::Map(m) => Value::Map(
+                m.iter()
+                    .map(|(key, value)| (key.to_value(), value.to_value()))
+                    .collect::<Map<_, _>>(),
+            ),
+            Self::Tuple(t) => {
+                Value::Tuple(Tuple::from_vec(t.iter().map(ValueRef::to_value).collect()))
+            }
+            Self::Dynamic(d) => Value::Dynamic(Box::new(Dynamic::from_value(d.to_value()))),
+        }
+    }
+}
+
+impl<'a> From<&'a str> for ValueRef<'a> {
+    fn from(s: &'a str) -> Self {
+        Self::String(s)
+    }
+}
+
+impl From<ValueRef<'_>> for Value {
+    fn from(value: ValueRef<'_>) -> Self {
+        value.to_value()
+    }
+}
+
+/// Displays the value as its owned equivalent [`Value`] is displayed.
+impl std::fmt::Display for ValueRef<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unit => f.write_str("()"),
+            Self::Bool(b) => b.fmt(f),
+            Self::Number(n) => n.fmt(f),
+            Self::String(s) => f.write_escaped_str(s),
+            Self::Raw(r) => f.write_raw(r),
+            Self::Option(o) => f.write_option(o),
+            Self::List(l) => f.write_list(l),
+            Self::Map(m) => {
+                f.write_str("{")?;
+                let mut add_sep = false;
+                for (key, value) in *m {
+                    if add_sep {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{key}: {value}")?;
+                    add_sep = true;
+                }
+                f.write_str("}")
+            }
+            Self::Tuple(t) => {
+                f.write_str("(")?;
+                f.write_list(t)?;
+                f.write_str(")")
+            }
+            Self::Dynamic(d) => d.fmt(f),
+        }
+    }
+}
+
+/// Deserializes a [`ValueRef`] of a type known beforehand in an arena.
+///
+/// If the type is unknown, the value is deserialized as a `dynamic` value, which carries its own
+/// type information.
+#[derive(Debug, Clone, Copy)]
+pub struct ValueRefSeed<'a, 't> {
+    arena: &'a Arena,
+    t: Option<&'t Type>,
+}
+
+impl<'a, 't> ValueRefSeed<'a, 't> {
+    pub fn new(arena: &'a Arena, t: Option<&'t Type>) -> Self {
+        Self { arena, t }
+    }
+
+    fn with_type<'u>(&self, t: Option<&'u Type>) -> ValueRefSeed<'a, 'u> {
+        ValueRefSeed::new(self.arena, t)
+    }
+
+    fn alloc(&self, value: ValueRef<'a>) -> &'a ValueRef<'a> {
+        self.arena.alloc(value)
+    }
+}
+
+impl<'de: 'a, 'a, 't> serde::de::DeserializeSeed<'de> for ValueRefSeed<'a, 't> {
+    type Value = ValueRef<'a>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::Deserialize;
+        let t = match self.t {
+            Some(t) => t,
+            None => {
+                let value = deserializer.deserialize_tuple(2, DynamicVisitor(self))?;
+                return Ok(ValueRef::Dynamic(self.alloc(value)));
+            }
+        };
+        let value = match t {
+            Type::Unit => {
+                <()>::deserialize(deserializer)?;
+                ValueRef::Unit
+            }
+            Type::Bool => ValueRef::Bool(bool::deserialize(deserializer)?),
+            Type::Int8 => ValueRef::Number(Number::Int8(i8::deserialize(deserializer)?)),
+            Type::UInt8 => ValueRef::Number(Number::UInt8(u8::deserialize(deserializer)?)),
+            Type::Int16 => ValueRef::Number(Number::Int16(i16::deserialize(deserializer)?)),
+            Type::UInt16 => ValueRef::Number(Number::UInt16(u16::deserialize(deserializer)?)),
+            Type::Int32 => ValueRef::Number(Number::Int32(i32::deserialize(deserializer)?)),
+            Type::UInt32 => ValueRef::Number(Number::UInt32(u32::deserialize(deserializer)?)),
+            Type::Int64 => ValueRef::Number(Number::Int64(i64::deserialize(deserializer)?)),
+            Type::UInt64 => ValueRef::Number(Number::UInt64(u64::deserialize(deserializer)?)),
+            Type::Float32 => ValueRef::Number(Number::Float32(Float32::deserialize(deserializer)?)),
+            Type::Float64 => ValueRef::Number(Number::Float64(Float64::deserialize(deserializer)?)),
+            Type::String => ValueRef::String(deserializer.deserialize_str(StrVisitor(self.arena))?),
+            Type::Raw => ValueRef::Raw(deserializer.deserialize_bytes(BytesVisitor(self.arena))?),
+            Type::Object => {
+                return Err(serde::de::Error::custom(
+                    "objects cannot be allocated in an arena",
+                ))
+            }
+            Type::Option(t) => {
+                let option =
+                    deserializer.deserialize_option(OptionVisitor(self.with_type(t.as_deref())))?;
+                ValueRef::Option(option)
+            }
+            Type::List(t) | Type::VarArgs(t) => {
+                let list =
+                    deserializer.deserialize_seq(ListVisitor(self.with_type(t.as_deref())))?;
+                ValueRef::List(list)
+            }
+            Type::Map { key, value } => {
+                let map = deserializer.deserialize_map(MapVisitor {
+                    key: self.with_type(key.as_deref()),
+                    value: self.with_type(value.as_deref()),
+                })?;
+                ValueRef::Map(map)
+            }
+            Type::Tuple(tuple) => {
+                let element_types = tuple.element_types();
+                let elements = deserializer.deserialize_tuple(
+                    element_types.len(),
+                    TupleVisitor {
+                        seed: self,
+                        element_types: &element_types,
+                    },
+                )?;
+                ValueRef::Tuple(elements)
+            }
+        };
+        Ok(value)
+    }
+}
+
+struct DynamicVisitor<'a, 't>(ValueRefSeed<'a, 't>);
+
+impl<'de: 'a, 'a, 't> serde::de::Visitor<'de> for DynamicVisitor<'a, 't> {
+    type Value = ValueRef<'a>;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a dynamic value")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::SeqAccess<'de>,
+    {
+        use serde::de;
+        let invalid_length =
+            |i| de::Error::invalid_length(i, &"a sequence of size 2 (signature, value)");
+        let signature: Signature = seq.next_element()?.ok_or_else(|| invalid_length(0))?;
+        let value_type = signature.into_type();
+        seq.next_element_seed(self.0.with_type(value_type.as_ref()))?
+            .ok_or_else(|| invalid_length(1))
+    }
+}
+
+struct StrVisitor<'a>(&'a Arena);
+
+impl<'de: 'a, 'a> serde::de::Visitor<'de> for StrVisitor<'a> {
+    type Value = &'a str;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a string")
+    }
+
+    fn visit_borrowed_str<E>(self, v: &'de str) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        Ok(v)
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        Ok(self.0.alloc_str(v))
+    }
+}
+
+struct BytesVisitor<'a>(&'a Arena);
+
+impl<'de: 'a, 'a> serde::de::Visitor<'de> for BytesVisitor<'a> {
+    type Value = &'a [u8];
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("raw data")
+    }
+
+    fn visit_borrowed_bytes<E>(self, v: &'de [u8]) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        Ok(v)
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        Ok(self.0.alloc_slice_copy(v))
+    }
+}
+
+struct OptionVisitor<'a, 't>(ValueRefSeed<'a, 't>);
+
+impl<'de: 'a, 'a, 't> serde::de::Visitor<'de> for OptionVisitor<'a, 't> {
+    type Value = Option<&'a ValueRef<'a>>;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("an optional value")
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        Ok(None)
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::DeserializeSeed;
+        let value = self.0.deserialize(deserializer)?;
+        Ok(Some(self.0.alloc(value)))
+    }
+}
+
+struct ListVisitor<'a, 't>(ValueRefSeed<'a, 't>);
+
+impl<'de: 'a, 'a, 't> serde::de::Visitor<'de> for ListVisitor<'a, 't> {
+    type Value = &'a [ValueRef<'a>];
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a list")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::SeqAccess<'de>,
+    {
+        // The size hint comes from the input, it must not be trusted for the allocation.
+        const MAX_RESERVED_ELEMENTS: usize = 1024;
+        let capacity = seq.size_hint().unwrap_or(0).min(MAX_RESERVED_ELEMENTS);
+        let mut elements = ArenaVec::with_capacity_in(capacity, self.0.arena);
+        while let Some(element) = seq.next_element_seed(self.0)? {
+            elements.push(element);
+        }
+        Ok(elements.into_bump_slice())
+    }
+}
+
+struct MapVisitor<'a, 'k, 'v> {
+    key: ValueRefSeed<'a, 'k>,
+    value: ValueRefSeed<'a, 'v>,
+}
+
+impl<'de: 'a, 'a, 'k, 'v> serde::de::Visitor<'de> for MapVisitor<'a, 'k, 'v> {
+    type Value = &'a [(ValueRef<'a>, ValueRef<'a>)];
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a map")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::MapAccess<'de>,
+    {
+        const MAX_RESERVED_ENTRIES: usize = 1024;
+        let capacity = map.size_hint().unwrap_or(0).min(MAX_RESERVED_ENTRIES);
+        let mut entries = ArenaVec::with_capacity_in(capacity, self.key.arena);
+        while let Some(entry) = map.next_entry_seed(self.key, self.value)? {
+            entries.push(entry);
+        }
+        Ok(entries.into_bump_slice())
+    }
+}
+
+struct TupleVisitor<'a, 't, 'e> {
+    seed: ValueRefSeed<'a, 't>,
+    element_types: &'e [Option<Type>],
+}
+
+impl<'de: 'a, 'a, 't, 'e> serde::de::Visitor<'de> for TupleVisitor<'a, 't, 'e> {
+    type Value = &'a [ValueRef<'a>];
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "a tuple of size {}", self.element_types.len())
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::SeqAccess<'de>,
+    {
+        let mut elements = ArenaVec::with_capacity_in(self.element_types.len(), self.seed.arena);
+        for (index, t) in self.element_types.iter().enumerate() {
+            let element = seq
+                .next_element_seed(self.seed.with_type(t.as_ref()))?
+                .ok_or_else(|| serde::de::Error::invalid_length(index, &self))?;
+            elements.push(element);
+        }
+        Ok(elements.into_bump_slice())
+    }
+}
@@ -10,7 +10,7 @@ use crate::{
 /// [`Dynamic`] represents a `dynamic` value in the `qi` type system.
 ///
 /// It is a value associated with its type information.
-#[derive(Clone, PartialEq, Eq, Debug, derive_more::From, derive_more::TryInto)]
+#[derive(Clone, PartialEq, Eq, Hash, Debug, derive_more::From, derive_more::TryInto)]
 pub enum Dynamic {
     #[from]
     Unit,
@@ -44,48 +44,35 @@ impl Dynamic {
                 actual: value.dynamic_type(),
             });
         }
-        let value = match value {
-            Value::Unit => Self::Unit,
-            Value::Bool(b) => Self::Bool(b),
-            Value::Number(n) => Self::Number(n),
-            Value::String(s) => Self::String(s),
-            Value::Raw(r) => Self::Raw(r),
-            Value::Object(o) => Self::Object(o),
-            Value::Dynamic(d) => Self::Dynamic(d),
-            Value::Option(option) => {
-                let value_type = match t {
-                    Some(Type::Option(o)) => o.as_deref().cloned(),
-                    _ => unreachable!(),
-                };
-                Self::Option(OptionDynamic(*option, value_type))
+        let value = match (value, t) {
+            (Value::Unit, _) => Self::Unit,
+            (Value::Bool(b), _) => Self::Bool(b),
+            (Value::Number(n), _) => Self::Number(n),
+            (Value::String(s), _) => Self::String(s),
+            (Value::Raw(r), _) => Self::Raw(r),
+            (Value::Object(o), _) => Self::Object(o),
+            (Value::Dynamic(d), _) => Self::Dynamic(d),
+            (Value::Option(option), Some(Type::Option(value_type))) => {
+                Self::Option(OptionDynamic(*option, value_type.map(|t| *t)))
             }
-            Value::List(list) => {
-                let value_type = match t {
-                    Some(Type::List(l)) => l.as_deref().cloned(),
-                    _ => unreachable!(),
-                };
-                Self::List(ListDynamic(list, value_type))
+            (Value::List(list), Some(Type::List(value_type))) => {
+                Self::List(ListDynamic(list, value_type.map(|t| *t)))
             }
-            Value::Map(map) => {
-                let (key_type, value_type) = match t {
-                    Some(Type::Map { key, value }) => {
-                        (key.as_deref().cloned(), value.as_deref().cloned())
-                    }
-                    _ => unreachable!(),
-                };
-                Self::Map(MapDynamic {
-                    value: map,
-                    key_type,
-                    value_type,
-                })
-            }
-            Value::Tuple(tuple) => {
-                let tuple_type = match t {
-                    Some(Type::Tuple(tuple_type)) => tuple_type,
-                    _ => unreachable!(),
-                };
+            (Value::Map(map), Some(Type::Map { key, value })) => Self::Map(MapDynamic {
+                value: map,
+                key_type: key.map(|t| *t),
+                value_type: value.map(|t| *t),
+            }),
+            (Value::Tuple(tuple), Some(Type::Tuple(tuple_type))) => {
                 Self::Tuple(TupleDynamic(tuple, tuple_type))
             }
+            // Values with a composite type always have a type that matches, as checked above.
+            (value, t) => {
+                return Err(TypeMismatchError {
+                    expected: t,
+                    actual: value.dynamic_type(),
+                })
+            }
         };
         Ok(value)
     }
@@ -131,6 +118,13 @@ impl Dynamic {
         }
     }
 
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Dynamic::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
     pub fn as_raw(&self) -> Option<&Raw> {
         match self {
             Dynamic::Raw(r) => Some(r),
@@ -244,6 +238,96 @@ impl Dynamic {
             Self::Dynamic(d) => Value::Dynamic(d),
         }
     }
+
+    /// Converts the value into a value of a type, that is checked against its signature.
+    ///
+    /// The signature embedded in the value is used when it is complete, the type is then
+    /// required to be a supertype of it. It may instead lack the types of some of its elements,
+    /// for instance if it was built from a list of values whose type could not be deduced, in
+    /// which case the supplied type is used, and must be one of the value.
+    ///
+    /// Nested `dynamic` values are decoded through, as if their value was in their place.
+    ///
+    /// ```
+    /// use qi_types::{dynamic::DecodeError, Dynamic, Type, Value};
+    ///
+    /// let list = Dynamic::from_value(Value::List(vec![Value::from("hello")]));
+    /// let list_of_str = Type::List(Some(Box::new(Type::String)));
+    /// assert!(list.decode_with_type(&list_of_str).is_ok());
+    /// assert!(matches!(
+    ///     Dynamic::from("hello").decode_with_type(&Type::Int32),
+    ///     Err(DecodeError::SignatureMismatch { .. })
+    /// ));
+    /// ```
+    pub fn decode_with_type(&self, t: &Type) -> Result<Self, DecodeError> {
+        let mut dynamic = self;
+        while let Self::Dynamic(inner) = dynamic {
+            dynamic = inner;
+        }
+        let embedded = dynamic.embedded_type();
+        let value = dynamic.clone().into_value();
+        let mismatch = |actual: Option<Type>| DecodeError::SignatureMismatch {
+            expected: Signature::from(t.clone()),
+            actual: Signature::new(actual),
+        };
+        let embedded_signed = is_signed(&value, embedded.as_ref());
+        if embedded_signed && !embedded.as_ref().is_some_and(|e| e.is_subtype_of(t)) {
+            return Err(mismatch(embedded));
+        }
+        let value_type = if is_signed(&value, Some(t)) {
+            Some(t.clone())
+        } else if embedded_signed {
+            embedded
+        } else {
+            return Err(DecodeError::MissingSignature);
+        };
+        Self::new(value, value_type).map_err(|err| mismatch(err.actual))
+    }
+
+    /// The type of the value, as embedded in it.
+    fn embedded_type(&self) -> Option<Type> {
+        use ty::DynamicGetType;
+        match self {
+            Self::Unit => Some(Type::Unit),
+            Self::Bool(_) => Some(Type::Bool),
+            Self::Number(n) => Some(n.ty()),
+            Self::String(_) => Some(Type::String),
+            Self::Raw(_) => Some(Type::Raw),
+            Self::Option(o) => o.dynamic_type(),
+            Self::List(l) => l.dynamic_type(),
+            Self::Map(m) => m.dynamic_type(),
+            Self::Tuple(t) => t.dynamic_type(),
+            Self::Object(_) => Some(Type::Object),
+            Self::Dynamic(_) => None,
+        }
+    }
+}
+
+/// Returns true if the type gives the signature of all the elements of the value.
+///
+/// Elements of a `dynamic` type carry their own signature, as long as they are `dynamic` values.
+/// Elements that do not have the type are not checked here, but by the conversion of the value.
+fn is_signed(value: &Value, t: Option<&Type>) -> bool {
+    match (value, t) {
+        (Value::Dynamic(_), None) => true,
+        (_, None) => false,
+        (Value::Option(option), Some(Type::Option(t))) => option
+            .as_ref()
+            .as_ref()
+            .is_none_or(|value| is_signed(value, t.as_deref())),
+        (Value::List(list), Some(Type::List(t) | Type::VarArgs(t))) => {
+            list.iter().all(|value| is_signed(value, t.as_deref()))
+        }
+        (Value::Map(map), Some(Type::Map { key, value })) => map
+            .iter()
+            .all(|(k, v)| is_signed(k, key.as_deref()) && is_signed(v, value.as_deref())),
+        (Value::Tuple(tuple), Some(Type::Tuple(tuple_type))) => tuple
+            .elements()
+            .iter()
+            .zip(tuple_type.element_types())
+            .all(|(value, t)| is_signed(value, t.as_ref())),
+        _ => true,
+    }
 }
 
 impl Default for Dynamic {
@@ -310,6 +394,12 @@ impl From<&str> for Dynamic {
     }
 }
 
+impl From<std::borrow::Cow<'_, str>> for Dynamic {
+    fn from(s: std::borrow::Cow<'_, str>) -> Self {
+        Self::String(s.into_owned())
+    }
+}
+
 impl From<Option<Value>> for Dynamic {
     fn from(v: Option<Value>) -> Self {
         Self::Option(OptionDynamic::from(v))
@@ -334,7 +424,7 @@ impl std::fmt::Display for Dynamic {
             Self::Unit => f.write_str("()"),
             Self::Bool(b) => b.fmt(f),
             Self::Number(n) => n.fmt(f),
-            Self::String(s) => s.fmt(f),
+            Self::String(s) => f.write_escaped_str(s),
             Self::Raw(r) => f.write_raw(r),
             Self::Option(o) => o.fmt(f),
             Self::List(l) => l.fmt(f),
@@ -421,7 +511,18 @@ impl<'de> serde::Deserialize<'de> for Dynamic {
     }
 }
 
-struct DynamicSeed(Option<Type>);
+/// Deserializes a [`Dynamic`] value of a type known beforehand.
+///
+/// If the type is unknown, the value is deserialized as a `dynamic` value, which carries its own
+/// type information.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DynamicSeed(Option<Type>);
+
+impl DynamicSeed {
+    pub fn new(t: Option<Type>) -> Self {
+        Self(t)
+    }
+}
 
 impl<'de> serde::de::DeserializeSeed<'de> for DynamicSeed {
     type Value = Dynamic;
@@ -523,7 +624,7 @@ impl<'de> serde::de::DeserializeSeed<'de> for DynamicSeed {
     }
 }
 
-#[derive(Default, Clone, PartialEq, Eq, PartialOrd, Debug)]
+#[derive(Default, Clone, PartialEq, Eq, PartialOrd, Hash, Debug)]
 pub struct OptionDynamic(Option<Value>, Option<Type>);
 
 impl OptionDynamic {
@@ -619,7 +720,7 @@ impl<'de> serde::de::DeserializeSeed<'de> for OptionDynamicSeed {
     }
 }
 
-#[derive(Default, Clone, PartialEq, Eq, PartialOrd, Debug)]
+#[derive(Default, Clone, PartialEq, Eq, PartialOrd, Hash, Debug)]
 pub struct ListDynamic(List<Value>, Option<Type>);
 
 impl ListDynamic {
@@ -702,7 +803,7 @@ impl<'de> serde::de::DeserializeSeed<'de> for ListDynamicSeed {
     }
 }
 
-#[derive(Default, Clone, PartialEq, Eq, PartialOrd, Debug)]
+#[derive(Default, Clone, PartialEq, Eq, PartialOrd, Hash, Debug)]
 pub struct MapDynamic {
     value: Map<Value, Value>,
     key_type: Option<Type>,
@@ -812,7 +913,7 @@ impl<'de> serde::de::DeserializeSeed<'de> for MapDynamicSeed {
     }
 }
 
-#[derive(Default, Clone, PartialEq, Eq, PartialOrd, Debug)]
+#[derive(Default, Clone, PartialEq, Eq, PartialOrd, Hash, Debug)]
 pub struct TupleDynamic(Tuple, ty::TupleType);
 
 impl TupleDynamic {
@@ -824,6 +925,10 @@ impl TupleDynamic {
         self.0
     }
 
+    pub fn tuple_type(&self) -> &ty::TupleType {
+        &self.1
+    }
+
     fn ty(&self) -> Type {
         Type::Tuple(self.1.clone())
     }
@@ -987,6 +1092,21 @@ impl std::fmt::Display for TypeMismatchError {
     }
 }
 
+/// An error of the conversion of a [`Dynamic`] value, see [`Dynamic::decode_with_type`].
+#[derive(Clone, PartialEq, Eq, Hash, Debug, thiserror::Error)]
+pub enum DecodeError {
+    #[error("the dynamic value lacks the signature of some of its elements, and the supplied type does not give it")]
+    MissingSignature,
+
+    #[error(
+        "the dynamic value has the signature \"{actual}\", which does not match \"{expected}\""
+    )]
+    SignatureMismatch {
+        expected: Signature,
+        actual: Signature,
+    },
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1061,4 +1181,48 @@ mod tests {
             ],
         );
     }
+
+    #[test]
+    fn test_dynamic_decode_with_type() {
+        let int_list = list_ty!(Type::Int32);
+        let list = Dynamic::new(
+            Value::List(vec![Value::from(Number::Int32(1))]),
+            Some(int_list.clone()),
+        )
+        .unwrap();
+        assert_eq!(list.decode_with_type(&int_list), Ok(list.clone()));
+        // Nested dynamic values are decoded through.
+        let nested = Dynamic::Dynamic(Box::new(list.clone()));
+        assert_eq!(nested.decode_with_type(&int_list), Ok(list.clone()));
+        // The embedded signature is kept if the supplied type lacks some of it.
+        assert_eq!(list.decode_with_type(&Type::List(None)), Ok(list.clone()));
+        assert_eq!(
+            list.decode_with_type(&list_ty!(Type::String)),
+            Err(DecodeError::SignatureMismatch {
+                expected: Signature::from(list_ty!(Type::String)),
+                actual: Signature::from(int_list.clone()),
+            })
+        );
+
+        // Lists of values of different types have no signature for their elements.
+        let mixed = Dynamic::new(
+            Value::List(vec![Value::from(Number::Int32(1)), Value::from("a")]),
+            Some(Type::List(None)),
+        )
+        .unwrap();
+        assert_eq!(
+            mixed.decode_with_type(&Type::List(None)),
+            Err(DecodeError::MissingSignature)
+        );
+        assert!(matches!(
+            mixed.decode_with_type(&int_list),
+            Err(DecodeError::SignatureMismatch { .. })
+        ));
+        let ints = Dynamic::new(
+            Value::List(vec![Value::from(Number::Int32(1))]),
+            Some(Type::List(None)),
+        )
+        .unwrap();
+        assert_eq!(ints.decode_with_type(&int_list), Ok(list));
+    }
 }
@@ -1,14 +1,16 @@
 #![doc(test(attr(deny(warnings))))]
 #![doc = include_str!("../README.md")]
 
+#[cfg(feature = "arena")]
+pub mod arena;
 pub mod dynamic;
 pub mod map;
 mod num_bool;
 pub mod object;
-mod signature;
+pub mod signature;
 mod tuple;
 pub mod ty;
-mod value;
+pub mod value;
 
 #[doc(inline)]
 pub use crate::{
@@ -19,7 +21,7 @@ pub use crate::{
     signature::Signature,
     tuple::Tuple,
     ty::Type,
-    value::Value,
+    value::{Value, ValuePath},
 };
 
 pub use bytes;
@@ -59,6 +61,10 @@ pub trait FormatterExt {
 
     fn write_raw(&mut self, raw: &[u8]) -> std::fmt::Result;
 
+    /// Writes a string with its control characters escaped, such as the NUL characters that some
+    /// services leave in their strings, so that they do not garble the output.
+    fn write_escaped_str(&mut self, s: &str) -> std::fmt::Result;
+
     fn write_list<T>(&mut self, list: &[T]) -> std::fmt::Result
     where
         T: std::fmt::Display;
@@ -82,6 +88,17 @@ impl<'a> FormatterExt for std::fmt::Formatter<'a> {
         Ok(())
     }
 
+    fn write_escaped_str(&mut self, s: &str) -> std::fmt::Result {
+        for c in s.chars() {
+            if c.is_control() {
+                write!(self, "{}", c.escape_default())?;
+            } else {
+                std::fmt::Write::write_char(self, c)?;
+            }
+        }
+        Ok(())
+    }
+
     fn write_list<T>(&mut self, list: &[T]) -> std::fmt::Result
     where
         T: std::fmt::Display,
@@ -1,5 +1,7 @@
 use crate::{ty, Type};
-use derive_more::{From, Index, Into, IntoIterator};
+use derive_more::IntoIterator;
+use indexmap::IndexMap;
+use std::{borrow::Borrow, hash::Hash};
 
 /// The [`Map`] value represents an association of keys to values in the `qi` type system.
 ///
@@ -8,105 +10,124 @@ use derive_more::{From, Index, Into, IntoIterator};
 /// The key-value pairs have a consistent order that is determined by the sequence of insertion and
 /// removal calls on the map. The order does not depend on the keys.
 ///
-/// All iterators traverse the map in the order.
+/// All iterators traverse the map in the order. Comparisons between maps also take this order
+/// into account.
 ///
 /// # Unicity of keys
 ///
 /// This type guarantees the unicity of keys. When an insertion is done, if the key already exists
-/// in the map, its value is overwritten with the inserted one.
-#[derive(
-    Default, Clone, PartialEq, Eq, PartialOrd, Ord, From, Into, Index, IntoIterator, Debug, Hash,
-)]
-pub struct Map<K, V>(Vec<(K, V)>);
+/// in the map, its value is overwritten with the inserted one, and the key keeps its position.
+///
+/// # Complexity
+///
+/// Lookups by key are done in constant time (on average), thanks to an hash table indexing the
+/// key-value pairs.
+#[derive(Clone, IntoIterator, Debug)]
+pub struct Map<K, V>(IndexMap<K, V>);
 
 impl<K, V> Map<K, V> {
     pub fn new() -> Self {
-        Self(Vec::new())
+        Self(IndexMap::new())
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self(IndexMap::with_capacity(capacity))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
     }
 
     pub fn keys(&self) -> impl Iterator<Item = &K> {
-        self.0.iter().map(|(k, _v)| k)
+        self.0.keys()
     }
 
     pub fn values(&self) -> impl Iterator<Item = &V> {
-        self.0.iter().map(|(_k, v)| v)
+        self.0.values()
     }
 
     pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
-        self.0.iter().map(|(k, v)| (k, v))
+        self.0.iter()
     }
 
-    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&mut K, &mut V)> {
-        self.0.iter_mut().map(|(k, v)| (k, v))
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&K, &mut V)> {
+        self.0.iter_mut()
     }
 
-    pub fn get<'s, Q>(&'s self, key: &Q) -> Option<&'s V>
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
     where
-        Q: PartialEq<K> + ?Sized,
+        K: Borrow<Q> + Hash + Eq,
+        Q: Hash + Eq + ?Sized,
     {
-        self.0
-            .iter()
-            .find_map(|(k, v)| if key == k { Some(v) } else { None })
+        self.0.get(key)
     }
 
-    pub fn get_mut<'s, Q>(&'s mut self, key: &Q) -> Option<&'s mut V>
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
     where
-        Q: PartialEq<K> + ?Sized,
+        K: Borrow<Q> + Hash + Eq,
+        Q: Hash + Eq + ?Sized,
     {
-        self.0
-            .iter_mut()
-            .find_map(|(k, v)| if key == k { Some(v) } else { None })
+        self.0.get_mut(key)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q> + Hash + Eq,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.0.contains_key(key)
     }
 
     pub fn entry(&mut self, key: K) -> Entry<'_, K, V>
     where
-        K: PartialEq,
+        K: Hash + Eq,
     {
-        let item = self
-            .0
-            .iter_mut()
-            .enumerate()
-            .find(|(_idx, (k, _v))| k == &key);
-        match item {
-            Some((idx, _pair)) => Entry::Occupied(OccupiedEntry {
-                vec: &mut self.0,
-                idx,
-            }),
-            None => Entry::Vacant(VacantEntry {
-                key,
-                vec: &mut self.0,
-            }),
+        match self.0.entry(key) {
+            indexmap::map::Entry::Occupied(entry) => Entry::Occupied(OccupiedEntry(entry)),
+            indexmap::map::Entry::Vacant(entry) => Entry::Vacant(VacantEntry(entry)),
         }
     }
 
-    fn position<Q>(&self, key: &Q) -> Option<usize>
+    pub fn insert(&mut self, key: K, value: V) -> Option<V>
     where
-        Q: PartialEq<K> + ?Sized,
+        K: Hash + Eq,
     {
-        self.0.iter().position(|(k, _)| key == k)
+        self.0.insert(key, value)
     }
 
-    pub fn insert(&mut self, key: K, mut value: V) -> Option<V>
+    /// Removes a key from the map, returning its value if it was present.
+    ///
+    /// The order of the remaining key-value pairs is preserved, which makes this operation linear
+    /// in the number of pairs that follow the removed one.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
     where
-        K: PartialEq,
+        K: Borrow<Q> + Hash + Eq,
+        Q: Hash + Eq + ?Sized,
     {
-        match self.position(&key) {
-            Some(position) => {
-                std::mem::swap(&mut value, &mut self.0[position].1);
-                Some(value)
-            }
-            None => {
-                self.0.push((key, value));
-                None
-            }
-        }
+        self.0.shift_remove(key)
     }
 
-    pub fn retain<F>(&mut self, mut f: F)
+    /// Removes a key from the map, returning the stored key and its value if it was present.
+    ///
+    /// See [`Map::remove`] for details about the order of the remaining pairs.
+    pub fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
     where
+        K: Borrow<Q> + Hash + Eq,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.0.shift_remove_entry(key)
+    }
+
+    pub fn retain<F>(&mut self, f: F)
+    where
+        K: Hash + Eq,
         F: FnMut(&K, &mut V) -> bool,
     {
-        self.0.retain_mut(|(key, value)| f(key, value))
+        self.0.retain(f)
     }
 
     fn type_reduce<F>(&self, f: F) -> Type
@@ -136,12 +157,77 @@ impl<K, V> Map<K, V> {
     {
         Some(self.type_reduce(|(key, value)| (key.dynamic_type(), value.dynamic_type())))
     }
+}
 
-    pub fn contains_key<Q>(&self, key: &Q) -> bool
-    where
-        Q: PartialEq<K> + ?Sized,
-    {
-        self.0.iter().any(|(key_in, _)| key == key_in)
+impl<K, V> Default for Map<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V> PartialEq for Map<K, V>
+where
+    K: PartialEq,
+    V: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.iter().eq(other.iter())
+    }
+}
+
+impl<K, V> Eq for Map<K, V>
+where
+    K: Eq,
+    V: Eq,
+{
+}
+
+impl<K, V> PartialOrd for Map<K, V>
+where
+    K: PartialOrd,
+    V: PartialOrd,
+{
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        self.iter().partial_cmp(other.iter())
+    }
+}
+
+impl<K, V> Ord for Map<K, V>
+where
+    K: Ord,
+    V: Ord,
+{
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.iter().cmp(other.iter())
+    }
+}
+
+impl<K, V> Hash for Map<K, V>
+where
+    K: Hash,
+    V: Hash,
+{
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        state.write_usize(self.len());
+        for (key, value) in self {
+            key.hash(state);
+            value.hash(state);
+        }
+    }
+}
+
+impl<K, V> From<Vec<(K, V)>> for Map<K, V>
+where
+    K: Hash + Eq,
+{
+    fn from(pairs: Vec<(K, V)>) -> Self {
+        Self::from_iter(pairs)
+    }
+}
+
+impl<K, V> From<Map<K, V>> for Vec<(K, V)> {
+    fn from(map: Map<K, V>) -> Self {
+        map.0.into_iter().collect()
     }
 }
 
@@ -152,55 +238,52 @@ pub enum Entry<'a, K, V> {
 }
 
 #[derive(Debug)]
-pub struct OccupiedEntry<'a, K, V> {
-    vec: &'a mut Vec<(K, V)>,
-    idx: usize,
-}
+pub struct OccupiedEntry<'a, K, V>(indexmap::map::OccupiedEntry<'a, K, V>);
 
 impl<'a, K, V> OccupiedEntry<'a, K, V> {
     pub fn key(&self) -> &K {
-        &self.vec[self.idx].0
+        self.0.key()
     }
 
     pub fn get(&self) -> &V {
-        &self.vec[self.idx].1
+        self.0.get()
     }
 
     pub fn get_mut(&mut self) -> &mut V {
-        &mut self.vec[self.idx].1
+        self.0.get_mut()
+    }
+
+    pub fn into_mut(self) -> &'a mut V {
+        self.0.into_mut()
     }
 
     pub fn insert(&mut self, value: V) -> V {
-        std::mem::replace(self.get_mut(), value)
+        self.0.insert(value)
     }
 
     pub fn remove(self) -> V {
-        self.remove_entry().1
+        self.0.shift_remove()
     }
 
     pub fn remove_entry(self) -> (K, V) {
-        self.vec.remove(self.idx)
+        self.0.shift_remove_entry()
     }
 }
 
 #[derive(Debug)]
-pub struct VacantEntry<'a, K, V> {
-    key: K,
-    vec: &'a mut Vec<(K, V)>,
-}
+pub struct VacantEntry<'a, K, V>(indexmap::map::VacantEntry<'a, K, V>);
 
 impl<'a, K, V> VacantEntry<'a, K, V> {
     pub fn key(&self) -> &K {
-        &self.key
+        self.0.key()
     }
 
     pub fn into_key(self) -> K {
-        self.key
+        self.0.into_key()
     }
 
     pub fn insert(self, value: V) -> &'a mut V {
-        self.vec.push((self.key, value));
-        &mut self.vec.last_mut().unwrap().1
+        self.0.insert(value)
     }
 }
 
@@ -212,7 +295,7 @@ where
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_str("{")?;
         let mut add_sep = false;
-        for (key, value) in &self.0 {
+        for (key, value) in self {
             if add_sep {
                 f.write_str(", ")?;
             }
@@ -224,8 +307,8 @@ where
 }
 
 impl<'a, K, V> std::iter::IntoIterator for &'a Map<K, V> {
-    type Item = &'a (K, V);
-    type IntoIter = std::slice::Iter<'a, (K, V)>;
+    type Item = (&'a K, &'a V);
+    type IntoIter = indexmap::map::Iter<'a, K, V>;
 
     fn into_iter(self) -> Self::IntoIter {
         self.0.iter()
@@ -233,8 +316,8 @@ impl<'a, K, V> std::iter::IntoIterator for &'a Map<K, V> {
 }
 
 impl<'a, K, V> std::iter::IntoIterator for &'a mut Map<K, V> {
-    type Item = &'a mut (K, V);
-    type IntoIter = std::slice::IterMut<'a, (K, V)>;
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = indexmap::map::IterMut<'a, K, V>;
 
     fn into_iter(self) -> Self::IntoIter {
         self.0.iter_mut()
@@ -243,7 +326,7 @@ impl<'a, K, V> std::iter::IntoIterator for &'a mut Map<K, V> {
 
 impl<K, V> std::iter::FromIterator<(K, V)> for Map<K, V>
 where
-    K: PartialEq,
+    K: Hash + Eq,
 {
     fn from_iter<I>(iter: I) -> Self
     where
@@ -259,7 +342,7 @@ where
 
 impl<K, V> std::iter::Extend<(K, V)> for Map<K, V>
 where
-    K: PartialEq,
+    K: Hash + Eq,
 {
     fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
         for (key, value) in iter {
@@ -278,8 +361,8 @@ where
         S: serde::Serializer,
     {
         use serde::ser::SerializeMap;
-        let mut serializer = serializer.serialize_map(Some(self.0.len()))?;
-        for (key, value) in &self.0 {
+        let mut serializer = serializer.serialize_map(Some(self.len()))?;
+        for (key, value) in self {
             serializer.serialize_entry(key, value)?;
         }
         serializer.end()
@@ -288,7 +371,7 @@ where
 
 impl<'de, K, V> serde::Deserialize<'de> for Map<K, V>
 where
-    K: serde::Deserialize<'de> + PartialEq,
+    K: serde::Deserialize<'de> + Hash + Eq,
     V: serde::Deserialize<'de>,
 {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
@@ -303,7 +386,7 @@ where
         }
         impl<'de, K, V> serde::de::Visitor<'de> for Visitor<K, V>
         where
-            K: serde::Deserialize<'de> + PartialEq,
+            K: serde::Deserialize<'de> + Hash + Eq,
             V: serde::Deserialize<'de>,
         {
             type Value = Map<K, V>;
@@ -316,13 +399,13 @@ where
                 A: serde::de::MapAccess<'de>,
             {
                 let mut values = match map.size_hint() {
-                    Some(size) => Vec::with_capacity(size),
-                    None => Vec::new(),
+                    Some(size) => Map::with_capacity(size),
+                    None => Map::new(),
                 };
                 while let Some((key, value)) = map.next_entry()? {
-                    values.push((key, value))
+                    values.insert(key, value);
                 }
-                Ok(Map::from_iter(values))
+                Ok(values)
             }
         }
         deserializer.deserialize_map(Visitor::new())
@@ -343,10 +426,56 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_map_insert_preserves_order() {
+        let mut map = Map::new();
+        assert_eq!(map.insert("b", 1), None);
+        assert_eq!(map.insert("a", 2), None);
+        assert_eq!(map.insert("c", 3), None);
+        assert_eq!(map.insert("b", 4), Some(1));
+        assert_eq!(map.keys().copied().collect::<Vec<_>>(), ["b", "a", "c"]);
+        assert_eq!(map.get("b"), Some(&4));
+        assert_eq!(map.get("d"), None);
+    }
+
+    #[test]
+    fn test_map_remove_preserves_order() {
+        let mut map = Map::from_iter([(1, 'a'), (2, 'b'), (3, 'c'), (4, 'd')]);
+        assert_eq!(map.remove(&2), Some('b'));
+        assert_eq!(map.remove(&2), None);
+        assert_eq!(map.remove_entry(&4), Some((4, 'd')));
+        assert_eq!(map, Map::from_iter([(1, 'a'), (3, 'c')]));
+    }
+
+    #[test]
+    fn test_map_entry() {
+        let mut map = Map::from_iter([(String::from("un"), 1)]);
+        match map.entry("un".to_owned()) {
+            Entry::Occupied(mut entry) => assert_eq!(entry.insert(11), 1),
+            Entry::Vacant(_) => panic!("expected an occupied entry"),
+        }
+        match map.entry("deux".to_owned()) {
+            Entry::Occupied(_) => panic!("expected a vacant entry"),
+            Entry::Vacant(entry) => *entry.insert(2) += 20,
+        }
+        assert_eq!(
+            map,
+            Map::from_iter([(String::from("un"), 11), (String::from("deux"), 22)])
+        );
+    }
+
+    #[test]
+    fn test_map_eq_depends_on_order() {
+        assert_ne!(
+            Map::from_iter([(1, 'a'), (2, 'b')]),
+            Map::from_iter([(2, 'b'), (1, 'a')])
+        );
+    }
+
     #[test]
     fn test_map_ser_de() {
         assert_tokens(
-            &Map(vec![
+            &Map::from_iter([
                 (Value::from(32i16), Value::from("trente deux")),
                 (Value::from(34i16), Value::from("trente quatre")),
             ]),
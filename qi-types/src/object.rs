@@ -1,6 +1,6 @@
 use crate::{struct_ty, ty, Map, Signature, Type};
 
-#[derive(Clone, Default, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Clone, Default, PartialEq, Eq, Hash, Debug, serde::Serialize, serde::Deserialize)]
 pub struct Object {
     pub meta_object: MetaObject,
     pub service_id: ServiceId,
@@ -166,7 +166,7 @@ impl<'de> serde::Deserialize<'de> for ObjectUid {
     }
 }
 
-#[derive(Clone, Default, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Clone, Default, PartialEq, Eq, Hash, Debug, serde::Serialize, serde::Deserialize)]
 pub struct MetaObject {
     pub methods: Map<ActionId, MetaMethod>,
     pub signals: Map<ActionId, MetaSignal>,
@@ -211,6 +211,10 @@ impl MetaObjectBuilder {
         }
     }
 
+    /// Adds a method to the meta object.
+    ///
+    /// The signatures are normalized as other implementations expect them: a method without
+    /// parameters takes "()", and a method that returns nothing returns "v".
     pub fn add_method(
         &mut self,
         uid: ActionId,
@@ -222,9 +226,9 @@ impl MetaObjectBuilder {
             uid,
             MetaMethod {
                 uid,
-                return_signature: return_signature.into(),
+                return_signature: return_signature.into().into_return_signature(),
                 name: name.into(),
-                parameters_signature: parameters_signature.into(),
+                parameters_signature: parameters_signature.into().into_parameters_signature(),
                 description: String::new(),
                 parameters: Vec::new(),
                 return_description: String::new(),
@@ -17,6 +17,44 @@ impl Signature {
     pub fn into_type(self) -> Option<Type> {
         self.0
     }
+
+    /// Normalizes the signature of the parameters of a method, see
+    /// [`Type::into_parameters_type`].
+    pub fn into_parameters_signature(self) -> Self {
+        Self(self.0.map(Type::into_parameters_type))
+    }
+
+    /// Normalizes the signature of the return value of a method, see [`Type::into_return_type`].
+    pub fn into_return_signature(self) -> Self {
+        Self(self.0.map(Type::into_return_type))
+    }
+
+    /// The maximum number of nested types of the signatures parsed with [`str::parse`].
+    pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+    /// Parses a signature whose types are nested at most `max_depth` times.
+    ///
+    /// Signatures are received from remote peers, the depth of their types is limited so that
+    /// their parsing does not exhaust the stack.
+    ///
+    /// ```
+    /// use qi_types::{signature::SignatureParseError, Signature};
+    ///
+    /// assert!(Signature::parse_with_max_depth("[[i]]", 2).is_ok());
+    /// assert!(matches!(
+    ///     Signature::parse_with_max_depth("[[i]]", 1),
+    ///     Err(err) if matches!(err.0, SignatureParseError::ListValueTypeParsing(_))
+    /// ));
+    /// ```
+    pub fn parse_with_max_depth(src: &str, max_depth: usize) -> Result<Self, FromStrError> {
+        let mut iter = src.chars();
+        let t = parse_type(&mut iter, Nesting::new(max_depth))?;
+        let rest = iter.as_str();
+        if !rest.is_empty() {
+            return Err(SignatureParseError::TrailingInput(rest.to_owned()).into());
+        }
+        Ok(Self(t))
+    }
 }
 
 impl From<Type> for Signature {
@@ -35,9 +73,7 @@ impl std::str::FromStr for Signature {
     type Err = FromStrError;
 
     fn from_str(src: &str) -> Result<Self, Self::Err> {
-        let mut iter = src.chars();
-        let t = parse_type(&mut iter)?;
-        Ok(Self(t))
+        Self::parse_with_max_depth(src, Self::DEFAULT_MAX_DEPTH)
     }
 }
 
@@ -57,6 +93,8 @@ const CHAR_STRING: char = 's';
 const CHAR_RAW: char = 'r';
 const CHAR_OBJECT: char = 'o';
 const CHAR_DYNAMIC: char = 'm';
+const CHAR_UNKNOWN: char = 'X';
+const CHAR_MARK_POINTER: char = '*';
 const CHAR_MARK_OPTION: char = '+';
 const CHAR_LIST_BEGIN: char = '[';
 const CHAR_LIST_END: char = ']';
@@ -130,34 +168,66 @@ fn write_type(t: Option<&Type>, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Re
     }
 }
 
-fn advance_once<I>(mut iter: I)
+/// Advances the iterator past an element that was peeked by the caller.
+fn advance_once<I>(mut iter: I) -> Result<(), SignatureParseError>
 where
     I: Iterator,
 {
-    if iter.next().is_none() {
-        unreachable!(
-            "the precondition over the presence of an element in the iterator is not verified"
-        )
+    iter.next()
+        .map(|_elem| ())
+        .ok_or(SignatureParseError::EndOfInput)
+}
+
+/// The depth of the type being parsed, in number of enclosing types.
+#[derive(Debug, Clone, Copy)]
+struct Nesting {
+    depth: usize,
+    max_depth: usize,
+}
+
+impl Nesting {
+    fn new(max_depth: usize) -> Self {
+        Self {
+            depth: 0,
+            max_depth,
+        }
+    }
+
+    /// Returns the nesting of the types contained in a type starting at the given input.
+    fn enter(self, type_str: &str) -> Result<Self, SignatureParseError> {
+        if self.depth >= self.max_depth {
+            return Err(SignatureParseError::DepthLimitExceeded(
+                self.max_depth,
+                type_str.to_owned(),
+            ));
+        }
+        Ok(Self {
+            depth: self.depth + 1,
+            ..self
+        })
     }
 }
 
-fn parse_type(iter: &mut std::str::Chars) -> Result<Option<Type>, SignatureParseError> {
+fn parse_type(
+    iter: &mut std::str::Chars,
+    nesting: Nesting,
+) -> Result<Option<Type>, SignatureParseError> {
     let type_str = iter.as_str();
     // Multiple characters types are read from the beginning. Therefore we clone the iterator,
     // read one char, and if we detect any marker of those types, pass the original iterator to
     // the subparsing function and return its result immediately.
     let c = iter.clone().next().ok_or(SignatureParseError::EndOfInput)?;
     match c {
-        CHAR_MARK_OPTION => return Ok(Some(parse_option(iter)?)),
-        CHAR_MARK_VAR_ARGS => return Ok(Some(parse_var_args(iter)?)),
-        CHAR_LIST_BEGIN => return Ok(Some(parse_list(iter)?)),
-        CHAR_MAP_BEGIN => return Ok(Some(parse_map(iter)?)),
-        CHAR_TUPLE_BEGIN => return Ok(Some(parse_tuple(iter)?)),
+        CHAR_MARK_OPTION => return Ok(Some(parse_option(iter, nesting.enter(type_str)?)?)),
+        CHAR_MARK_VAR_ARGS => return Ok(Some(parse_var_args(iter, nesting.enter(type_str)?)?)),
+        CHAR_LIST_BEGIN => return Ok(Some(parse_list(iter, nesting.enter(type_str)?)?)),
+        CHAR_MAP_BEGIN => return Ok(Some(parse_map(iter, nesting.enter(type_str)?)?)),
+        CHAR_TUPLE_BEGIN => return Ok(Some(parse_tuple(iter, nesting.enter(type_str)?)?)),
         _ => (),
     };
     // Now all that's left are simple character types, which we already have the value of.
     // Therefore we can advance the iterator by one.
-    advance_once(iter.by_ref());
+    advance_once(iter.by_ref())?;
     let t = match c {
         CHAR_VOID => Some(Type::Unit),
         CHAR_BOOL => Some(Type::Bool),
@@ -175,16 +245,24 @@ fn parse_type(iter: &mut std::str::Chars) -> Result<Option<Type>, SignatureParse
         CHAR_RAW => Some(Type::Raw),
         CHAR_OBJECT => Some(Type::Object),
         CHAR_DYNAMIC => None,
+        // Types that are unknown to the type system of the remote, such as the ones of methods
+        // that take or return unregistered types. Their values can be of any type, as with
+        // dynamics.
+        CHAR_UNKNOWN => None,
+        // Pointers cannot be transmitted, their values are never serialized.
+        CHAR_MARK_POINTER => {
+            return Err(SignatureParseError::UnsupportedPointer(type_str.to_owned()))
+        }
         // Anything else is unexpected.
         c => return Err(SignatureParseError::UnexpectedChar(c, type_str.to_owned())),
     };
     Ok(t)
 }
 
-fn parse_option(iter: &mut std::str::Chars) -> Result<Type, SignatureParseError> {
+fn parse_option(iter: &mut std::str::Chars, nesting: Nesting) -> Result<Type, SignatureParseError> {
     let option_str = iter.as_str();
-    advance_once(iter.by_ref());
-    let value_type = match parse_type(iter) {
+    advance_once(iter.by_ref())?;
+    let value_type = match parse_type(iter, nesting) {
         Ok(t) => t,
         Err(err) => {
             return Err(match err {
@@ -198,10 +276,13 @@ fn parse_option(iter: &mut std::str::Chars) -> Result<Type, SignatureParseError>
     Ok(Type::Option(value_type.map(Box::new)))
 }
 
-fn parse_var_args(iter: &mut std::str::Chars) -> Result<Type, SignatureParseError> {
+fn parse_var_args(
+    iter: &mut std::str::Chars,
+    nesting: Nesting,
+) -> Result<Type, SignatureParseError> {
     let var_args_str = iter.as_str();
-    advance_once(iter.by_ref());
-    let value_type = match parse_type(iter) {
+    advance_once(iter.by_ref())?;
+    let value_type = match parse_type(iter, nesting) {
         Ok(t) => t,
         Err(err) => {
             return Err(match err {
@@ -215,10 +296,10 @@ fn parse_var_args(iter: &mut std::str::Chars) -> Result<Type, SignatureParseErro
     Ok(Type::VarArgs(value_type.map(Box::new)))
 }
 
-fn parse_list(iter: &mut std::str::Chars) -> Result<Type, SignatureParseError> {
+fn parse_list(iter: &mut std::str::Chars, nesting: Nesting) -> Result<Type, SignatureParseError> {
     let list_str = iter.as_str();
-    advance_once(iter.by_ref());
-    let value_type = match parse_type(iter) {
+    advance_once(iter.by_ref())?;
+    let value_type = match parse_type(iter, nesting) {
         Ok(t) => t,
         Err(err) => {
             return Err(match err {
@@ -233,14 +314,14 @@ fn parse_list(iter: &mut std::str::Chars) -> Result<Type, SignatureParseError> {
     if iter.clone().next() != Some(CHAR_LIST_END) {
         return Err(SignatureParseError::MissingListEnd(list_str.to_owned()));
     }
-    advance_once(iter);
+    advance_once(iter)?;
     Ok(Type::List(value_type.map(Box::new)))
 }
 
-fn parse_map(iter: &mut std::str::Chars) -> Result<Type, SignatureParseError> {
+fn parse_map(iter: &mut std::str::Chars, nesting: Nesting) -> Result<Type, SignatureParseError> {
     let map_str = iter.as_str();
-    advance_once(iter.by_ref());
-    let key_type = match parse_type(iter) {
+    advance_once(iter.by_ref())?;
+    let key_type = match parse_type(iter, nesting) {
         Ok(t) => t,
         Err(err) => {
             return Err(match err {
@@ -252,7 +333,7 @@ fn parse_map(iter: &mut std::str::Chars) -> Result<Type, SignatureParseError> {
             })
         }
     };
-    let value_type = match parse_type(iter) {
+    let value_type = match parse_type(iter, nesting) {
         Ok(t) => t,
         Err(err) => {
             return Err(match err {
@@ -266,19 +347,19 @@ fn parse_map(iter: &mut std::str::Chars) -> Result<Type, SignatureParseError> {
     if iter.clone().next() != Some(CHAR_MAP_END) {
         return Err(SignatureParseError::MissingMapEnd(map_str.to_owned()));
     }
-    advance_once(iter.by_ref());
+    advance_once(iter.by_ref())?;
     Ok(Type::Map {
         key: key_type.map(Box::new),
         value: value_type.map(Box::new),
     })
 }
 
-fn parse_tuple(iter: &mut std::str::Chars) -> Result<Type, SignatureParseError> {
+fn parse_tuple(iter: &mut std::str::Chars, nesting: Nesting) -> Result<Type, SignatureParseError> {
     let tuple_str = iter.as_str();
-    advance_once(iter.by_ref());
+    advance_once(iter.by_ref())?;
     let mut elements = Vec::new();
     let elements = loop {
-        match parse_type(iter) {
+        match parse_type(iter, nesting) {
             Ok(element) => elements.push(element),
             Err(err) => match err {
                 SignatureParseError::UnexpectedChar(CHAR_TUPLE_END, _) => break elements,
@@ -328,7 +409,7 @@ fn parse_tuple(iter: &mut std::str::Chars) -> Result<Type, SignatureParseError>
 fn parse_tuple_annotations(
     iter: &mut std::str::Chars,
 ) -> Result<Option<StructAnnotations>, AnnotationsError> {
-    advance_once(iter.by_ref());
+    advance_once(iter.by_ref()).map_err(|_err| AnnotationsError::MissingTupleAnnotationEnd)?;
     enum Accumulator {
         Name(Option<String>),
         Field {
@@ -424,6 +505,15 @@ pub enum SignatureParseError {
     #[error("unexpected character \'{0}\' in input \"{1}\"")]
     UnexpectedChar(char, String),
 
+    #[error("unexpected input \"{0}\" after the end of the signature")]
+    TrailingInput(String),
+
+    #[error("pointer type starting at input \"{0}\" is not supported")]
+    UnsupportedPointer(String),
+
+    #[error("type starting at input \"{1}\" exceeds the maximum depth of {0} nested types")]
+    DepthLimitExceeded(usize, String),
+
     #[error("value type of option starting at input \"{0}\" is missing")]
     MissingOptionValueType(String),
 
@@ -814,6 +904,94 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_signature_from_str_edge_cases() {
+        use pretty_assertions::assert_eq;
+        // Structures nested in a structure may have a name without field names.
+        assert_eq!(
+            "((ff)<Position2D>(ff)<Velocity2D>d)<Odometry,position,velocity,time>"
+                .parse::<Signature>()
+                .map(Signature::into_type),
+            Ok(Some(struct_ty! {
+                Odometry {
+                    position: struct_ty! { Position2D(Type::Float32, Type::Float32) },
+                    velocity: struct_ty! { Velocity2D(Type::Float32, Type::Float32) },
+                    time: Type::Float64,
+                }
+            }))
+        );
+        // Trailing commas after the structure name or the last field name are ignored, including
+        // in structures that are elements of lists.
+        assert_eq!(
+            "[(dd)<Point,x,y,>]".parse::<Signature>(),
+            "[(dd)<Point,x,y>]".parse::<Signature>()
+        );
+        assert_eq!(
+            "(s[(dd)<Point,>])<Path,name,points,>".parse::<Signature>(),
+            "(s[(dd)<Point>])<Path,name,points>".parse::<Signature>()
+        );
+        // Types unknown to the remote are read as dynamics.
+        assert_eq!(
+            "(sX)".parse::<Signature>().map(Signature::into_type),
+            Ok(Some(tuple_ty!(Type::String, None)))
+        );
+        assert_eq!(
+            "{sX}".parse::<Signature>().map(|s| s.to_string()),
+            Ok("{sm}".to_owned())
+        );
+        // Pointers are not supported.
+        assert_eq!(
+            "(s*o)".parse::<Signature>(),
+            Err(FromStrError(SignatureParseError::TupleElementTypeParsing(
+                Box::new(SignatureParseError::UnsupportedPointer("*o)".to_owned()))
+            )))
+        );
+        // The whole input must be a single type.
+        assert_eq!(
+            "(i)<A><B>".parse::<Signature>(),
+            Err(FromStrError(SignatureParseError::TrailingInput(
+                "<B>".to_owned()
+            )))
+        );
+        assert_eq!(
+            "ii".parse::<Signature>(),
+            Err(FromStrError(SignatureParseError::TrailingInput(
+                "i".to_owned()
+            )))
+        );
+    }
+
+    #[test]
+    fn test_signature_parse_with_max_depth() {
+        use pretty_assertions::assert_eq;
+        assert_eq!(
+            Signature::parse_with_max_depth("+{i[(s)]}", 4).map(|s| s.to_string()),
+            Ok("+{i[(s)]}".to_owned())
+        );
+        assert_eq!(
+            Signature::parse_with_max_depth("i", 0),
+            Ok(Signature::from(Type::Int32))
+        );
+        assert_eq!(
+            Signature::parse_with_max_depth("{i[(s)]}", 2),
+            Err(FromStrError(SignatureParseError::MapValueTypeParsing(
+                Box::new(SignatureParseError::ListValueTypeParsing(Box::new(
+                    SignatureParseError::DepthLimitExceeded(2, "(s)]}".to_owned())
+                )))
+            )))
+        );
+
+        // Deeply nested signatures are refused by default instead of exhausting the stack.
+        let depth = Signature::DEFAULT_MAX_DEPTH + 1;
+        let input = format!("{}i{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(input.parse::<Signature>().is_err());
+        let depth = Signature::DEFAULT_MAX_DEPTH;
+        let input = format!("{}i{}", "[".repeat(depth), "]".repeat(depth));
+        assert_eq!(input.parse::<Signature>().map(|s| s.to_string()), Ok(input));
+        let input = "(".repeat(100_000);
+        assert!(input.parse::<Signature>().is_err());
+    }
+
     #[test]
     fn test_signature_from_str_meta_object() {
         use pretty_assertions::assert_eq;
@@ -827,6 +1005,43 @@ mod tests {
         assert_eq!(sig, Signature(Some(object::MetaObject::static_type())));
     }
 
+    #[test]
+    fn test_signature_unit_and_empty_tuple() {
+        use ty::DynamicGetType;
+
+        let unit: Signature = "v".parse().unwrap();
+        let empty_tuple: Signature = "()".parse().unwrap();
+        assert_eq!(unit.clone().into_type(), Some(Type::Unit));
+        assert_eq!(empty_tuple.clone().into_type(), Some(tuple_ty!()));
+        for signature in [&unit, &empty_tuple] {
+            assert!(signature.clone().into_type().unwrap().is_unit_like());
+            assert_eq!(
+                signature.clone().into_parameters_signature().to_string(),
+                "()"
+            );
+            assert_eq!(signature.clone().into_return_signature().to_string(), "v");
+        }
+        assert!(!Type::Int32.is_unit_like());
+
+        // Values of either type are accepted where the other is expected.
+        let empty_tuple_value = crate::Value::Tuple(crate::Tuple::new());
+        assert!(crate::Value::Unit.has_type(empty_tuple.into_type().as_ref()));
+        assert!(empty_tuple_value.has_type(unit.into_type().as_ref()));
+
+        // Empty structures are still checked against other structures.
+        let empty_struct = struct_ty!(Empty {});
+        assert_eq!(empty_struct.clone().into_return_type(), empty_struct);
+        assert!(empty_tuple_value.has_type(Some(&empty_struct)));
+
+        let mut builder = object::MetaObjectBuilder::new();
+        let uid = object::ActionId::new(100);
+        builder.add_method(uid, "ping", Type::Unit, tuple_ty!());
+        let meta_object = builder.build();
+        let method = meta_object.methods.get(&uid).unwrap();
+        assert_eq!(method.parameters_signature.to_string(), "()");
+        assert_eq!(method.return_signature.to_string(), "v");
+    }
+
     #[test]
     fn test_signature_ser_de() {
         use serde_test::{assert_tokens, Token};
@@ -3,7 +3,7 @@ use derive_more::{AsRef, From, Index, Into, IntoIterator};
 
 /// [`Tuple`] represents a `tuple` value in the `qi` type system.
 #[derive(
-    Default, Clone, PartialEq, Eq, PartialOrd, From, Into, Index, IntoIterator, AsRef, Debug,
+    Default, Clone, PartialEq, Eq, PartialOrd, Hash, From, Into, Index, IntoIterator, AsRef, Debug,
 )]
 #[into_iterator(owned, ref)]
 pub struct Tuple(Vec<Value>);
@@ -36,6 +36,10 @@ impl Tuple {
     pub fn elements(&self) -> &Vec<Value> {
         &self.0
     }
+
+    pub fn elements_mut(&mut self) -> &mut [Value] {
+        &mut self.0
+    }
 }
 
 impl std::fmt::Display for Tuple {
@@ -1,4 +1,6 @@
 mod impls;
+#[cfg(feature = "json-schema")]
+mod json_schema;
 
 /// The type of a value in the `qi` type system.
 ///
@@ -31,8 +33,44 @@ pub enum Type {
 }
 
 impl Type {
+    /// Returns true for the unit type and the empty tuple types, which have no value, and are both
+    /// represented by no data at all.
+    ///
+    /// Their signatures are respectively "v" and "()". Each one is a subtype of the other, values
+    /// of one type are accepted where the other one is expected.
+    pub fn is_unit_like(&self) -> bool {
+        match self {
+            Type::Unit => true,
+            Type::Tuple(tuple) => tuple.is_empty(),
+            _ => false,
+        }
+    }
+
+    /// Normalizes the type of the parameters of a method.
+    ///
+    /// Methods take their parameters as a tuple, a method without parameters takes the empty
+    /// tuple "()", and not the unit type "v" which some services are known to reject.
+    pub fn into_parameters_type(self) -> Type {
+        match self {
+            Type::Unit => Type::Tuple(TupleType::new()),
+            t => t,
+        }
+    }
+
+    /// Normalizes the type of the return value of a method.
+    ///
+    /// A method that returns nothing returns the unit type "v", and not the empty tuple "()".
+    /// Empty structures keep their annotations, as they are a type of their own.
+    pub fn into_return_type(self) -> Type {
+        match self {
+            Type::Tuple(TupleType::Tuple(elements)) if elements.is_empty() => Type::Unit,
+            t => t,
+        }
+    }
+
     pub(crate) fn is_subtype_of(&self, target: &Type) -> bool {
         match (self, target) {
+            (Type::Unit, Type::Tuple(tuple)) | (Type::Tuple(tuple), Type::Unit) => tuple.is_empty(),
             (Type::Option(source), Type::Option(target)) => {
                 is_subtype_of(source.as_deref(), target.as_deref())
             }
@@ -315,6 +353,67 @@ impl std::fmt::Display for StructAnnotations {
     }
 }
 
+impl StructAnnotations {
+    /// Checks the annotations of a tuple value against these annotations of a structure, before
+    /// the value is converted into the structure.
+    ///
+    /// Lenient checks accept any annotations. Strict checks require the value to be annotated with
+    /// the name of the structure and, if the structure has named fields, with the same field names
+    /// in the same order.
+    pub fn check(
+        &self,
+        actual: Option<&StructAnnotations>,
+        check: StructCheck,
+    ) -> Result<(), StructAnnotationsError> {
+        if check == StructCheck::Lenient {
+            return Ok(());
+        }
+        let actual = actual.ok_or_else(|| StructAnnotationsError::Missing(self.name.clone()))?;
+        if actual.name != self.name {
+            return Err(StructAnnotationsError::NameMismatch {
+                expected: self.name.clone(),
+                actual: actual.name.clone(),
+            });
+        }
+        match &self.field_names {
+            Some(field_names) if actual.field_names.as_ref() != Some(field_names) => {
+                Err(StructAnnotationsError::FieldNamesMismatch {
+                    name: self.name.clone(),
+                    expected: field_names.join(","),
+                    actual: actual.field_names.as_deref().unwrap_or_default().join(","),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// How the annotations of a tuple value are checked when it is converted into a structure.
+#[derive(Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum StructCheck {
+    /// The annotations are ignored, only the elements of the tuple matter.
+    #[default]
+    Lenient,
+    /// The name of the structure and the names of its fields must match the annotations.
+    Strict,
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, thiserror::Error)]
+pub enum StructAnnotationsError {
+    #[error("the tuple has no annotations, expected those of structure \"{0}\"")]
+    Missing(String),
+
+    #[error("the tuple is annotated as structure \"{actual}\", expected \"{expected}\"")]
+    NameMismatch { expected: String, actual: String },
+
+    #[error("the tuple is annotated with fields <{actual}> of structure \"{name}\", expected <{expected}>")]
+    FieldNamesMismatch {
+        name: String,
+        expected: String,
+        actual: String,
+    },
+}
+
 pub fn option_of<T>(t: T) -> Type
 where
     T: Into<Option<Type>>,
@@ -0,0 +1,204 @@
+use super::{TupleType, Type};
+use serde_json::{json, Map, Value};
+
+const DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+impl Type {
+    /// Returns a JSON Schema document that validates the JSON representation of the values of this
+    /// type, as produced by `serde_json`.
+    ///
+    /// The schema of a type that implements [`StaticGetType`](super::StaticGetType) is obtained
+    /// from its static type. Elements that have no type information (the `Dynamic` type) accept
+    /// any value.
+    ///
+    /// ```
+    /// use qi_types::ty::{list_of, Type};
+    ///
+    /// let schema = list_of(Type::Int8).to_json_schema();
+    /// assert_eq!(schema["type"], "array");
+    /// assert_eq!(schema["items"]["minimum"], -128);
+    /// ```
+    pub fn to_json_schema(&self) -> Value {
+        let mut schema = schema_of(Some(self));
+        if let Value::Object(schema) = &mut schema {
+            schema.insert("$schema".to_owned(), Value::from(DIALECT));
+        }
+        schema
+    }
+}
+
+fn schema_of(t: Option<&Type>) -> Value {
+    let t = match t {
+        Some(t) => t,
+        None => return json!({}),
+    };
+    match t {
+        Type::Unit => json!({ "type": "null" }),
+        Type::Bool => json!({ "type": "boolean" }),
+        Type::Int8 => integer(i8::MIN.into(), i8::MAX.into()),
+        Type::UInt8 => integer(u8::MIN.into(), u8::MAX.into()),
+        Type::Int16 => integer(i16::MIN.into(), i16::MAX.into()),
+        Type::UInt16 => integer(u16::MIN.into(), u16::MAX.into()),
+        Type::Int32 => integer(i32::MIN.into(), i32::MAX.into()),
+        Type::UInt32 => integer(u32::MIN.into(), u32::MAX.into()),
+        Type::Int64 => integer(i64::MIN.into(), i64::MAX.into()),
+        Type::UInt64 => integer(u64::MIN.into(), u64::MAX.into()),
+        Type::Float32 | Type::Float64 => json!({ "type": "number" }),
+        Type::String => json!({ "type": "string" }),
+        // Raw buffers are represented as arrays of bytes.
+        Type::Raw => json!({
+            "type": "array",
+            "items": integer(u8::MIN.into(), u8::MAX.into()),
+        }),
+        Type::Object => json!({ "type": "object", "title": "object" }),
+        Type::Option(t) => json!({
+            "anyOf": [{ "type": "null" }, schema_of(t.as_deref())],
+        }),
+        Type::List(t) | Type::VarArgs(t) => json!({
+            "type": "array",
+            "items": schema_of(t.as_deref()),
+        }),
+        Type::Map { key, value } => map_schema(key.as_deref(), value.as_deref()),
+        Type::Tuple(tuple) => tuple_schema(tuple),
+    }
+}
+
+fn integer(minimum: Value, maximum: Value) -> Value {
+    json!({ "type": "integer", "minimum": minimum, "maximum": maximum })
+}
+
+/// Maps are represented as JSON objects, whose keys are the string representation of the keys of
+/// the map.
+fn map_schema(key: Option<&Type>, value: Option<&Type>) -> Value {
+    let mut schema = json!({
+        "type": "object",
+        "additionalProperties": schema_of(value),
+    });
+    let key_pattern = match key {
+        Some(Type::Int8 | Type::Int16 | Type::Int32 | Type::Int64) => Some("^-?[0-9]+$"),
+        Some(Type::UInt8 | Type::UInt16 | Type::UInt32 | Type::UInt64) => Some("^[0-9]+$"),
+        _ => None,
+    };
+    if let Some(pattern) = key_pattern {
+        schema["propertyNames"] = json!({ "pattern": pattern });
+    }
+    schema
+}
+
+/// Structures are represented as JSON objects, tuples and tuple structures as JSON arrays.
+fn tuple_schema(tuple: &TupleType) -> Value {
+    let mut schema = match tuple {
+        TupleType::Tuple(elements) | TupleType::TupleStruct(_, elements) => json!({
+            "type": "array",
+            "prefixItems": elements.iter().map(|t| schema_of(t.as_ref())).collect::<Vec<_>>(),
+            "minItems": elements.len(),
+            "maxItems": elements.len(),
+        }),
+        TupleType::Struct(_, fields) => {
+            let properties: Map<_, _> = fields
+                .iter()
+                .map(|field| (field.name.clone(), schema_of(field.value_type.as_ref())))
+                .collect();
+            let required: Vec<_> = fields.iter().map(|field| field.name.clone()).collect();
+            json!({
+                "type": "object",
+                "properties": properties,
+                "required": required,
+                "additionalProperties": false,
+            })
+        }
+    };
+    if let Some(name) = tuple.name() {
+        schema["title"] = Value::from(name);
+    }
+    schema
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        struct_ty,
+        ty::{map_of, option_of, StaticGetType},
+    };
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_type_to_json_schema_primitive() {
+        assert_eq!(
+            u16::static_type().to_json_schema(),
+            json!({
+                "$schema": DIALECT,
+                "type": "integer",
+                "minimum": 0,
+                "maximum": 65535,
+            })
+        );
+        assert_eq!(
+            option_of(Type::String).to_json_schema(),
+            json!({
+                "$schema": DIALECT,
+                "anyOf": [{ "type": "null" }, { "type": "string" }],
+            })
+        );
+        assert_eq!(
+            map_of(Type::Int32, None).to_json_schema(),
+            json!({
+                "$schema": DIALECT,
+                "type": "object",
+                "additionalProperties": {},
+                "propertyNames": { "pattern": "^-?[0-9]+$" },
+            })
+        );
+    }
+
+    #[test]
+    fn test_type_to_json_schema_struct() {
+        let t = struct_ty!(Point {
+            x: Type::Float32,
+            y: Type::Float32
+        });
+        assert_eq!(
+            t.to_json_schema(),
+            json!({
+                "$schema": DIALECT,
+                "title": "Point",
+                "type": "object",
+                "properties": {
+                    "x": { "type": "number" },
+                    "y": { "type": "number" },
+                },
+                "required": ["x", "y"],
+                "additionalProperties": false,
+            })
+        );
+
+        let t = struct_ty!(Pair(Type::Bool, None));
+        assert_eq!(
+            t.to_json_schema(),
+            json!({
+                "$schema": DIALECT,
+                "title": "Pair",
+                "type": "array",
+                "prefixItems": [{ "type": "boolean" }, {}],
+                "minItems": 2,
+                "maxItems": 2,
+            })
+        );
+    }
+
+    #[test]
+    fn test_json_string_with_control_characters() {
+        use crate::Value;
+        let value = Value::List(vec![
+            Value::from("cookies\0muffins"),
+            Value::from("\u{1b}[0m"),
+        ]);
+        let json = serde_json::to_value(&value).unwrap();
+        assert_eq!(json, json!(["cookies\0muffins", "\u{1b}[0m"]));
+        assert_eq!(
+            serde_json::to_string(&json).unwrap(),
+            r#"["cookies\u0000muffins","\u001b[0m"]"#
+        );
+    }
+}
@@ -5,9 +5,12 @@ use crate::{
     Dynamic, FormatterExt, List, Map, Object, Raw,
 };
 
+mod redact;
+pub use redact::{ParseValuePathError, PathSegment, ValuePath};
+
 /// The [`Value`] structure represents any value of `qi` type system and
 /// is is an enumeration of every types of values.
-#[derive(Clone, PartialEq, Eq, Debug, derive_more::From, derive_more::TryInto)]
+#[derive(Clone, PartialEq, Eq, Hash, Debug, derive_more::From, derive_more::TryInto)]
 pub enum Value {
     #[from]
     Unit,
@@ -68,6 +71,13 @@ impl Value {
         }
     }
 
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
     pub fn as_raw(&self) -> Option<&Raw> {
         match self {
             Self::Raw(r) => Some(r),
@@ -229,6 +239,12 @@ impl From<&str> for Value {
     }
 }
 
+impl From<std::borrow::Cow<'_, str>> for Value {
+    fn from(s: std::borrow::Cow<'_, str>) -> Self {
+        Self::String(s.into_owned())
+    }
+}
+
 /// Converts an option into a value.
 ///
 /// # Example
@@ -274,7 +290,7 @@ impl std::fmt::Display for Value {
             Self::Unit => f.write_str("()"),
             Self::Bool(b) => b.fmt(f),
             Self::Number(n) => n.fmt(f),
-            Self::String(s) => s.fmt(f),
+            Self::String(s) => f.write_escaped_str(s),
             Self::Raw(r) => f.write_raw(r),
             Self::Option(o) => f.write_option(o),
             Self::List(l) => f.write_list(l),
@@ -516,6 +532,16 @@ mod tests {
         assert_eq!(Value::from(Number::Int32(321)).as_string(), None);
     }
 
+    #[test]
+    fn test_value_as_str() {
+        assert_eq!(Value::from("muffins").as_str(), Some("muffins"));
+        assert_eq!(
+            Value::from(std::borrow::Cow::Borrowed("cookies")).as_str(),
+            Some("cookies")
+        );
+        assert_eq!(Value::from(Number::Int32(321)).as_str(), None);
+    }
+
     #[test]
     fn test_value_as_tuple() {
         assert_eq!(
@@ -524,4 +550,13 @@ mod tests {
         );
         assert_eq!(Value::from(Number::Int32(42)).as_tuple(), None);
     }
+
+    #[test]
+    fn test_value_display_escapes_control_characters() {
+        let value = Value::from("cookies\0\u{1b}[31m\tmuffins\n");
+        assert_eq!(value.to_string(), r"cookies\u{0}\u{1b}[31m\tmuffins\n");
+        // The value itself is preserved.
+        assert_eq!(value.as_str(), Some("cookies\0\u{1b}[31m\tmuffins\n"));
+        assert_eq!(Value::from("é, ü").to_string(), "é, ü");
+    }
 }
@@ -0,0 +1,248 @@
+use super::Value;
+use crate::Dynamic;
+use std::str::FromStr;
+
+/// A path to parts of a value, for instance to redact them, see [`Value::redact`].
+///
+/// A path is a sequence of segments, each of which selects children of the value selected by the
+/// previous ones:
+/// - an index selects an element of a list or of a tuple, such as a field of a structure;
+/// - a key selects the value of an entry of a map whose key is this string;
+/// - the wildcard selects all the elements of a list or of a tuple, and all the values of a map.
+///
+/// Options and dynamic values are transparent: a path goes through them as if their value was in
+/// their place. The empty path selects the whole value.
+///
+/// Paths may be parsed from strings where segments are separated by dots, `*` being the
+/// wildcard, numbers being indices and other segments being keys: `0.credentials.*.token`.
+#[derive(Default, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct ValuePath(Vec<PathSegment>);
+
+impl ValuePath {
+    /// Returns the empty path, that selects the whole value.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_index(mut self, index: usize) -> Self {
+        self.0.push(PathSegment::Index(index));
+        self
+    }
+
+    pub fn with_key<K>(mut self, key: K) -> Self
+    where
+        K: Into<String>,
+    {
+        self.0.push(PathSegment::Key(key.into()));
+        self
+    }
+
+    pub fn with_any(mut self) -> Self {
+        self.0.push(PathSegment::Any);
+        self
+    }
+
+    pub fn segments(&self) -> &[PathSegment] {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for ValuePath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (index, segment) in self.0.iter().enumerate() {
+            if index > 0 {
+                f.write_str(".")?;
+            }
+            segment.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for ValuePath {
+    type Err = ParseValuePathError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Ok(Self::new());
+        }
+        s.split('.')
+            .map(|segment| match segment {
+                "" => Err(ParseValuePathError(s.to_owned())),
+                "*" => Ok(PathSegment::Any),
+                segment => Ok(match segment.parse() {
+                    Ok(index) => PathSegment::Index(index),
+                    Err(_err) => PathSegment::Key(segment.to_owned()),
+                }),
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+/// A segment of a [`ValuePath`].
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum PathSegment {
+    Index(usize),
+    Key(String),
+    Any,
+}
+
+impl PathSegment {
+    fn matches_index(&self, index: usize) -> bool {
+        match self {
+            Self::Index(value) => *value == index,
+            Self::Key(_) => false,
+            Self::Any => true,
+        }
+    }
+
+    fn matches_key(&self, key: &Value) -> bool {
+        match self {
+            Self::Index(_) => false,
+            Self::Key(value) => key.as_str() == Some(value.as_str()),
+            Self::Any => true,
+        }
+    }
+}
+
+impl std::fmt::Display for PathSegment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Index(index) => index.fmt(f),
+            Self::Key(key) => f.write_str(key),
+            Self::Any => f.write_str("*"),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug, thiserror::Error)]
+#[error("the value path \"{0}\" has an empty segment")]
+pub struct ParseValuePathError(String);
+
+impl Value {
+    /// The placeholder of the redacted parts of a value, see [`Value::redact`].
+    pub const REDACTED: &'static str = "<redacted>";
+
+    /// Returns a clone of the value where the parts selected by the paths are replaced by the
+    /// [`Value::REDACTED`] placeholder.
+    ///
+    /// This is meant to log or export values with sensitive parts, such as the credentials in the
+    /// arguments of a call. Paths that select nothing are ignored. The redacted value may not have
+    /// the type of the original value anymore.
+    pub fn redact<'a, I>(&self, paths: I) -> Self
+    where
+        I: IntoIterator<Item = &'a ValuePath>,
+    {
+        let mut value = self.clone();
+        for path in paths {
+            value.redact_segments(path.segments());
+        }
+        value
+    }
+
+    fn redact_segments(&mut self, segments: &[PathSegment]) {
+        let (segment, rest) = match segments.split_first() {
+            Some(split) => split,
+            None => {
+                *self = Self::String(Self::REDACTED.to_owned());
+                return;
+            }
+        };
+        let redact_elements = |elements: &mut [Value]| {
+            for (index, element) in elements.iter_mut().enumerate() {
+                if segment.matches_index(index) {
+                    element.redact_segments(rest);
+                }
+            }
+        };
+        match self {
+            Self::Option(option) => {
+                if let Some(value) = option.as_mut() {
+                    value.redact_segments(segments);
+                }
+            }
+            Self::Dynamic(dynamic) => {
+                let mut value = std::mem::take(dynamic.as_mut()).into_value();
+                value.redact_segments(segments);
+                **dynamic = Dynamic::from_value(value);
+            }
+            Self::List(list) => redact_elements(list),
+            Self::Tuple(tuple) => redact_elements(tuple.elements_mut()),
+            Self::Map(map) => {
+                for (key, value) in map.iter_mut() {
+                    if segment.matches_key(key) {
+                        value.redact_segments(rest);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Map, Number, Tuple};
+    use pretty_assertions::assert_eq;
+
+    fn redacted() -> Value {
+        Value::from(Value::REDACTED)
+    }
+
+    #[test]
+    fn test_value_path_to_from_str() {
+        let path: ValuePath = "0.credentials.*.token".parse().unwrap();
+        assert_eq!(
+            path,
+            ValuePath::new()
+                .with_index(0)
+                .with_key("credentials")
+                .with_any()
+                .with_key("token")
+        );
+        assert_eq!(path.to_string(), "0.credentials.*.token");
+        assert_eq!("".parse(), Ok(ValuePath::new()));
+        assert_eq!(
+            "0..token".parse::<ValuePath>(),
+            Err(ParseValuePathError("0..token".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_value_redact() {
+        let credentials = |user: &str, token: &str| {
+            Value::Map(Map::from_iter([
+                (Value::from("user"), Value::from(user)),
+                (Value::from("token"), Value::from(token)),
+            ]))
+        };
+        let value = Value::Tuple(Tuple::from_vec(vec![
+            Value::from(Number::Int32(42)),
+            Value::List(vec![credentials("a", "secret"), credentials("b", "secret")]),
+            Value::Option(Box::new(Some(Value::from("password")))),
+            Value::Dynamic(Box::new(Dynamic::from("private"))),
+        ]));
+
+        let paths = ["1.*.token", "2", "3", "4.ignored"].map(|path| path.parse().unwrap());
+        let expected = Value::Tuple(Tuple::from_vec(vec![
+            Value::from(Number::Int32(42)),
+            Value::List(vec![
+                Value::Map(Map::from_iter([
+                    (Value::from("user"), Value::from("a")),
+                    (Value::from("token"), redacted()),
+                ])),
+                Value::Map(Map::from_iter([
+                    (Value::from("user"), Value::from("b")),
+                    (Value::from("token"), redacted()),
+                ])),
+            ]),
+            redacted(),
+            redacted(),
+        ]));
+        assert_eq!(value.redact(&paths), expected);
+        assert_eq!(value.redact(&[ValuePath::new()]), redacted());
+        assert_eq!(value.redact(&[]), value);
+    }
+}
@@ -0,0 +1,195 @@
+//! An interactive shell to explore and call the services of a namespace.
+//!
+//! Usage: `cargo run --example repl -- --qi-url tcp://localhost:9559`
+//!
+//! Type a call expression such as `ALTextToSpeech.say('hello')` to evaluate it, or one of the
+//! commands:
+//!  - `:services` lists the services of the namespace,
+//!  - `:methods <service>` lists the methods of a service,
+//!  - `:quit` exits the shell.
+//!
+//! Service and method names are completed with the tab key.
+
+use anyhow::Result;
+use qi::{script, types::object::MetaObject, Node};
+use rustyline::{
+    completion::Completer, error::ReadlineError, history::DefaultHistory, Editor, Helper,
+    Highlighter, Hinter, Validator,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::Duration,
+};
+use tokio::{task, time};
+
+const DIRECTORY_WATCH_PERIOD: Duration = Duration::from_secs(5);
+
+/// The services of the namespace, with their meta object once it has been fetched.
+#[derive(Debug, Default, Clone)]
+struct Services(Arc<Mutex<BTreeMap<String, Option<MetaObject>>>>);
+
+impl Services {
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<String, Option<MetaObject>>> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn names(&self) -> Vec<String> {
+        self.lock().keys().cloned().collect()
+    }
+
+    fn meta_object(&self, service: &str) -> Option<MetaObject> {
+        self.lock().get(service).cloned().flatten()
+    }
+}
+
+/// Periodically updates the list of services from the service directory, and fetches the meta
+/// objects of the new services.
+async fn watch_directory(node: Arc<Node>, services: Services) {
+    loop {
+        match node.service_directory().services().await {
+            Ok(infos) => {
+                let names: BTreeSet<_> = infos.into_iter().map(|info| info.name).collect();
+                let added: Vec<_> = {
+                    let mut services = services.lock();
+                    services.retain(|name, _| names.contains(name));
+                    let added: Vec<_> = names
+                        .into_iter()
+                        .filter(|name| !services.contains_key(name))
+                        .collect();
+                    for name in &added {
+                        services.insert(name.clone(), None);
+                    }
+                    added
+                };
+                for name in added {
+                    let node = Arc::clone(&node);
+                    let services = services.clone();
+                    task::spawn(async move {
+                        if let Ok(object) = node.service(&name).await {
+                            services.lock().insert(name, object.meta_object().cloned());
+                        }
+                    });
+                }
+            }
+            Err(err) => eprintln!("error: failed to list the services: {err}"),
+        }
+        time::sleep(DIRECTORY_WATCH_PERIOD).await;
+    }
+}
+
+#[derive(Helper, Hinter, Highlighter, Validator)]
+struct ReplHelper {
+    services: Services,
+}
+
+impl Completer for ReplHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &rustyline::Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let line = line.get(..pos).unwrap_or(line);
+        let completion = match line.split_once('.') {
+            // Completes the name of a service.
+            None => {
+                let candidates = self
+                    .services
+                    .names()
+                    .into_iter()
+                    .filter(|name| name.starts_with(line))
+                    .collect();
+                (0, candidates)
+            }
+            // Completes the name of a method, from the meta object of the service.
+            Some((service, method)) if !method.contains('(') => {
+                let candidates = self
+                    .services
+                    .meta_object(service)
+                    .map(|meta_object| {
+                        meta_object
+                            .methods
+                            .values()
+                            .filter(|meta_method| meta_method.name.starts_with(method))
+                            .map(|meta_method| meta_method.name.clone())
+                            .collect::<BTreeSet<_>>()
+                    })
+                    .unwrap_or_default();
+                (service.len() + 1, candidates.into_iter().collect())
+            }
+            Some(_) => (pos, Vec::new()),
+        };
+        Ok(completion)
+    }
+}
+
+fn print_methods(meta_object: &MetaObject) {
+    for method in meta_object.methods.values() {
+        println!(
+            "{}{} -> {}",
+            method.name, method.parameters_signature, method.return_signature
+        );
+        if !method.description.is_empty() {
+            println!("    {}", method.description);
+        }
+    }
+}
+
+async fn eval(line: &str, node: &Node, services: &Services) {
+    match line.split_once(char::is_whitespace) {
+        _ if line == ":services" => {
+            for name in services.names() {
+                println!("{name}");
+            }
+        }
+        Some((":methods", service)) => match services.meta_object(service.trim()) {
+            Some(meta_object) => print_methods(&meta_object),
+            None => eprintln!("error: unknown service \"{}\"", service.trim()),
+        },
+        _ => match script::eval(line, node).await {
+            // Values are pretty printed by their display implementation.
+            Ok(value) => println!("{value}"),
+            Err(err) => eprintln!("error: {:#}", anyhow::Error::new(err)),
+        },
+    }
+}
+
+#[qi::main]
+async fn main(node: Node) -> Result<()> {
+    let node = Arc::new(node);
+    let services = Services::default();
+    task::spawn(watch_directory(Arc::clone(&node), services.clone()));
+
+    let mut editor = Editor::<ReplHelper, DefaultHistory>::new()?;
+    editor.set_helper(Some(ReplHelper {
+        services: services.clone(),
+    }));
+    loop {
+        // Reading a line blocks, it must not hold a thread of the runtime.
+        let (line, returned_editor) = task::spawn_blocking(move || {
+            let line = editor.readline("qi> ");
+            (line, editor)
+        })
+        .await?;
+        editor = returned_editor;
+        let line = match line {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(err) => return Err(err.into()),
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        editor.add_history_entry(line)?;
+        if line == ":quit" {
+            break;
+        }
+        eval(line, &node, &services).await;
+    }
+    Ok(())
+}
@@ -0,0 +1,212 @@
+//! Bootstrap of the applications that use a namespace, see [`main`](macro@crate::main).
+//!
+//! The URL of the namespace is given to the application by the `--qi-url` argument, or else by
+//! the `QI_URL` environment variable, or else is [`DEFAULT_URL`].
+
+use crate::{messaging::CallTermination, object::node::ToNamespaceError, Node, Uri};
+use std::{future::Future, str::FromStr};
+
+/// The URL of the namespace of applications that do not specify one.
+pub const DEFAULT_URL: &str = "tcp://127.0.0.1:9559";
+
+/// The environment variable that gives the URL of the namespace, if the arguments do not.
+pub const URL_ENV_VAR: &str = "QI_URL";
+
+const URL_FLAG: &str = "--qi-url";
+
+/// The options of an application, as given by its arguments and its environment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Options {
+    url: Uri,
+    args: Vec<String>,
+}
+
+impl Options {
+    /// Reads the options from the arguments of the process and from the environment.
+    pub fn from_env() -> Result<Self, OptionsError> {
+        Self::from_args(std::env::args().skip(1), std::env::var(URL_ENV_VAR).ok())
+    }
+
+    /// Reads the options from arguments, not including the name of the program, and from the
+    /// URL of the environment, if any.
+    pub fn from_args<I>(args: I, env_url: Option<String>) -> Result<Self, OptionsError>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut args = args.into_iter();
+        let mut url = None;
+        let mut other_args = Vec::new();
+        while let Some(arg) = args.next() {
+            if arg == URL_FLAG {
+                url = Some(args.next().ok_or(OptionsError::MissingUrl)?);
+            } else if let Some(value) = arg
+                .strip_prefix(URL_FLAG)
+                .and_then(|rest| rest.strip_prefix('='))
+            {
+                url = Some(value.to_owned());
+            } else {
+                other_args.push(arg);
+            }
+        }
+        let url = url.or(env_url);
+        let url = url.as_deref().unwrap_or(DEFAULT_URL);
+        let url = url.parse().map_err(|source| OptionsError::InvalidUrl {
+            url: url.to_owned(),
+            source,
+        })?;
+        Ok(Self {
+            url,
+            args: other_args,
+        })
+    }
+
+    /// The URL of the namespace.
+    pub fn url(&self) -> &Uri {
+        &self.url
+    }
+
+    /// The arguments that are not options of `qi`, in order.
+    pub fn args(&self) -> &[String] {
+        &self.args
+    }
+}
+
+/// Runs the main function of an application, which is the expansion of
+/// [`main`](macro@crate::main).
+///
+/// Exits the process with an error message if the application could not be started.
+pub fn run<F, Fut>(main: F) -> Fut::Output
+where
+    F: FnOnce(Node) -> Fut,
+    Fut: Future,
+{
+    match try_run(main) {
+        Ok(output) => output,
+        // The application cannot report the error, it happened before it started.
+        #[allow(clippy::print_stderr, clippy::exit)]
+        Err(err) => {
+            eprintln!("error: {err}");
+            let mut source = std::error::Error::source(&err);
+            while let Some(err) = source {
+                eprintln!("  caused by: {err}");
+                source = err.source();
+            }
+            std::process::exit(1)
+        }
+    }
+}
+
+/// Same as [`run`], but returns the error if the application could not be started.
+///
+/// The tracing events are written on the standard error, filtered by the `RUST_LOG` environment
+/// variable, unless a global subscriber is already set. In debug builds, the serialization is
+/// checked with [`self_check`](crate::self_check). A multithreaded runtime is started, the node
+/// is connected to the namespace of the options of the process, and the main function is run.
+pub fn try_run<F, Fut>(main: F) -> Result<Fut::Output, Error>
+where
+    F: FnOnce(Node) -> Fut,
+    Fut: Future,
+{
+    let _result = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .with_writer(std::io::stderr)
+        .try_init();
+    if cfg!(debug_assertions) {
+        let report = crate::self_check();
+        if !report.is_ok() {
+            return Err(Error::SelfCheck(report));
+        }
+    }
+    let options = Options::from_env()?;
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .map_err(Error::Runtime)?;
+    runtime.block_on(async move {
+        let node = Node::to_namespace(options.url)
+            .await
+            .map_err(Error::Connect)?;
+        Ok(main(node).await)
+    })
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum OptionsError {
+    #[error("missing value of the --qi-url argument")]
+    MissingUrl,
+
+    #[error("invalid namespace URL \"{url}\"")]
+    InvalidUrl {
+        url: String,
+        source: <Uri as FromStr>::Err,
+    },
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("invalid options")]
+    Options(#[from] OptionsError),
+
+    #[error("the self-check of the serialization failed:\n{0}")]
+    SelfCheck(crate::self_check::Report),
+
+    #[error("failed to start the runtime")]
+    Runtime(#[source] std::io::Error),
+
+    #[error("failed to connect to the namespace")]
+    Connect(#[source] CallTermination<ToNamespaceError>),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|&arg| arg.to_owned()).collect()
+    }
+
+    #[test]
+    fn test_options_from_args() {
+        let options = Options::from_args(
+            args(&["-v", "--qi-url", "tcp://10.0.0.2:9559", "input.txt"]),
+            None,
+        )
+        .unwrap();
+        assert_eq!(options.url().as_str(), "tcp://10.0.0.2:9559");
+        assert_eq!(options.args(), ["-v", "input.txt"]);
+
+        let options = Options::from_args(args(&["--qi-url=tcp://10.0.0.2:9559"]), None).unwrap();
+        assert_eq!(options.url().as_str(), "tcp://10.0.0.2:9559");
+        assert!(options.args().is_empty());
+    }
+
+    #[test]
+    fn test_options_url_fallbacks() {
+        let options =
+            Options::from_args(args(&[]), Some("tcp://10.0.0.3:9559".to_owned())).unwrap();
+        assert_eq!(options.url().as_str(), "tcp://10.0.0.3:9559");
+
+        let options = Options::from_args(args(&[]), None).unwrap();
+        assert_eq!(options.url().as_str(), DEFAULT_URL);
+
+        // The argument has precedence over the environment.
+        let options = Options::from_args(
+            args(&["--qi-url", "tcp://10.0.0.2:9559"]),
+            Some("tcp://10.0.0.3:9559".to_owned()),
+        )
+        .unwrap();
+        assert_eq!(options.url().as_str(), "tcp://10.0.0.2:9559");
+    }
+
+    #[test]
+    fn test_options_errors() {
+        assert!(matches!(
+            Options::from_args(args(&["--qi-url"]), None),
+            Err(OptionsError::MissingUrl)
+        ));
+        assert!(matches!(
+            Options::from_args(args(&["--qi-url", "not a url"]), None),
+            Err(OptionsError::InvalidUrl { .. })
+        ));
+    }
+}
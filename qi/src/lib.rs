@@ -32,7 +32,6 @@
     clippy::mod_module_files,
     clippy::str_to_string,
     clippy::string_slice,
-    clippy::string_to_string,
     clippy::todo,
     clippy::try_err,
     clippy::unimplemented,
@@ -40,11 +39,116 @@
     clippy::unneeded_field_pattern,
     clippy::use_debug
 )]
+// Errors keep the failures of calls by value, which makes them large.
+#![allow(clippy::result_large_err)]
 // Deny warnings in doc test.
 #![doc(test(attr(deny(warnings))))]
 #![doc = include_str!("../README.md")]
 
+pub mod application;
+pub mod msg;
+pub mod script;
+pub mod self_check;
+pub mod services;
+pub mod testing;
+pub mod types;
+pub mod wire;
+
+// Dependencies of the examples.
+#[cfg(test)]
+use {anyhow as _, rustyline as _};
+
+/// The `qi-codegen` crate, to generate Rust code from the types of `qi`. This path is
+/// semver-exempt.
+#[cfg(feature = "codegen")]
+pub use qi_codegen as codegen;
+/// The `qi-format` crate. This path is semver-exempt, prefer [`wire`].
 pub use qi_format as format;
-pub use qi_messaging::{self as messaging, session};
-pub use qi_object::{self as object, Node, ServiceDirectory, ServiceInfo, Uri};
-pub use qi_types as types;
+/// Runs an asynchronous function as the entry point of an application connected to a namespace.
+///
+/// The function takes the [`Node`] connected to the namespace of the application. Before it is
+/// called, a multithreaded `tokio` runtime is started, tracing events are written on the standard
+/// error filtered by the `RUST_LOG` environment variable, and the URL of the namespace is read
+/// from the `--qi-url` argument. See the [`application`] module for details.
+///
+/// ```no_run
+/// #[qi::main]
+/// async fn main(node: qi::Node) {
+///     let services = node.service_directory().services().await;
+///     println!("{services:?}");
+/// }
+/// ```
+#[cfg(feature = "macros")]
+pub use qi_macros::main;
+/// Derives [`wire::FromValue`] for a structure, to convert `dynamic` values into it.
+///
+/// The annotations of the structure are its name and the names of its fields, as written in Rust.
+/// By default they are not checked, any tuple value with the right elements is converted. With the
+/// `#[qi(strict)]` attribute, the value must be annotated with the same names, which catches
+/// values of another structure that happen to have the same elements.
+///
+/// ```no_run
+/// #[derive(serde::Deserialize, qi::FromValue)]
+/// #[qi(strict)]
+/// pub struct Position {
+///     pub x: f32,
+///     pub y: f32,
+/// }
+///
+/// pub fn position(value: &qi::types::Dynamic) -> qi::wire::Result<Position> {
+///     qi::wire::FromValue::from_dynamic(value)
+/// }
+/// # fn main() {}
+/// ```
+///
+/// Fields of type [`Raw`](types::Raw) annotated with `#[qi(raw)]` share the data of the value
+/// they are read from with [`wire::FromValue::from_value`], instead of copying it. A value
+/// received in a message keeps the payload of the message, large buffers such as images are then
+/// never copied.
+///
+/// ```no_run
+/// #[derive(serde::Deserialize, qi::FromValue)]
+/// pub struct Image {
+///     pub width: u32,
+///     pub height: u32,
+///     #[qi(raw)]
+///     pub data: qi::types::Raw,
+/// }
+///
+/// pub fn image(value: &qi::wire::Value) -> qi::wire::Result<Image> {
+///     qi::wire::FromValue::from_value(value)
+/// }
+/// # fn main() {}
+/// ```
+#[cfg(feature = "macros")]
+pub use qi_macros::FromValue;
+/// Derives the configuration of the signals of an object, for an enumeration of which each variant
+/// is a signal, see [`object::signal::Signals`].
+///
+/// Each variant has the action of its signal, and optionally the number of its last values that
+/// are replayed to late subscribers, with the `#[qi(action = ..., replay = ...)]` attribute.
+///
+/// ```no_run
+/// #[derive(qi::Signals)]
+/// pub enum MotionSignal {
+///     #[qi(action = 100, replay = 1)]
+///     Posture,
+///     #[qi(action = 101)]
+///     Moved,
+/// }
+///
+/// pub fn configure(subscriptions: &qi::object::signal::SubscriptionSet) {
+///     subscriptions.configure::<MotionSignal>();
+/// }
+/// # fn main() {}
+/// ```
+#[cfg(feature = "macros")]
+pub use qi_macros::Signals;
+/// The `qi-messaging` crate. This path is semver-exempt, prefer [`msg`].
+pub use qi_messaging as messaging;
+/// The sessions of the `qi-messaging` crate. This path is semver-exempt, prefer [`msg`].
+pub use qi_messaging::session;
+/// The `qi-object` crate. This path is semver-exempt.
+pub use qi_object as object;
+pub use qi_object::{Node, NodeBuilder, ServiceDirectory, ServiceEvent, ServiceInfo, Uri};
+pub use self_check::self_check;
@@ -0,0 +1,8 @@
+//! The messaging of `qi`: the sessions between nodes and the requests that they exchange.
+//!
+//! This module is part of the facade of the crate, see [the crate documentation](crate#stability).
+
+pub use qi_messaging::{
+    session::{Call, Client, Event, Notification, Post, Reply, Subject},
+    CallResult, CallTermination, CapabilitiesMap, RequestId, Service,
+};
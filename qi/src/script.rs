@@ -0,0 +1,444 @@
+//! Evaluation of call expressions on the services of a namespace.
+//!
+//! An expression is a call of a method of a service with literal arguments, such as
+//! `ALTextToSpeech.say('hello')`. Arguments are converted to the types of the parameters of the
+//! method, as described by the meta object of the service.
+//!
+//! Supported literals are booleans (`true`, `false`), integers (`-42`), floating point numbers
+//! (`3.14`, `1e-3`), strings (`'hello'`, `"world"`) and lists (`[1, 2, 3]`).
+
+use crate::{
+    messaging::{CallResult, CallTermination},
+    object::{self, node},
+    types::{
+        object::{ActionId, MetaObject},
+        Dynamic, Number, Raw, Signature, Tuple, Type, Value,
+    },
+    Node,
+};
+
+/// Parses and evaluates a call expression on a service of the namespace of the node.
+///
+/// Returns the result of the call, typed after the return signature of the method.
+pub async fn eval(expression: &str, node: &Node) -> CallResult<Dynamic, EvalError> {
+    let expression = parse(expression).map_err(|err| CallTermination::Error(err.into()))?;
+    let service = node
+        .service(expression.service())
+        .await
+        .map_err(|err| err.map_err(EvalError::Service))?;
+    let meta_object = service
+        .fetch_meta_object()
+        .await
+        .map_err(|err| err.map_err(EvalError::Call))?;
+    let (action, args) = select_method(meta_object, &expression).map_err(CallTermination::Error)?;
+    service
+        .call_dynamic(action, args)
+        .await
+        .map_err(|err| err.map_err(EvalError::Call))
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum EvalError {
+    #[error("failed to parse the expression")]
+    Parse(#[from] ParseError),
+
+    #[error("failed to get the service")]
+    Service(#[source] node::ServiceError),
+
+    #[error("the service has no method named \"{0}\"")]
+    MethodNotFound(String),
+
+    #[error("no overload of method \"{0}\" accepts the arguments")]
+    NoMatchingOverload(String),
+
+    #[error("the call failed")]
+    Call(#[source] object::object::client::CallError),
+}
+
+/// A parsed call expression.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expression {
+    service: String,
+    method: String,
+    arguments: Vec<Literal>,
+}
+
+impl Expression {
+    pub fn service(&self) -> &str {
+        &self.service
+    }
+
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    pub fn arguments(&self) -> &[Literal] {
+        &self.arguments
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    List(Vec<Literal>),
+}
+
+impl Literal {
+    /// Converts the literal into a value of the given type, if it is compatible with it.
+    ///
+    /// If the type is unknown, the literal is converted into a `dynamic` value of its natural
+    /// type.
+    pub fn to_value(&self, t: Option<&Type>) -> Option<Value> {
+        let t = match t {
+            Some(t) => t,
+            None => {
+                return Some(Value::Dynamic(Box::new(Dynamic::from_value(
+                    self.natural_value(),
+                ))))
+            }
+        };
+        match (self, t) {
+            (Self::Bool(b), Type::Bool) => Some(Value::Bool(*b)),
+            (Self::Integer(i), t) => integer_to_value(*i, t),
+            (Self::Float(f), Type::Float32) => Some(Value::Number(Number::from(*f as f32))),
+            (Self::Float(f), Type::Float64) => Some(Value::Number(Number::from(*f))),
+            (Self::String(s), Type::String) => Some(Value::String(s.clone())),
+            (Self::String(s), Type::Raw) => Some(Value::Raw(Raw::copy_from_slice(s.as_bytes()))),
+            (Self::List(elements), Type::List(t) | Type::VarArgs(t)) => elements
+                .iter()
+                .map(|element| element.to_value(t.as_deref()))
+                .collect::<Option<Vec<_>>>()
+                .map(Value::List),
+            (literal, Type::Option(t)) => literal
+                .to_value(t.as_deref())
+                .map(|value| Value::Option(Box::new(Some(value)))),
+            _ => None,
+        }
+    }
+
+    fn natural_value(&self) -> Value {
+        match self {
+            Self::Bool(b) => Value::Bool(*b),
+            Self::Integer(i) => match i32::try_from(*i) {
+                Ok(i) => Value::Number(Number::Int32(i)),
+                Err(_err) => Value::Number(Number::Int64(*i)),
+            },
+            Self::Float(f) => Value::Number(Number::from(*f)),
+            Self::String(s) => Value::String(s.clone()),
+            Self::List(elements) => Value::List(elements.iter().map(Self::natural_value).collect()),
+        }
+    }
+}
+
+fn integer_to_value(i: i64, t: &Type) -> Option<Value> {
+    let number = match t {
+        Type::Int8 => Number::Int8(i8::try_from(i).ok()?),
+        Type::UInt8 => Number::UInt8(u8::try_from(i).ok()?),
+        Type::Int16 => Number::Int16(i16::try_from(i).ok()?),
+        Type::UInt16 => Number::UInt16(u16::try_from(i).ok()?),
+        Type::Int32 => Number::Int32(i32::try_from(i).ok()?),
+        Type::UInt32 => Number::UInt32(u32::try_from(i).ok()?),
+        Type::Int64 => Number::Int64(i),
+        Type::UInt64 => Number::UInt64(u64::try_from(i).ok()?),
+        Type::Float32 => Number::from(i as f32),
+        Type::Float64 => Number::from(i as f64),
+        _ => return None,
+    };
+    Some(Value::Number(number))
+}
+
+/// Selects the method of the meta object called by the expression, among its overloads, and
+/// converts the arguments to its parameters.
+fn select_method(
+    meta_object: &MetaObject,
+    expression: &Expression,
+) -> Result<(ActionId, Value), EvalError> {
+    let mut overloads = meta_object
+        .methods
+        .iter()
+        .filter(|(_action, method)| method.name == expression.method)
+        .peekable();
+    if overloads.peek().is_none() {
+        return Err(EvalError::MethodNotFound(expression.method.clone()));
+    }
+    overloads
+        .find_map(|(action, method)| {
+            let types = parameter_types(&method.parameters_signature)?;
+            if types.len() != expression.arguments.len() {
+                return None;
+            }
+            let args = expression
+                .arguments
+                .iter()
+                .zip(&types)
+                .map(|(argument, t)| argument.to_value(t.as_ref()))
+                .collect::<Option<Vec<_>>>()?;
+            Some((*action, Value::Tuple(Tuple::from_vec(args))))
+        })
+        .ok_or_else(|| EvalError::NoMatchingOverload(expression.method.clone()))
+}
+
+fn parameter_types(signature: &Signature) -> Option<Vec<Option<Type>>> {
+    match signature.clone().into_type() {
+        Some(Type::Tuple(tuple)) => Some(tuple.element_types()),
+        _ => None,
+    }
+}
+
+pub fn parse(expression: &str) -> Result<Expression, ParseError> {
+    let mut parser = Parser::new(expression);
+    let service = parser.identifier()?;
+    parser.expect('.')?;
+    let method = parser.identifier()?;
+    parser.expect('(')?;
+    let arguments = parser.literals_until(')')?;
+    parser.skip_whitespaces();
+    if let Some((position, c)) = parser.peek() {
+        return Err(ParseError::UnexpectedChar(c, position));
+    }
+    Ok(Expression {
+        service,
+        method,
+        arguments,
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ParseError {
+    #[error("unexpected end of expression")]
+    UnexpectedEnd,
+
+    #[error("unexpected character '{0}' at position {1}")]
+    UnexpectedChar(char, usize),
+
+    #[error("invalid number \"{0}\"")]
+    InvalidNumber(String),
+
+    #[error("invalid escape sequence '\\{0}' at position {1}")]
+    InvalidEscape(char, usize),
+}
+
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl Parser {
+    fn new(input: &str) -> Self {
+        Self {
+            chars: input.chars().collect(),
+            position: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<(usize, char)> {
+        self.chars.get(self.position).map(|&c| (self.position, c))
+    }
+
+    fn next_char(&mut self) -> Result<(usize, char), ParseError> {
+        let next = self.peek().ok_or(ParseError::UnexpectedEnd)?;
+        self.position += 1;
+        Ok(next)
+    }
+
+    fn skip_whitespaces(&mut self) {
+        while matches!(self.peek(), Some((_, c)) if c.is_whitespace()) {
+            self.position += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), ParseError> {
+        self.skip_whitespaces();
+        match self.next_char()? {
+            (_, c) if c == expected => Ok(()),
+            (position, c) => Err(ParseError::UnexpectedChar(c, position)),
+        }
+    }
+
+    fn identifier(&mut self) -> Result<String, ParseError> {
+        self.skip_whitespaces();
+        let mut identifier = String::new();
+        match self.next_char()? {
+            (_, c) if c.is_ascii_alphabetic() || c == '_' => identifier.push(c),
+            (position, c) => return Err(ParseError::UnexpectedChar(c, position)),
+        }
+        while let Some((_, c)) = self.peek() {
+            if !(c.is_ascii_alphanumeric() || c == '_') {
+                break;
+            }
+            identifier.push(c);
+            self.position += 1;
+        }
+        Ok(identifier)
+    }
+
+    /// Parses a comma separated sequence of literals, until the closing character is consumed.
+    fn literals_until(&mut self, close: char) -> Result<Vec<Literal>, ParseError> {
+        let mut literals = Vec::new();
+        self.skip_whitespaces();
+        if matches!(self.peek(), Some((_, c)) if c == close) {
+            self.position += 1;
+            return Ok(literals);
+        }
+        loop {
+            literals.push(self.literal()?);
+            self.skip_whitespaces();
+            match self.next_char()? {
+                (_, ',') => continue,
+                (_, c) if c == close => break Ok(literals),
+                (position, c) => break Err(ParseError::UnexpectedChar(c, position)),
+            }
+        }
+    }
+
+    fn literal(&mut self) -> Result<Literal, ParseError> {
+        self.skip_whitespaces();
+        match self.peek().ok_or(ParseError::UnexpectedEnd)? {
+            (_, quote @ ('\'' | '"')) => {
+                self.position += 1;
+                self.string(quote).map(Literal::String)
+            }
+            (_, '[') => {
+                self.position += 1;
+                self.literals_until(']').map(Literal::List)
+            }
+            (_, c) if c == '-' || c.is_ascii_digit() => self.number(),
+            (_, c) if c.is_ascii_alphabetic() => {
+                let position = self.position;
+                match self.identifier()?.as_str() {
+                    "true" => Ok(Literal::Bool(true)),
+                    "false" => Ok(Literal::Bool(false)),
+                    _ => Err(ParseError::UnexpectedChar(c, position)),
+                }
+            }
+            (position, c) => Err(ParseError::UnexpectedChar(c, position)),
+        }
+    }
+
+    fn string(&mut self, quote: char) -> Result<String, ParseError> {
+        let mut string = String::new();
+        loop {
+            match self.next_char()? {
+                (_, c) if c == quote => break Ok(string),
+                (_, '\\') => {
+                    let (position, c) = self.next_char()?;
+                    let c = match c {
+                        '\\' | '\'' | '"' => c,
+                        'n' => '\n',
+                        't' => '\t',
+                        'r' => '\r',
+                        '0' => '\0',
+                        _ => return Err(ParseError::InvalidEscape(c, position)),
+                    };
+                    string.push(c);
+                }
+                (_, c) => string.push(c),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Literal, ParseError> {
+        let mut number = String::new();
+        let mut is_float = false;
+        while let Some((_, c)) = self.peek() {
+            match c {
+                '0'..='9' | '-' | '+' => {}
+                '.' | 'e' | 'E' => is_float = true,
+                _ => break,
+            }
+            number.push(c);
+            self.position += 1;
+        }
+        let literal = if is_float {
+            number.parse().ok().map(Literal::Float)
+        } else {
+            number.parse().ok().map(Literal::Integer)
+        };
+        literal.ok_or(ParseError::InvalidNumber(number))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse("ALTextToSpeech.say('hello')"),
+            Ok(Expression {
+                service: "ALTextToSpeech".to_owned(),
+                method: "say".to_owned(),
+                arguments: vec![Literal::String("hello".to_owned())],
+            })
+        );
+        assert_eq!(
+            parse(r#" ALMotion . moveTo ( 1, -2.5e1 , [true, "a\"b"] ) "#),
+            Ok(Expression {
+                service: "ALMotion".to_owned(),
+                method: "moveTo".to_owned(),
+                arguments: vec![
+                    Literal::Integer(1),
+                    Literal::Float(-25.0),
+                    Literal::List(vec![
+                        Literal::Bool(true),
+                        Literal::String("a\"b".to_owned())
+                    ]),
+                ],
+            })
+        );
+        assert_eq!(
+            parse("ALMemory.getDataListName()").map(|expr| expr.arguments),
+            Ok(vec![])
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(parse("ALMemory"), Err(ParseError::UnexpectedEnd));
+        assert_eq!(
+            parse("ALMemory.getData('a') b"),
+            Err(ParseError::UnexpectedChar('b', 22))
+        );
+        assert_eq!(
+            parse("ALMemory.getData(1-2)"),
+            Err(ParseError::InvalidNumber("1-2".to_owned()))
+        );
+        assert_eq!(
+            parse("ALMemory.getData('\\q')"),
+            Err(ParseError::InvalidEscape('q', 19))
+        );
+        assert_eq!(
+            parse("ALMemory.getData(nope)"),
+            Err(ParseError::UnexpectedChar('n', 17))
+        );
+    }
+
+    #[test]
+    fn test_literal_to_value() {
+        assert_eq!(
+            Literal::Integer(3).to_value(Some(&Type::UInt8)),
+            Some(Value::Number(Number::UInt8(3)))
+        );
+        assert_eq!(Literal::Integer(-3).to_value(Some(&Type::UInt8)), None);
+        assert_eq!(
+            Literal::Integer(3).to_value(Some(&Type::Float32)),
+            Some(Value::Number(Number::from(3.0f32)))
+        );
+        assert_eq!(Literal::Float(3.5).to_value(Some(&Type::Int32)), None);
+        assert_eq!(
+            Literal::List(vec![Literal::String("a".to_owned())])
+                .to_value(Some(&Type::List(Some(Box::new(Type::String))))),
+            Some(Value::List(vec![Value::String("a".to_owned())]))
+        );
+        assert_eq!(
+            Literal::Bool(true).to_value(None),
+            Some(Value::Dynamic(Box::new(Dynamic::from_value(Value::Bool(
+                true
+            )))))
+        );
+    }
+}
@@ -0,0 +1,270 @@
+//! A self-test of the serialization of values, against vectors embedded in the crate, see
+//! [`self_check`].
+
+use crate::{
+    format::de::from_value_seed,
+    types::{
+        dynamic::DynamicSeed,
+        ty::{StructField, TupleType},
+        Dynamic, Map, Number, Tuple, Type, Value,
+    },
+    wire,
+};
+use qi_types::DisplayBytes;
+use std::fmt;
+
+/// Checks that values are serialized as expected by other nodes, by running a suite of embedded
+/// vectors.
+///
+/// Each vector is a value, of each kind of type, along with its expected encoding, including the
+/// byte order of numbers and the signature of `dynamic` values. The value must be encoded into
+/// those bytes exactly, and decoded back from them. A failure means that the serialization of this
+/// build is broken, for instance by a miscompilation, by an unexpected byte order of the platform
+/// or by features of the crates that change the format, and that the values exchanged with other
+/// nodes would be corrupted.
+///
+/// The suite takes a few microseconds. Applications started with
+/// [`application::run`](crate::application::run) run it in debug builds, and do not start if it
+/// fails.
+///
+/// ```
+/// let report = qi::self_check();
+/// assert!(report.is_ok(), "{report}");
+/// ```
+pub fn self_check() -> Report {
+    let checks = vectors()
+        .into_iter()
+        .map(|vector| Check {
+            name: vector.name,
+            failure: vector.check().err(),
+        })
+        .collect();
+    Report { checks }
+}
+
+/// The outcome of each vector of [`self_check`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    checks: Vec<Check>,
+}
+
+impl Report {
+    /// The checks, in the order in which they were run.
+    pub fn checks(&self) -> &[Check] {
+        &self.checks
+    }
+
+    /// The checks that failed.
+    pub fn failures(&self) -> impl Iterator<Item = &Check> {
+        self.checks.iter().filter(|check| check.failure.is_some())
+    }
+
+    /// Returns true if no check failed.
+    pub fn is_ok(&self) -> bool {
+        self.failures().next().is_none()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            writeln!(f, "{check}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Check {
+    name: &'static str,
+    failure: Option<String>,
+}
+
+impl Check {
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// The reason of the failure of the check, if it failed.
+    pub fn failure(&self) -> Option<&str> {
+        self.failure.as_deref()
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.failure {
+            None => write!(f, "[pass] {}", self.name),
+            Some(reason) => write!(f, "[FAIL] {}: {reason}", self.name),
+        }
+    }
+}
+
+struct Vector {
+    name: &'static str,
+    t: Option<Type>,
+    value: Value,
+    bytes: &'static [u8],
+}
+
+impl Vector {
+    fn new(name: &'static str, t: Type, value: Value, bytes: &'static [u8]) -> Self {
+        Self {
+            name,
+            t: Some(t),
+            value,
+            bytes,
+        }
+    }
+
+    fn check(&self) -> Result<(), String> {
+        let encoded =
+            wire::to_value(&self.value).map_err(|err| format!("the encoding failed: {err}"))?;
+        if encoded.as_bytes().as_ref() != self.bytes {
+            return Err(format!(
+                "the value is encoded as \"{}\" instead of \"{}\"",
+                DisplayBytes(encoded.as_bytes()),
+                DisplayBytes(self.bytes)
+            ));
+        }
+        let decoded = from_value_seed(
+            &wire::Value::from(self.bytes),
+            DynamicSeed::new(self.t.clone()),
+        )
+        .map_err(|err| format!("the decoding failed: {err}"))?
+        .into_value();
+        if decoded != self.value {
+            return Err(format!(
+                "the value is decoded as {decoded} instead of {}",
+                self.value
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn vectors() -> Vec<Vector> {
+    let point_type = TupleType::Struct(
+        "Point".to_owned(),
+        vec![
+            StructField {
+                name: "x".to_owned(),
+                value_type: Some(Type::Int32),
+            },
+            StructField {
+                name: "y".to_owned(),
+                value_type: Some(Type::Int32),
+            },
+        ],
+    );
+    let point = Value::Tuple(Tuple::from_vec(vec![
+        Value::Number(Number::Int32(1)),
+        Value::Number(Number::Int32(-2)),
+    ]));
+    let point = Dynamic::new(point, Some(Type::Tuple(point_type)))
+        .expect("the point has the type of its structure");
+    vec![
+        Vector::new("bool", Type::Bool, Value::Bool(true), &[0x01]),
+        Vector::new("int8", Type::Int8, Number::Int8(-2).into(), &[0xfe]),
+        Vector::new(
+            "uint16",
+            Type::UInt16,
+            Number::UInt16(0x0102).into(),
+            &[0x02, 0x01],
+        ),
+        Vector::new(
+            "int32",
+            Type::Int32,
+            Number::Int32(-0x0102_0304).into(),
+            &[0xfc, 0xfc, 0xfd, 0xfe],
+        ),
+        Vector::new(
+            "uint64",
+            Type::UInt64,
+            Number::UInt64(0x0102_0304_0506_0708).into(),
+            &[0x08, 0x07, 0x06, 0x05, 0x04, 0x03, 0x02, 0x01],
+        ),
+        Vector::new(
+            "float32",
+            Type::Float32,
+            Number::from(1.5f32).into(),
+            &[0x00, 0x00, 0xc0, 0x3f],
+        ),
+        Vector::new(
+            "float64",
+            Type::Float64,
+            Number::from(-2.25f64).into(),
+            &[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0xc0],
+        ),
+        Vector::new(
+            "string",
+            Type::String,
+            Value::from("qi"),
+            &[0x02, 0x00, 0x00, 0x00, b'q', b'i'],
+        ),
+        Vector::new(
+            "raw",
+            Type::Raw,
+            Value::Raw(vec![0x00, 0xff].into()),
+            &[0x02, 0x00, 0x00, 0x00, 0x00, 0xff],
+        ),
+        Vector::new(
+            "option",
+            Type::Option(Some(Box::new(Type::Int16))),
+            Value::Option(Box::new(Some(Number::Int16(-1).into()))),
+            &[0x01, 0xff, 0xff],
+        ),
+        Vector::new(
+            "list",
+            Type::List(Some(Box::new(Type::UInt8))),
+            Value::List(vec![Number::UInt8(1).into(), Number::UInt8(2).into()]),
+            &[0x02, 0x00, 0x00, 0x00, 0x01, 0x02],
+        ),
+        // Entries are kept in their order, which is not the order of their keys.
+        Vector::new(
+            "map",
+            Type::Map {
+                key: Some(Box::new(Type::String)),
+                value: Some(Box::new(Type::Bool)),
+            },
+            Value::Map(Map::from_iter([
+                (Value::from("b"), Value::Bool(true)),
+                (Value::from("a"), Value::Bool(false)),
+            ])),
+            &[
+                0x02, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, b'b', 0x01, 0x01, 0x00, 0x00, 0x00,
+                b'a', 0x00,
+            ],
+        ),
+        Vector {
+            name: "dynamic_struct",
+            t: None,
+            value: Value::Dynamic(Box::new(point)),
+            bytes: &[
+                0x0f, 0x00, 0x00, 0x00, b'(', b'i', b'i', b')', b'<', b'P', b'o', b'i', b'n', b't',
+                b',', b'x', b',', b'y', b'>', 0x01, 0x00, 0x00, 0x00, 0xfe, 0xff, 0xff, 0xff,
+            ],
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_self_check() {
+        let report = self_check();
+        assert_eq!(report.checks().len(), vectors().len());
+        assert!(report.is_ok(), "{report}");
+    }
+
+    #[test]
+    fn test_self_check_vector_failure() {
+        let vector = Vector::new("int8", Type::Int8, Number::Int8(-2).into(), &[0xff]);
+        assert_eq!(
+            vector.check(),
+            Err("the value is encoded as \"\\xfe\" instead of \"\\xff\"".to_owned())
+        );
+    }
+}
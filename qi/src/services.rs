@@ -0,0 +1,4 @@
+//! Clients of the standard services of the `qi` framework.
+
+pub mod log;
+pub mod video;
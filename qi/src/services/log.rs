@@ -0,0 +1,413 @@
+//! Client of the `LogManager` service, which gathers the logs of a robot.
+//!
+//! Log messages of the robot are received as a stream with [`LogClient::subscribe`]. Local
+//! `tracing` events can be sent to the log system of the robot with
+//! [`LogClient::forward_tracing`].
+
+use crate::{
+    format,
+    messaging::CallResult,
+    object::{
+        node,
+        object::{
+            client::{CallError, ConnectError, SignalStream},
+            Client,
+        },
+    },
+    types::object::Object,
+    Node,
+};
+use futures::{
+    channel::mpsc,
+    task::{Context, Poll},
+    Future, Stream, StreamExt,
+};
+use std::{
+    fmt::Write,
+    pin::Pin,
+    sync::atomic::{AtomicU32, Ordering},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// The name of the service in the service directory.
+pub const SERVICE_NAME: &str = "LogManager";
+
+const SIGNAL_ON_LOG_MESSAGE: &str = "onLogMessage";
+
+/// Prefix of the targets of the `tracing` events of the `qi` crates, which are not forwarded.
+const QI_TARGET_PREFIX: &str = "qi";
+
+/// A client of the `LogManager` service.
+#[derive(Debug, Clone)]
+pub struct LogClient {
+    manager: Client,
+}
+
+impl LogClient {
+    pub async fn connect(node: &Node) -> CallResult<Self, node::ServiceError> {
+        let manager = node.service(SERVICE_NAME).await?;
+        Ok(Self { manager })
+    }
+
+    /// Subscribes to the log messages of the robot.
+    ///
+    /// The level of the messages that are received can be set with the returned stream.
+    pub async fn subscribe(&self) -> CallResult<LogMessages, Error> {
+        let listener: Object = self
+            .manager
+            .call("createListener", ())
+            .await
+            .map_err(|err| err.map_err(Error::Call))?;
+        let listener = self
+            .manager
+            .object(&listener)
+            .await
+            .map_err(|err| err.map_err(Error::ConnectListener))?;
+        let messages = listener
+            .subscribe(SIGNAL_ON_LOG_MESSAGE)
+            .await
+            .map_err(|err| err.map_err(Error::Call))?;
+        Ok(LogMessages { listener, messages })
+    }
+
+    /// Sends messages to the log system of the robot.
+    pub async fn log(&self, messages: Vec<LogMessage>) -> CallResult<(), Error> {
+        self.manager
+            .call("log", messages)
+            .await
+            .map_err(|err| err.map_err(Error::Call))
+    }
+
+    /// Forwards the local `tracing` events to the log system of the robot.
+    ///
+    /// Returns a layer that captures the events, to be added to a `tracing` subscriber, and the
+    /// future that sends them to the robot, which must be polled for the events to be sent. Events
+    /// are sent on a best effort basis.
+    ///
+    /// Events of the `qi` crates are not forwarded, since sending the messages emits some of them.
+    pub fn forward_tracing(&self) -> (LogLayer, impl Future<Output = ()>) {
+        const MAX_BATCH_SIZE: usize = 64;
+        let (sender, receiver) = mpsc::unbounded();
+        let client = self.clone();
+        let forward = async move {
+            let mut batches = receiver.ready_chunks(MAX_BATCH_SIZE);
+            while let Some(messages) = batches.next().await {
+                let _result = client.log(messages).await;
+            }
+        };
+        let layer = LogLayer {
+            sender,
+            next_id: AtomicU32::new(0),
+        };
+        (layer, forward)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("failed to connect to the log listener")]
+    ConnectListener(#[source] ConnectError),
+
+    #[error("a call to the log manager failed")]
+    Call(#[source] CallError),
+}
+
+/// The stream of the log messages of the robot, see [`LogClient::subscribe`].
+#[derive(Debug)]
+pub struct LogMessages {
+    listener: Client,
+    messages: SignalStream<LogMessage>,
+}
+
+impl LogMessages {
+    /// Sets the minimum level of the messages that are received.
+    pub async fn set_level(&self, level: LogLevel) -> CallResult<(), Error> {
+        self.listener
+            .call("setLevel", level)
+            .await
+            .map_err(|err| err.map_err(Error::Call))
+    }
+
+    /// Sets the minimum level of the messages of a category that are received.
+    pub async fn add_filter(&self, category: &str, level: LogLevel) -> CallResult<(), Error> {
+        self.listener
+            .call("addFilter", (category, level))
+            .await
+            .map_err(|err| err.map_err(Error::Call))
+    }
+
+    pub async fn clear_filters(&self) -> CallResult<(), Error> {
+        self.listener
+            .call("clearFilters", ())
+            .await
+            .map_err(|err| err.map_err(Error::Call))
+    }
+}
+
+impl Stream for LogMessages {
+    type Item = Result<LogMessage, format::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.messages.poll_next_unpin(cx)
+    }
+}
+
+/// The level of a log message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LogLevel {
+    Silent = 0,
+    Fatal = 1,
+    Error = 2,
+    Warning = 3,
+    Info = 4,
+    Verbose = 5,
+    Debug = 6,
+}
+
+impl LogLevel {
+    fn from_i32(level: i32) -> Option<Self> {
+        let level = match level {
+            0 => Self::Silent,
+            1 => Self::Fatal,
+            2 => Self::Error,
+            3 => Self::Warning,
+            4 => Self::Info,
+            5 => Self::Verbose,
+            6 => Self::Debug,
+            _ => return None,
+        };
+        Some(level)
+    }
+}
+
+impl From<tracing::Level> for LogLevel {
+    fn from(level: tracing::Level) -> Self {
+        match level {
+            tracing::Level::ERROR => Self::Error,
+            tracing::Level::WARN => Self::Warning,
+            tracing::Level::INFO => Self::Info,
+            tracing::Level::DEBUG => Self::Verbose,
+            tracing::Level::TRACE => Self::Debug,
+        }
+    }
+}
+
+impl serde::Serialize for LogLevel {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_i32(*self as i32)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for LogLevel {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::{Error, Unexpected};
+        let level = i32::deserialize(deserializer)?;
+        Self::from_i32(level).ok_or_else(|| {
+            D::Error::invalid_value(
+                Unexpected::Signed(level.into()),
+                &"a log level between 0 and 6",
+            )
+        })
+    }
+}
+
+/// A time value, as a number of seconds and microseconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Timeval {
+    pub tv_sec: i64,
+    pub tv_usec: i64,
+}
+
+impl From<Duration> for Timeval {
+    fn from(duration: Duration) -> Self {
+        Self {
+            tv_sec: i64::try_from(duration.as_secs()).unwrap_or(i64::MAX),
+            tv_usec: duration.subsec_micros().into(),
+        }
+    }
+}
+
+/// A message of the log system.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct LogMessage {
+    /// The location in the source code where the message was emitted, as `file:function:line`.
+    pub source: String,
+    pub level: LogLevel,
+    /// The date of the message, relative to the epoch of the system clock.
+    pub timestamp: Timeval,
+    pub category: String,
+    /// The process that emitted the message.
+    pub location: String,
+    pub message: String,
+    pub id: u32,
+    /// The date of the message, in nanoseconds relative to the epoch of the steady clock.
+    pub date: i64,
+    /// The date of the message, in nanoseconds relative to the epoch of the system clock.
+    pub system_date: i64,
+}
+
+impl LogMessage {
+    /// Creates a message dated now.
+    pub fn new(level: LogLevel, category: impl Into<String>, message: impl Into<String>) -> Self {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let now_nanos = i64::try_from(now.as_nanos()).unwrap_or(i64::MAX);
+        Self {
+            source: String::new(),
+            level,
+            timestamp: now.into(),
+            category: category.into(),
+            location: String::new(),
+            message: message.into(),
+            id: 0,
+            date: now_nanos,
+            system_date: now_nanos,
+        }
+    }
+
+    pub fn with_source(mut self, source: impl Into<String>) -> Self {
+        self.source = source.into();
+        self
+    }
+
+    pub fn with_id(mut self, id: u32) -> Self {
+        self.id = id;
+        self
+    }
+}
+
+/// A `tracing` layer that forwards events to the log system of a robot, see
+/// [`LogClient::forward_tracing`].
+#[derive(Debug)]
+pub struct LogLayer {
+    sender: mpsc::UnboundedSender<LogMessage>,
+    next_id: AtomicU32,
+}
+
+impl<S> tracing_subscriber::Layer<S> for LogLayer
+where
+    S: tracing::Subscriber,
+{
+    fn on_event(
+        &self,
+        event: &tracing::Event<'_>,
+        _ctx: tracing_subscriber::layer::Context<'_, S>,
+    ) {
+        let metadata = event.metadata();
+        if metadata.target().starts_with(QI_TARGET_PREFIX) {
+            return;
+        }
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        let source = format!(
+            "{}:{}:{}",
+            metadata.file().unwrap_or_default(),
+            metadata.module_path().unwrap_or_default(),
+            metadata.line().unwrap_or_default()
+        );
+        let message = LogMessage::new(
+            (*metadata.level()).into(),
+            metadata.target(),
+            visitor.into_message(),
+        )
+        .with_source(source)
+        .with_id(self.next_id.fetch_add(1, Ordering::Relaxed));
+        let _result = self.sender.unbounded_send(message);
+    }
+}
+
+/// Formats the fields of an event as a message: the `message` field followed by the others, as
+/// `name=value`.
+#[derive(Debug, Default)]
+struct MessageVisitor {
+    message: String,
+    fields: String,
+}
+
+impl MessageVisitor {
+    fn into_message(self) -> String {
+        if self.fields.is_empty() {
+            self.message
+        } else if self.message.is_empty() {
+            self.fields.trim_start().to_owned()
+        } else {
+            self.message + &self.fields
+        }
+    }
+}
+
+impl tracing::field::Visit for MessageVisitor {
+    fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+        if field.name() == "message" {
+            self.message = value.to_owned();
+        } else {
+            let _result = write!(self.fields, " {}={}", field.name(), value);
+        }
+    }
+
+    // Fields are only available through their debug representation.
+    #[allow(clippy::use_debug)]
+    fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            self.message = format!("{value:?}");
+        } else {
+            let _result = write!(self.fields, " {}={:?}", field.name(), value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_level_serde() {
+        let value = format::to_value(&LogLevel::Warning).unwrap();
+        assert_eq!(format::from_value::<i32>(&value).unwrap(), 3);
+        assert_eq!(
+            format::from_value::<LogLevel>(&value).unwrap(),
+            LogLevel::Warning
+        );
+
+        let value = format::to_value(&7i32).unwrap();
+        assert!(format::from_value::<LogLevel>(&value).is_err());
+    }
+
+    #[test]
+    fn test_log_message_serde() {
+        let message = LogMessage::new(LogLevel::Info, "qi.test", "hello")
+            .with_source("log.rs:tests:1")
+            .with_id(42);
+        let value = format::to_value(&message).unwrap();
+        assert_eq!(format::from_value::<LogMessage>(&value).unwrap(), message);
+    }
+
+    #[test]
+    fn test_layer_forwards_events() {
+        use tracing_subscriber::layer::SubscriberExt;
+        let (sender, receiver) = mpsc::unbounded();
+        let layer = LogLayer {
+            sender,
+            next_id: AtomicU32::new(0),
+        };
+        let subscriber = tracing_subscriber::registry().with(layer);
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::warn!(target: "app", answer = 42, "the answer");
+            tracing::info!(target: "qi_messaging", "ignored");
+        });
+        // The subscriber, and therefore the sender, is dropped once the closure returns.
+        let messages: Vec<_> = futures::executor::block_on(receiver.collect());
+        let [message]: [LogMessage; 1] = messages.try_into().unwrap();
+        assert_eq!(message.level, LogLevel::Warning);
+        assert_eq!(message.category, "app");
+        assert_eq!(message.message, "the answer answer=42");
+        assert_eq!(message.id, 0);
+    }
+}
@@ -0,0 +1,406 @@
+//! Client of the `ALVideoDevice` service, which gives access to the cameras of a robot.
+//!
+//! Images are received through a subscription to a camera, see
+//! [`VideoClient::subscribe_camera`]. The pixels of the images are not copied once received.
+
+use crate::{
+    messaging::{CallResult, CallTermination},
+    object::{
+        node,
+        object::{client::CallError, Client},
+    },
+    types::{Dynamic, Raw, Value},
+    Node,
+};
+use std::time::Duration;
+use tracing::trace;
+
+/// The name of the service in the service directory.
+pub const SERVICE_NAME: &str = "ALVideoDevice";
+
+/// A client of the `ALVideoDevice` service.
+#[derive(Debug, Clone)]
+pub struct VideoClient {
+    device: Client,
+}
+
+impl VideoClient {
+    pub async fn connect(node: &Node) -> CallResult<Self, node::ServiceError> {
+        let device = node.service(SERVICE_NAME).await?;
+        Ok(Self { device })
+    }
+
+    /// Subscribes to the images of a camera.
+    ///
+    /// The name identifies the subscriber, the service makes it unique. The subscription is
+    /// terminated when it is dropped, or explicitly with [`CameraSubscription::unsubscribe`].
+    pub async fn subscribe_camera(
+        &self,
+        name: &str,
+        camera: Camera,
+        resolution: Resolution,
+        color_space: ColorSpace,
+        fps: i32,
+    ) -> CallResult<CameraSubscription, CallError> {
+        let handle: String = self
+            .device
+            .call(
+                "subscribeCamera",
+                (
+                    name,
+                    camera as i32,
+                    resolution as i32,
+                    color_space.as_i32(),
+                    fps,
+                ),
+            )
+            .await?;
+        Ok(CameraSubscription {
+            device: self.device.clone(),
+            handle: Some(handle),
+        })
+    }
+}
+
+/// A subscription to the images of a camera, see [`VideoClient::subscribe_camera`].
+#[derive(Debug)]
+pub struct CameraSubscription {
+    device: Client,
+    handle: Option<String>,
+}
+
+impl CameraSubscription {
+    /// The handle of the subscription, as named by the service.
+    pub fn handle(&self) -> &str {
+        self.handle.as_deref().unwrap_or_default()
+    }
+
+    /// Gets the latest image of the camera.
+    pub async fn image(&self) -> CallResult<QiImage, Error> {
+        let image: Dynamic = self
+            .device
+            .call("getImageRemote", (self.handle(),))
+            .await
+            .map_err(|err| err.map_err(Error::Call))?;
+        QiImage::from_dynamic(image).map_err(|err| CallTermination::Error(Error::Decode(err)))
+    }
+
+    /// Terminates the subscription.
+    pub async fn unsubscribe(mut self) -> CallResult<(), CallError> {
+        match self.handle.take() {
+            Some(handle) => unsubscribe(&self.device, handle).await,
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for CameraSubscription {
+    fn drop(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            let device = self.device.clone();
+            // This is a best effort, the service terminates the subscription anyway when the
+            // session is closed.
+            tokio::spawn(async move {
+                if let Err(err) = unsubscribe(&device, handle).await {
+                    trace!(error = ?err, "failed to unsubscribe from a camera");
+                }
+            });
+        }
+    }
+}
+
+async fn unsubscribe(device: &Client, handle: String) -> CallResult<(), CallError> {
+    let _unsubscribed: bool = device.call("unsubscribe", (handle,)).await?;
+    Ok(())
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("a call to the video device failed")]
+    Call(#[source] CallError),
+
+    #[error("failed to decode the image")]
+    Decode(#[source] DecodeImageError),
+}
+
+/// A camera of a robot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Camera {
+    Top = 0,
+    Bottom = 1,
+    Depth = 2,
+}
+
+/// A resolution of the images of a camera.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Resolution {
+    /// 40x30 pixels.
+    QQQQVGA = 8,
+    /// 80x60 pixels.
+    QQQVGA = 7,
+    /// 160x120 pixels.
+    QQVGA = 0,
+    /// 320x240 pixels.
+    QVGA = 1,
+    /// 640x480 pixels.
+    VGA = 2,
+    /// 1280x960 pixels.
+    VGA4 = 3,
+    /// 2560x1920 pixels.
+    VGA16 = 4,
+    /// 1280x720 pixels.
+    HD720 = 5,
+    /// 1920x1080 pixels.
+    HD1080 = 6,
+}
+
+/// The color space of the pixels of an image.
+///
+/// The color spaces are identified by their value in the `ALVideoDevice` service. The most
+/// common ones are available as constants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ColorSpace(i32);
+
+impl ColorSpace {
+    /// One layer, the luminance of the pixels.
+    pub const Y: Self = Self(0);
+    /// Two layers, packed as `Y0 U Y1 V` for each couple of pixels.
+    pub const YUV422: Self = Self(9);
+    pub const YUV: Self = Self(10);
+    pub const RGB: Self = Self(11);
+    pub const HSY: Self = Self(12);
+    pub const BGR: Self = Self(13);
+    /// One layer of 16 bits, the distance of the pixels in millimeters.
+    pub const DEPTH: Self = Self(17);
+
+    pub const fn new(value: i32) -> Self {
+        Self(value)
+    }
+
+    pub const fn as_i32(self) -> i32 {
+        self.0
+    }
+}
+
+/// An image of a camera.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QiImage {
+    width: u32,
+    height: u32,
+    layers: u32,
+    color_space: ColorSpace,
+    timestamp: Duration,
+    camera: i32,
+    pixels: Raw,
+}
+
+impl QiImage {
+    /// The number of fields of the images sent by the service.
+    const FIELD_COUNT: usize = 8;
+
+    /// Decodes an image as sent by the service.
+    ///
+    /// Images are sent as a list of dynamic values: the width, the height, the number of
+    /// layers, the color space, the seconds and the microseconds of the timestamp, the pixels,
+    /// the camera index, and possibly more values that are ignored.
+    pub fn from_dynamic(image: Dynamic) -> Result<Self, DecodeImageError> {
+        let fields: Vec<Value> = match unwrap_dynamic(image.into_value()) {
+            Value::List(fields) => fields,
+            Value::Tuple(fields) => fields.into(),
+            _ => return Err(DecodeImageError::NotAList),
+        };
+        if fields.len() < Self::FIELD_COUNT {
+            return Err(DecodeImageError::MissingFields(fields.len()));
+        }
+        let mut fields = fields.into_iter().map(unwrap_dynamic);
+        let mut next_int = |name| {
+            fields
+                .next()
+                .and_then(|value| value.as_number())
+                .and_then(|number| number.as_int32())
+                .ok_or(DecodeImageError::InvalidField(name))
+        };
+        let width = next_int("width")?;
+        let height = next_int("height")?;
+        let layers = next_int("layers")?;
+        let color_space = ColorSpace(next_int("color space")?);
+        let seconds = next_int("timestamp seconds")?;
+        let microseconds = next_int("timestamp microseconds")?;
+        let pixels = fields
+            .next()
+            .and_then(Value::into_raw)
+            .ok_or(DecodeImageError::InvalidField("pixels"))?;
+        let camera = fields
+            .next()
+            .and_then(|value| value.as_number())
+            .and_then(|number| number.as_int32())
+            .ok_or(DecodeImageError::InvalidField("camera"))?;
+
+        let to_u32 = |value: i32, name| {
+            u32::try_from(value).map_err(|_err| DecodeImageError::InvalidField(name))
+        };
+        let width = to_u32(width, "width")?;
+        let height = to_u32(height, "height")?;
+        let layers = to_u32(layers, "layers")?;
+        let timestamp = Duration::from_secs(to_u32(seconds, "timestamp seconds")?.into())
+            + Duration::from_micros(to_u32(microseconds, "timestamp microseconds")?.into());
+
+        let expected_size = u64::from(width) * u64::from(height) * u64::from(layers);
+        if u64::try_from(pixels.len()).ok() != Some(expected_size) {
+            return Err(DecodeImageError::PixelsSizeMismatch {
+                expected: expected_size,
+                actual: pixels.len(),
+            });
+        }
+
+        Ok(Self {
+            width,
+            height,
+            layers,
+            color_space,
+            timestamp,
+            camera,
+            pixels,
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The number of bytes of each pixel.
+    pub fn layers(&self) -> u32 {
+        self.layers
+    }
+
+    pub fn color_space(&self) -> ColorSpace {
+        self.color_space
+    }
+
+    /// The date the image was taken at, relative to the boot of the robot.
+    pub fn timestamp(&self) -> Duration {
+        self.timestamp
+    }
+
+    /// The index of the camera that took the image.
+    pub fn camera(&self) -> i32 {
+        self.camera
+    }
+
+    /// The pixels of the image, row by row, each pixel being [`QiImage::layers`] bytes long.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Returns the buffer of the pixels of the image, without copying them.
+    pub fn into_pixels(self) -> Raw {
+        self.pixels
+    }
+}
+
+impl TryFrom<Dynamic> for QiImage {
+    type Error = DecodeImageError;
+
+    fn try_from(image: Dynamic) -> Result<Self, Self::Error> {
+        Self::from_dynamic(image)
+    }
+}
+
+/// Values of the service are dynamics that may contain other dynamics.
+fn unwrap_dynamic(value: Value) -> Value {
+    match value {
+        Value::Dynamic(dynamic) => unwrap_dynamic(dynamic.into_value()),
+        value => value,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum DecodeImageError {
+    #[error("the image is not a list of values")]
+    NotAList,
+
+    #[error("the image only has {0} fields")]
+    MissingFields(usize),
+
+    #[error("the {0} of the image is invalid")]
+    InvalidField(&'static str),
+
+    #[error("the image has {actual} bytes of pixels but its dimensions require {expected} bytes")]
+    PixelsSizeMismatch { expected: u64, actual: usize },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(value: impl Into<Value>) -> Value {
+        Value::Dynamic(Box::new(Dynamic::from_value(value.into())))
+    }
+
+    fn image_fields(pixels: Raw) -> Vec<Value> {
+        vec![
+            field(2i32),
+            field(3i32),
+            field(1i32),
+            field(ColorSpace::Y.as_i32()),
+            field(12i32),
+            field(500i32),
+            field(pixels),
+            field(Camera::Bottom as i32),
+            field(0i32),
+        ]
+    }
+
+    #[test]
+    fn test_image_from_dynamic() {
+        let pixels = Raw::from_static(&[1, 2, 3, 4, 5, 6]);
+        let image = QiImage::from_dynamic(Dynamic::from_value(Value::List(image_fields(
+            pixels.clone(),
+        ))))
+        .unwrap();
+        assert_eq!(image.width(), 2);
+        assert_eq!(image.height(), 3);
+        assert_eq!(image.layers(), 1);
+        assert_eq!(image.color_space(), ColorSpace::Y);
+        assert_eq!(image.timestamp(), Duration::from_micros(12_000_500));
+        assert_eq!(image.camera(), 1);
+        assert_eq!(image.pixels(), [1, 2, 3, 4, 5, 6]);
+        // The pixels are not copied.
+        assert_eq!(image.into_pixels().as_ptr(), pixels.as_ptr());
+    }
+
+    #[test]
+    fn test_image_from_dynamic_errors() {
+        assert_eq!(
+            QiImage::from_dynamic(Dynamic::from_value(Value::from("image".to_owned()))),
+            Err(DecodeImageError::NotAList)
+        );
+
+        let mut fields = image_fields(Raw::from_static(&[1, 2, 3, 4, 5, 6]));
+        fields.truncate(4);
+        assert_eq!(
+            QiImage::from_dynamic(Dynamic::from_value(Value::List(fields))),
+            Err(DecodeImageError::MissingFields(4))
+        );
+
+        let mut fields = image_fields(Raw::from_static(&[1, 2, 3, 4, 5, 6]));
+        fields[0] = field("two".to_owned());
+        assert_eq!(
+            QiImage::from_dynamic(Dynamic::from_value(Value::List(fields))),
+            Err(DecodeImageError::InvalidField("width"))
+        );
+
+        let fields = image_fields(Raw::from_static(&[1, 2, 3]));
+        assert_eq!(
+            QiImage::from_dynamic(Dynamic::from_value(Value::List(fields))),
+            Err(DecodeImageError::PixelsSizeMismatch {
+                expected: 6,
+                actual: 3
+            })
+        );
+    }
+}
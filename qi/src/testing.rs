@@ -0,0 +1,230 @@
+//! Test doubles of the components of the `qi` framework, for the unit tests of applications.
+
+use crate::{
+    messaging::CallResult,
+    object::service_directory::{Error, ServiceDirectory, ServiceEvent, ServiceInfo},
+    types::object::ServiceId,
+};
+use futures::{
+    channel::mpsc,
+    future::{self, BoxFuture},
+    stream::BoxStream,
+    FutureExt, StreamExt,
+};
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+/// An in-memory service directory, that requires no connection.
+///
+/// Services are assigned ids in order of registration, starting after the id of the directory
+/// itself, unless they request a free id. Clones share the same services.
+///
+/// ```
+/// use futures::StreamExt;
+/// use qi::{testing::FakeServiceDirectory, ServiceDirectory, ServiceEvent, ServiceInfo};
+///
+/// # futures::executor::block_on(async {
+/// let directory = FakeServiceDirectory::new();
+/// let mut events = directory.watch().await.unwrap();
+/// let id = directory
+///     .register_service(ServiceInfo {
+///         name: "Motion".to_owned(),
+///         ..Default::default()
+///     })
+///     .await
+///     .unwrap();
+/// assert_eq!(directory.resolve("Motion").await.unwrap().service_id, id);
+/// assert_eq!(
+///     events.next().await,
+///     Some(ServiceEvent::Added {
+///         id,
+///         name: "Motion".to_owned()
+///     })
+/// );
+/// # });
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct FakeServiceDirectory {
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    services: BTreeMap<ServiceId, ServiceInfo>,
+    watchers: Vec<mpsc::UnboundedSender<ServiceEvent>>,
+}
+
+impl FakeServiceDirectory {
+    /// The id of the first service registered to the directory, the id 1 being the directory.
+    const FIRST_SERVICE_ID: u32 = 2;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the information of the registered services, ordered by id.
+    pub fn registered_services(&self) -> Vec<ServiceInfo> {
+        self.lock().services.values().cloned().collect()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        // The state is always left consistent, poisoning can be ignored.
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl State {
+    fn register(&mut self, mut info: ServiceInfo) -> Result<ServiceId, Error> {
+        if self
+            .services
+            .values()
+            .any(|service| service.name == info.name)
+        {
+            return Err(Error::ServiceAlreadyRegistered(info.name));
+        }
+        if info.service_id == ServiceId::default() || self.services.contains_key(&info.service_id) {
+            info.service_id = self.next_service_id();
+        }
+        let id = info.service_id;
+        self.publish(ServiceEvent::Added {
+            id,
+            name: info.name.clone(),
+        });
+        self.services.insert(id, info);
+        Ok(id)
+    }
+
+    fn unregister(&mut self, id: ServiceId) -> Result<(), Error> {
+        let info = self
+            .services
+            .remove(&id)
+            .ok_or(Error::ServiceIdNotFound(id))?;
+        self.publish(ServiceEvent::Removed {
+            id,
+            name: info.name,
+        });
+        Ok(())
+    }
+
+    fn next_service_id(&self) -> ServiceId {
+        let next = self
+            .services
+            .keys()
+            .next_back()
+            .map_or(FakeServiceDirectory::FIRST_SERVICE_ID, |&id| {
+                u32::from(id) + 1
+            });
+        ServiceId::new(next.max(FakeServiceDirectory::FIRST_SERVICE_ID))
+    }
+
+    fn publish(&mut self, event: ServiceEvent) {
+        // Watchers that dropped their stream are forgotten.
+        self.watchers
+            .retain(|watcher| watcher.unbounded_send(event.clone()).is_ok());
+    }
+}
+
+impl ServiceDirectory for FakeServiceDirectory {
+    fn resolve(&self, name: &str) -> BoxFuture<'static, CallResult<ServiceInfo, Error>> {
+        let service = self
+            .lock()
+            .services
+            .values()
+            .find(|service| service.name == name)
+            .cloned()
+            .ok_or_else(|| Error::ServiceNotFound(name.to_owned()).into());
+        future::ready(service).boxed()
+    }
+
+    fn services(&self) -> BoxFuture<'static, CallResult<Vec<ServiceInfo>, Error>> {
+        future::ok(self.registered_services()).boxed()
+    }
+
+    fn register_service(
+        &self,
+        info: ServiceInfo,
+    ) -> BoxFuture<'static, CallResult<ServiceId, Error>> {
+        let result = self.lock().register(info).map_err(Into::into);
+        future::ready(result).boxed()
+    }
+
+    fn unregister_service(&self, id: ServiceId) -> BoxFuture<'static, CallResult<(), Error>> {
+        let result = self.lock().unregister(id).map_err(Into::into);
+        future::ready(result).boxed()
+    }
+
+    fn watch(&self) -> BoxFuture<'static, CallResult<BoxStream<'static, ServiceEvent>, Error>> {
+        let (sender, receiver) = mpsc::unbounded();
+        self.lock().watchers.push(sender);
+        future::ok(receiver.boxed()).boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messaging::CallTermination;
+
+    fn info(name: &str, id: u32) -> ServiceInfo {
+        ServiceInfo {
+            name: name.to_owned(),
+            service_id: ServiceId::new(id),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fake_service_directory_register() {
+        let directory = FakeServiceDirectory::new();
+        let first = directory.register_service(info("A", 0)).await.unwrap();
+        let requested = directory.register_service(info("B", 10)).await.unwrap();
+        let taken = directory.register_service(info("C", 10)).await.unwrap();
+        assert_eq!(first, ServiceId::new(2));
+        assert_eq!(requested, ServiceId::new(10));
+        assert_eq!(taken, ServiceId::new(11));
+        assert!(matches!(
+            directory.register_service(info("A", 0)).await,
+            Err(CallTermination::Error(Error::ServiceAlreadyRegistered(name))) if name == "A"
+        ));
+
+        let names: Vec<_> = directory
+            .services()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|service| service.name)
+            .collect();
+        assert_eq!(names, ["A", "B", "C"]);
+    }
+
+    #[tokio::test]
+    async fn test_fake_service_directory_unregister() {
+        let directory = FakeServiceDirectory::new();
+        let mut events = directory.watch().await.unwrap();
+        let id = directory.register_service(info("A", 0)).await.unwrap();
+        directory.unregister_service(id).await.unwrap();
+        assert!(matches!(
+            directory.resolve("A").await,
+            Err(CallTermination::Error(Error::ServiceNotFound(_)))
+        ));
+        assert!(matches!(
+            directory.unregister_service(id).await,
+            Err(CallTermination::Error(Error::ServiceIdNotFound(_)))
+        ));
+
+        let name = "A".to_owned();
+        assert_eq!(
+            events.next().await,
+            Some(ServiceEvent::Added {
+                id,
+                name: name.clone()
+            })
+        );
+        assert_eq!(
+            events.next().await,
+            Some(ServiceEvent::Removed { id, name })
+        );
+    }
+}
@@ -0,0 +1,8 @@
+//! The values and the types of the `qi` type system.
+//!
+//! This module is part of the facade of the crate, see [the crate documentation](crate#stability).
+
+pub use qi_types::{
+    dynamic, object, signature, ty, Dynamic, Float32, Float64, List, Map, Number, Object, Raw,
+    Signature, Tuple, Type, Value,
+};
@@ -0,0 +1,8 @@
+//! The `qi` format, in which values are written in messages.
+//!
+//! This module is part of the facade of the crate, see [the crate documentation](crate#stability).
+
+pub use qi_format::{
+    from_value, to_value, DecodeDynamic, Error, FieldsReader, FromValue, Result, Utf8Policy, Value,
+    DEFAULT_MAX_DEPTH,
+};